@@ -9,6 +9,67 @@ import {
   TOKEN_PROGRAM_ID 
 } from "@solana/spl-token";
 
+// Market settings `createMarket` uses unless given others
+export const DEFAULT_MARKET_CONFIG = {
+  voidIfSingleSided: false,
+  marketType: { standard: {} },
+  pivotValue: new anchor.BN(0),
+  circuitBreakerBps: 0,
+  circuitBreakerWindow: new anchor.BN(0),
+  outcomeOracles: [],
+  maxPayoutShareBps: 0,
+  capRegistrationPeriod: new anchor.BN(0),
+  externalId: null,
+  withdrawalDelay: new anchor.BN(0),
+  useSlots: false,
+  resolutionSlot: new anchor.BN(0),
+  restrictTokenAccounts: false,
+  outcomeCommitments: [],
+  scheduledOracle: null,
+  scheduledTime: new anchor.BN(0),
+  scheduledWindow: new anchor.BN(0),
+  minDiverseOutcomes: 1,
+  fixedOddsBps: [],
+  graduatedMinBet: false,
+  requireOutcomeEvidence: false,
+  staleGracePeriod: new anchor.BN(0),
+  scoredResolution: false,
+  resolutionAccount: null,
+  resolutionOffset: 0,
+  expectedBytes: Buffer.alloc(0),
+  guardian: null,
+  vetoWindow: new anchor.BN(0),
+  maxVetoes: 0,
+  ranked: false,
+  challengePeriod: new anchor.BN(0),
+  isNative: false,
+  bettingCutoffOffset: new anchor.BN(0),
+  creatorFeeBps: 0,
+  initialLiquidity: [],
+  accessMode: { public: {} },
+  maxBet: null,
+  maxTotalPool: null,
+  resolvers: [],
+  resolutionThreshold: 0,
+  closeGracePeriod: new anchor.BN(0),
+  minLiquidityPerOutcome: new anchor.BN(0),
+  weighted: false,
+  cancelPenaltyBps: null,
+  category: { other: {} },
+  tags: [],
+  oracle: null,
+  scalarLowerBound: new anchor.BN(0),
+  scalarUpperBound: new anchor.BN(0),
+  marketId: null,
+  claimDeadline: new anchor.BN(0),
+  twap: null,
+  promptFeeTiers: [],
+  feeDecayPeriod: new anchor.BN(0),
+  feeCap: null,
+  residualDestination: null,
+  preliminaryTrancheBps: 0,
+};
+
 // Client class for interacting with the Betting Markets program
 export class BettingMarketsClient {
   constructor(
@@ -16,14 +77,18 @@ export class BettingMarketsClient {
     public provider: anchor.AnchorProvider
   ) {}
 
-  async initialize(authority: anchor.web3.Keypair): Promise<string> {
+  async initialize(
+    authority: anchor.web3.Keypair,
+    feeBps: number = 0,
+    globalMinBet: number = 0
+  ): Promise<string> {
     const [globalState] = anchor.web3.PublicKey.findProgramAddressSync(
       [Buffer.from("global_state")],
       this.program.programId
     );
 
     const tx = await this.program.methods
-      .initialize()
+      .initialize(feeBps, new anchor.BN(globalMinBet))
       .accountsPartial({
         globalState,
         authority: authority.publicKey,
@@ -41,7 +106,14 @@ export class BettingMarketsClient {
     outcomes: string[],
     resolutionTime: number,
     minBet: number,
-    marketId: number = 0
+    mint: anchor.web3.PublicKey,
+    marketId: number = 0,
+    config: any = DEFAULT_MARKET_CONFIG,
+    // Needed when the platform charges a creation fee
+    feeAccounts: {
+      creatorTokenAccount: anchor.web3.PublicKey;
+      treasury: anchor.web3.PublicKey;
+    } | null = null
   ): Promise<{
     transaction: string;
     marketAddress: anchor.web3.PublicKey;
@@ -61,12 +133,29 @@ export class BettingMarketsClient {
         question,
         outcomes,
         new anchor.BN(resolutionTime),
-        new anchor.BN(minBet)
+        new anchor.BN(minBet),
+        config
       )
       .accountsPartial({
         market,
         globalState,
+        externalIdEntry: config.externalId
+          ? anchor.web3.PublicKey.findProgramAddressSync(
+              [Buffer.from("external_id"), Buffer.from(config.externalId)],
+              this.program.programId
+            )[0]
+          : null,
         authority: authority.publicKey,
+        mint,
+        creatorTokenAccount: feeAccounts?.creatorTokenAccount ?? null,
+        treasury: feeAccounts?.treasury ?? null,
+        marketTokenAccount: null,
+        // Dust and forfeited winnings go to the config's destination, or else the treasury
+        residualDestination: config.isNative
+          ? null
+          : config.residualDestination ?? (await this.program.account.globalState.fetch(globalState)).treasury,
+        tokenProgram: feeAccounts ? TOKEN_PROGRAM_ID : null,
+        solVault: config.isNative ? this.getSolVaultAddress(market) : null,
         systemProgram: anchor.web3.SystemProgram.programId,
       })
       .signers([authority])
@@ -84,17 +173,18 @@ export class BettingMarketsClient {
     outcomeIndex: number,
     amount: number,
     bettorTokenAccount: anchor.web3.PublicKey,
-    marketTokenAccount: anchor.web3.PublicKey
+    marketTokenAccount: anchor.web3.PublicKey,
+    betNonce: number = Date.now()
   ): Promise<{
     transaction: string;
     betAddress: anchor.web3.PublicKey;
   }> {
-    const bet = anchor.web3.Keypair.generate();
+    const bet = this.getBetAddress(market, bettor.publicKey, betNonce);
 
     const tx = await this.program.methods
-      .placeBet(outcomeIndex, new anchor.BN(amount))
+      .placeBet(outcomeIndex, new anchor.BN(amount), new anchor.BN(betNonce))
       .accountsPartial({
-        bet: bet.publicKey,
+        bet,
         market,
         bettor: bettor.publicKey,
         bettorTokenAccount,
@@ -102,22 +192,132 @@ export class BettingMarketsClient {
         tokenProgram: TOKEN_PROGRAM_ID,
         systemProgram: anchor.web3.SystemProgram.programId,
       })
-      .signers([bettor, bet])
+      .signers([bettor])
       .rpc();
 
     return {
       transaction: tx,
-      betAddress: bet.publicKey,
+      betAddress: bet,
     };
   }
 
+  // Native SOL markets stake lamports straight from the bettor's wallet
+  async placeBetSol(
+    bettor: anchor.web3.Keypair,
+    market: anchor.web3.PublicKey,
+    outcomeIndex: number,
+    amount: number,
+    betNonce: number = Date.now()
+  ): Promise<{
+    transaction: string;
+    betAddress: anchor.web3.PublicKey;
+  }> {
+    const bet = this.getBetAddress(market, bettor.publicKey, betNonce);
+
+    const tx = await this.program.methods
+      .placeBetSol(outcomeIndex, new anchor.BN(amount), new anchor.BN(betNonce))
+      .accountsPartial({
+        bet,
+        market,
+        bettor: bettor.publicKey,
+        solVault: this.getSolVaultAddress(market),
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([bettor])
+      .rpc();
+
+    return {
+      transaction: tx,
+      betAddress: bet,
+    };
+  }
+
+  async claimPayoutSol(
+    bettor: anchor.web3.Keypair,
+    bet: anchor.web3.PublicKey,
+    market: anchor.web3.PublicKey
+  ): Promise<string> {
+    return this.program.methods
+      .claimPayoutSol()
+      .accountsPartial({
+        bet,
+        market,
+        bettor: bettor.publicKey,
+        solVault: this.getSolVaultAddress(market),
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([bettor])
+      .rpc();
+  }
+
+  getSolVaultAddress(market: anchor.web3.PublicKey): anchor.web3.PublicKey {
+    return anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("sol_vault"), market.toBuffer()],
+      this.program.programId
+    )[0];
+  }
+
+  getBetAddress(
+    market: anchor.web3.PublicKey,
+    bettor: anchor.web3.PublicKey,
+    betNonce: number
+  ): anchor.web3.PublicKey {
+    return anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("bet"), market.toBuffer(), bettor.toBuffer(), new anchor.BN(betNonce).toArrayLike(Buffer, "le", 8)],
+      this.program.programId
+    )[0];
+  }
+
+  async addToBet(
+    bettor: anchor.web3.Keypair,
+    bet: anchor.web3.PublicKey,
+    market: anchor.web3.PublicKey,
+    amount: number,
+    bettorTokenAccount: anchor.web3.PublicKey,
+    marketTokenAccount: anchor.web3.PublicKey
+  ): Promise<string> {
+    const tx = await this.program.methods
+      .addToBet(new anchor.BN(amount))
+      .accountsPartial({
+        bet,
+        market,
+        bettor: bettor.publicKey,
+        bettorTokenAccount,
+        marketTokenAccount,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([bettor])
+      .rpc();
+
+    return tx;
+  }
+
   async resolveMarket(
     authority: anchor.web3.Keypair,
     market: anchor.web3.PublicKey,
-    winningOutcomeIndex: number
+    winningOutcomeIndex: number,
+    runnerUpIndex: number | null = null,
+    consolationBps: number = 0,
+    evidence: number[][] = []
   ): Promise<string> {
     const tx = await this.program.methods
-      .resolveMarket(winningOutcomeIndex)
+      .resolveMarket(winningOutcomeIndex, runnerUpIndex, consolationBps, evidence)
+      .accountsPartial({
+        market,
+        authority: authority.publicKey,
+      })
+      .signers([authority])
+      .rpc();
+
+    return tx;
+  }
+
+  async cancelMarket(
+    authority: anchor.web3.Keypair,
+    market: anchor.web3.PublicKey
+  ): Promise<string> {
+    const tx = await this.program.methods
+      .cancelMarket()
       .accountsPartial({
         market,
         authority: authority.publicKey,
@@ -133,10 +333,79 @@ export class BettingMarketsClient {
     bet: anchor.web3.PublicKey,
     market: anchor.web3.PublicKey,
     bettorTokenAccount: anchor.web3.PublicKey,
-    marketTokenAccount: anchor.web3.PublicKey
+    marketTokenAccount: anchor.web3.PublicKey,
+    feePayerTokenAccount: anchor.web3.PublicKey | null = null
   ): Promise<string> {
+    const marketAccount = await this.program.account.market.fetch(market);
+    const escrow = marketAccount.withdrawalDelay.toNumber() > 0
+      ? this.getEscrowAddress(market, bettor.publicKey)
+      : null;
+    // Fee token markets charge the platform fee from the bettor's fee token account
+    const feeToken = marketAccount.feeToken;
+
     const tx = await this.program.methods
       .claimPayout()
+      .accountsPartial({
+        bet,
+        market,
+        bettor: bettor.publicKey,
+        bettorTokenAccount,
+        marketTokenAccount,
+        escrow,
+        feePayerTokenAccount: feeToken ? feePayerTokenAccount : null,
+        feeTreasury: feeToken ? feeToken.treasury : null,
+        feeRateSource: feeToken ? feeToken.rateSource : null,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      // Split markets pay out of every outcome's sub-vault
+      .remainingAccounts(
+        marketAccount.outcomeVaults.map((pubkey) => ({ pubkey, isSigner: false, isWritable: true }))
+      )
+      .signers([bettor])
+      .rpc();
+
+    return tx;
+  }
+
+  async withdrawEscrow(
+    bettor: anchor.web3.Keypair,
+    market: anchor.web3.PublicKey,
+    bettorTokenAccount: anchor.web3.PublicKey,
+    marketTokenAccount: anchor.web3.PublicKey
+  ): Promise<string> {
+    const tx = await this.program.methods
+      .withdrawEscrow()
+      .accountsPartial({
+        market,
+        escrow: this.getEscrowAddress(market, bettor.publicKey),
+        bettor: bettor.publicKey,
+        bettorTokenAccount,
+        marketTokenAccount,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([bettor])
+      .rpc();
+
+    return tx;
+  }
+
+  getEscrowAddress(market: anchor.web3.PublicKey, bettor: anchor.web3.PublicKey): anchor.web3.PublicKey {
+    return anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("escrow"), market.toBuffer(), bettor.toBuffer()],
+      this.program.programId
+    )[0];
+  }
+
+  async claimRefund(
+    bettor: anchor.web3.Keypair,
+    bet: anchor.web3.PublicKey,
+    market: anchor.web3.PublicKey,
+    bettorTokenAccount: anchor.web3.PublicKey,
+    marketTokenAccount: anchor.web3.PublicKey
+  ): Promise<string> {
+    const tx = await this.program.methods
+      .claimRefund()
       .accountsPartial({
         bet,
         market,
@@ -214,13 +483,19 @@ export async function demonstrateUsage() {
   const outcomes = ["Yes", "No"];
   const resolutionTime = Math.floor(Date.now() / 1000) + 3600; // 1 hour from now
   const minBet = 1000000; // 1 token (6 decimals)
+  const mint = await createMint(provider.connection, authority, authority.publicKey, null, 6);
+  // The treasury doesn't hold the new mint, so the market's residuals need their own account
+  const residualDestination = await createAccount(provider.connection, authority, mint, authority.publicKey);
 
   const { transaction: createTx, marketAddress } = await client.createMarket(
     authority,
     question,
     outcomes,
     resolutionTime,
-    minBet
+    minBet,
+    mint,
+    0,
+    { ...DEFAULT_MARKET_CONFIG, residualDestination }
   );
   console.log(`   ✅ Market created: ${createTx}`);
   console.log(`   📊 Market Address: ${marketAddress.toString()}\n`);
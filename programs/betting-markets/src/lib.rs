@@ -1,5 +1,10 @@
+// The `#[program]` macro expands to `AccountInfo::realloc`, deprecated in newer solana crates.
+#![allow(deprecated)]
+
 use anchor_lang::prelude::*;
-use anchor_spl::token::{self, Token, TokenAccount, Transfer};
+use anchor_lang::solana_program::hash::hashv;
+use anchor_lang::system_program;
+use anchor_spl::token::{self, CloseAccount, Mint, Token, TokenAccount, Transfer};
 
 declare_id!("EHgavRW857rfGMyP17kjKcuSqj8Gh9fVKC6A2HcBkeF5");
 
@@ -7,39 +12,680 @@ declare_id!("EHgavRW857rfGMyP17kjKcuSqj8Gh9fVKC6A2HcBkeF5");
 pub mod betting_markets {
     use super::*;
 
-    pub fn initialize(ctx: Context<Initialize>) -> Result<()> {
+    /// Sets up the platform once. Later calls fail with `AlreadyInitialized` instead of
+    /// resetting state; use the `set_*` instructions to change settings afterwards.
+    pub fn initialize(ctx: Context<Initialize>, fee_bps: u16, global_min_bet: u64) -> Result<()> {
+        require!(fee_bps <= 10_000, ErrorCode::InvalidFee);
+
         let global_state = &mut ctx.accounts.global_state;
+        // The account is opened if needed so a repeat call reaches this check, and an authority
+        // is only ever written here
+        require!(global_state.authority == Pubkey::default(), ErrorCode::AlreadyInitialized);
         global_state.authority = ctx.accounts.authority.key();
         global_state.market_count = 0;
+        global_state.max_question_len = DEFAULT_MAX_QUESTION_LEN;
+        global_state.fee_bps = fee_bps;
+        global_state.paused = false;
+        global_state.global_min_bet = global_min_bet;
+        global_state.fee_token = None;
+        global_state.insurance_fee_bps = 0;
         msg!("Betting Markets platform initialized!");
         Ok(())
     }
 
+    pub fn set_max_question_len(ctx: Context<UpdateGlobalState>, max_question_len: u16) -> Result<()> {
+        let global_state = &mut ctx.accounts.global_state;
+
+        require!(ctx.accounts.authority.key() == global_state.authority, ErrorCode::Unauthorized);
+        require!(
+            max_question_len > 0 && max_question_len <= MAX_QUESTION_LEN_LIMIT,
+            ErrorCode::InvalidQuestionLimit
+        );
+
+        msg!("Max question length {} -> {}", global_state.max_question_len, max_question_len);
+        global_state.max_question_len = max_question_len;
+        Ok(())
+    }
+
+    /// Sets the protocol fee taken from every winning payout, in basis points.
+    pub fn set_fee(ctx: Context<UpdateGlobalState>, fee_bps: u16) -> Result<()> {
+        let global_state = &mut ctx.accounts.global_state;
+
+        require!(ctx.accounts.authority.key() == global_state.authority, ErrorCode::Unauthorized);
+        require!(fee_bps <= 10_000, ErrorCode::InvalidFee);
+
+        msg!("Fee {} -> {} bps", global_state.fee_bps, fee_bps);
+        global_state.fee_bps = fee_bps;
+        Ok(())
+    }
+
+    /// Sets the share of every platform fee withdrawal `withdraw_fees` diverts into the mint's
+    /// insurance fund.
+    pub fn set_insurance_fee(ctx: Context<UpdateGlobalState>, insurance_fee_bps: u16) -> Result<()> {
+        let global_state = &mut ctx.accounts.global_state;
+
+        require!(ctx.accounts.authority.key() == global_state.authority, ErrorCode::Unauthorized);
+        require!(insurance_fee_bps <= 10_000, ErrorCode::InvalidFee);
+
+        global_state.insurance_fee_bps = insurance_fee_bps;
+        Ok(())
+    }
+
+    /// Sets the floor every new market's `min_bet` must meet. Existing markets keep theirs.
+    pub fn set_global_min_bet(ctx: Context<UpdateGlobalState>, global_min_bet: u64) -> Result<()> {
+        let global_state = &mut ctx.accounts.global_state;
+
+        require!(ctx.accounts.authority.key() == global_state.authority, ErrorCode::Unauthorized);
+
+        msg!("Global min bet {} -> {}", global_state.global_min_bet, global_min_bet);
+        global_state.global_min_bet = global_min_bet;
+        Ok(())
+    }
+
+    /// Incident switch: halts market creation and new stakes platform-wide. Claims and
+    /// refunds stay open so no one's funds are trapped.
+    pub fn pause(ctx: Context<UpdateGlobalState>) -> Result<()> {
+        set_paused(&mut ctx.accounts.global_state, ctx.accounts.authority.key(), true)
+    }
+
+    pub fn unpause(ctx: Context<UpdateGlobalState>) -> Result<()> {
+        set_paused(&mut ctx.accounts.global_state, ctx.accounts.authority.key(), false)
+    }
+
+    /// Hands the platform admin role to `new_authority`, e.g. to move it to a multisig.
+    pub fn set_authority(ctx: Context<UpdateGlobalState>, new_authority: Pubkey) -> Result<()> {
+        let global_state = &mut ctx.accounts.global_state;
+
+        require!(ctx.accounts.authority.key() == global_state.authority, ErrorCode::Unauthorized);
+
+        let old_authority = global_state.authority;
+        global_state.authority = new_authority;
+
+        emit!(AuthorityChanged {
+            market_id: None,
+            old_authority,
+            new_authority,
+        });
+
+        Ok(())
+    }
+
+    /// Charges the platform fee on markets created from now on in `fee_token` instead of the
+    /// payout currency; `None` goes back to deducting it from payouts. Existing markets keep
+    /// the setting they were created with. Setting a fee token takes its `rate_source`, which
+    /// must already hold a fresh rate.
+    pub fn set_fee_token(ctx: Context<SetFeeToken>, fee_token: Option<FeeToken>) -> Result<()> {
+        let global_state = &mut ctx.accounts.global_state;
+
+        require!(ctx.accounts.authority.key() == global_state.authority, ErrorCode::Unauthorized);
+        if let Some(fee_token) = fee_token {
+            require!(
+                fee_token.mint != Pubkey::default()
+                    && fee_token.treasury != Pubkey::default()
+                    && fee_token.rate_source != Pubkey::default(),
+                ErrorCode::InvalidFeeToken
+            );
+            let rate_source = ctx.accounts.rate_source.as_ref().ok_or(ErrorCode::FeeTokenAccountsRequired)?;
+            read_fee_rate(&fee_token, rate_source, Clock::get()?.unix_timestamp)?;
+        }
+
+        global_state.fee_token = fee_token;
+        Ok(())
+    }
+
     pub fn create_market(
         ctx: Context<CreateMarket>,
         question: String,
         outcomes: Vec<String>,
         resolution_time: i64,
         min_bet: u64,
-    ) -> Result<()> {
+        config: MarketConfig,
+    ) -> Result<MarketReceipt> {
+        require!(!ctx.accounts.global_state.paused, ErrorCode::PlatformPaused);
+        require!(
+            question.len() <= ctx.accounts.global_state.max_question_len as usize,
+            ErrorCode::QuestionTooLong
+        );
+        require!(min_bet >= ctx.accounts.global_state.global_min_bet, ErrorCode::MinBetBelowFloor);
         require!(outcomes.len() >= 2, ErrorCode::InsufficientOutcomes);
         require!(outcomes.len() <= 10, ErrorCode::TooManyOutcomes);
-        require!(resolution_time > Clock::get()?.unix_timestamp, ErrorCode::InvalidResolutionTime);
+        // The account is sized for `MAX_OUTCOME_LABEL_LEN` labels and `MAX_TAGS` tags
+        validate_outcome_labels(&outcomes)?;
+        require!(
+            config.tags.len() <= MAX_TAGS && config.tags.iter().all(|tag| !tag.is_empty() && tag.len() <= MAX_TAG_LEN),
+            ErrorCode::InvalidTags
+        );
+        require!(
+            config.market_type != MarketType::Pivot || outcomes.len() == 2,
+            ErrorCode::PivotRequiresTwoOutcomes
+        );
+        // Scalar payouts split the whole pool by the reading, so nothing else may claim a share
+        // of it or settle the market another way
+        require!(
+            if config.market_type == MarketType::Scalar {
+                outcomes.len() == 2
+                    && config.scalar_upper_bound > config.scalar_lower_bound
+                    && config.max_payout_share_bps == 0
+                    && config.initial_liquidity.is_empty()
+                    && !config.scored_resolution
+                    && !config.ranked
+                    && !config.require_outcome_evidence
+                    && config.challenge_period == 0
+                    && config.resolution_account.is_none()
+                    && config.outcome_oracles.is_empty()
+                    && config.resolvers.is_empty()
+            } else {
+                config.scalar_lower_bound == 0 && config.scalar_upper_bound == 0
+            },
+            ErrorCode::InvalidScalarMarket
+        );
+        // Released tranches can't be refunded, so the markets that may void after betting
+        // closes, or pay through escrow or lamports, settle in one go
+        require!(
+            config.preliminary_tranche_bps == 0
+                || (config.market_type == MarketType::Scalar
+                    && config.preliminary_tranche_bps < 10_000
+                    && config.withdrawal_delay == 0
+                    && !config.is_native
+                    && !config.void_if_single_sided
+                    && config.min_diverse_outcomes == 0
+                    && config.min_liquidity_per_outcome == 0),
+            ErrorCode::InvalidScalarMarket
+        );
+        require!(
+            config.circuit_breaker_bps == 0 || config.circuit_breaker_window > 0,
+            ErrorCode::InvalidCircuitBreaker
+        );
+        require!(
+            config.external_id.is_some() == ctx.accounts.external_id_entry.is_some(),
+            ErrorCode::ExternalIdEntryMismatch
+        );
+        require!(
+            if config.market_type == MarketType::FixedOdds {
+                config.fixed_odds_bps.len() == outcomes.len()
+                    && config.fixed_odds_bps.iter().all(|odds| *odds > 10_000)
+                    && config.max_payout_share_bps == 0
+            } else {
+                config.fixed_odds_bps.is_empty()
+            },
+            ErrorCode::InvalidFixedOdds
+        );
+        require!(
+            !config.require_outcome_evidence || config.market_type != MarketType::Pivot,
+            ErrorCode::InvalidOutcomeEvidence
+        );
+        require!(
+            !config.scored_resolution
+                || (config.market_type == MarketType::Standard && config.max_payout_share_bps == 0),
+            ErrorCode::InvalidScores
+        );
+        require!(
+            !config.ranked
+                || (config.market_type == MarketType::Standard
+                    && outcomes.len() <= MAX_RANKED_OUTCOMES
+                    && config.max_payout_share_bps == 0
+                    && !config.scored_resolution
+                    && config.resolution_account.is_none()),
+            ErrorCode::InvalidRanking
+        );
+        require!(
+            config.challenge_period == 0
+                || (config.challenge_period > 0
+                    && config.market_type == MarketType::Standard
+                    && !config.scored_resolution
+                    && !config.ranked
+                    && config.resolution_account.is_none()),
+            ErrorCode::InvalidChallengePeriod
+        );
+        // Lamport stakes skip the token-only features: escrow, allowlists, ranked bets and
+        // creator fees
+        require!(
+            !config.is_native
+                || (config.market_type == MarketType::Standard
+                    && !config.ranked
+                    && config.withdrawal_delay == 0
+                    && !config.restrict_token_accounts
+                    && config.creator_fee_bps == 0),
+            ErrorCode::InvalidNativeMarket
+        );
+        require!(config.creator_fee_bps <= MAX_CREATOR_FEE_BPS, ErrorCode::InvalidCreatorFee);
+        // A committee is the market's only resolver, so the single-signer resolution sources are out
+        require!(
+            if config.resolvers.is_empty() {
+                config.resolution_threshold == 0
+            } else {
+                config.resolvers.len() <= MAX_COMMITTEE_SIZE
+                    && config.resolution_threshold > 0
+                    && config.resolution_threshold as usize <= config.resolvers.len()
+                    && config
+                        .resolvers
+                        .iter()
+                        .enumerate()
+                        .all(|(i, resolver)| !config.resolvers[..i].contains(resolver))
+                    && config.market_type != MarketType::Pivot
+                    && !config.scored_resolution
+                    && !config.ranked
+                    && !config.require_outcome_evidence
+                    && config.challenge_period == 0
+                    && config.resolution_account.is_none()
+                    && config.outcome_oracles.is_empty()
+                    && config.oracle.is_none()
+            },
+            ErrorCode::InvalidCommittee
+        );
+        for cap in [config.max_bet, config.max_total_pool].into_iter().flatten() {
+            require!(cap >= min_bet, ErrorCode::InvalidBetLimits);
+        }
+        // Seeds carry no weight and caps and consolations divide by raw stake, so time weighting
+        // stands alone
+        require!(
+            !config.weighted
+                || (config.market_type == MarketType::Standard
+                    && !config.use_slots
+                    && !config.ranked
+                    && !config.scored_resolution
+                    && config.max_payout_share_bps == 0
+                    && config.initial_liquidity.is_empty()),
+            ErrorCode::InvalidWeighting
+        );
+        // Opening liquidity follows the same rules as `seed_liquidity`
+        require!(
+            config.initial_liquidity.is_empty()
+                || (config.initial_liquidity.len() == outcomes.len()
+                    && config.market_type != MarketType::FixedOdds
+                    && !config.ranked
+                    && !config.is_native),
+            ErrorCode::InvalidSeedAmounts
+        );
+        require!(
+            if config.resolution_account.is_some() {
+                config.market_type == MarketType::Standard
+                    && outcomes.len() == 2
+                    && !config.expected_bytes.is_empty()
+                    && config.expected_bytes.len() <= MAX_EXPECTED_BYTES
+                    && !config.scored_resolution
+                    && !config.require_outcome_evidence
+            } else {
+                config.expected_bytes.is_empty() && config.resolution_offset == 0
+            },
+            ErrorCode::InvalidResolutionSource
+        );
+        require!(
+            config.min_diverse_outcomes as usize <= outcomes.len(),
+            ErrorCode::InvalidDiversity
+        );
+        require!(config.withdrawal_delay >= 0, ErrorCode::InvalidWithdrawalDelay);
+        require!(config.close_grace_period >= 0, ErrorCode::InvalidCloseGracePeriod);
+        // Escrowed winnings, house liquidity and lamport vaults aren't part of what a reclaim
+        // can tell apart from unclaimed winnings
+        require!(
+            config.claim_deadline == 0
+                || (config.claim_deadline > 0
+                    && config.withdrawal_delay == 0
+                    && config.market_type != MarketType::FixedOdds
+                    && !config.is_native),
+            ErrorCode::InvalidClaimDeadline
+        );
+        // Cancelling a fixed-odds or ranked bet would have to unwind house cover and rank stakes
+        if let Some(penalty_bps) = config.cancel_penalty_bps {
+            require!(
+                penalty_bps <= 10_000
+                    && config.market_type != MarketType::FixedOdds
+                    && !config.ranked
+                    && !config.is_native,
+                ErrorCode::InvalidCancelPenalty
+            );
+        }
+        // Fixed odds don't come from the pools, so thin outcomes don't skew them
+        require!(
+            config.min_liquidity_per_outcome == 0 || config.market_type != MarketType::FixedOdds,
+            ErrorCode::InvalidMinLiquidity
+        );
+        require!(config.stale_grace_period >= 0, ErrorCode::InvalidKeeperAmount);
+        require!(config.max_payout_share_bps <= 10_000, ErrorCode::InvalidPayoutCap);
+        require!(
+            if config.guardian.is_some() {
+                // Capped bets register against a resolution, which a veto would pull out from under them
+                config.veto_window > 0 && config.max_vetoes > 0 && config.max_payout_share_bps == 0
+            } else {
+                config.veto_window == 0 && config.max_vetoes == 0
+            },
+            ErrorCode::InvalidGuardian
+        );
+        require!(config.cap_registration_period >= 0, ErrorCode::InvalidPayoutCap);
+        // Pivot winners are derived from a single reported value, so per-outcome sources don't apply
+        require!(
+            config.outcome_oracles.is_empty()
+                || (config.outcome_oracles.len() == outcomes.len() && config.market_type == MarketType::Standard),
+            ErrorCode::InvalidOutcomeOracles
+        );
+        require!(
+            config.outcome_commitments.is_empty()
+                || (config.outcome_commitments.len() == outcomes.len() && config.market_type == MarketType::Standard),
+            ErrorCode::InvalidOutcomeCommitments
+        );
+        // The reading must come after betting closes, and the window must be reachable; a
+        // scheduled market resolves on that one reading, not a TWAP
+        require!(
+            config.scheduled_oracle.is_none()
+                || (config.market_type == MarketType::Pivot
+                    && config.oracle.is_none()
+                    && !config.use_slots
+                    && config.twap.is_none()
+                    && config.scheduled_time >= resolution_time
+                    && config.scheduled_window > 0),
+            ErrorCode::InvalidSchedule
+        );
+        if config.use_slots {
+            require!(config.resolution_slot > Clock::get()?.slot, ErrorCode::InvalidResolutionTime);
+        } else {
+            require!(resolution_time > Clock::get()?.unix_timestamp, ErrorCode::InvalidResolutionTime);
+        }
+        // The cutoff is a time offset, and a market must open for betting at all
+        require!(
+            config.betting_cutoff_offset == 0
+                || (config.betting_cutoff_offset > 0
+                    && !config.use_slots
+                    && resolution_time - config.betting_cutoff_offset > Clock::get()?.unix_timestamp),
+            ErrorCode::InvalidBettingCutoff
+        );
+        // Each tier gives a longer window and never a cheaper fee than the one before it
+        require!(
+            config.prompt_fee_tiers.is_empty()
+                || (!config.use_slots
+                    && config.prompt_fee_tiers.len() <= MAX_PROMPT_FEE_TIERS
+                    && config.prompt_fee_tiers[0].within > 0
+                    && config.prompt_fee_tiers.iter().all(|tier| tier.fee_share_bps <= 10_000)
+                    && config.prompt_fee_tiers.windows(2).all(|pair| {
+                        pair[1].within > pair[0].within && pair[1].fee_share_bps >= pair[0].fee_share_bps
+                    })),
+            ErrorCode::InvalidFeeTiers
+        );
+        require!(
+            config.fee_decay_period == 0 || (config.fee_decay_period > 0 && !config.use_slots),
+            ErrorCode::InvalidFeeDecay
+        );
+        require!(config.fee_cap != Some(0), ErrorCode::InvalidFeeCap);
+        // The cap counts fees taken from payouts, which a fee token market never takes
+        require!(
+            config.fee_cap.is_none() || config.is_native || ctx.accounts.global_state.fee_token.is_none(),
+            ErrorCode::FeeTokenFeeCap
+        );
+        require!(
+            config.residual_destination != Some(Pubkey::default()),
+            ErrorCode::InvalidResidualDestination
+        );
+        // Dust and forfeited winnings are swept in the market's mint, so wherever they go has to
+        // hold it; without a destination of its own the treasury has to
+        let residual_destination = if config.is_native {
+            config.residual_destination
+        } else {
+            let destination = ctx
+                .accounts
+                .residual_destination
+                .as_ref()
+                .ok_or(ErrorCode::ResidualDestinationRequired)?;
+            require!(
+                destination.key() == config.residual_destination.unwrap_or(ctx.accounts.global_state.treasury),
+                ErrorCode::InvalidResidualDestination
+            );
+            require!(
+                destination.mint == ctx.accounts.mint.key(),
+                if config.residual_destination.is_some() {
+                    ErrorCode::MintMismatch
+                } else {
+                    ErrorCode::ResidualDestinationRequired
+                }
+            );
+            Some(destination.key())
+        };
+        // The window has to fit the required samples at their spacing
+        if let Some(twap) = config.twap {
+            require!(
+                matches!(config.market_type, MarketType::Pivot | MarketType::Scalar)
+                    && !config.use_slots
+                    && config.preliminary_tranche_bps == 0
+                    && twap.min_spacing > 0
+                    && twap.min_samples >= 2
+                    && matches!(
+                        twap.min_spacing.checked_mul(twap.min_samples as i64 - 1),
+                        Some(needed) if twap.window >= needed
+                    ),
+                ErrorCode::InvalidTwapConfig
+            );
+        }
+
+        // Creators holding a fee waiver list for free
+        let fee_waived = !ctx.accounts.fee_waiver.data_is_empty();
+        let creation_fee = if fee_waived { 0 } else { ctx.accounts.global_state.creation_fee };
+        let mut creation_fee_account = Pubkey::default();
+        if creation_fee > 0 {
+            let (Some(creator_token_account), Some(treasury), Some(token_program)) = (
+                ctx.accounts.creator_token_account.as_ref(),
+                ctx.accounts.treasury.as_ref(),
+                ctx.accounts.token_program.as_ref(),
+            ) else {
+                return err!(ErrorCode::CreationFeeRequired);
+            };
+            require!(treasury.key() == ctx.accounts.global_state.treasury, ErrorCode::InvalidTreasury);
+
+            token::transfer(
+                CpiContext::new(
+                    token_program.to_account_info(),
+                    Transfer {
+                        from: creator_token_account.to_account_info(),
+                        to: treasury.to_account_info(),
+                        authority: ctx.accounts.authority.to_account_info(),
+                    },
+                ),
+                creation_fee,
+            )?;
+            creation_fee_account = creator_token_account.key();
+        }
+
+        let seed_total = if config.initial_liquidity.is_empty() {
+            0
+        } else {
+            let total = seed_total(&config.initial_liquidity)?;
+            let (Some(creator_token_account), Some(market_token_account), Some(token_program)) = (
+                ctx.accounts.creator_token_account.as_ref(),
+                ctx.accounts.market_token_account.as_ref(),
+                ctx.accounts.token_program.as_ref(),
+            ) else {
+                return err!(ErrorCode::SeedAccountsRequired);
+            };
+            require!(market_token_account.owner == ctx.accounts.market.key(), ErrorCode::InvalidMarketVault);
+            require!(market_token_account.mint == ctx.accounts.mint.key(), ErrorCode::MintMismatch);
+
+            token::transfer(
+                CpiContext::new(
+                    token_program.to_account_info(),
+                    Transfer {
+                        from: creator_token_account.to_account_info(),
+                        to: market_token_account.to_account_info(),
+                        authority: ctx.accounts.authority.to_account_info(),
+                    },
+                ),
+                total,
+            )?;
+            total
+        };
 
         let market = &mut ctx.accounts.market;
         let global_state = &mut ctx.accounts.global_state;
         
         market.authority = ctx.accounts.authority.key();
+        market.mint = ctx.accounts.mint.key();
         market.question = question;
         market.outcomes = outcomes.clone();
         market.outcome_pools = vec![0; outcomes.len()];
+        market.bet_counts = vec![0; outcomes.len()];
+        market.total_bets = 0;
+        market.authority_stakes = vec![0; outcomes.len()];
+        market.merged_into = vec![None; outcomes.len()];
+        market.withdrawal_delay = config.withdrawal_delay;
+        market.use_slots = config.use_slots;
+        market.restrict_token_accounts = config.restrict_token_accounts;
+        market.allowed_token_accounts = Vec::new();
+        market.outcome_commitments = config.outcome_commitments;
+        market.outcomes_revealed = false;
+        market.scheduled_oracle = config.scheduled_oracle;
+        market.scheduled_time = config.scheduled_time;
+        market.scheduled_window = config.scheduled_window;
+        market.min_diverse_outcomes = config.min_diverse_outcomes;
+        market.fixed_odds_bps = config.fixed_odds_bps;
+        market.graduated_min_bet = config.graduated_min_bet;
+        market.require_outcome_evidence = config.require_outcome_evidence;
+        market.outcome_evidence = Vec::new();
+        market.stale_grace_period = config.stale_grace_period;
+        market.keeper_reserve = 0;
+        market.outcome_vaults = Vec::new();
+        market.fees_accrued = 0;
+        market.creator_fee_bps = config.creator_fee_bps;
+        market.creator_fees_accrued = 0;
+        market.creator_fees_collected = 0;
+        market.fees_collected = 0;
+        market.scored_resolution = config.scored_resolution;
+        market.outcome_scores = Vec::new();
+        market.ranked = config.ranked;
+        market.rank_stakes = if config.ranked {
+            vec![0; market.outcomes.len() * market.outcomes.len()]
+        } else {
+            Vec::new()
+        };
+        market.final_ranking = Vec::new();
+        market.challenge_period = config.challenge_period;
+        market.is_native = config.is_native;
+        market.proposed_outcome = None;
+        market.proposed_at = 0;
+        market.resolution_account = config.resolution_account;
+        market.resolution_offset = config.resolution_offset;
+        market.expected_bytes = config.expected_bytes;
+        market.house_liquidity = 0;
+        market.house_locked = 0;
+        market.house_withdrawn = false;
+        market.runner_up = None;
+        market.consolation_pool = 0;
+        market.total_staked = 0;
+        market.winners_paid = 0;
+        market.winnings_paid = 0;
+        market.dust_swept = 0;
+        market.unclaimed_forfeited = 0;
+        market.resolution_slot = config.resolution_slot;
         market.resolution_time = resolution_time;
+        market.betting_cutoff = resolution_time - config.betting_cutoff_offset;
+        market.betting_closed = false;
+        market.donated_total = 0;
         market.min_bet = min_bet;
+        market.max_bet = config.max_bet;
+        market.max_total_pool = config.max_total_pool;
+        market.resolution_votes = vec![None; config.resolvers.len()];
+        market.resolvers = config.resolvers;
+        market.resolution_threshold = config.resolution_threshold;
         market.resolved = false;
         market.winning_outcome = None;
         market.total_pool = 0;
         market.market_id = global_state.market_count;
         market.created_at = Clock::get()?.unix_timestamp;
+        market.void_if_single_sided = config.void_if_single_sided;
+        market.voided = false;
+        market.authority_had_winning_stake = false;
+        market.market_type = config.market_type;
+        market.pivot_value = config.pivot_value;
+        market.circuit_breaker_bps = config.circuit_breaker_bps;
+        market.circuit_breaker_window = config.circuit_breaker_window;
+        market.circuit_breaker_tripped = false;
+        market.breaker_window_start = market.created_at;
+        market.breaker_window_pool = 0;
+        market.outcome_oracles = config.outcome_oracles;
+        market.max_payout_share_bps = config.max_payout_share_bps;
+        market.cap_registration_period = config.cap_registration_period;
+        market.capped_stake = 0;
+        market.capped_count = 0;
+        market.external_id = config.external_id;
+        market.seed_amounts = vec![];
+        market.seed_reclaimed = false;
+        market.claimed_count = 0;
+        market.close_grace_period = config.close_grace_period;
+        market.claim_deadline = config.claim_deadline;
+        market.unclaimed_reclaimed = false;
+        market.dispute_duration = 0;
+        market.min_liquidity_per_outcome = config.min_liquidity_per_outcome;
+        market.weighted = config.weighted;
+        market.cancel_penalty_bps = config.cancel_penalty_bps;
+        market.category = config.category;
+        market.oracle = config.oracle;
+        market.scalar_lower_bound = config.scalar_lower_bound;
+        market.scalar_upper_bound = config.scalar_upper_bound;
+        market.scalar_value = 0;
+        market.scalar_long_share = 0;
+        market.preliminary_tranche_bps = config.preliminary_tranche_bps;
+        market.preliminary_value = None;
+        market.preliminary_long_share = 0;
+        market.tags = config.tags;
+        market.weighted_pools = if config.weighted {
+            vec![0; market.outcomes.len()]
+        } else {
+            Vec::new()
+        };
+        market.active = false;
+        refresh_active(market);
+        market.access_mode = config.access_mode;
+        if seed_total > 0 {
+            seed_pools(market, config.initial_liquidity.clone(), seed_total);
+        }
+        market.quarantined = false;
+        market.cancelled = false;
+        market.immutable = false;
+        market.creation_fee_paid = creation_fee;
+        market.guardian = config.guardian;
+        market.veto_window = config.veto_window;
+        market.max_vetoes = config.max_vetoes;
+        market.veto_count = 0;
+        market.resolved_at = 0;
+        market.resolved_by = Pubkey::default();
+        market.final_pools = Vec::new();
+        market.creation_fee_account = creation_fee_account;
+
+        if config.is_native {
+            // Pre-fund the vault's rent so payouts only ever move staked lamports
+            let sol_vault = ctx.accounts.sol_vault.as_ref().ok_or(ErrorCode::SolVaultRequired)?;
+            let rent = Rent::get()?.minimum_balance(0).saturating_sub(sol_vault.lamports());
+            if rent > 0 {
+                system_program::transfer(
+                    CpiContext::new(
+                        ctx.accounts.system_program.to_account_info(),
+                        system_program::Transfer {
+                            from: ctx.accounts.authority.to_account_info(),
+                            to: sol_vault.to_account_info(),
+                        },
+                    ),
+                    rent,
+                )?;
+            }
+        }
+
+        if let Some(entry) = ctx.accounts.external_id_entry.as_mut() {
+            entry.external_id = config.external_id.unwrap_or_default();
+            entry.market = market.key();
+        }
+        market.twap = config.twap;
+        market.twap_samples = 0;
+        market.twap_first_at = 0;
+        market.twap_last_at = 0;
+        market.twap_last_price = 0;
+        market.twap_cumulative = 0;
+        market.prompt_fee_tiers = config.prompt_fee_tiers;
+        market.fee_decay_period = config.fee_decay_period;
+        market.fee_cap = config.fee_cap;
+        market.residual_destination = residual_destination;
+        market.platform_fee_share_bps = 10_000;
+        market.receipt = None;
+        market.receipt_rate = 0;
+        market.receipt_rate_at = 0;
+        // Lamport payouts go through their own paths, which deduct the fee as before
+        market.fee_token = if config.is_native { None } else { global_state.fee_token };
+        market.fee_token_fees_collected = 0;
 
         global_state.market_count += 1;
 
@@ -47,32 +693,117 @@ pub mod betting_markets {
             market_id: market.market_id,
             authority: market.authority,
             question: market.question.clone(),
-            outcomes: outcomes,
+            outcomes,
             resolution_time,
+            external_id: market.external_id,
+            outcome_commitments: market.outcome_commitments.clone(),
+            outcome_pools: market.outcome_pools.clone(),
+            total_pool: market.total_pool,
+            category: market.category,
+            tags: market.tags.clone(),
+        });
+
+        if seed_total > 0 {
+            emit!(LiquiditySeeded {
+                market_id: market.market_id,
+                amounts: config.initial_liquidity,
+                total_pool: market.total_pool,
+            });
+        }
+
+        emit!(MarketListed {
+            market_id: market.market_id,
+            creator: market.authority,
+            creation_fee,
+            fee_waived,
+        });
+
+        Ok(MarketReceipt {
+            market: market.key(),
+            market_id: market.market_id,
+        })
+    }
+
+    /// Sets the flat fee `create_market` charges, paid into the `treasury` token account.
+    pub fn set_creation_fee(ctx: Context<UpdateGlobalState>, creation_fee: u64, treasury: Pubkey) -> Result<()> {
+        let global_state = &mut ctx.accounts.global_state;
+
+        require!(ctx.accounts.authority.key() == global_state.authority, ErrorCode::Unauthorized);
+
+        msg!("Creation fee {} -> {}", global_state.creation_fee, creation_fee);
+        global_state.creation_fee = creation_fee;
+        global_state.treasury = treasury;
+        Ok(())
+    }
+
+    /// Exempts a trusted `creator` from the creation fee by opening their waiver record.
+    pub fn waive_creation_fee(ctx: Context<WaiveCreationFee>, creator: Pubkey) -> Result<()> {
+        require!(
+            ctx.accounts.authority.key() == ctx.accounts.global_state.authority,
+            ErrorCode::Unauthorized
+        );
+
+        let waiver = &mut ctx.accounts.fee_waiver;
+        waiver.creator = creator;
+        waiver.granted_at = Clock::get()?.unix_timestamp;
+
+        emit!(CreationFeeWaived { creator });
+
+        Ok(())
+    }
+
+    /// Withdraws a creator's fee waiver by closing the record.
+    pub fn revoke_fee_waiver(ctx: Context<RevokeFeeWaiver>) -> Result<()> {
+        require!(
+            ctx.accounts.authority.key() == ctx.accounts.global_state.authority,
+            ErrorCode::Unauthorized
+        );
+
+        emit!(FeeWaiverRevoked {
+            creator: ctx.accounts.fee_waiver.creator,
         });
 
         Ok(())
     }
 
+    /// Opens a bet at the PDA for (`market`, `bettor`, `bet_nonce`); a bettor picks a fresh
+    /// nonce for each separate bet on the same market.
+    /// Adds the stake straight to its outcome's pool. Bets never rest on a book or match
+    /// against each other, so there is no maker or taker side to price differently.
     pub fn place_bet(
         ctx: Context<PlaceBet>,
         outcome_index: u8,
         amount: u64,
-    ) -> Result<()> {
+        bet_nonce: u64,
+    ) -> Result<BetReceipt> {
+        let market_key = ctx.accounts.market.key();
         let market = &mut ctx.accounts.market;
         
-        require!(!market.resolved, ErrorCode::MarketResolved);
-        require!(Clock::get()?.unix_timestamp < market.resolution_time, ErrorCode::BettingClosed);
-        require!(amount >= market.min_bet, ErrorCode::BetTooSmall);
-        require!((outcome_index as usize) < market.outcomes.len(), ErrorCode::InvalidOutcome);
+        require!(!ctx.accounts.global_state.paused, ErrorCode::PlatformPaused);
+        require!(!market.ranked, ErrorCode::RankedMarket);
+        require_token_market(market)?;
+        require_not_frozen(&ctx.accounts.bettor_freeze)?;
+        require_whitelisted(market, &ctx.accounts.whitelist_entry)?;
+        validate_new_stake(market, outcome_index, amount, &Clock::get()?)?;
+        require!(
+            !market.restrict_token_accounts
+                || market.allowed_token_accounts.contains(&ctx.accounts.bettor_token_account.key()),
+            ErrorCode::TokenAccountNotAllowed
+        );
+        reserve_house_cover(market, outcome_index, amount)?;
+        require_stake_vault(market, outcome_index, ctx.accounts.market_token_account.key())?;
 
         let bet = &mut ctx.accounts.bet;
         bet.bettor = ctx.accounts.bettor.key();
-        bet.market = ctx.accounts.market.key();
+        bet.market = market_key;
         bet.outcome_index = outcome_index;
         bet.amount = amount;
         bet.claimed = false;
         bet.timestamp = Clock::get()?.unix_timestamp;
+        bet.payout_capped = false;
+        bet.nonce = bet_nonce;
+        bet.ranking = Vec::new();
+        bet.underlying_amount = underlying_value(market, amount, bet.timestamp)?;
 
         // Transfer tokens from bettor to market pool
         token::transfer(
@@ -87,67 +818,214 @@ pub mod betting_markets {
             amount,
         )?;
 
-        // Update market pools
-        market.outcome_pools[outcome_index as usize] += amount;
-        market.total_pool += amount;
+        bet.weight = credit_stake(market, bet.bettor, outcome_index, amount, bet.timestamp)?;
+        count_bet(market, outcome_index);
 
         emit!(BetPlaced {
             bettor: bet.bettor,
             market_id: market.market_id,
             outcome_index,
             amount,
+            outcome_pools: market.outcome_pools.clone(),
+            total_pool: market.total_pool,
+            bet_counts: market.bet_counts.clone(),
+            total_bets: market.total_bets,
         });
 
-        Ok(())
+        if market.receipt.is_some() {
+            emit!(ReceiptStakeValued {
+                market_id: market.market_id,
+                bettor: bet.bettor,
+                amount,
+                rate: market.receipt_rate,
+                underlying_amount: bet.underlying_amount,
+            });
+        }
+
+        Ok(BetReceipt {
+            bet: bet.key(),
+            amount,
+        })
     }
 
-    pub fn resolve_market(
-        ctx: Context<ResolveMarket>,
-        winning_outcome_index: u8,
-    ) -> Result<()> {
+    /// Bets on a full predicted `ranking` of a ranked market's outcomes, best first. The stake
+    /// pools under the predicted winner and is paid by how close the ranking lands.
+    pub fn place_ranked_bet(
+        ctx: Context<PlaceRankedBet>,
+        ranking: Vec<u8>,
+        amount: u64,
+        bet_nonce: u64,
+    ) -> Result<BetReceipt> {
+        let market_key = ctx.accounts.market.key();
         let market = &mut ctx.accounts.market;
-        
-        require!(ctx.accounts.authority.key() == market.authority, ErrorCode::Unauthorized);
-        require!(!market.resolved, ErrorCode::MarketAlreadyResolved);
-        require!(Clock::get()?.unix_timestamp >= market.resolution_time, ErrorCode::TooEarlyToResolve);
-        require!((winning_outcome_index as usize) < market.outcomes.len(), ErrorCode::InvalidOutcome);
 
-        market.resolved = true;
-        market.winning_outcome = Some(winning_outcome_index);
+        require!(!ctx.accounts.global_state.paused, ErrorCode::PlatformPaused);
+        require!(market.ranked, ErrorCode::WrongMarketType);
+        require!(is_ranking(market, &ranking), ErrorCode::InvalidRanking);
+        let predicted_winner = ranking[0];
+        require_not_frozen(&ctx.accounts.bettor_freeze)?;
+        require_whitelisted(market, &ctx.accounts.whitelist_entry)?;
+        validate_new_stake(market, predicted_winner, amount, &Clock::get()?)?;
+        require!(
+            !market.restrict_token_accounts
+                || market.allowed_token_accounts.contains(&ctx.accounts.bettor_token_account.key()),
+            ErrorCode::TokenAccountNotAllowed
+        );
+        require_stake_vault(market, predicted_winner, ctx.accounts.market_token_account.key())?;
+
+        token::transfer(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.bettor_token_account.to_account_info(),
+                    to: ctx.accounts.market_token_account.to_account_info(),
+                    authority: ctx.accounts.bettor.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
+
+        let bet = &mut ctx.accounts.bet;
+        bet.bettor = ctx.accounts.bettor.key();
+        bet.market = market_key;
+        bet.outcome_index = predicted_winner;
+        bet.amount = amount;
+        bet.claimed = false;
+        bet.timestamp = Clock::get()?.unix_timestamp;
+        bet.payout_capped = false;
+        bet.nonce = bet_nonce;
+
+        bet.weight = credit_stake(market, bet.bettor, predicted_winner, amount, bet.timestamp)?;
+        count_bet(market, predicted_winner);
+        let outcomes = market.outcomes.len();
+        for (position, outcome) in ranking.iter().enumerate() {
+            let slot = *outcome as usize * outcomes + position;
+            market.rank_stakes[slot] = market.rank_stakes[slot]
+                .checked_add(amount)
+                .ok_or(ErrorCode::MathOverflow)?;
+        }
+        bet.ranking = ranking;
 
-        emit!(MarketResolved {
+        emit!(BetPlaced {
+            bettor: bet.bettor,
             market_id: market.market_id,
-            winning_outcome: winning_outcome_index,
-            winning_outcome_name: market.outcomes[winning_outcome_index as usize].clone(),
+            outcome_index: predicted_winner,
+            amount,
+            outcome_pools: market.outcome_pools.clone(),
+            total_pool: market.total_pool,
+            bet_counts: market.bet_counts.clone(),
+            total_bets: market.total_bets,
         });
 
-        Ok(())
+        Ok(BetReceipt {
+            bet: bet.key(),
+            amount,
+        })
     }
 
-    pub fn claim_payout(ctx: Context<ClaimPayout>) -> Result<()> {
-        let market = &ctx.accounts.market;
+    /// Tops up an existing open bet instead of opening a new bet account for the same outcome.
+    pub fn add_to_bet(ctx: Context<AddToBet>, amount: u64) -> Result<()> {
+        let market = &mut ctx.accounts.market;
         let bet = &mut ctx.accounts.bet;
-        
-        require!(market.resolved, ErrorCode::MarketNotResolved);
-        require!(!bet.claimed, ErrorCode::AlreadyClaimed);
+        let clock = Clock::get()?;
+
         require!(bet.bettor == ctx.accounts.bettor.key(), ErrorCode::Unauthorized);
-        
-        let winning_outcome = market.winning_outcome.unwrap();
-        require!(bet.outcome_index == winning_outcome, ErrorCode::LosingBet);
+        require!(bet.market == market.key(), ErrorCode::InvalidBet);
+        require!(!ctx.accounts.global_state.paused, ErrorCode::PlatformPaused);
+        require!(!market.ranked, ErrorCode::RankedMarket);
+        require_token_market(market)?;
+        require_not_frozen(&ctx.accounts.bettor_freeze)?;
+        require_whitelisted(market, &ctx.accounts.whitelist_entry)?;
+        validate_new_stake(market, bet.outcome_index, amount, &clock)?;
+        // The per-bet cap applies to the position as a whole, not just the top-up
+        if let Some(max_bet) = market.max_bet {
+            require!(bet.amount.saturating_add(amount) <= max_bet, ErrorCode::BetTooLarge);
+        }
+        require!(
+            !market.restrict_token_accounts
+                || market.allowed_token_accounts.contains(&ctx.accounts.bettor_token_account.key()),
+            ErrorCode::TokenAccountNotAllowed
+        );
+        reserve_house_cover(market, bet.outcome_index, amount)?;
+        require_stake_vault(market, bet.outcome_index, ctx.accounts.market_token_account.key())?;
+        let added_underlying = underlying_value(market, amount, clock.unix_timestamp)?;
 
-        // Calculate payout
-        let winning_pool = market.outcome_pools[winning_outcome as usize];
-        let payout = if winning_pool > 0 {
-            (bet.amount as u128 * market.total_pool as u128 / winning_pool as u128) as u64
-        } else {
-            0
-        };
+        token::transfer(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.bettor_token_account.to_account_info(),
+                    to: ctx.accounts.market_token_account.to_account_info(),
+                    authority: ctx.accounts.bettor.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
 
-        require!(payout > 0, ErrorCode::NoPayoutAvailable);
+        bet.amount = bet.amount.checked_add(amount).ok_or(ErrorCode::MathOverflow)?;
+        bet.underlying_amount += added_underlying;
+        let weight = credit_stake(market, bet.bettor, bet.outcome_index, amount, clock.unix_timestamp)?;
+        bet.weight = bet.weight.checked_add(weight).ok_or(ErrorCode::MathOverflow)?;
 
-        bet.claimed = true;
+        emit!(BetIncreased {
+            bettor: bet.bettor,
+            market_id: market.market_id,
+            outcome_index: bet.outcome_index,
+            added: amount,
+            new_amount: bet.amount,
+        });
+
+        if market.receipt.is_some() {
+            emit!(ReceiptStakeValued {
+                market_id: market.market_id,
+                bettor: bet.bettor,
+                amount,
+                rate: market.receipt_rate,
+                underlying_amount: added_underlying,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Withdraws a bet before betting closes, refunding its stake less `cancel_penalty_bps`.
+    /// The penalty stays in `total_pool` for the remaining bettors and the bet is closed.
+    pub fn cancel_bet(ctx: Context<CancelBet>) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        let bet = &ctx.accounts.bet;
+        let clock = Clock::get()?;
+
+        let penalty_bps = market.cancel_penalty_bps.ok_or(ErrorCode::CancellationDisabled)?;
+        require!(!bet.claimed, ErrorCode::AlreadyClaimed);
+        require!(!market.resolved, ErrorCode::MarketAlreadyResolved);
+        require!(!market.voided, ErrorCode::MarketVoided);
+        require!(!deadline_passed(market, &clock) && !cutoff_passed(market, &clock), ErrorCode::BettingClosed);
+        require_token_market(market)?;
+        require_not_frozen(&ctx.accounts.bettor_freeze)?;
+        let outcome = effective_outcome(market, bet.outcome_index);
+        require_stake_vault(market, outcome, ctx.accounts.market_token_account.key())?;
+
+        let penalty = to_u64(bet.amount as u128 * penalty_bps as u128 / 10_000)?;
+        let refund = bet.amount - penalty;
+        let idx = outcome as usize;
+        market.outcome_pools[idx] = market.outcome_pools[idx]
+            .checked_sub(bet.amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        market.total_pool = market.total_pool.checked_sub(refund).ok_or(ErrorCode::MathOverflow)?;
+        // The breaker's window baseline may include this stake and must not exceed the pool
+        market.breaker_window_pool = market.breaker_window_pool.saturating_sub(refund);
+        if market.weighted {
+            market.weighted_pools[idx] = market.weighted_pools[idx]
+                .checked_sub(bet.weight)
+                .ok_or(ErrorCode::MathOverflow)?;
+        }
+        if bet.bettor == market.authority {
+            market.authority_stakes[idx] = market.authority_stakes[idx].saturating_sub(bet.amount);
+        }
+        market.bet_counts[idx] = market.bet_counts[idx].saturating_sub(1);
+        market.total_bets = market.total_bets.saturating_sub(1);
+        refresh_active(market);
 
-        // Transfer payout to bettor
         token::transfer(
             CpiContext::new_with_signer(
                 ctx.accounts.token_program.to_account_info(),
@@ -158,165 +1036,7675 @@ pub mod betting_markets {
                 },
                 &[&[
                     b"market",
-                    &market.market_id.to_le_bytes(),
+                    &ctx.accounts.market.market_id.to_le_bytes(),
                     &[ctx.bumps.market],
                 ]],
             ),
-            payout,
+            refund,
         )?;
 
-        emit!(PayoutClaimed {
+        let market = &ctx.accounts.market;
+        emit!(BetCancelled {
             bettor: bet.bettor,
             market_id: market.market_id,
-            bet_amount: bet.amount,
-            payout_amount: payout,
+            outcome_index: bet.outcome_index,
+            amount: bet.amount,
+            refund,
+            penalty,
+            outcome_pools: market.outcome_pools.clone(),
+            total_pool: market.total_pool,
         });
 
         Ok(())
     }
-}
-
-#[derive(Accounts)]
-pub struct Initialize<'info> {
-    #[account(
-        init,
-        payer = authority,
-        space = 8 + GlobalState::INIT_SPACE,
-        seeds = [b"global_state"],
-        bump
-    )]
-    pub global_state: Account<'info, GlobalState>,
-    #[account(mut)]
-    pub authority: Signer<'info>,
-    pub system_program: Program<'info, System>,
-}
 
-#[derive(Accounts)]
-#[instruction(question: String)]
-pub struct CreateMarket<'info> {
-    #[account(
-        init,
-        payer = authority,
-        space = 8 + Market::INIT_SPACE + question.len() + 200, // Extra space for outcomes
-        seeds = [b"market", &global_state.market_count.to_le_bytes()],
-        bump
-    )]
-    pub market: Account<'info, Market>,
-    #[account(mut)]
-    pub global_state: Account<'info, GlobalState>,
-    #[account(mut)]
-    pub authority: Signer<'info>,
-    pub system_program: Program<'info, System>,
-}
+    /// Stakes `amount` into the bettor's `UserPosition` on `outcome_index`, opening it on the
+    /// first call. Repeat bets pay no new rent and settle with one `claim_position`, but they
+    /// share a single timestamp and can't register for a payout cap, so capped and
+    /// time-weighted markets only take per-bet `place_bet` stakes. Returns the position and the amount just staked.
+    pub fn place_position_bet(
+        ctx: Context<PlacePositionBet>,
+        outcome_index: u8,
+        amount: u64,
+    ) -> Result<BetReceipt> {
+        let market_key = ctx.accounts.market.key();
+        let market = &mut ctx.accounts.market;
+        let clock = Clock::get()?;
 
-#[derive(Accounts)]
-pub struct PlaceBet<'info> {
-    #[account(
-        init,
-        payer = bettor,
-        space = 8 + Bet::INIT_SPACE,
-    )]
-    pub bet: Account<'info, Bet>,
-    #[account(mut)]
-    pub market: Account<'info, Market>,
-    #[account(mut)]
-    pub bettor: Signer<'info>,
-    #[account(mut)]
-    pub bettor_token_account: Account<'info, TokenAccount>,
-    #[account(mut)]
-    pub market_token_account: Account<'info, TokenAccount>,
-    pub token_program: Program<'info, Token>,
-    pub system_program: Program<'info, System>,
-}
+        require!(!ctx.accounts.global_state.paused, ErrorCode::PlatformPaused);
+        require!(!market.ranked, ErrorCode::RankedMarket);
+        require!(market.max_payout_share_bps == 0 && !market.weighted, ErrorCode::PositionsUnsupported);
+        require_token_market(market)?;
+        require_not_frozen(&ctx.accounts.bettor_freeze)?;
+        require_whitelisted(market, &ctx.accounts.whitelist_entry)?;
+        validate_new_stake(market, outcome_index, amount, &clock)?;
+        let position = &mut ctx.accounts.position;
+        // The per-bet cap applies to the position as a whole, as with `add_to_bet`
+        if let Some(max_bet) = market.max_bet {
+            require!(position.total_amount.saturating_add(amount) <= max_bet, ErrorCode::BetTooLarge);
+        }
+        require!(
+            !market.restrict_token_accounts
+                || market.allowed_token_accounts.contains(&ctx.accounts.bettor_token_account.key()),
+            ErrorCode::TokenAccountNotAllowed
+        );
+        reserve_house_cover(market, outcome_index, amount)?;
+        require_stake_vault(market, outcome_index, ctx.accounts.market_token_account.key())?;
 
-#[derive(Accounts)]
-pub struct ResolveMarket<'info> {
-    #[account(mut)]
-    pub market: Account<'info, Market>,
-    pub authority: Signer<'info>,
-}
+        token::transfer(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.bettor_token_account.to_account_info(),
+                    to: ctx.accounts.market_token_account.to_account_info(),
+                    authority: ctx.accounts.bettor.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
 
-#[derive(Accounts)]
-pub struct ClaimPayout<'info> {
-    #[account(mut)]
-    pub bet: Account<'info, Bet>,
-    pub market: Account<'info, Market>,
-    #[account(mut)]
-    pub bettor: Signer<'info>,
-    #[account(mut)]
-    pub bettor_token_account: Account<'info, TokenAccount>,
-    #[account(mut)]
-    pub market_token_account: Account<'info, TokenAccount>,
-    pub token_program: Program<'info, Token>,
-}
+        let opened = position.market == Pubkey::default();
+        if opened {
+            position.market = market_key;
+            position.bettor = ctx.accounts.bettor.key();
+            position.outcome_index = outcome_index;
+        }
+        position.total_amount = position.total_amount.checked_add(amount).ok_or(ErrorCode::MathOverflow)?;
+        position.bet_count += 1;
+        position.last_bet_at = clock.unix_timestamp;
 
-#[account]
-#[derive(InitSpace)]
-pub struct GlobalState {
-    pub authority: Pubkey,
-    pub market_count: u64,
-}
+        credit_stake(market, position.bettor, outcome_index, amount, clock.unix_timestamp)?;
+        // A position is one account to claim, so it counts as one bet against `claimed_count`
+        if opened {
+            count_bet(market, outcome_index);
+        }
 
-#[account]
-#[derive(InitSpace)]
-pub struct Market {
+        emit!(BetPlaced {
+            bettor: position.bettor,
+            market_id: market.market_id,
+            outcome_index,
+            amount,
+            outcome_pools: market.outcome_pools.clone(),
+            total_pool: market.total_pool,
+            bet_counts: market.bet_counts.clone(),
+            total_bets: market.total_bets,
+        });
+
+        Ok(BetReceipt {
+            bet: position.key(),
+            amount,
+        })
+    }
+
+    /// Settles a standard market on `winning_outcome_index`. Optionally sets aside
+    /// `consolation_bps` of the pool for bettors on `runner_up_index`; winners are always
+    /// left at least their stake. `evidence` holds one hash per outcome justifying its result,
+    /// required on markets created with `require_outcome_evidence` and optional elsewhere.
+    /// Signed by the market's `oracle` when it has one, otherwise by the authority.
+    pub fn resolve_market(
+        ctx: Context<ResolveMarket>,
+        winning_outcome_index: u8,
+        runner_up_index: Option<u8>,
+        consolation_bps: u16,
+        evidence: Vec<[u8; 32]>,
+    ) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        let resolver = ctx.accounts.authority.key();
+        
+        validate_standard_resolution(market, winning_outcome_index, resolver, &evidence, &Clock::get()?)?;
+        require!(!market.scored_resolution, ErrorCode::ScoredMarket);
+        require!(!market.ranked, ErrorCode::RankedMarket);
+        require!(market.resolution_account.is_none(), ErrorCode::AccountResolvedMarket);
+        require!(market.challenge_period == 0, ErrorCode::ProposalRequired);
+        market.outcome_evidence = evidence;
+
+        if let Some(runner_up) = runner_up_index {
+            let consolation_pool = (market.total_pool as u128 * consolation_bps as u128 / 10_000) as u64;
+            let winning_pool = market.outcome_pools[winning_outcome_index as usize];
+            require!(
+                runner_up != winning_outcome_index
+                    && (runner_up as usize) < market.outcomes.len()
+                    && market.merged_into[runner_up as usize].is_none()
+                    && market.outcome_pools[runner_up as usize] > 0,
+                ErrorCode::InvalidConsolation
+            );
+            require!(
+                consolation_pool > 0 && consolation_pool <= market.total_pool - winning_pool,
+                ErrorCode::InvalidConsolation
+            );
+            // Caps are computed against the whole pool, so the two don't combine
+            require!(market.max_payout_share_bps == 0, ErrorCode::InvalidConsolation);
+            require!(
+                market.market_type == MarketType::Standard && !market.weighted,
+                ErrorCode::InvalidConsolation
+            );
+
+            market.runner_up = Some(runner_up);
+            market.consolation_pool = consolation_pool;
+
+            emit!(ConsolationAwarded {
+                market_id: market.market_id,
+                runner_up,
+                consolation_pool,
+            });
+        } else {
+            require!(consolation_bps == 0, ErrorCode::InvalidConsolation);
+        }
+
+        apply_resolution(market, winning_outcome_index, resolver, Clock::get()?.unix_timestamp)?;
+        write_resolved_outcome(&mut ctx.accounts.resolved_outcome, market, Clock::get()?.unix_timestamp);
+
+        Ok(())
+    }
+
+    /// Puts `outcome_index` forward as the result of a market with a challenge period. The
+    /// market stays unresolved until `finalize_resolution`; proposing again before then
+    /// replaces the outcome and restarts the window.
+    pub fn propose_resolution(
+        ctx: Context<ProposeResolution>,
+        outcome_index: u8,
+        evidence: Vec<[u8; 32]>,
+    ) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        let proposer = ctx.accounts.authority.key();
+        let clock = Clock::get()?;
+
+        require!(market.challenge_period > 0, ErrorCode::WrongMarketType);
+        validate_standard_resolution(market, outcome_index, proposer, &evidence, &clock)?;
+        // Once a proposal has stood its full window only finalizing remains
+        require!(
+            market.proposed_outcome.is_none() || clock.unix_timestamp < market.proposed_at + market.challenge_period,
+            ErrorCode::ChallengePeriodEnded
+        );
+
+        market.proposed_outcome = Some(outcome_index);
+        market.proposed_at = clock.unix_timestamp;
+        market.outcome_evidence = evidence;
+
+        emit!(ResolutionProposed {
+            market_id: market.market_id,
+            proposed_outcome: outcome_index,
+            proposed_by: proposer,
+            finalizes_at: market.proposed_at + market.challenge_period,
+        });
+
+        Ok(())
+    }
+
+    /// Permissionlessly resolves a market to its proposed outcome once the challenge period
+    /// has run without a new proposal.
+    pub fn finalize_resolution(ctx: Context<FinalizeResolution>) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        let now = Clock::get()?.unix_timestamp;
+
+        require!(market.challenge_period > 0, ErrorCode::WrongMarketType);
+        require!(!market.resolved, ErrorCode::MarketAlreadyResolved);
+        require!(!market.voided, ErrorCode::MarketVoided);
+        let outcome_index = market.proposed_outcome.ok_or(ErrorCode::NoResolutionProposed)?;
+        require!(now >= market.proposed_at + market.challenge_period, ErrorCode::ChallengePeriodActive);
+
+        let resolver = outcome_resolver(market, outcome_index);
+        apply_resolution(market, outcome_index, resolver, now)?;
+        write_resolved_outcome(&mut ctx.accounts.resolved_outcome, market, now);
+
+        Ok(())
+    }
+
+    /// Records a committee member's vote for `outcome_index`. Each member votes once; the
+    /// vote that brings an outcome to `resolution_threshold` resolves the market on it.
+    pub fn submit_resolution_vote(ctx: Context<SubmitResolutionVote>, outcome_index: u8) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        let voter = ctx.accounts.voter.key();
+        let clock = Clock::get()?;
+
+        let seat = market
+            .resolvers
+            .iter()
+            .position(|resolver| *resolver == voter)
+            .ok_or(ErrorCode::NotCommitteeMember)?;
+        validate_resolvable(market, outcome_index, &[], &clock)?;
+        require!(market.resolution_votes[seat].is_none(), ErrorCode::AlreadyVoted);
+
+        market.resolution_votes[seat] = Some(outcome_index);
+        let votes = market
+            .resolution_votes
+            .iter()
+            .filter(|vote| **vote == Some(outcome_index))
+            .count() as u8;
+
+        emit!(ResolutionVoteSubmitted {
+            market_id: market.market_id,
+            voter,
+            outcome_index,
+            votes,
+            threshold: market.resolution_threshold,
+        });
+
+        if votes >= market.resolution_threshold {
+            apply_resolution(market, outcome_index, voter, clock.unix_timestamp)?;
+            write_resolved_outcome(&mut ctx.accounts.resolved_outcome, market, clock.unix_timestamp);
+        }
+
+        Ok(())
+    }
+
+    /// Settles a scored market by giving every outcome an accuracy score from 0 to 100. Each
+    /// bet is paid its share of the whole pool weighted by `stake * score`, so the payouts
+    /// sum to the pool. The top-scoring outcome is recorded as `winning_outcome`.
+    pub fn resolve_market_scored(
+        ctx: Context<ResolveMarket>,
+        scores: Vec<u8>,
+        evidence: Vec<[u8; 32]>,
+    ) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        let resolver = ctx.accounts.authority.key();
+        let clock = Clock::get()?;
+
+        require!(market.scored_resolution, ErrorCode::WrongMarketType);
+        require!(
+            scores.len() == market.outcomes.len() && scores.iter().all(|score| *score <= MAX_OUTCOME_SCORE),
+            ErrorCode::InvalidScores
+        );
+        // Bets on a merged outcome are scored as bets on the outcome it was merged into
+        require!(
+            scores.iter().zip(market.merged_into.iter()).all(|(score, target)| target.is_none() || *score == 0),
+            ErrorCode::InvalidScores
+        );
+
+        let top_outcome = (0..scores.len())
+            .max_by_key(|index| (scores[*index], std::cmp::Reverse(*index)))
+            .unwrap() as u8;
+        validate_standard_resolution(market, top_outcome, resolver, &evidence, &clock)?;
+
+        market.outcome_scores = scores;
+        require!(scored_weight(market) > 0, ErrorCode::InvalidScores);
+        market.outcome_evidence = evidence;
+
+        emit!(MarketScored {
+            market_id: market.market_id,
+            scores: market.outcome_scores.clone(),
+        });
+
+        apply_resolution(market, top_outcome, resolver, clock.unix_timestamp)?;
+        write_resolved_outcome(&mut ctx.accounts.resolved_outcome, market, clock.unix_timestamp);
+
+        Ok(())
+    }
+
+    /// Settles a ranked market on the full `ranking` of its outcomes, best first. Each bet
+    /// scores the maximum rank distance less the distance between its ranking and this one,
+    /// and is paid its share of the whole pool weighted by `stake * score`.
+    pub fn resolve_ranked(ctx: Context<ResolveMarket>, ranking: Vec<u8>, evidence: Vec<[u8; 32]>) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        let resolver = ctx.accounts.authority.key();
+        let clock = Clock::get()?;
+
+        require!(market.ranked, ErrorCode::WrongMarketType);
+        require!(is_ranking(market, &ranking), ErrorCode::InvalidRanking);
+        let winner = ranking[0];
+        validate_standard_resolution(market, winner, resolver, &evidence, &clock)?;
+
+        market.final_ranking = ranking;
+        market.outcome_evidence = evidence;
+
+        emit!(MarketRanked {
+            market_id: market.market_id,
+            ranking: market.final_ranking.clone(),
+        });
+
+        apply_resolution(market, winner, resolver, clock.unix_timestamp)?;
+        write_resolved_outcome(&mut ctx.accounts.resolved_outcome, market, clock.unix_timestamp);
+
+        Ok(())
+    }
+
+    /// Permissionlessly settles a market tied to another program's state. Reads
+    /// `expected_bytes.len()` bytes of the configured account at `resolution_offset`: the first
+    /// outcome wins if they equal `expected_bytes`, the second otherwise.
+    pub fn resolve_from_account(ctx: Context<ResolveFromAccount>) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        let clock = Clock::get()?;
+
+        let source = market.resolution_account.ok_or(ErrorCode::WrongMarketType)?;
+        require!(ctx.accounts.source_account.key() == source, ErrorCode::InvalidResolutionSource);
+
+        let data = ctx.accounts.source_account.try_borrow_data()?;
+        let start = market.resolution_offset as usize;
+        let observed = data
+            .get(start..start + market.expected_bytes.len())
+            .ok_or(ErrorCode::ResolutionDataOutOfRange)?;
+        let matched = observed == market.expected_bytes.as_slice();
+        drop(data);
+
+        let winning_outcome_index = if matched { 0 } else { 1 };
+        // The account read stands in for the resolver's signature
+        let resolver = outcome_resolver(market, winning_outcome_index);
+        validate_standard_resolution(market, winning_outcome_index, resolver, &[], &clock)?;
+
+        emit!(AccountResolutionRead {
+            market_id: market.market_id,
+            source_account: source,
+            matched,
+        });
+
+        apply_resolution(market, winning_outcome_index, ctx.accounts.caller.key(), clock.unix_timestamp)?;
+        write_resolved_outcome(&mut ctx.accounts.resolved_outcome, market, clock.unix_timestamp);
+
+        Ok(())
+    }
+
+    pub fn resolve_pivot_market(
+        ctx: Context<ResolveMarket>,
+        actual_value: i64,
+    ) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+
+        require!(ctx.accounts.authority.key() == market_resolver(market), ErrorCode::Unauthorized);
+        require!(!market.resolved, ErrorCode::MarketAlreadyResolved);
+        require!(!market.voided, ErrorCode::MarketVoided);
+        require!(deadline_passed(market, &Clock::get()?), ErrorCode::TooEarlyToResolve);
+        require!(market.market_type == MarketType::Pivot, ErrorCode::WrongMarketType);
+        require!(market.scheduled_oracle.is_none(), ErrorCode::ScheduledResolutionOnly);
+        require!(
+            funded_outcomes(market) >= market.min_diverse_outcomes as usize,
+            ErrorCode::InsufficientDiversity
+        );
+        require!(market.twap.is_none(), ErrorCode::TwapResolutionOnly);
+
+        settle_pivot(market, actual_value, ctx.accounts.authority.key(), Clock::get()?.unix_timestamp)?;
+        write_resolved_outcome(&mut ctx.accounts.resolved_outcome, market, Clock::get()?.unix_timestamp);
+
+        Ok(())
+    }
+
+    /// Settles a scalar market on `value`, clamped to the market's bounds. Long holders split
+    /// `total_pool * (value - lower) / (upper - lower)` and short holders the rest; a side nobody
+    /// backed passes its share to the other.
+    pub fn resolve_market_scalar(ctx: Context<ResolveMarket>, value: i64) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        let clock = Clock::get()?;
+
+        require!(ctx.accounts.authority.key() == market_resolver(market), ErrorCode::Unauthorized);
+        require!(!market.resolved, ErrorCode::MarketAlreadyResolved);
+        require!(!market.voided, ErrorCode::MarketVoided);
+        require!(deadline_passed(market, &clock), ErrorCode::TooEarlyToResolve);
+        require!(market.market_type == MarketType::Scalar, ErrorCode::WrongMarketType);
+        require!(market.twap.is_none(), ErrorCode::TwapResolutionOnly);
+        require!(
+            funded_outcomes(market) >= market.min_diverse_outcomes as usize,
+            ErrorCode::InsufficientDiversity
+        );
+
+        settle_scalar(market, value, ctx.accounts.authority.key(), clock.unix_timestamp)?;
+        write_resolved_outcome(&mut ctx.accounts.resolved_outcome, market, clock.unix_timestamp);
+
+        Ok(())
+    }
+
+    /// Settles the first `preliminary_tranche_bps` of a tranche scalar market's pool on a
+    /// preliminary `value`, which winners can take with `claim_preliminary_tranche` while the
+    /// resolver waits to confirm the final value. Reported once, by the market's resolver.
+    pub fn report_preliminary_value(ctx: Context<ReportPreliminaryValue>, value: i64) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        let clock = Clock::get()?;
+
+        require!(ctx.accounts.authority.key() == market_resolver(market), ErrorCode::Unauthorized);
+        require!(market.preliminary_tranche_bps > 0, ErrorCode::WrongMarketType);
+        require!(market.preliminary_value.is_none(), ErrorCode::PreliminaryValueReported);
+        require!(!market.resolved, ErrorCode::MarketAlreadyResolved);
+        require!(!market.voided, ErrorCode::MarketVoided);
+        require!(deadline_passed(market, &clock), ErrorCode::TooEarlyToResolve);
+        require!(market.total_pool > 0, ErrorCode::EmptyMarket);
+
+        let value = value.clamp(market.scalar_lower_bound, market.scalar_upper_bound);
+        let tranche_pool = preliminary_tranche_pool(market);
+        let long_share = scalar_long_share(market, tranche_pool, value)?;
+        market.preliminary_value = Some(value);
+        market.preliminary_long_share = long_share;
+
+        emit!(PreliminaryValueReported {
+            market_id: market.market_id,
+            value,
+            tranche_pool,
+            long_share,
+            short_share: tranche_pool - long_share,
+        });
+
+        Ok(())
+    }
+
+    /// Pays a bet its share of the preliminary tranche, net of fees, ahead of final
+    /// resolution. The bet stays open; its final claim pays the rest.
+    pub fn claim_preliminary_tranche(ctx: Context<ClaimPayoutPartial>) -> Result<u64> {
+        let market = &mut ctx.accounts.market;
+        let bet = &mut ctx.accounts.bet;
+
+        require!(market.preliminary_value.is_some(), ErrorCode::NoPreliminaryValue);
+        require!(!market.resolved, ErrorCode::MarketAlreadyResolved);
+        require!(!market.voided, ErrorCode::MarketVoided);
+        require!(!bet.tranche_claimed, ErrorCode::AlreadyClaimed);
+        require!(bet.bettor == ctx.accounts.bettor.key(), ErrorCode::Unauthorized);
+        require_combined_vault(market)?;
+        require_not_frozen(&ctx.accounts.bettor_freeze)?;
+
+        let outcome = effective_outcome(market, bet.outcome_index);
+        let side_share = if outcome == SCALAR_LONG {
+            market.preliminary_long_share
+        } else {
+            preliminary_tranche_pool(market) - market.preliminary_long_share
+        };
+        let pool = market.outcome_pools[outcome as usize];
+        let gross = to_u64(bet.amount as u128 * side_share as u128 / pool as u128)?;
+        require!(gross > 0, ErrorCode::LosingBet);
+        let (net, fee_amount, creator_fee_amount) = take_fees(market, ctx.accounts.global_state.fee_bps, gross);
+
+        market.winners_paid += 1;
+        market.winnings_paid += net;
+        bet.tranche_claimed = true;
+        bet.tranche_payout = gross;
+        charge_fee_token(
+            market,
+            fee_amount,
+            ctx.accounts.fee_payer_token_account.as_ref(),
+            ctx.accounts.fee_treasury.as_ref(),
+            ctx.accounts.fee_rate_source.as_ref(),
+            &ctx.accounts.bettor,
+            &ctx.accounts.token_program,
+        )?;
+        let market = &ctx.accounts.market;
+
+        token::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.market_token_account.to_account_info(),
+                    to: ctx.accounts.bettor_token_account.to_account_info(),
+                    authority: ctx.accounts.market.to_account_info(),
+                },
+                &[&[
+                    b"market",
+                    &market.market_id.to_le_bytes(),
+                    &[ctx.bumps.market],
+                ]],
+            ),
+            net,
+        )?;
+
+        emit!(PreliminaryTrancheClaimed {
+            bettor: bet.bettor,
+            market_id: market.market_id,
+            bet_amount: bet.amount,
+            payout_amount: net,
+            fee_amount,
+            creator_fee_amount,
+        });
+
+        Ok(net)
+    }
+
+    /// Settles a pivot market committed to a scheduled reading. Only the committed oracle can
+    /// sign, and only within `scheduled_window` seconds of `scheduled_time`. If the window is
+    /// missed the market can only be quarantined into refunds.
+    pub fn resolve_at_scheduled_time(ctx: Context<ResolveMarket>, reading: i64) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        let now = Clock::get()?.unix_timestamp;
+
+        let oracle = market.scheduled_oracle.ok_or(ErrorCode::NoScheduledResolution)?;
+        require!(ctx.accounts.authority.key() == oracle, ErrorCode::Unauthorized);
+        require!(!market.resolved, ErrorCode::MarketAlreadyResolved);
+        require!(!market.voided, ErrorCode::MarketVoided);
+        require!(
+            now >= market.scheduled_time && now < market.scheduled_time + market.scheduled_window,
+            ErrorCode::OutsideScheduledWindow
+        );
+        require!(
+            funded_outcomes(market) >= market.min_diverse_outcomes as usize,
+            ErrorCode::InsufficientDiversity
+        );
+
+        settle_pivot(market, reading, oracle, now)?;
+        write_resolved_outcome(&mut ctx.accounts.resolved_outcome, market, now);
+
+        Ok(())
+    }
+
+    /// On a market with per-outcome vaults, `remaining_accounts` lists every outcome vault in
+    /// order and the payout is drawn from them in proportion to their balances.
+    /// Returns the payout net of the protocol fee, whether sent now or held in escrow; after
+    /// `claim_payout_partial` that's whatever the partial claims left.
+    pub fn claim_payout<'info>(ctx: Context<'_, '_, 'info, 'info, ClaimPayout<'info>>) -> Result<u64> {
+        let market = &mut ctx.accounts.market;
+        let bet = &mut ctx.accounts.bet;
+        
+        require!(market.resolved, ErrorCode::MarketNotResolved);
+        require!(!bet.claimed, ErrorCode::AlreadyClaimed);
+        require!(bet.bettor == ctx.accounts.bettor.key(), ErrorCode::Unauthorized);
+        require_token_market(market)?;
+        require_not_frozen(&ctx.accounts.bettor_freeze)?;
+        
+        let now = Clock::get()?.unix_timestamp;
+        require_veto_window_closed(market, now)?;
+        require_claim_deadline_open(market, now)?;
+        let (payout, fee_amount, creator_fee_amount) =
+            settle_bet_payout(market, bet, ctx.accounts.global_state.fee_bps, now)?;
+        market.claimed_count += 1;
+
+        bet.claimed = true;
+        let market = &mut ctx.accounts.market;
+        market.winnings_paid += payout;
+        charge_fee_token(
+            market,
+            fee_amount,
+            ctx.accounts.fee_payer_token_account.as_ref(),
+            ctx.accounts.fee_treasury.as_ref(),
+            ctx.accounts.fee_rate_source.as_ref(),
+            &ctx.accounts.bettor,
+            &ctx.accounts.token_program,
+        )?;
+        let market = &ctx.accounts.market;
+
+        if market.withdrawal_delay > 0 {
+            // Hold the winnings; the tokens stay in the vault until `withdraw_escrow`
+            let escrow = ctx.accounts.escrow.as_mut().ok_or(ErrorCode::EscrowAccountRequired)?;
+            escrow.bettor = bet.bettor;
+            escrow.market = market.key();
+            escrow.amount += payout;
+            escrow.release_at = now + market.withdrawal_delay;
+
+            emit!(PayoutClaimed {
+                bettor: bet.bettor,
+                market_id: market.market_id,
+                bet_amount: bet.amount,
+                payout_amount: payout,
+                fee_amount,
+                creator_fee_amount,
+            });
+
+            emit!(PayoutEscrowed {
+                bettor: bet.bettor,
+                market_id: market.market_id,
+                amount: payout,
+                escrow_balance: escrow.amount,
+                release_at: escrow.release_at,
+            });
+
+            return Ok(payout);
+        }
+
+        let market_id_bytes = market.market_id.to_le_bytes();
+        let bump = [ctx.bumps.market];
+        let signer_seeds: &[&[u8]] = &[b"market", &market_id_bytes, &bump];
+
+        if market.outcome_vaults.is_empty() {
+            // Transfer payout to bettor
+            token::transfer(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    Transfer {
+                        from: ctx.accounts.market_token_account.to_account_info(),
+                        to: ctx.accounts.bettor_token_account.to_account_info(),
+                        authority: ctx.accounts.market.to_account_info(),
+                    },
+                    &[signer_seeds],
+                ),
+                payout,
+            )?;
+        } else {
+            transfer_from_outcome_vaults(
+                market,
+                ctx.remaining_accounts,
+                &ctx.accounts.bettor_token_account.to_account_info(),
+                &ctx.accounts.token_program.to_account_info(),
+                signer_seeds,
+                payout,
+            )?;
+        }
+
+        emit!(PayoutClaimed {
+            bettor: bet.bettor,
+            market_id: market.market_id,
+            bet_amount: bet.amount,
+            payout_amount: payout,
+            fee_amount,
+            creator_fee_amount,
+        });
+
+        // Valued at the last rate read, however old: a stale rate never holds up a claim
+        if market.receipt.is_some() {
+            emit!(ReceiptPayoutValued {
+                market_id: market.market_id,
+                bettor: bet.bettor,
+                payout_amount: payout,
+                rate: market.receipt_rate,
+                rate_updated_at: market.receipt_rate_at,
+                underlying_amount: receipt_underlying(payout, market.receipt_rate)?,
+                staked_underlying: bet.underlying_amount,
+            });
+        }
+
+        Ok(payout)
+    }
+
+    /// Claims up to `requested_amount` of a winning bet's payout, net of fees, leaving the rest
+    /// for later calls or `claim_payout`. Fees on the whole payout are booked by the first claim;
+    /// the bet closes once `claimed_amount` reaches `payout_due`. Split markets pass their
+    /// outcome vaults in `remaining_accounts` as in `claim_payout`. Returns the amount sent.
+    pub fn claim_payout_partial<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ClaimPayoutPartial<'info>>,
+        requested_amount: u64,
+    ) -> Result<u64> {
+        let market = &mut ctx.accounts.market;
+        let bet = &mut ctx.accounts.bet;
+
+        require!(market.resolved, ErrorCode::MarketNotResolved);
+        require!(!bet.claimed, ErrorCode::AlreadyClaimed);
+        require!(bet.bettor == ctx.accounts.bettor.key(), ErrorCode::Unauthorized);
+        require!(market.withdrawal_delay == 0, ErrorCode::WithdrawalDelayActive);
+        require_token_market(market)?;
+        require_not_frozen(&ctx.accounts.bettor_freeze)?;
+
+        let now = Clock::get()?.unix_timestamp;
+        require_veto_window_closed(market, now)?;
+        require_claim_deadline_open(market, now)?;
+        let (remaining, fee_amount, creator_fee_amount) =
+            settle_bet_payout(market, bet, ctx.accounts.global_state.fee_bps, now)?;
+        let amount = remaining.min(requested_amount);
+        require!(amount > 0, ErrorCode::NoPayoutAvailable);
+
+        bet.claimed_amount += amount;
+        market.winnings_paid += amount;
+        let fully_claimed = bet.claimed_amount == bet.payout_due;
+        if fully_claimed {
+            bet.claimed = true;
+            market.claimed_count += 1;
+        }
+        charge_fee_token(
+            market,
+            fee_amount,
+            ctx.accounts.fee_payer_token_account.as_ref(),
+            ctx.accounts.fee_treasury.as_ref(),
+            ctx.accounts.fee_rate_source.as_ref(),
+            &ctx.accounts.bettor,
+            &ctx.accounts.token_program,
+        )?;
+        let market = &ctx.accounts.market;
+
+        let market_id_bytes = market.market_id.to_le_bytes();
+        let bump = [ctx.bumps.market];
+        let signer_seeds: &[&[u8]] = &[b"market", &market_id_bytes, &bump];
+
+        if market.outcome_vaults.is_empty() {
+            token::transfer(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    Transfer {
+                        from: ctx.accounts.market_token_account.to_account_info(),
+                        to: ctx.accounts.bettor_token_account.to_account_info(),
+                        authority: ctx.accounts.market.to_account_info(),
+                    },
+                    &[signer_seeds],
+                ),
+                amount,
+            )?;
+        } else {
+            transfer_from_outcome_vaults(
+                market,
+                ctx.remaining_accounts,
+                &ctx.accounts.bettor_token_account.to_account_info(),
+                &ctx.accounts.token_program.to_account_info(),
+                signer_seeds,
+                amount,
+            )?;
+        }
+
+        let bet = &ctx.accounts.bet;
+        emit!(PayoutPartiallyClaimed {
+            bettor: bet.bettor,
+            market_id: market.market_id,
+            bet_amount: bet.amount,
+            amount,
+            claimed_amount: bet.claimed_amount,
+            payout_due: bet.payout_due,
+            fee_amount,
+            creator_fee_amount,
+        });
+
+        if fully_claimed {
+            ctx.accounts.bet.close(ctx.accounts.bettor.to_account_info())?;
+        }
+
+        Ok(amount)
+    }
+
+    /// Claims up to `MAX_CLAIM_BATCH` of the signer's winning bets on one market with a single
+    /// transfer. `remaining_accounts` lists the bet accounts; bets already claimed, including
+    /// ones a previous batch closed, are skipped so a batch can be resent. Returns the total
+    /// paid, net of fees.
+    pub fn claim_payouts_batch<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ClaimPayoutsBatch<'info>>,
+    ) -> Result<u64> {
+        let market = &ctx.accounts.market;
+
+        require!(market.resolved, ErrorCode::MarketNotResolved);
+        require_token_market(market)?;
+        require_combined_vault(market)?;
+        require!(market.withdrawal_delay == 0, ErrorCode::WithdrawalDelayActive);
+        require_not_frozen(&ctx.accounts.bettor_freeze)?;
+        require!(ctx.remaining_accounts.len() <= MAX_CLAIM_BATCH, ErrorCode::BatchTooLarge);
+
+        let now = Clock::get()?.unix_timestamp;
+        require_veto_window_closed(market, now)?;
+        require_claim_deadline_open(market, now)?;
+
+        let fee_bps = ctx.accounts.global_state.fee_bps;
+        let market_key = market.key();
+        let bettor = ctx.accounts.bettor.key();
+        let mut claimed_count: u32 = 0;
+        let mut total_payout: u64 = 0;
+        let mut total_fee: u64 = 0;
+
+        for info in ctx.remaining_accounts.iter() {
+            if info.data_is_empty() {
+                continue;
+            }
+            let mut bet = Account::<Bet>::try_from(info)?;
+
+            require!(bet.market == market_key, ErrorCode::InvalidBet);
+            require!(bet.bettor == bettor, ErrorCode::Unauthorized);
+            if bet.claimed {
+                continue;
+            }
+
+            // Fails with LosingBet for anything that isn't a winning position
+            let (payout, fee_amount, creator_fee_amount) =
+                settle_bet_payout(&mut ctx.accounts.market, &mut bet, fee_bps, now)?;
+            ctx.accounts.market.claimed_count += 1;
+
+            emit!(PayoutClaimed {
+                bettor,
+                market_id: ctx.accounts.market.market_id,
+                bet_amount: bet.amount,
+                payout_amount: payout,
+                fee_amount,
+                creator_fee_amount,
+            });
+
+            claimed_count += 1;
+            total_payout = total_payout.checked_add(payout).ok_or(ErrorCode::MathOverflow)?;
+            total_fee += fee_amount;
+
+            // Closing the account is what marks the bet as claimed
+            bet.close(ctx.accounts.bettor.to_account_info())?;
+        }
+
+        let market = &mut ctx.accounts.market;
+        market.winnings_paid += total_payout;
+        charge_fee_token(
+            market,
+            total_fee,
+            ctx.accounts.fee_payer_token_account.as_ref(),
+            ctx.accounts.fee_treasury.as_ref(),
+            ctx.accounts.fee_rate_source.as_ref(),
+            &ctx.accounts.bettor,
+            &ctx.accounts.token_program,
+        )?;
+        let market = &ctx.accounts.market;
+        if total_payout > 0 {
+            let market_id_bytes = market.market_id.to_le_bytes();
+            let bump = [ctx.bumps.market];
+            let signer_seeds: &[&[u8]] = &[b"market", &market_id_bytes, &bump];
+
+            token::transfer(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    Transfer {
+                        from: ctx.accounts.market_token_account.to_account_info(),
+                        to: ctx.accounts.bettor_token_account.to_account_info(),
+                        authority: ctx.accounts.market.to_account_info(),
+                    },
+                    &[signer_seeds],
+                ),
+                total_payout,
+            )?;
+        }
+
+        emit!(PayoutsBatchClaimed {
+            bettor,
+            market_id: market.market_id,
+            claimed_count,
+            total_payout,
+        });
+
+        Ok(total_payout)
+    }
+
+    /// Settles a `UserPosition` in one go and closes it: the payout on its `total_amount` once
+    /// the market resolves, or the whole stake back if it's voided. Split markets pass their
+    /// outcome vaults in `remaining_accounts` as in `claim_payout`. Returns the amount sent.
+    pub fn claim_position<'info>(ctx: Context<'_, '_, 'info, 'info, ClaimPosition<'info>>) -> Result<u64> {
+        let market = &mut ctx.accounts.market;
+        let position = &ctx.accounts.position;
+
+        require_token_market(market)?;
+        require_not_frozen(&ctx.accounts.bettor_freeze)?;
+
+        let fees = if market.voided {
+            require_stake_vault(
+                market,
+                effective_outcome(market, position.outcome_index),
+                ctx.accounts.market_token_account.key(),
+            )?;
+            None
+        } else {
+            require!(market.resolved, ErrorCode::MarketNotResolved);
+            require!(market.withdrawal_delay == 0, ErrorCode::WithdrawalDelayActive);
+            let now = Clock::get()?.unix_timestamp;
+            require_veto_window_closed(market, now)?;
+            require_claim_deadline_open(market, now)?;
+
+            let gross = outcome_payout(market, position.outcome_index, position.total_amount, false, now)?;
+            let fees = take_fees(market, ctx.accounts.global_state.fee_bps, gross);
+            market.claimed_count += 1;
+            market.winners_paid += 1;
+            market.winnings_paid += fees.0;
+            charge_fee_token(
+                market,
+                fees.1,
+                ctx.accounts.fee_payer_token_account.as_ref(),
+                ctx.accounts.fee_treasury.as_ref(),
+                ctx.accounts.fee_rate_source.as_ref(),
+                &ctx.accounts.bettor,
+                &ctx.accounts.token_program,
+            )?;
+            Some(fees)
+        };
+        let amount = fees.map_or(position.total_amount, |(payout, _, _)| payout);
+
+        let market = &ctx.accounts.market;
+        let market_id_bytes = market.market_id.to_le_bytes();
+        let bump = [ctx.bumps.market];
+        let signer_seeds: &[&[u8]] = &[b"market", &market_id_bytes, &bump];
+
+        // Refunds come from the stake's own vault, checked above
+        if market.outcome_vaults.is_empty() || fees.is_none() {
+            token::transfer(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    Transfer {
+                        from: ctx.accounts.market_token_account.to_account_info(),
+                        to: ctx.accounts.bettor_token_account.to_account_info(),
+                        authority: ctx.accounts.market.to_account_info(),
+                    },
+                    &[signer_seeds],
+                ),
+                amount,
+            )?;
+        } else {
+            transfer_from_outcome_vaults(
+                market,
+                ctx.remaining_accounts,
+                &ctx.accounts.bettor_token_account.to_account_info(),
+                &ctx.accounts.token_program.to_account_info(),
+                signer_seeds,
+                amount,
+            )?;
+        }
+
+        if let Some((payout, fee_amount, creator_fee_amount)) = fees {
+            emit!(PayoutClaimed {
+                bettor: position.bettor,
+                market_id: market.market_id,
+                bet_amount: position.total_amount,
+                payout_amount: payout,
+                fee_amount,
+                creator_fee_amount,
+            });
+        } else {
+            emit!(RefundClaimed {
+                bettor: position.bettor,
+                market_id: market.market_id,
+                amount,
+            });
+        }
+
+        Ok(amount)
+    }
+
+    /// Native SOL counterpart of `place_bet`: the stake moves as lamports into the market's
+    /// `sol_vault` PDA.
+    pub fn place_bet_sol(
+        ctx: Context<PlaceBetSol>,
+        outcome_index: u8,
+        amount: u64,
+        bet_nonce: u64,
+    ) -> Result<BetReceipt> {
+        let market_key = ctx.accounts.market.key();
+        let market = &mut ctx.accounts.market;
+
+        require!(!ctx.accounts.global_state.paused, ErrorCode::PlatformPaused);
+        require!(market.is_native, ErrorCode::WrongMarketType);
+        require_not_frozen(&ctx.accounts.bettor_freeze)?;
+        require_whitelisted(market, &ctx.accounts.whitelist_entry)?;
+        validate_new_stake(market, outcome_index, amount, &Clock::get()?)?;
+
+        let bet = &mut ctx.accounts.bet;
+        bet.bettor = ctx.accounts.bettor.key();
+        bet.market = market_key;
+        bet.outcome_index = outcome_index;
+        bet.amount = amount;
+        bet.claimed = false;
+        bet.timestamp = Clock::get()?.unix_timestamp;
+        bet.payout_capped = false;
+        bet.nonce = bet_nonce;
+        bet.ranking = Vec::new();
+
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.bettor.to_account_info(),
+                    to: ctx.accounts.sol_vault.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
+
+        bet.weight = credit_stake(market, bet.bettor, outcome_index, amount, bet.timestamp)?;
+        count_bet(market, outcome_index);
+
+        emit!(BetPlaced {
+            bettor: bet.bettor,
+            market_id: market.market_id,
+            outcome_index,
+            amount,
+            outcome_pools: market.outcome_pools.clone(),
+            total_pool: market.total_pool,
+            bet_counts: market.bet_counts.clone(),
+            total_bets: market.total_bets,
+        });
+
+        Ok(BetReceipt {
+            bet: bet.key(),
+            amount,
+        })
+    }
+
+    /// Native SOL counterpart of `claim_payout`, paying lamports out of the `sol_vault`.
+    pub fn claim_payout_sol(ctx: Context<ClaimPayoutSol>) -> Result<u64> {
+        let market = &mut ctx.accounts.market;
+        let bet = &mut ctx.accounts.bet;
+
+        require!(market.is_native, ErrorCode::WrongMarketType);
+        require!(market.resolved, ErrorCode::MarketNotResolved);
+        require!(!bet.claimed, ErrorCode::AlreadyClaimed);
+        require!(bet.bettor == ctx.accounts.bettor.key(), ErrorCode::Unauthorized);
+        require_not_frozen(&ctx.accounts.bettor_freeze)?;
+
+        let now = Clock::get()?.unix_timestamp;
+        require_veto_window_closed(market, now)?;
+        let (payout, fee_amount, creator_fee_amount) =
+            settle_bet_payout(market, bet, ctx.accounts.global_state.fee_bps, now)?;
+        market.claimed_count += 1;
+
+        bet.claimed = true;
+        market.winnings_paid += payout;
+
+        transfer_from_sol_vault(
+            &ctx.accounts.sol_vault,
+            &ctx.accounts.bettor.to_account_info(),
+            &ctx.accounts.system_program,
+            ctx.accounts.market.key(),
+            ctx.bumps.sol_vault,
+            payout,
+        )?;
+
+        emit!(PayoutClaimed {
+            bettor: bet.bettor,
+            market_id: ctx.accounts.market.market_id,
+            bet_amount: bet.amount,
+            payout_amount: payout,
+            fee_amount,
+            creator_fee_amount,
+        });
+
+        Ok(payout)
+    }
+
+    /// Native SOL counterpart of `claim_refund`.
+    pub fn claim_refund_sol(ctx: Context<ClaimRefundSol>) -> Result<()> {
+        let market = &ctx.accounts.market;
+        let bet = &mut ctx.accounts.bet;
+
+        require!(market.is_native, ErrorCode::WrongMarketType);
+        require!(market.voided, ErrorCode::MarketNotVoided);
+        require!(!bet.claimed, ErrorCode::AlreadyClaimed);
+        require!(bet.bettor == ctx.accounts.bettor.key(), ErrorCode::Unauthorized);
+        require!(bet.market == market.key(), ErrorCode::InvalidBet);
+
+        bet.claimed = true;
+
+        transfer_from_sol_vault(
+            &ctx.accounts.sol_vault,
+            &ctx.accounts.bettor.to_account_info(),
+            &ctx.accounts.system_program,
+            market.key(),
+            ctx.bumps.sol_vault,
+            bet.amount,
+        )?;
+
+        emit!(RefundClaimed {
+            bettor: bet.bettor,
+            market_id: market.market_id,
+            amount: bet.amount,
+        });
+
+        Ok(())
+    }
+
+    /// Native SOL counterpart of `withdraw_fees`.
+    pub fn withdraw_fees_sol(ctx: Context<WithdrawFeesSol>) -> Result<()> {
+        require!(
+            ctx.accounts.authority.key() == ctx.accounts.global_state.authority,
+            ErrorCode::Unauthorized
+        );
+
+        let market = &mut ctx.accounts.market;
+        require!(market.is_native, ErrorCode::WrongMarketType);
+        let amount = market.fees_accrued;
+        require!(amount > 0, ErrorCode::NoPayoutAvailable);
+        market.fees_accrued = 0;
+
+        transfer_from_sol_vault(
+            &ctx.accounts.sol_vault,
+            &ctx.accounts.destination.to_account_info(),
+            &ctx.accounts.system_program,
+            ctx.accounts.market.key(),
+            ctx.bumps.sol_vault,
+            amount,
+        )?;
+
+        emit!(FeesWithdrawn {
+            market_id: ctx.accounts.market.market_id,
+            amount,
+            destination: ctx.accounts.destination.key(),
+        });
+
+        Ok(())
+    }
+
+    /// Sends a market's accrued protocol fees to `destination`, less the `insurance_fee_bps`
+    /// share, which goes into the insurance fund for the market's mint. On split markets
+    /// `remaining_accounts` lists every outcome vault, as in `claim_payout`.
+    pub fn withdraw_fees<'info>(ctx: Context<'_, '_, 'info, 'info, WithdrawFees<'info>>) -> Result<()> {
+        require!(
+            ctx.accounts.authority.key() == ctx.accounts.global_state.authority,
+            ErrorCode::Unauthorized
+        );
+
+        let market = &mut ctx.accounts.market;
+        require_token_market(market)?;
+        let amount = market.fees_accrued;
+        require!(amount > 0, ErrorCode::NoPayoutAvailable);
+        market.fees_accrued = 0;
+
+        let insurance_amount = to_u64(amount as u128 * ctx.accounts.global_state.insurance_fee_bps as u128 / 10_000)?;
+        let insurance_vault = if insurance_amount > 0 {
+            let (Some(fund), Some(vault)) = (ctx.accounts.insurance_fund.as_ref(), ctx.accounts.insurance_vault.as_ref())
+            else {
+                return err!(ErrorCode::InsuranceFundRequired);
+            };
+            require!(vault.key() == fund.vault, ErrorCode::InsuranceFundRequired);
+            Some(vault.to_account_info())
+        } else {
+            None
+        };
+
+        let market = &ctx.accounts.market;
+        let market_id_bytes = market.market_id.to_le_bytes();
+        let bump = [ctx.bumps.market];
+        let signer_seeds: &[&[u8]] = &[b"market", &market_id_bytes, &bump];
+        let send = |to: AccountInfo<'info>, amount: u64| -> Result<()> {
+            if market.outcome_vaults.is_empty() {
+                token::transfer(
+                    CpiContext::new_with_signer(
+                        ctx.accounts.token_program.to_account_info(),
+                        Transfer {
+                            from: ctx.accounts.market_token_account.to_account_info(),
+                            to,
+                            authority: ctx.accounts.market.to_account_info(),
+                        },
+                        &[signer_seeds],
+                    ),
+                    amount,
+                )
+            } else {
+                transfer_from_outcome_vaults(
+                    market,
+                    ctx.remaining_accounts,
+                    &to,
+                    &ctx.accounts.token_program.to_account_info(),
+                    signer_seeds,
+                    amount,
+                )
+            }
+        };
+
+        if let Some(insurance_vault) = insurance_vault {
+            send(insurance_vault, insurance_amount)?;
+        }
+        send(ctx.accounts.destination.to_account_info(), amount - insurance_amount)?;
+
+        emit!(FeesWithdrawn {
+            market_id: market.market_id,
+            amount: amount - insurance_amount,
+            destination: ctx.accounts.destination.key(),
+        });
+
+        if let Some(fund) = ctx.accounts.insurance_fund.as_mut().filter(|_| insurance_amount > 0) {
+            fund.total_contributed += insurance_amount;
+            emit!(InsuranceContributed {
+                contributor: ctx.accounts.market.key(),
+                mint: fund.mint,
+                amount: insurance_amount,
+                total_contributed: fund.total_contributed,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Sends the market's accrued creator fees to the authority's token account. On split
+    /// markets `remaining_accounts` lists every outcome vault, as in `claim_payout`.
+    pub fn withdraw_creator_fees<'info>(
+        ctx: Context<'_, '_, 'info, 'info, WithdrawCreatorFees<'info>>,
+    ) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        require!(ctx.accounts.authority.key() == market.authority, ErrorCode::Unauthorized);
+        require_token_market(market)?;
+        let amount = market.creator_fees_accrued;
+        require!(amount > 0, ErrorCode::NoPayoutAvailable);
+        market.creator_fees_accrued = 0;
+
+        let market = &ctx.accounts.market;
+        let market_id_bytes = market.market_id.to_le_bytes();
+        let bump = [ctx.bumps.market];
+        let signer_seeds: &[&[u8]] = &[b"market", &market_id_bytes, &bump];
+
+        if market.outcome_vaults.is_empty() {
+            token::transfer(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    Transfer {
+                        from: ctx.accounts.market_token_account.to_account_info(),
+                        to: ctx.accounts.destination.to_account_info(),
+                        authority: ctx.accounts.market.to_account_info(),
+                    },
+                    &[signer_seeds],
+                ),
+                amount,
+            )?;
+        } else {
+            transfer_from_outcome_vaults(
+                market,
+                ctx.remaining_accounts,
+                &ctx.accounts.destination.to_account_info(),
+                &ctx.accounts.token_program.to_account_info(),
+                signer_seeds,
+                amount,
+            )?;
+        }
+
+        emit!(CreatorFeesWithdrawn {
+            market_id: market.market_id,
+            amount,
+            destination: ctx.accounts.destination.key(),
+        });
+
+        Ok(())
+    }
+
+    /// Sends the rounding dust left in a resolved market's vault to its residual destination
+    /// once every paying bet and the winning seed have been claimed. Accrued fees and the keeper
+    /// reserve stay behind for their own withdrawals. Only tokens move, so the vault account
+    /// keeps its rent-exempt lamports. Callable by the market or platform authority.
+    pub fn sweep_dust(ctx: Context<SweepDust>) -> Result<()> {
+        let market = &ctx.accounts.market;
+        let authority = ctx.accounts.authority.key();
+
+        require!(
+            authority == market.authority || authority == ctx.accounts.global_state.authority,
+            ErrorCode::Unauthorized
+        );
+        require!(market.resolved, ErrorCode::MarketNotResolved);
+        require_token_market(market)?;
+        require_combined_vault(market)?;
+        // Paying positions are only countable on single-winner parimutuel markets, and escrowed
+        // winnings would still be sitting in the vault
+        require!(
+            matches!(market.market_type, MarketType::Standard | MarketType::Pivot)
+                && market.final_ranking.is_empty()
+                && market.outcome_scores.is_empty()
+                && market.withdrawal_delay == 0,
+            ErrorCode::WrongMarketType
+        );
+        require_veto_window_closed(market, Clock::get()?.unix_timestamp)?;
+        require_claims_settled(market)?;
+
+        let amount = ctx
+            .accounts
+            .market_token_account
+            .amount
+            .saturating_sub(market.fees_accrued)
+            .saturating_sub(market.creator_fees_accrued)
+            .saturating_sub(market.keeper_reserve);
+        require!(amount > 0, ErrorCode::NoPayoutAvailable);
+
+        token::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.market_token_account.to_account_info(),
+                    to: ctx.accounts.destination.to_account_info(),
+                    authority: ctx.accounts.market.to_account_info(),
+                },
+                &[&[
+                    b"market",
+                    &market.market_id.to_le_bytes(),
+                    &[ctx.bumps.market],
+                ]],
+            ),
+            amount,
+        )?;
+
+        emit!(DustSwept {
+            market_id: market.market_id,
+            amount,
+            destination: ctx.accounts.destination.key(),
+        });
+        emit!(ResidualDonated {
+            market_id: market.market_id,
+            amount,
+            destination: ctx.accounts.destination.key(),
+        });
+
+        ctx.accounts.market.dust_swept += amount;
+
+        Ok(())
+    }
+
+    /// Lets the market authority sweep what winners left unclaimed to the market's residual
+    /// destination once its claim deadline has passed; winning claims are refused from the
+    /// deadline on, so nothing owed to a bettor who claimed in time is touched. Accrued fees and
+    /// the keeper reserve stay behind for their own withdrawals. Afterwards `close_market` no
+    /// longer waits on claims.
+    pub fn reclaim_unclaimed(ctx: Context<ReclaimUnclaimed>) -> Result<u64> {
+        let market = &mut ctx.accounts.market;
+        let now = Clock::get()?.unix_timestamp;
+
+        require!(ctx.accounts.authority.key() == market.authority, ErrorCode::Unauthorized);
+        require!(market.resolved, ErrorCode::MarketNotResolved);
+        require!(!market.unclaimed_reclaimed, ErrorCode::AlreadyClaimed);
+        require_token_market(market)?;
+        require_combined_vault(market)?;
+        require_veto_window_closed(market, now)?;
+        let deadline = claim_deadline_at(market).ok_or(ErrorCode::NoClaimDeadline)?;
+        require!(now >= deadline, ErrorCode::ClaimDeadlineNotReached);
+
+        let amount = ctx
+            .accounts
+            .market_token_account
+            .amount
+            .saturating_sub(market.fees_accrued)
+            .saturating_sub(market.creator_fees_accrued)
+            .saturating_sub(market.keeper_reserve);
+
+        market.unclaimed_reclaimed = true;
+        market.unclaimed_forfeited = amount;
+        // The authority had until now to `reclaim_seed`; an unreclaimed seed leaves with the rest
+        market.seed_reclaimed = true;
+        let market = &ctx.accounts.market;
+
+        if amount > 0 {
+            token::transfer(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    Transfer {
+                        from: ctx.accounts.market_token_account.to_account_info(),
+                        to: ctx.accounts.destination.to_account_info(),
+                        authority: ctx.accounts.market.to_account_info(),
+                    },
+                    &[&[
+                        b"market",
+                        &market.market_id.to_le_bytes(),
+                        &[ctx.bumps.market],
+                    ]],
+                ),
+                amount,
+            )?;
+        }
+
+        emit!(UnclaimedReclaimed {
+            market_id: market.market_id,
+            amount,
+            claim_deadline: deadline,
+            destination: ctx.accounts.destination.key(),
+        });
+        emit!(ResidualDonated {
+            market_id: market.market_id,
+            amount,
+            destination: ctx.accounts.destination.key(),
+        });
+
+        Ok(amount)
+    }
+
+    /// Closes a resolved market whose winners have all been paid, returning the rent of the
+    /// market account and its vault to the authority. The vault must already be empty, so fees,
+    /// house liquidity and any dust are withdrawn first, and `close_grace_period` must have
+    /// passed since resolution. Emits the market's final `SettlementReport`.
+    pub fn close_market(ctx: Context<CloseMarket>) -> Result<()> {
+        let market = &ctx.accounts.market;
+        let now = Clock::get()?.unix_timestamp;
+
+        require!(market.resolved, ErrorCode::MarketNotResolved);
+        require_token_market(market)?;
+        require_combined_vault(market)?;
+        // Claims are only countable where each paying bet is paid exactly once
+        require!(
+            market.final_ranking.is_empty()
+                && market.outcome_scores.is_empty()
+                && market.market_type != MarketType::Scalar,
+            ErrorCode::WrongMarketType
+        );
+        require_veto_window_closed(market, now)?;
+        require!(now >= market.resolved_at + market.close_grace_period, ErrorCode::CloseGracePeriodActive);
+        // Whatever winners left unclaimed was swept out with `reclaim_unclaimed`
+        if !market.unclaimed_reclaimed {
+            require_claims_settled(market)?;
+        }
+        require!(ctx.accounts.market_token_account.amount == 0, ErrorCode::VaultNotEmpty);
+
+        token::close_account(CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            CloseAccount {
+                account: ctx.accounts.market_token_account.to_account_info(),
+                destination: ctx.accounts.authority.to_account_info(),
+                authority: ctx.accounts.market.to_account_info(),
+            },
+            &[&[
+                b"market",
+                &market.market_id.to_le_bytes(),
+                &[ctx.bumps.market],
+            ]],
+        ))?;
+
+        emit!(MarketClosed {
+            market_id: market.market_id,
+            authority: market.authority,
+            claimed_count: market.claimed_count,
+        });
+        emit!(settlement_report(market));
+
+        Ok(())
+    }
+
+    /// Releases escrowed winnings once the market's withdrawal delay has passed, closing the
+    /// escrow record.
+    pub fn withdraw_escrow(ctx: Context<WithdrawEscrow>) -> Result<()> {
+        let market = &ctx.accounts.market;
+        let escrow = &ctx.accounts.escrow;
+
+        require_not_frozen(&ctx.accounts.bettor_freeze)?;
+        require!(Clock::get()?.unix_timestamp >= escrow.release_at, ErrorCode::EscrowLocked);
+        require!(escrow.amount > 0, ErrorCode::NoPayoutAvailable);
+
+        token::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.market_token_account.to_account_info(),
+                    to: ctx.accounts.bettor_token_account.to_account_info(),
+                    authority: ctx.accounts.market.to_account_info(),
+                },
+                &[&[
+                    b"market",
+                    &market.market_id.to_le_bytes(),
+                    &[ctx.bumps.market],
+                ]],
+            ),
+            escrow.amount,
+        )?;
+
+        emit!(EscrowWithdrawn {
+            bettor: escrow.bettor,
+            market_id: market.market_id,
+            amount: escrow.amount,
+        });
+
+        Ok(())
+    }
+
+    /// Permanently freezes the market's terms. Afterwards only betting, resolution, refunds and
+    /// claims remain; admin instructions that change the market are rejected.
+    pub fn lock_market(ctx: Context<LockMarket>) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+
+        require!(ctx.accounts.authority.key() == market.authority, ErrorCode::Unauthorized);
+        require!(!market.immutable, ErrorCode::MarketImmutable);
+
+        market.immutable = true;
+
+        emit!(MarketLocked {
+            market_id: market.market_id,
+            authority: market.authority,
+        });
+
+        Ok(())
+    }
+
+    /// Approves `token_account` as a source of bets on a market that restricts them.
+    pub fn allow_token_account(ctx: Context<UpdateAllowlist>, token_account: Pubkey) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+
+        require!(ctx.accounts.authority.key() == market.authority, ErrorCode::Unauthorized);
+        require!(!market.immutable, ErrorCode::MarketImmutable);
+        require!(
+            !market.allowed_token_accounts.contains(&token_account),
+            ErrorCode::TokenAccountAlreadyAllowed
+        );
+        require!(
+            market.allowed_token_accounts.len() < MAX_ALLOWED_TOKEN_ACCOUNTS,
+            ErrorCode::AllowlistFull
+        );
+
+        market.allowed_token_accounts.push(token_account);
+
+        emit!(TokenAccountAllowed {
+            market_id: market.market_id,
+            token_account,
+        });
+
+        Ok(())
+    }
+
+    /// Withdraws approval from `token_account`. Bets already placed from it are unaffected.
+    pub fn disallow_token_account(ctx: Context<UpdateAllowlist>, token_account: Pubkey) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+
+        require!(ctx.accounts.authority.key() == market.authority, ErrorCode::Unauthorized);
+        require!(!market.immutable, ErrorCode::MarketImmutable);
+
+        let position = market
+            .allowed_token_accounts
+            .iter()
+            .position(|allowed| *allowed == token_account)
+            .ok_or(ErrorCode::TokenAccountNotAllowed)?;
+        market.allowed_token_accounts.remove(position);
+
+        emit!(TokenAccountDisallowed {
+            market_id: market.market_id,
+            token_account,
+        });
+
+        Ok(())
+    }
+
+    /// Replaces a blind market's placeholder labels with the real ones once betting has closed.
+    /// Each label is checked against its commitment, `hash(label || salt)`.
+    pub fn reveal_outcomes(ctx: Context<RevealOutcomes>, labels: Vec<String>, salts: Vec<[u8; 32]>) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+
+        require!(ctx.accounts.authority.key() == market.authority, ErrorCode::Unauthorized);
+        require!(!market.outcome_commitments.is_empty(), ErrorCode::NotBlindMarket);
+        require!(!market.outcomes_revealed, ErrorCode::OutcomesAlreadyRevealed);
+        require!(deadline_passed(market, &Clock::get()?), ErrorCode::BettingStillOpen);
+        require!(
+            labels.len() == market.outcome_commitments.len() && salts.len() == labels.len(),
+            ErrorCode::InvalidOutcomeCommitments
+        );
+
+        validate_outcome_labels(&labels)?;
+        for ((label, salt), commitment) in labels.iter().zip(&salts).zip(&market.outcome_commitments) {
+            require!(
+                hashv(&[label.as_bytes(), salt]).to_bytes() == *commitment,
+                ErrorCode::CommitmentMismatch
+            );
+        }
+
+        market.outcomes = labels;
+        market.outcomes_revealed = true;
+
+        emit!(OutcomesRevealed {
+            market_id: market.market_id,
+            outcomes: market.outcomes.clone(),
+        });
+
+        Ok(())
+    }
+
+    /// Folds outcome `from` into `into` while betting is open, e.g. when a candidate withdraws
+    /// and endorses another. `from`'s pool moves to `into`, and bets on `from` keep their
+    /// `outcome_index` but pay out as bets on `into` through `merged_into`.
+    pub fn merge_outcomes(ctx: Context<MergeOutcomes>, from: u8, into: u8) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+
+        require!(ctx.accounts.authority.key() == market.authority, ErrorCode::Unauthorized);
+        require!(!market.immutable, ErrorCode::MarketImmutable);
+        require!(market.market_type == MarketType::Standard, ErrorCode::WrongMarketType);
+        require!(!market.ranked, ErrorCode::RankedMarket);
+        require_combined_vault(market)?;
+        require!(!market.resolved && !market.voided, ErrorCode::MarketResolved);
+        require!(!deadline_passed(market, &Clock::get()?), ErrorCode::BettingClosed);
+        require!(
+            from != into && (from as usize) < market.outcomes.len() && (into as usize) < market.outcomes.len(),
+            ErrorCode::InvalidOutcome
+        );
+        require!(
+            market.merged_into[from as usize].is_none() && market.merged_into[into as usize].is_none(),
+            ErrorCode::OutcomeMerged
+        );
+        let active_outcomes = market.merged_into.iter().filter(|target| target.is_none()).count();
+        require!(active_outcomes > 2, ErrorCode::TooFewOutcomesToMerge);
+
+        let (from, into) = (from as usize, into as usize);
+        let merged_pool = market.outcome_pools[from];
+        market.outcome_pools[into] += merged_pool;
+        market.outcome_pools[from] = 0;
+        if market.weighted {
+            let merged_weight = market.weighted_pools[from];
+            market.weighted_pools[into] += merged_weight;
+            market.weighted_pools[from] = 0;
+        }
+        let merged_count = market.bet_counts[from];
+        market.bet_counts[into] += merged_count;
+        market.bet_counts[from] = 0;
+        let merged_stake = market.authority_stakes[from];
+        market.authority_stakes[into] += merged_stake;
+        market.authority_stakes[from] = 0;
+        if let Some(seed) = market.seed_amounts.get(from).copied() {
+            market.seed_amounts[into] += seed;
+            market.seed_amounts[from] = 0;
+        }
+
+        // Re-point earlier merges into `from` so every bet resolves in one hop
+        for target in market.merged_into.iter_mut() {
+            if *target == Some(from as u8) {
+                *target = Some(into as u8);
+            }
+        }
+        market.merged_into[from] = Some(into as u8);
+        // Folding away an underfunded outcome can complete activation
+        refresh_active(market);
+
+        emit!(OutcomesMerged {
+            market_id: market.market_id,
+            from: from as u8,
+            into: into as u8,
+            merged_pool,
+            into_pool: market.outcome_pools[into],
+        });
+
+        Ok(())
+    }
+
+    /// Lets the market authority set non-uniform starting odds before any bets arrive.
+    /// Seeds join the pools exactly like bets by the authority: seed on losing outcomes goes to
+    /// winners, and the seed on the winning outcome earns its pro-rata share via `reclaim_seed`.
+    pub fn seed_liquidity(ctx: Context<SeedLiquidity>, amounts: Vec<u64>) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+
+        require!(ctx.accounts.authority.key() == market.authority, ErrorCode::Unauthorized);
+        require!(!market.immutable, ErrorCode::MarketImmutable);
+        require!(market.total_pool == 0, ErrorCode::MarketAlreadyFunded);
+        require!(
+            !matches!(market.market_type, MarketType::FixedOdds | MarketType::Scalar),
+            ErrorCode::WrongMarketType
+        );
+        require!(!market.weighted, ErrorCode::WrongMarketType);
+        require!(!market.ranked, ErrorCode::RankedMarket);
+        require_token_market(market)?;
+        require_combined_vault(market)?;
+        require!(amounts.len() == market.outcomes.len(), ErrorCode::InvalidSeedAmounts);
+        require!(!market.resolved && !market.voided, ErrorCode::MarketResolved);
+        require!(!deadline_passed(market, &Clock::get()?), ErrorCode::BettingClosed);
+
+        let total = seed_total(&amounts)?;
+
+        token::transfer(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.authority_token_account.to_account_info(),
+                    to: ctx.accounts.market_token_account.to_account_info(),
+                    authority: ctx.accounts.authority.to_account_info(),
+                },
+            ),
+            total,
+        )?;
+
+        seed_pools(market, amounts.clone(), total);
+
+        emit!(LiquiditySeeded {
+            market_id: market.market_id,
+            amounts,
+            total_pool: market.total_pool,
+        });
+
+        Ok(())
+    }
+
+    /// Pays the authority what its seed earned: the winning outcome's seed share on a resolved
+    /// market, or the full seed back on a voided one.
+    pub fn reclaim_seed(ctx: Context<ReclaimSeed>) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+
+        require!(ctx.accounts.authority.key() == market.authority, ErrorCode::Unauthorized);
+        require!(!market.seed_reclaimed, ErrorCode::AlreadyClaimed);
+        require!(market.resolved || market.voided, ErrorCode::MarketNotResolved);
+        require_veto_window_closed(market, Clock::get()?.unix_timestamp)?;
+
+        let amount = if market.voided {
+            market.seed_amounts.iter().sum()
+        } else if !market.outcome_scores.is_empty() {
+            (0..market.seed_amounts.len())
+                .map(|outcome| scored_payout(market, outcome as u8, market.seed_amounts[outcome]))
+                .sum()
+        } else {
+            let seed_on = |outcome: u8| market.seed_amounts.get(outcome as usize).copied().unwrap_or(0);
+            let winning_seed = seed_on(market.winning_outcome.unwrap());
+            let runner_up_seed = market.runner_up.map_or(0, seed_on);
+            require!(winning_seed > 0 || runner_up_seed > 0, ErrorCode::NoPayoutAvailable);
+
+            let mut amount = 0;
+            if winning_seed > 0 {
+                amount += stake_payout(market, winning_seed, false, Clock::get()?.unix_timestamp)?;
+            }
+            if runner_up_seed > 0 {
+                amount += consolation_payout(market, runner_up_seed)?;
+            }
+            amount
+        };
+        require!(amount > 0, ErrorCode::NoPayoutAvailable);
+
+        market.seed_reclaimed = true;
+
+        token::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.market_token_account.to_account_info(),
+                    to: ctx.accounts.authority_token_account.to_account_info(),
+                    authority: ctx.accounts.market.to_account_info(),
+                },
+                &[&[
+                    b"market",
+                    &ctx.accounts.market.market_id.to_le_bytes(),
+                    &[ctx.bumps.market],
+                ]],
+            ),
+            amount,
+        )?;
+
+        emit!(SeedReclaimed {
+            market_id: ctx.accounts.market.market_id,
+            amount,
+        });
+
+        Ok(())
+    }
+
+    /// Moves `outcome_index`'s pool out of the combined vault into its own sub-vault PDA, so each
+    /// outcome's stakes are held apart. Outcomes are split in order; once a sub-vault exists,
+    /// bets and refunds on that outcome go through it, and payouts draw on every sub-vault.
+    pub fn split_vault(ctx: Context<SplitVault>, outcome_index: u8) -> Result<()> {
+        let market = &ctx.accounts.market;
+
+        require!(ctx.accounts.authority.key() == market.authority, ErrorCode::Unauthorized);
+        require!(!market.immutable, ErrorCode::MarketImmutable);
+        require!(market.market_type == MarketType::Standard, ErrorCode::WrongMarketType);
+        require_token_market(market)?;
+        require!(!market.resolved && !market.voided, ErrorCode::MarketResolved);
+        require!(
+            outcome_index as usize == market.outcome_vaults.len() && (outcome_index as usize) < market.outcomes.len(),
+            ErrorCode::InvalidOutcome
+        );
+        // Escrowed winnings, keeper rewards and seed reclaims are all paid from the one vault
+        require!(market.withdrawal_delay == 0, ErrorCode::WithdrawalDelayActive);
+        require!(market.keeper_reserve == 0, ErrorCode::VaultSplit);
+        require!(market.seed_amounts.iter().all(|seed| *seed == 0), ErrorCode::VaultSplit);
+        require!(ctx.accounts.market_token_account.owner == market.key(), ErrorCode::InvalidMarketVault);
+        require!(
+            !market.outcome_vaults.contains(&ctx.accounts.market_token_account.key()),
+            ErrorCode::InvalidMarketVault
+        );
+
+        let amount = market.outcome_pools[outcome_index as usize];
+        if amount > 0 {
+            token::transfer(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    Transfer {
+                        from: ctx.accounts.market_token_account.to_account_info(),
+                        to: ctx.accounts.outcome_vault.to_account_info(),
+                        authority: ctx.accounts.market.to_account_info(),
+                    },
+                    &[&[
+                        b"market",
+                        &market.market_id.to_le_bytes(),
+                        &[ctx.bumps.market],
+                    ]],
+                ),
+                amount,
+            )?;
+        }
+
+        let outcome_vault = ctx.accounts.outcome_vault.key();
+        let market = &mut ctx.accounts.market;
+        market.outcome_vaults.push(outcome_vault);
+
+        emit!(OutcomeVaultSplit {
+            market_id: market.market_id,
+            outcome_index,
+            outcome_vault,
+            amount,
+        });
+
+        Ok(())
+    }
+
+    /// Adds house liquidity to a fixed-odds market. It sits in the market vault and bounds how
+    /// much winning profit bets may lock.
+    pub fn fund_house(ctx: Context<FundHouse>, amount: u64) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+
+        require!(ctx.accounts.authority.key() == market.authority, ErrorCode::Unauthorized);
+        require!(market.market_type == MarketType::FixedOdds, ErrorCode::WrongMarketType);
+        require!(!market.resolved && !market.voided, ErrorCode::MarketResolved);
+        require!(amount > 0, ErrorCode::InvalidHouseAmount);
+
+        token::transfer(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.authority_token_account.to_account_info(),
+                    to: ctx.accounts.market_token_account.to_account_info(),
+                    authority: ctx.accounts.authority.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
+
+        market.house_liquidity += amount;
+
+        emit!(HouseFunded {
+            market_id: market.market_id,
+            amount,
+            house_liquidity: market.house_liquidity,
+        });
+
+        Ok(())
+    }
+
+    /// Pays the house what's left once a fixed-odds market settles: its liquidity plus losing
+    /// stakes, less what winners are owed. A voided market returns the liquidity untouched.
+    pub fn withdraw_house_profit(ctx: Context<WithdrawHouseProfit>) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+
+        require!(ctx.accounts.authority.key() == market.authority, ErrorCode::Unauthorized);
+        require!(market.market_type == MarketType::FixedOdds, ErrorCode::WrongMarketType);
+        require!(market.resolved || market.voided, ErrorCode::MarketNotResolved);
+        require_veto_window_closed(market, Clock::get()?.unix_timestamp)?;
+        require!(!market.house_withdrawn, ErrorCode::AlreadyClaimed);
+
+        let amount = if market.voided {
+            market.house_liquidity
+        } else {
+            let winning_outcome = market.winning_outcome.unwrap() as usize;
+            let owed_to_winners = (market.outcome_pools[winning_outcome] as u128
+                * market.fixed_odds_bps[winning_outcome] as u128
+                / 10_000) as u64;
+            market.house_liquidity + market.total_pool - owed_to_winners
+        };
+        require!(amount > 0, ErrorCode::NoPayoutAvailable);
+
+        market.house_withdrawn = true;
+
+        token::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.market_token_account.to_account_info(),
+                    to: ctx.accounts.authority_token_account.to_account_info(),
+                    authority: ctx.accounts.market.to_account_info(),
+                },
+                &[&[
+                    b"market",
+                    &ctx.accounts.market.market_id.to_le_bytes(),
+                    &[ctx.bumps.market],
+                ]],
+            ),
+            amount,
+        )?;
+
+        emit!(HouseProfitWithdrawn {
+            market_id: ctx.accounts.market.market_id,
+            amount,
+        });
+
+        Ok(())
+    }
+
+    /// Claims a winning bet and stakes the whole payout on `outcome_index` of another market,
+    /// moving the tokens directly between the two vaults.
+    pub fn roll_payout(ctx: Context<RollPayout>, outcome_index: u8, bet_nonce: u64) -> Result<()> {
+        let clock = Clock::get()?;
+        let now = clock.unix_timestamp;
+        let source_market = &ctx.accounts.source_market;
+        let source_bet = &mut ctx.accounts.source_bet;
+
+        require!(!ctx.accounts.global_state.paused, ErrorCode::PlatformPaused);
+        require!(ctx.accounts.target_market.key() != source_market.key(), ErrorCode::InvalidRollTarget);
+        require!(
+            ctx.accounts.source_market_token_account.mint == ctx.accounts.target_market_token_account.mint,
+            ErrorCode::MintMismatch
+        );
+        require!(source_market.resolved, ErrorCode::MarketNotResolved);
+        require_veto_window_closed(source_market, now)?;
+        require_claim_deadline_open(source_market, now)?;
+        require!(!source_bet.claimed, ErrorCode::AlreadyClaimed);
+        require!(source_bet.bettor == ctx.accounts.bettor.key(), ErrorCode::Unauthorized);
+        require!(source_bet.market == source_market.key(), ErrorCode::InvalidBet);
+        require!(source_market.withdrawal_delay == 0, ErrorCode::WithdrawalDelayActive);
+        require_token_market(source_market)?;
+        require_token_market(&ctx.accounts.target_market)?;
+        require_combined_vault(source_market)?;
+        require_stake_vault(
+            &ctx.accounts.target_market,
+            outcome_index,
+            ctx.accounts.target_market_token_account.key(),
+        )?;
+        require_not_frozen(&ctx.accounts.source_bettor_freeze)?;
+        require_not_frozen(&ctx.accounts.target_bettor_freeze)?;
+        require_whitelisted(&ctx.accounts.target_market, &ctx.accounts.target_whitelist_entry)?;
+        require!(!ctx.accounts.target_market.ranked, ErrorCode::RankedMarket);
+
+        let fee_bps = ctx.accounts.global_state.fee_bps;
+        let (payout, fee_amount, creator_fee_amount) =
+            settle_bet_payout(&mut ctx.accounts.source_market, source_bet, fee_bps, now)?;
+        validate_new_stake(&ctx.accounts.target_market, outcome_index, payout, &clock)?;
+        // Rolled stakes come from a vault, never an approved source account
+        require!(!ctx.accounts.target_market.restrict_token_accounts, ErrorCode::TokenAccountNotAllowed);
+
+        source_bet.claimed = true;
+        let source_market = &mut ctx.accounts.source_market;
+        source_market.claimed_count += 1;
+        source_market.winnings_paid += payout;
+        charge_fee_token(
+            source_market,
+            fee_amount,
+            ctx.accounts.fee_payer_token_account.as_ref(),
+            ctx.accounts.fee_treasury.as_ref(),
+            ctx.accounts.fee_rate_source.as_ref(),
+            &ctx.accounts.bettor,
+            &ctx.accounts.token_program,
+        )?;
+        let source_market = &ctx.accounts.source_market;
+
+        // Move the payout straight from the resolved market's vault into the target's
+        token::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.source_market_token_account.to_account_info(),
+                    to: ctx.accounts.target_market_token_account.to_account_info(),
+                    authority: ctx.accounts.source_market.to_account_info(),
+                },
+                &[&[
+                    b"market",
+                    &source_market.market_id.to_le_bytes(),
+                    &[ctx.bumps.source_market],
+                ]],
+            ),
+            payout,
+        )?;
+
+        emit!(PayoutClaimed {
+            bettor: source_bet.bettor,
+            market_id: source_market.market_id,
+            bet_amount: source_bet.amount,
+            payout_amount: payout,
+            fee_amount,
+            creator_fee_amount,
+        });
+
+        let target_key = ctx.accounts.target_market.key();
+        let target_market = &mut ctx.accounts.target_market;
+        let new_bet = &mut ctx.accounts.new_bet;
+        new_bet.bettor = ctx.accounts.bettor.key();
+        new_bet.market = target_key;
+        new_bet.outcome_index = outcome_index;
+        new_bet.amount = payout;
+        new_bet.claimed = false;
+        new_bet.timestamp = now;
+        new_bet.payout_capped = false;
+        new_bet.nonce = bet_nonce;
+        new_bet.ranking = Vec::new();
+        new_bet.underlying_amount = underlying_value(target_market, payout, now)?;
+
+        reserve_house_cover(target_market, outcome_index, payout)?;
+        new_bet.weight = credit_stake(target_market, new_bet.bettor, outcome_index, payout, now)?;
+        count_bet(target_market, outcome_index);
+
+        emit!(BetPlaced {
+            bettor: new_bet.bettor,
+            market_id: target_market.market_id,
+            outcome_index,
+            amount: payout,
+            outcome_pools: target_market.outcome_pools.clone(),
+            total_pool: target_market.total_pool,
+            bet_counts: target_market.bet_counts.clone(),
+            total_bets: target_market.total_bets,
+        });
+
+        Ok(())
+    }
+
+    /// Creates a basket over up to `MAX_BASKET_LEGS` markets. `payout_bps` holds one multiplier
+    /// per possible number of correct legs, from zero up to all of them, and winnings are backed
+    /// by liquidity the creator adds with `fund_basket`.
+    pub fn create_basket(
+        ctx: Context<CreateBasket>,
+        basket_id: u64,
+        markets: Vec<Pubkey>,
+        payout_bps: Vec<u32>,
+    ) -> Result<()> {
+        require!(
+            markets.len() >= 2 && markets.len() <= MAX_BASKET_LEGS,
+            ErrorCode::InvalidBasket
+        );
+        require!(payout_bps.len() == markets.len() + 1, ErrorCode::InvalidBasket);
+        require!(
+            markets.iter().enumerate().all(|(i, market)| !markets[..i].contains(market)),
+            ErrorCode::InvalidBasket
+        );
+
+        let basket = &mut ctx.accounts.basket;
+        basket.creator = ctx.accounts.creator.key();
+        basket.basket_id = basket_id;
+        basket.mint = ctx.accounts.mint.key();
+        basket.vault = ctx.accounts.basket_vault.key();
+        basket.markets = markets.clone();
+        basket.payout_bps = payout_bps.clone();
+        basket.locked = 0;
+        basket.bump = ctx.bumps.basket;
+
+        emit!(BasketCreated {
+            basket: basket.key(),
+            creator: basket.creator,
+            markets,
+            payout_bps,
+        });
+
+        Ok(())
+    }
+
+    /// Adds liquidity that backs a basket's payouts.
+    pub fn fund_basket(ctx: Context<FundBasket>, amount: u64) -> Result<()> {
+        require!(amount > 0, ErrorCode::InvalidHouseAmount);
+
+        token::transfer(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.creator_token_account.to_account_info(),
+                    to: ctx.accounts.basket_vault.to_account_info(),
+                    authority: ctx.accounts.creator.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
+
+        Ok(())
+    }
+
+    /// Returns basket liquidity that no open basket bet could still claim.
+    pub fn withdraw_basket_liquidity(ctx: Context<FundBasket>, amount: u64) -> Result<()> {
+        let basket = &ctx.accounts.basket;
+
+        require!(
+            amount > 0 && amount <= ctx.accounts.basket_vault.amount.saturating_sub(basket.locked),
+            ErrorCode::InvalidHouseAmount
+        );
+
+        token::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.basket_vault.to_account_info(),
+                    to: ctx.accounts.creator_token_account.to_account_info(),
+                    authority: ctx.accounts.basket.to_account_info(),
+                },
+                &[&[
+                    b"basket",
+                    basket.creator.as_ref(),
+                    &basket.basket_id.to_le_bytes(),
+                    &[basket.bump],
+                ]],
+            ),
+            amount,
+        )?;
+
+        Ok(())
+    }
+
+    /// Stakes `amount` on one outcome per basket leg. `remaining_accounts` lists the basket's
+    /// markets in order; every leg must still be open for betting. The best-case payout is
+    /// locked against the basket's liquidity until the bet is claimed. As with `place_bet`,
+    /// `bet_nonce` picks the basket bet's address so a bettor can hold several.
+    pub fn create_basket_bet<'info>(
+        ctx: Context<'_, '_, 'info, 'info, CreateBasketBet<'info>>,
+        picks: Vec<u8>,
+        amount: u64,
+        bet_nonce: u64,
+    ) -> Result<()> {
+        let basket = &ctx.accounts.basket;
+        let clock = Clock::get()?;
+
+        require!(amount > 0, ErrorCode::BetTooSmall);
+        require!(picks.len() == basket.markets.len(), ErrorCode::InvalidBasketLegs);
+        require!(ctx.remaining_accounts.len() == basket.markets.len(), ErrorCode::InvalidBasketLegs);
+
+        let leg_accounts = ctx.remaining_accounts.iter().zip(basket.markets.iter()).zip(picks.iter());
+        for ((info, expected), pick) in leg_accounts {
+            require!(info.key() == *expected, ErrorCode::InvalidBasketLegs);
+            let market = Account::<Market>::try_from(info)?;
+            require!(!market.resolved && !market.voided, ErrorCode::MarketResolved);
+            require!(!deadline_passed(&market, &clock), ErrorCode::BettingClosed);
+            require!(!cutoff_passed(&market, &clock), ErrorCode::BettingCutoffPassed);
+            require!((*pick as usize) < market.outcomes.len(), ErrorCode::InvalidOutcome);
+            require!(market.merged_into[*pick as usize].is_none(), ErrorCode::OutcomeMerged);
+        }
+
+        let best_bps = basket.payout_bps.iter().copied().max().unwrap_or(0).max(10_000);
+        let reserved = (amount as u128 * best_bps as u128 / 10_000) as u64;
+        require!(
+            basket.locked + reserved <= ctx.accounts.basket_vault.amount + amount,
+            ErrorCode::BasketUnderfunded
+        );
+
+        token::transfer(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.bettor_token_account.to_account_info(),
+                    to: ctx.accounts.basket_vault.to_account_info(),
+                    authority: ctx.accounts.bettor.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
+
+        let basket = &mut ctx.accounts.basket;
+        basket.locked += reserved;
+
+        let basket_bet = &mut ctx.accounts.basket_bet;
+        basket_bet.bettor = ctx.accounts.bettor.key();
+        basket_bet.basket = basket.key();
+        basket_bet.picks = picks.clone();
+        basket_bet.amount = amount;
+        basket_bet.reserved = reserved;
+        basket_bet.claimed = false;
+        basket_bet.timestamp = clock.unix_timestamp;
+        basket_bet.nonce = bet_nonce;
+
+        emit!(BasketBetPlaced {
+            basket: basket.key(),
+            bettor: basket_bet.bettor,
+            picks,
+            amount,
+        });
+
+        Ok(())
+    }
+
+    /// Settles a basket bet once every leg is resolved or voided, with `remaining_accounts`
+    /// listing the basket's markets in order. The stake is split evenly across legs: voided
+    /// legs' shares are refunded, and the rest pays out at the table entry for the number of
+    /// correct legs. The basket bet is closed with its rent returned to the bettor.
+    pub fn claim_basket<'info>(ctx: Context<'_, '_, 'info, 'info, ClaimBasket<'info>>) -> Result<()> {
+        let basket = &ctx.accounts.basket;
+        let basket_bet = &ctx.accounts.basket_bet;
+
+        require!(basket_bet.bettor == ctx.accounts.bettor.key(), ErrorCode::Unauthorized);
+        require!(!basket_bet.claimed, ErrorCode::AlreadyClaimed);
+        require!(ctx.remaining_accounts.len() == basket.markets.len(), ErrorCode::InvalidBasketLegs);
+
+        let mut hits = 0usize;
+        let mut live_legs = 0u64;
+        let leg_accounts = ctx.remaining_accounts.iter().zip(basket.markets.iter()).zip(basket_bet.picks.iter());
+        for ((info, expected), pick) in leg_accounts {
+            require!(info.key() == *expected, ErrorCode::InvalidBasketLegs);
+            let market = Account::<Market>::try_from(info)?;
+            if market.voided {
+                continue;
+            }
+            require!(market.resolved, ErrorCode::BasketLegOpen);
+            require_veto_window_closed(&market, Clock::get()?.unix_timestamp)?;
+            live_legs += 1;
+            if Some(effective_outcome(&market, *pick)) == market.winning_outcome {
+                hits += 1;
+            }
+        }
+
+        let legs = basket.markets.len() as u64;
+        let live_stake = (basket_bet.amount as u128 * live_legs as u128 / legs as u128) as u64;
+        let refunded = basket_bet.amount - live_stake;
+        let winnings = (live_stake as u128 * basket.payout_bps[hits] as u128 / 10_000) as u64;
+        let payout = refunded + winnings;
+
+        let basket_bet = &mut ctx.accounts.basket_bet;
+        basket_bet.claimed = true;
+        let basket = &mut ctx.accounts.basket;
+        basket.locked -= basket_bet.reserved;
+
+        if payout > 0 {
+            token::transfer(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    Transfer {
+                        from: ctx.accounts.basket_vault.to_account_info(),
+                        to: ctx.accounts.bettor_token_account.to_account_info(),
+                        authority: ctx.accounts.basket.to_account_info(),
+                    },
+                    &[&[
+                        b"basket",
+                        ctx.accounts.basket.creator.as_ref(),
+                        &ctx.accounts.basket.basket_id.to_le_bytes(),
+                        &[ctx.accounts.basket.bump],
+                    ]],
+                ),
+                payout,
+            )?;
+        }
+
+        emit!(BasketClaimed {
+            basket: ctx.accounts.basket.key(),
+            bettor: ctx.accounts.basket_bet.bettor,
+            hits: hits as u8,
+            refunded,
+            payout,
+        });
+
+        Ok(())
+    }
+
+    /// Marks a winning bet whose pro-rata payout exceeds the market's payout cap. Open to anyone
+    /// until `cap_registration_ends`, since every uncapped winner gains from the redistribution.
+    pub fn register_capped_bet(ctx: Context<RegisterCappedBet>) -> Result<()> {
+        let market_key = ctx.accounts.market.key();
+        let market = &mut ctx.accounts.market;
+        let bet = &mut ctx.accounts.bet;
+
+        require!(market.resolved, ErrorCode::MarketNotResolved);
+        require!(market.max_payout_share_bps > 0, ErrorCode::PayoutCapDisabled);
+        require!(
+            Clock::get()?.unix_timestamp < market.cap_registration_ends,
+            ErrorCode::CapRegistrationClosed
+        );
+        require!(bet.market == market_key, ErrorCode::InvalidBet);
+        require!(
+            Some(effective_outcome(market, bet.outcome_index)) == market.winning_outcome,
+            ErrorCode::LosingBet
+        );
+        require!(!bet.payout_capped, ErrorCode::BetAlreadyCapped);
+
+        // raw payout > cap  <=>  amount / winning_pool > max share
+        let winning_pool = market.outcome_pools[market.winning_outcome.unwrap() as usize];
+        require!(
+            bet.amount as u128 * 10_000 > winning_pool as u128 * market.max_payout_share_bps as u128,
+            ErrorCode::BetBelowPayoutCap
+        );
+
+        bet.payout_capped = true;
+        market.capped_stake += bet.amount;
+        market.capped_count += 1;
+
+        emit!(BetPayoutCapped {
+            market_id: market.market_id,
+            bet: bet.key(),
+            amount: bet.amount,
+        });
+
+        Ok(())
+    }
+
+    /// Frees a finished market's external id so a new market can claim it.
+    pub fn release_external_id(ctx: Context<ReleaseExternalId>) -> Result<()> {
+        let market = &ctx.accounts.market;
+
+        require!(ctx.accounts.authority.key() == market.authority, ErrorCode::Unauthorized);
+        require!(market.resolved || market.voided, ErrorCode::MarketStillActive);
+
+        Ok(())
+    }
+
+    /// Lets the market's guardian revert a resolution within `veto_window` seconds of it,
+    /// reopening the market for its resolver. The guardian can't pick an outcome, and can
+    /// veto at most `max_vetoes` times.
+    pub fn veto_resolution(ctx: Context<VetoResolution>) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        let now = Clock::get()?.unix_timestamp;
+
+        require!(market.guardian == Some(ctx.accounts.guardian.key()), ErrorCode::Unauthorized);
+        require!(market.resolved, ErrorCode::MarketNotResolved);
+        require!(now < market.resolved_at + market.veto_window, ErrorCode::VetoWindowClosed);
+        require!(market.veto_count < market.max_vetoes, ErrorCode::VetoLimitReached);
+
+        let vetoed_outcome = market.winning_outcome.unwrap();
+        market.veto_count += 1;
+        market.dispute_duration += now - market.resolved_at;
+        market.resolved = false;
+        market.winning_outcome = None;
+        market.resolved_at = 0;
+        market.resolved_by = Pubkey::default();
+        market.final_pools = Vec::new();
+        market.authority_had_winning_stake = false;
+        market.runner_up = None;
+        market.consolation_pool = 0;
+        market.outcome_scores = Vec::new();
+        market.final_ranking = Vec::new();
+        market.scalar_value = 0;
+        market.scalar_long_share = 0;
+        market.proposed_outcome = None;
+        market.outcome_evidence = Vec::new();
+        // The committee votes afresh on a vetoed result
+        market.resolution_votes = vec![None; market.resolvers.len()];
+
+        emit!(ResolutionVetoed {
+            market_id: market.market_id,
+            guardian: ctx.accounts.guardian.key(),
+            vetoed_outcome,
+            veto_count: market.veto_count,
+            dispute_duration: market.dispute_duration,
+        });
+
+        Ok(())
+    }
+
+    /// Hands a market's resolution and admin rights to `new_authority`. Locked markets keep theirs.
+    pub fn transfer_market_authority(ctx: Context<TransferMarketAuthority>, new_authority: Pubkey) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+
+        require!(ctx.accounts.authority.key() == market.authority, ErrorCode::Unauthorized);
+        require!(!market.immutable, ErrorCode::MarketImmutable);
+
+        let old_authority = market.authority;
+        market.authority = new_authority;
+
+        emit!(AuthorityChanged {
+            market_id: Some(market.market_id),
+            old_authority,
+            new_authority,
+        });
+
+        Ok(())
+    }
+
+    /// Pushes back an unresolved market's `resolution_time`, e.g. when the event is postponed.
+    /// The betting cutoff moves by the same amount, so a market whose betting had closed
+    /// reopens for stakes until the new cutoff.
+    pub fn extend_resolution(ctx: Context<ExtendResolution>, new_resolution_time: i64) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+
+        require!(ctx.accounts.authority.key() == market.authority, ErrorCode::Unauthorized);
+        require!(!market.immutable, ErrorCode::MarketImmutable);
+        require!(!market.resolved, ErrorCode::MarketAlreadyResolved);
+        require!(!market.voided, ErrorCode::MarketVoided);
+        require!(!market.use_slots, ErrorCode::WrongMarketType);
+        require!(market.proposed_outcome.is_none(), ErrorCode::ChallengePeriodActive);
+        require!(
+            new_resolution_time > market.resolution_time && new_resolution_time > Clock::get()?.unix_timestamp,
+            ErrorCode::InvalidResolutionTime
+        );
+        // A scheduled reading must still come after betting closes
+        require!(
+            market.scheduled_oracle.is_none() || new_resolution_time <= market.scheduled_time,
+            ErrorCode::InvalidSchedule
+        );
+
+        let old_resolution_time = market.resolution_time;
+        market.betting_cutoff += new_resolution_time - old_resolution_time;
+        market.resolution_time = new_resolution_time;
+        // The cutoff moved, so a close marked against the old one no longer holds
+        market.betting_closed = false;
+
+        emit!(ResolutionExtended {
+            market_id: market.market_id,
+            old_resolution_time,
+            new_resolution_time,
+            betting_cutoff: market.betting_cutoff,
+        });
+
+        Ok(())
+    }
+
+    /// Records on-chain that betting has closed once the cutoff (or, on slot-based markets, the
+    /// resolution slot) has passed, so indexers can key on the `BettingClosed` event rather
+    /// than compare clocks. Anyone may call it; calls after the first are no-ops.
+    pub fn mark_betting_closed(ctx: Context<MarkBettingClosed>) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        let clock = Clock::get()?;
+
+        if market.betting_closed {
+            return Ok(());
+        }
+        require!(
+            cutoff_passed(market, &clock) || deadline_passed(market, &clock),
+            ErrorCode::BettingStillOpen
+        );
+
+        market.betting_closed = true;
+
+        emit!(BettingClosed {
+            market_id: market.market_id,
+            betting_cutoff: market.betting_cutoff,
+            closed_at: clock.unix_timestamp,
+            caller: ctx.accounts.caller.key(),
+        });
+
+        Ok(())
+    }
+
+    /// Lets the market authority call off a market before resolution, e.g. one created with a
+    /// bad question. Every bettor gets their exact stake back through `claim_refund`.
+    pub fn cancel_market(ctx: Context<CancelMarket>) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+
+        require!(ctx.accounts.authority.key() == market.authority, ErrorCode::Unauthorized);
+        require!(!market.immutable, ErrorCode::MarketImmutable);
+        require!(!market.resolved, ErrorCode::MarketAlreadyResolved);
+        require!(!market.voided, ErrorCode::MarketVoided);
+        require_no_tranche_paid(market)?;
+
+        // Voiding reuses the refund paths, which return stakes whatever the outcome
+        market.cancelled = true;
+        market.voided = true;
+
+        emit!(MarketCancelled {
+            market_id: market.market_id,
+            total_pool: market.total_pool,
+        });
+
+        Ok(())
+    }
+
+    /// Returns a cancelled market's creation fee to the account it was paid from. The treasury is
+    /// not a program account, so its owner signs the refund.
+    pub fn refund_creation_fee(ctx: Context<RefundCreationFee>) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+
+        require!(market.cancelled, ErrorCode::MarketNotCancelled);
+        require!(market.creation_fee_paid > 0, ErrorCode::NoCreationFeePaid);
+
+        let amount = market.creation_fee_paid;
+        token::transfer(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.treasury.to_account_info(),
+                    to: ctx.accounts.creator_token_account.to_account_info(),
+                    authority: ctx.accounts.treasury_authority.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
+        market.creation_fee_paid = 0;
+
+        emit!(CreationFeeRefunded {
+            market_id: market.market_id,
+            amount,
+            destination: ctx.accounts.creator_token_account.key(),
+        });
+
+        Ok(())
+    }
+
+    /// Platform moderation: blocks all further betting and resolution and puts the market into
+    /// refund mode, without needing the market authority's cooperation.
+    pub fn quarantine_market(ctx: Context<QuarantineMarket>) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+
+        require!(ctx.accounts.authority.key() == ctx.accounts.global_state.authority, ErrorCode::Unauthorized);
+        require!(!market.resolved, ErrorCode::MarketAlreadyResolved);
+        require!(!market.quarantined, ErrorCode::MarketQuarantined);
+        require_no_tranche_paid(market)?;
+
+        // Voiding reuses the refund paths so quarantined stakes are never trapped
+        market.quarantined = true;
+        market.voided = true;
+
+        emit!(MarketQuarantined {
+            market_id: market.market_id,
+            authority: ctx.accounts.authority.key(),
+            total_pool: market.total_pool,
+        });
+
+        Ok(())
+    }
+
+    /// Blocks `bettor` from betting on, claiming from or rolling out of this market, e.g. under
+    /// a legal hold. Refunds stay open so a voided market never traps a frozen stake.
+    pub fn freeze_bettor(ctx: Context<FreezeBettor>, bettor: Pubkey) -> Result<()> {
+        let market = &ctx.accounts.market;
+
+        require!(ctx.accounts.authority.key() == market.authority, ErrorCode::Unauthorized);
+
+        let freeze = &mut ctx.accounts.bettor_freeze;
+        freeze.market = market.key();
+        freeze.bettor = bettor;
+        freeze.frozen_at = Clock::get()?.unix_timestamp;
+
+        emit!(BettorFrozen {
+            market_id: market.market_id,
+            bettor,
+        });
+
+        Ok(())
+    }
+
+    /// Lifts a freeze by closing the bettor's freeze record.
+    pub fn unfreeze_bettor(ctx: Context<UnfreezeBettor>) -> Result<()> {
+        let market = &ctx.accounts.market;
+
+        require!(ctx.accounts.authority.key() == market.authority, ErrorCode::Unauthorized);
+
+        emit!(BettorUnfrozen {
+            market_id: market.market_id,
+            bettor: ctx.accounts.bettor_freeze.bettor,
+        });
+
+        Ok(())
+    }
+
+    /// Admits `bettor` to a whitelist market.
+    pub fn add_to_whitelist(ctx: Context<AddToWhitelist>, bettor: Pubkey) -> Result<()> {
+        let market = &ctx.accounts.market;
+
+        require!(ctx.accounts.authority.key() == market.authority, ErrorCode::Unauthorized);
+        require!(market.access_mode == AccessMode::Whitelist, ErrorCode::WrongMarketType);
+
+        let entry = &mut ctx.accounts.whitelist_entry;
+        entry.market = market.key();
+        entry.bettor = bettor;
+        entry.added_at = Clock::get()?.unix_timestamp;
+
+        emit!(BettorWhitelisted {
+            market_id: market.market_id,
+            bettor,
+        });
+
+        Ok(())
+    }
+
+    /// Closes a bettor's whitelist entry. Bets already placed are unaffected.
+    pub fn remove_from_whitelist(ctx: Context<RemoveFromWhitelist>) -> Result<()> {
+        let market = &ctx.accounts.market;
+
+        require!(ctx.accounts.authority.key() == market.authority, ErrorCode::Unauthorized);
+
+        emit!(BettorRemovedFromWhitelist {
+            market_id: market.market_id,
+            bettor: ctx.accounts.whitelist_entry.bettor,
+        });
+
+        Ok(())
+    }
+
+    /// Tops up the reward paid to whoever processes this market if it goes stale. The tokens sit
+    /// in the market vault outside the betting pools.
+    pub fn fund_keeper_reserve(ctx: Context<FundKeeperReserve>, amount: u64) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+
+        require!(ctx.accounts.authority.key() == market.authority, ErrorCode::Unauthorized);
+        require!(market.stale_grace_period > 0, ErrorCode::StaleProcessingDisabled);
+        require_token_market(market)?;
+        require_combined_vault(market)?;
+        require!(!market.resolved && !market.voided, ErrorCode::MarketResolved);
+        require!(amount > 0, ErrorCode::InvalidKeeperAmount);
+
+        token::transfer(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.authority_token_account.to_account_info(),
+                    to: ctx.accounts.market_token_account.to_account_info(),
+                    authority: ctx.accounts.authority.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
+
+        market.keeper_reserve += amount;
+
+        emit!(KeeperReserveFunded {
+            market_id: market.market_id,
+            amount,
+            keeper_reserve: market.keeper_reserve,
+        });
+
+        Ok(())
+    }
+
+    /// Permissionless cleanup: once a market is still unresolved `stale_grace_period` seconds
+    /// past `resolution_time`, anyone can put it into refund mode and collect the keeper reserve.
+    /// Voiding happens once, so a market can't be processed twice.
+    pub fn process_stale_market(ctx: Context<ProcessStaleMarket>) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+
+        require!(market.stale_grace_period > 0, ErrorCode::StaleProcessingDisabled);
+        require!(!market.resolved, ErrorCode::MarketAlreadyResolved);
+        require!(!market.voided, ErrorCode::MarketVoided);
+        require!(
+            Clock::get()?.unix_timestamp >= market.resolution_time + market.stale_grace_period,
+            ErrorCode::MarketNotStale
+        );
+        require_no_tranche_paid(market)?;
+
+        let reward = market.keeper_reserve;
+        market.voided = true;
+        market.keeper_reserve = 0;
+
+        if reward > 0 {
+            token::transfer(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    Transfer {
+                        from: ctx.accounts.market_token_account.to_account_info(),
+                        to: ctx.accounts.keeper_token_account.to_account_info(),
+                        authority: ctx.accounts.market.to_account_info(),
+                    },
+                    &[&[
+                        b"market",
+                        &ctx.accounts.market.market_id.to_le_bytes(),
+                        &[ctx.bumps.market],
+                    ]],
+                ),
+                reward,
+            )?;
+        }
+
+        let market = &ctx.accounts.market;
+        emit!(MarketVoided {
+            market_id: market.market_id,
+            total_pool: market.total_pool,
+        });
+
+        emit!(StaleMarketProcessed {
+            market_id: market.market_id,
+            keeper: ctx.accounts.keeper.key(),
+            reward,
+        });
+
+        Ok(())
+    }
+
+    /// Returns an unused keeper reserve to the authority once the market settled without going
+    /// stale.
+    pub fn reclaim_keeper_reserve(ctx: Context<ReclaimKeeperReserve>) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+
+        require!(ctx.accounts.authority.key() == market.authority, ErrorCode::Unauthorized);
+        require!(market.resolved || market.voided, ErrorCode::MarketNotResolved);
+
+        let amount = market.keeper_reserve;
+        require!(amount > 0, ErrorCode::NoPayoutAvailable);
+        market.keeper_reserve = 0;
+
+        token::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.market_token_account.to_account_info(),
+                    to: ctx.accounts.authority_token_account.to_account_info(),
+                    authority: ctx.accounts.market.to_account_info(),
+                },
+                &[&[
+                    b"market",
+                    &ctx.accounts.market.market_id.to_le_bytes(),
+                    &[ctx.bumps.market],
+                ]],
+            ),
+            amount,
+        )?;
+
+        emit!(KeeperReserveReclaimed {
+            market_id: ctx.accounts.market.market_id,
+            amount,
+        });
+
+        Ok(())
+    }
+
+    pub fn check_and_void_single_sided(ctx: Context<CheckAndVoidSingleSided>) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+
+        require!(market.void_if_single_sided, ErrorCode::SingleSidedVoidDisabled);
+        require!(!market.resolved, ErrorCode::MarketAlreadyResolved);
+        require!(!market.voided, ErrorCode::MarketVoided);
+        require!(deadline_passed(market, &Clock::get()?), ErrorCode::BettingStillOpen);
+
+        require!(funded_outcomes(market) == 1, ErrorCode::MarketNotSingleSided);
+
+        market.voided = true;
+
+        emit!(MarketVoided {
+            market_id: market.market_id,
+            total_pool: market.total_pool,
+        });
+
+        Ok(())
+    }
+
+    /// Voids a closed market where fewer than `min_diverse_outcomes` outcomes attracted any
+    /// stake. Such markets can't be resolved, so this is the only way forward for them.
+    pub fn validate_diversity(ctx: Context<ValidateDiversity>) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+
+        require!(!market.resolved, ErrorCode::MarketAlreadyResolved);
+        require!(!market.voided, ErrorCode::MarketVoided);
+        require!(deadline_passed(market, &Clock::get()?), ErrorCode::BettingStillOpen);
+        require!(
+            funded_outcomes(market) < market.min_diverse_outcomes as usize,
+            ErrorCode::MarketSufficientlyDiverse
+        );
+
+        market.voided = true;
+
+        emit!(MarketVoided {
+            market_id: market.market_id,
+            total_pool: market.total_pool,
+        });
+
+        Ok(())
+    }
+
+    /// Returns a voided bet's whole stake. A cancelled market's creation fee goes back to its
+    /// creator separately, through `refund_creation_fee`.
+    pub fn claim_refund(ctx: Context<ClaimRefund>) -> Result<()> {
+        let market = &ctx.accounts.market;
+        let bet = &mut ctx.accounts.bet;
+
+        require!(market.voided, ErrorCode::MarketNotVoided);
+        require!(!bet.claimed, ErrorCode::AlreadyClaimed);
+        require!(bet.bettor == ctx.accounts.bettor.key(), ErrorCode::Unauthorized);
+        require!(bet.market == market.key(), ErrorCode::InvalidBet);
+        require_token_market(market)?;
+        require_stake_vault(
+            market,
+            effective_outcome(market, bet.outcome_index),
+            ctx.accounts.market_token_account.key(),
+        )?;
+
+        bet.claimed = true;
+
+        // Return the original stake regardless of outcome
+        token::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.market_token_account.to_account_info(),
+                    to: ctx.accounts.bettor_token_account.to_account_info(),
+                    authority: ctx.accounts.market.to_account_info(),
+                },
+                &[&[
+                    b"market",
+                    &market.market_id.to_le_bytes(),
+                    &[ctx.bumps.market],
+                ]],
+            ),
+            bet.amount,
+        )?;
+
+        emit!(RefundClaimed {
+            bettor: bet.bettor,
+            market_id: market.market_id,
+            amount: bet.amount,
+        });
+
+        Ok(())
+    }
+
+    /// Adds `amount` to the pool without taking a position, sweetening every winner's payout.
+    /// Donations join `total_pool` but no outcome pool, so they pay out pro rata with the rest
+    /// of the pool. If the market is voided they go back to the donor through
+    /// `reclaim_donation` rather than to bettors, whose refunds stay their exact stakes.
+    pub fn donate(ctx: Context<Donate>, amount: u64) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        let clock = Clock::get()?;
+
+        require!(amount > 0, ErrorCode::InvalidDonation);
+        require!(!ctx.accounts.global_state.paused, ErrorCode::PlatformPaused);
+        require!(!market.cancelled, ErrorCode::MarketCancelled);
+        require!(!market.resolved, ErrorCode::MarketResolved);
+        require!(!market.voided, ErrorCode::MarketVoided);
+        require!(!deadline_passed(market, &clock), ErrorCode::BettingClosed);
+        // Fixed-odds payouts don't draw on the pool, so a donation there would never be paid out
+        require!(market.market_type != MarketType::FixedOdds, ErrorCode::WrongMarketType);
+        require_token_market(market)?;
+        require_combined_vault(market)?;
+
+        token::transfer(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.donor_token_account.to_account_info(),
+                    to: ctx.accounts.market_token_account.to_account_info(),
+                    authority: ctx.accounts.donor.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
+
+        market.total_pool = market.total_pool.checked_add(amount).ok_or(ErrorCode::MathOverflow)?;
+        market.donated_total = market.donated_total.checked_add(amount).ok_or(ErrorCode::MathOverflow)?;
+
+        let donation = &mut ctx.accounts.donation;
+        donation.market = market.key();
+        donation.donor = ctx.accounts.donor.key();
+        donation.amount = donation.amount.checked_add(amount).ok_or(ErrorCode::MathOverflow)?;
+
+        emit!(MarketDonation {
+            market_id: market.market_id,
+            donor: donation.donor,
+            amount,
+            donated_total: market.donated_total,
+            total_pool: market.total_pool,
+        });
+
+        Ok(())
+    }
+
+    /// Returns a donor's donations to a voided market and closes their donation record.
+    pub fn reclaim_donation(ctx: Context<ReclaimDonation>) -> Result<()> {
+        let market = &ctx.accounts.market;
+        let donation = &ctx.accounts.donation;
+
+        require!(market.voided, ErrorCode::MarketNotVoided);
+        require_token_market(market)?;
+        require_combined_vault(market)?;
+
+        token::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.market_token_account.to_account_info(),
+                    to: ctx.accounts.donor_token_account.to_account_info(),
+                    authority: ctx.accounts.market.to_account_info(),
+                },
+                &[&[
+                    b"market",
+                    &market.market_id.to_le_bytes(),
+                    &[ctx.bumps.market],
+                ]],
+            ),
+            donation.amount,
+        )?;
+
+        emit!(DonationReclaimed {
+            market_id: market.market_id,
+            donor: donation.donor,
+            amount: donation.amount,
+        });
+
+        Ok(())
+    }
+
+    pub fn reset_circuit_breaker(ctx: Context<ResetCircuitBreaker>) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+
+        require!(ctx.accounts.authority.key() == market.authority, ErrorCode::Unauthorized);
+        require!(market.circuit_breaker_tripped, ErrorCode::CircuitBreakerNotTripped);
+
+        // Start a fresh window from the reviewed pool so the surge doesn't re-trip immediately
+        market.circuit_breaker_tripped = false;
+        market.breaker_window_start = Clock::get()?.unix_timestamp;
+        market.breaker_window_pool = market.total_pool;
+
+        emit!(CircuitBreakerReset {
+            market_id: market.market_id,
+            total_pool: market.total_pool,
+        });
+
+        Ok(())
+    }
+
+    pub fn get_market_timing(ctx: Context<ReadMarket>) -> Result<()> {
+        let market = &ctx.accounts.market;
+        let clock = Clock::get()?;
+        let now = clock.unix_timestamp;
+
+        // Deadlines that have already passed report zero rather than a negative countdown
+        emit!(MarketTiming {
+            market_id: market.market_id,
+            time_until_close: (market.betting_cutoff - now).max(0),
+            time_until_resolution: (market.resolution_time - now).max(0),
+            slots_until_resolution: market.resolution_slot.saturating_sub(clock.slot),
+            status: market_status(market, &clock),
+        });
+
+        Ok(())
+    }
+
+    /// Returns the derived state frontends would otherwise recompute from the raw account: the
+    /// pool, each outcome's implied probability, countdowns and lifecycle status. A 10-outcome
+    /// market's state is 57 bytes, well inside the return data limit.
+    pub fn get_market_state(ctx: Context<ReadMarket>) -> Result<MarketState> {
+        let market = &ctx.accounts.market;
+        let clock = Clock::get()?;
+        let now = clock.unix_timestamp;
+
+        Ok(MarketState {
+            market_id: market.market_id,
+            total_pool: market.total_pool,
+            implied_probabilities_bps: implied_probabilities_bps(market),
+            // Deadlines that have already passed report zero rather than a negative countdown
+            time_until_cutoff: (market.betting_cutoff - now).max(0),
+            time_until_resolution: (market.resolution_time - now).max(0),
+            status: market_status(market, &clock),
+        })
+    }
+
+    /// Suggests a Kelly-criterion stake for a bettor who believes `outcome_index` wins with
+    /// probability `probability_bps`, priced at the current pool-implied odds. The bet's own
+    /// effect on the pools is ignored, so treat the result as an upper bound for large stakes.
+    pub fn kelly_stake(
+        ctx: Context<ReadMarket>,
+        outcome_index: u8,
+        probability_bps: u16,
+        bankroll: u64,
+    ) -> Result<()> {
+        let market = &ctx.accounts.market;
+
+        require!((outcome_index as usize) < market.outcomes.len(), ErrorCode::InvalidOutcome);
+        require!(probability_bps <= 10_000, ErrorCode::InvalidProbability);
+
+        let kelly_fraction_bps = kelly_fraction_bps(
+            market.outcome_pools[outcome_index as usize],
+            market.total_pool,
+            probability_bps,
+        );
+        let suggested_stake = (bankroll as u128 * kelly_fraction_bps as u128 / 10_000) as u64;
+
+        emit!(KellyStake {
+            market_id: market.market_id,
+            outcome_index,
+            probability_bps,
+            bankroll,
+            kelly_fraction_bps,
+            suggested_stake,
+        });
+
+        Ok(())
+    }
+
+    /// Quotes what staking `amount` on `outcome_index` would pay if it won, net of the protocol
+    /// and creator fees, using the same math as `claim_payout`. The stake itself is counted in
+    /// the pools, but later bets move them, so this is an estimate. Payout caps and consolation
+    /// pools are not applied.
+    pub fn quote_payout(ctx: Context<QuotePayout>, outcome_index: u8, amount: u64) -> Result<u64> {
+        let market = &ctx.accounts.market;
+
+        require!(!market.ranked, ErrorCode::RankedMarket);
+        require!(!market.scored_resolution, ErrorCode::ScoredMarket);
+        require!((outcome_index as usize) < market.outcomes.len(), ErrorCode::InvalidOutcome);
+        require!(market.merged_into[outcome_index as usize].is_none(), ErrorCode::OutcomeMerged);
+        require!(amount > 0, ErrorCode::BetTooSmall);
+
+        let gross_payout = projected_payout(market, outcome_index, amount, Clock::get()?.unix_timestamp)?;
+        let (net_payout, fee_amount, creator_fee_amount) =
+            payout_fees(market, ctx.accounts.global_state.fee_bps, gross_payout);
+
+        emit!(PayoutQuote {
+            market_id: market.market_id,
+            outcome_index,
+            amount,
+            gross_payout,
+            fee_amount,
+            creator_fee_amount,
+            net_payout,
+        });
+
+        Ok(net_payout)
+    }
+
+    /// Emits every input to a resolved bet's payout so anyone can check a claim independently.
+    pub fn get_payout_breakdown(ctx: Context<GetPayoutBreakdown>) -> Result<()> {
+        let market = &ctx.accounts.market;
+        let bet = &ctx.accounts.bet;
+
+        require!(market.resolved, ErrorCode::MarketNotResolved);
+        require!(bet.market == market.key(), ErrorCode::InvalidBet);
+
+        let winning_outcome = market.winning_outcome.unwrap();
+        let outcome = effective_outcome(market, bet.outcome_index);
+        let payout = if !market.final_ranking.is_empty() {
+            ranked_payout(market, bet)?
+        } else if market.weighted {
+            weighted_payout(market, bet)?
+        } else if market.market_type == MarketType::Scalar {
+            scalar_payout(market, outcome, bet.amount)?
+        } else if !market.outcome_scores.is_empty() {
+            scored_payout(market, outcome, bet.amount)
+        } else if outcome == winning_outcome {
+            stake_payout(market, bet.amount, bet.payout_capped, Clock::get()?.unix_timestamp)?
+        } else if Some(outcome) == market.runner_up {
+            consolation_payout(market, bet.amount)?
+        } else {
+            0
+        };
+        let platform_fee_bps = platform_fee_bps(market, ctx.accounts.global_state.fee_bps);
+        let fee_cap_room = fee_room(market);
+        let (net_payout, fee_amount, creator_fee_amount) =
+            payout_fees(market, ctx.accounts.global_state.fee_bps, payout);
+
+        emit!(PayoutBreakdown {
+            market_id: market.market_id,
+            bet: bet.key(),
+            bet_amount: bet.amount,
+            outcome_index: bet.outcome_index,
+            winning_outcome,
+            winning_pool: market.outcome_pools[winning_outcome as usize],
+            total_pool: market.total_pool,
+            max_payout_share_bps: market.max_payout_share_bps,
+            payout_capped: bet.payout_capped,
+            capped_stake: market.capped_stake,
+            capped_count: market.capped_count,
+            runner_up: market.runner_up,
+            consolation_pool: market.consolation_pool,
+            bonus_pool: market.seed_amounts.iter().sum(),
+            payout,
+            platform_fee_share_bps: market.platform_fee_share_bps,
+            platform_fee_bps,
+            creator_fee_bps: market.creator_fee_bps,
+            fee_cap_room,
+            fee_amount,
+            creator_fee_amount,
+            net_payout,
+        });
+
+        Ok(())
+    }
+
+    /// Classifies a market for operator triage. `grace_period` is how long after
+    /// `resolution_time` an unresolved market, or a settled market still holding tokens,
+    /// is tolerated before it is flagged.
+    pub fn market_health(ctx: Context<MarketHealthCheck>, grace_period: i64) -> Result<()> {
+        let market = &ctx.accounts.market;
+        let vault = &ctx.accounts.market_token_account;
+        let clock = Clock::get()?;
+
+        require!(vault.owner == market.key(), ErrorCode::InvalidMarketVault);
+
+        let overdue = clock.unix_timestamp >= market.resolution_time.saturating_add(grace_period);
+        let health = if market.resolved || market.voided {
+            if vault.amount == 0 {
+                MarketHealthStatus::Settled
+            } else if overdue {
+                MarketHealthStatus::StuckFunds
+            } else {
+                MarketHealthStatus::Healthy
+            }
+        } else if deadline_passed(market, &clock) && overdue {
+            MarketHealthStatus::OverdueResolution
+        } else {
+            MarketHealthStatus::Healthy
+        };
+
+        emit!(MarketHealth {
+            market_id: market.market_id,
+            health,
+            vault_balance: vault.amount,
+            total_pool: market.total_pool,
+        });
+
+        Ok(())
+    }
+
+    /// Resolves a standard market and pays up to `MAX_SETTLE_BATCH` winning bets in the same
+    /// transaction, so the largest positions don't have to claim. `remaining_accounts` holds
+    /// `[bet, bettor_token_account, bettor_freeze, bettor]` groups; each settled bet is closed
+    /// with rent returned to its bettor. Everyone else claims as usual. Not available on fee
+    /// token markets, where each winner signs for their own fee.
+    pub fn resolve_and_settle_top<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ResolveAndSettle<'info>>,
+        winning_outcome_index: u8,
+        evidence: Vec<[u8; 32]>,
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+        let resolver = ctx.accounts.authority.key();
+
+        validate_standard_resolution(&ctx.accounts.market, winning_outcome_index, resolver, &evidence, &clock)?;
+        require!(!ctx.accounts.market.scored_resolution, ErrorCode::ScoredMarket);
+        require!(!ctx.accounts.market.ranked, ErrorCode::RankedMarket);
+        require!(ctx.accounts.market.challenge_period == 0, ErrorCode::ProposalRequired);
+        require_token_market(&ctx.accounts.market)?;
+        require!(ctx.accounts.market.resolution_account.is_none(), ErrorCode::AccountResolvedMarket);
+        require!(ctx.accounts.market.withdrawal_delay == 0, ErrorCode::WithdrawalDelayActive);
+        require_combined_vault(&ctx.accounts.market)?;
+        require!(ctx.accounts.market.fee_token.is_none(), ErrorCode::FeeTokenMarket);
+        // Paying out in the same transaction would leave nothing for a guardian to veto
+        require!(ctx.accounts.market.guardian.is_none(), ErrorCode::VetoWindowOpen);
+        require!(ctx.remaining_accounts.chunks_exact(4).remainder().is_empty(), ErrorCode::InvalidSettleAccounts);
+        require!(ctx.remaining_accounts.len() / 4 <= MAX_SETTLE_BATCH, ErrorCode::BatchTooLarge);
+
+        let market = &mut ctx.accounts.market;
+        market.outcome_evidence = evidence;
+        apply_resolution(market, winning_outcome_index, resolver, clock.unix_timestamp)?;
+        write_resolved_outcome(&mut ctx.accounts.resolved_outcome, market, clock.unix_timestamp);
+
+        let fee_bps = ctx.accounts.global_state.fee_bps;
+        let market_key = ctx.accounts.market.key();
+        let market_id = ctx.accounts.market.market_id;
+        let market_id_bytes = market_id.to_le_bytes();
+        let bump = [ctx.bumps.market];
+        let signer_seeds: &[&[u8]] = &[b"market", &market_id_bytes, &bump];
+
+        let mut settled_count: u32 = 0;
+        let mut total_paid: u64 = 0;
+
+        for accounts in ctx.remaining_accounts.chunks(4) {
+            let mut bet = Account::<Bet>::try_from(&accounts[0])?;
+            let bettor_token_account = Account::<TokenAccount>::try_from(&accounts[1])?;
+            let bettor = &accounts[3];
+
+            require!(bet.market == market_key, ErrorCode::InvalidBet);
+            require!(!bet.claimed, ErrorCode::AlreadyClaimed);
+            require!(bettor.key() == bet.bettor, ErrorCode::Unauthorized);
+            require!(bettor_token_account.owner == bet.bettor, ErrorCode::Unauthorized);
+            let (freeze_key, _) = Pubkey::find_program_address(
+                &[b"bettor_freeze", market_key.as_ref(), bet.bettor.as_ref()],
+                &crate::ID,
+            );
+            require!(accounts[2].key() == freeze_key, ErrorCode::InvalidSettleAccounts);
+            require_not_frozen(&accounts[2])?;
+
+            // Fails with LosingBet for anything that isn't a winning position
+            let (payout, fee_amount, creator_fee_amount) =
+                settle_bet_payout(&mut ctx.accounts.market, &mut bet, fee_bps, clock.unix_timestamp)?;
+            ctx.accounts.market.claimed_count += 1;
+            let market = &ctx.accounts.market;
+
+            token::transfer(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    Transfer {
+                        from: ctx.accounts.market_token_account.to_account_info(),
+                        to: accounts[1].clone(),
+                        authority: ctx.accounts.market.to_account_info(),
+                    },
+                    &[signer_seeds],
+                ),
+                payout,
+            )?;
+
+            emit!(PayoutClaimed {
+                bettor: bet.bettor,
+                market_id: market.market_id,
+                bet_amount: bet.amount,
+                payout_amount: payout,
+                fee_amount,
+                creator_fee_amount,
+            });
+
+            settled_count += 1;
+            total_paid = total_paid.checked_add(payout).ok_or(ErrorCode::MathOverflow)?;
+
+            // Closed to the bettor, as claim_payout would
+            bet.close(bettor.clone())?;
+        }
+
+        let market = &mut ctx.accounts.market;
+        market.winnings_paid += total_paid;
+
+        emit!(MarketResolvedAndSettled {
+            market_id,
+            winning_outcome: winning_outcome_index,
+            settled_count,
+            total_paid,
+        });
+
+        Ok(())
+    }
+
+    /// Emits the lifetime totals of a resolved or voided market.
+    pub fn get_settlement_report(ctx: Context<ReadMarket>) -> Result<()> {
+        let market = &ctx.accounts.market;
+        require!(market.resolved || market.voided, ErrorCode::MarketStillActive);
+
+        emit!(settlement_report(market));
+        Ok(())
+    }
+
+    /// Refunds up to `MAX_REFUND_BATCH` bets on a voided market. `remaining_accounts` holds
+    /// `[bet, bettor_token_account, bettor]` triples; each bet is closed with rent returned to its bettor.
+    pub fn refund_batch<'info>(ctx: Context<'_, '_, 'info, 'info, RefundBatch<'info>>) -> Result<()> {
+        let market = &ctx.accounts.market;
+
+        require!(ctx.accounts.authority.key() == market.authority, ErrorCode::Unauthorized);
+        require!(market.voided, ErrorCode::MarketNotVoided);
+        require_token_market(market)?;
+        require_combined_vault(market)?;
+        require!(ctx.remaining_accounts.chunks_exact(3).remainder().is_empty(), ErrorCode::InvalidBatchAccounts);
+        require!(ctx.remaining_accounts.len() / 3 <= MAX_REFUND_BATCH, ErrorCode::BatchTooLarge);
+
+        let market_key = market.key();
+        let market_id_bytes = market.market_id.to_le_bytes();
+        let bump = [ctx.bumps.market];
+        let signer_seeds: &[&[u8]] = &[b"market", &market_id_bytes, &bump];
+
+        let mut refunded_count: u32 = 0;
+        let mut total_refunded: u64 = 0;
+
+        for accounts in ctx.remaining_accounts.chunks(3) {
+            let bet = Account::<Bet>::try_from(&accounts[0])?;
+            let bettor_token_account = Account::<TokenAccount>::try_from(&accounts[1])?;
+            let bettor = &accounts[2];
+
+            require!(bet.market == market_key, ErrorCode::InvalidBet);
+            require!(bettor.key() == bet.bettor, ErrorCode::Unauthorized);
+            require!(bettor_token_account.owner == bet.bettor, ErrorCode::Unauthorized);
+
+            // Already refunded through claim_refund; leave it for the bettor to close
+            if bet.claimed {
+                continue;
+            }
+
+            token::transfer(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    Transfer {
+                        from: ctx.accounts.market_token_account.to_account_info(),
+                        to: accounts[1].clone(),
+                        authority: ctx.accounts.market.to_account_info(),
+                    },
+                    &[signer_seeds],
+                ),
+                bet.amount,
+            )?;
+
+            emit!(RefundClaimed {
+                bettor: bet.bettor,
+                market_id: market.market_id,
+                amount: bet.amount,
+            });
+
+            refunded_count += 1;
+            total_refunded += bet.amount;
+
+            // Closing the account is what marks the bet as settled
+            bet.close(bettor.clone())?;
+        }
+
+        emit!(RefundBatchProcessed {
+            market_id: market.market_id,
+            refunded_count,
+            total_refunded,
+        });
+
+        Ok(())
+    }
+
+    /// Sums the tokens held by up to `MAX_TVL_BATCH` market vaults in `mint`, for TVL and
+    /// solvency snapshots. `remaining_accounts` holds (market, vault) pairs; the vault balance
+    /// is used directly so escrowed and unclaimed winnings count until they leave the program.
+    pub fn get_platform_tvl<'info>(
+        ctx: Context<'_, '_, 'info, 'info, GetPlatformTvl<'info>>,
+        mint: Pubkey,
+    ) -> Result<()> {
+        require!(ctx.remaining_accounts.chunks_exact(2).remainder().is_empty(), ErrorCode::InvalidTvlAccounts);
+        require!(ctx.remaining_accounts.len() / 2 <= MAX_TVL_BATCH, ErrorCode::BatchTooLarge);
+
+        let mut seen_markets: Vec<Pubkey> = Vec::with_capacity(MAX_TVL_BATCH);
+        let mut tvl: u64 = 0;
+        let mut total_pool: u64 = 0;
+
+        for accounts in ctx.remaining_accounts.chunks(2) {
+            let market = Account::<Market>::try_from(&accounts[0])?;
+            let vault = Account::<TokenAccount>::try_from(&accounts[1])?;
+
+            require!(vault.owner == market.key(), ErrorCode::InvalidTvlAccounts);
+            require!(vault.mint == mint, ErrorCode::MintMismatch);
+            // Counting a market twice would inflate the figure
+            require!(!seen_markets.contains(&market.key()), ErrorCode::InvalidTvlAccounts);
+            seen_markets.push(market.key());
+
+            tvl += vault.amount;
+            total_pool += market.total_pool;
+        }
+
+        emit!(PlatformTvl {
+            mint,
+            market_count: seen_markets.len() as u32,
+            tvl,
+            total_pool,
+        });
+
+        Ok(())
+    }
+
+    /// Creates the platform insurance fund and its vault for `mint`.
+    pub fn initialize_insurance_fund(ctx: Context<InitializeInsuranceFund>) -> Result<()> {
+        require!(ctx.accounts.authority.key() == ctx.accounts.global_state.authority, ErrorCode::Unauthorized);
+
+        let fund = &mut ctx.accounts.insurance_fund;
+        fund.mint = ctx.accounts.mint.key();
+        fund.vault = ctx.accounts.insurance_vault.key();
+        fund.total_contributed = 0;
+        fund.total_covered = 0;
+        fund.bump = ctx.bumps.insurance_fund;
+
+        Ok(())
+    }
+
+    /// Adds tokens to the insurance fund. Open to anyone so the platform or third parties can
+    /// top it up.
+    pub fn contribute_insurance(ctx: Context<ContributeInsurance>, amount: u64) -> Result<()> {
+        require!(amount > 0, ErrorCode::InvalidInsuranceAmount);
+
+        token::transfer(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.contributor_token_account.to_account_info(),
+                    to: ctx.accounts.insurance_vault.to_account_info(),
+                    authority: ctx.accounts.contributor.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
+
+        let fund = &mut ctx.accounts.insurance_fund;
+        fund.total_contributed += amount;
+
+        emit!(InsuranceContributed {
+            contributor: ctx.accounts.contributor.key(),
+            mint: fund.mint,
+            amount,
+            total_contributed: fund.total_contributed,
+        });
+
+        Ok(())
+    }
+
+    /// Moves `amount` from the insurance fund into an under-collateralized market vault so its
+    /// winners can still be paid in full.
+    pub fn cover_shortfall(ctx: Context<CoverShortfall>, amount: u64) -> Result<()> {
+        let market = &ctx.accounts.market;
+
+        require!(ctx.accounts.authority.key() == ctx.accounts.global_state.authority, ErrorCode::Unauthorized);
+        require!(amount > 0, ErrorCode::InvalidInsuranceAmount);
+        require!(ctx.accounts.market_token_account.owner == market.key(), ErrorCode::InvalidMarketVault);
+        require_token_market(market)?;
+        require_combined_vault(market)?;
+        require!(
+            ctx.accounts.market_token_account.mint == ctx.accounts.insurance_fund.mint,
+            ErrorCode::MintMismatch
+        );
+
+        let mint = ctx.accounts.insurance_fund.mint;
+        let bump = [ctx.accounts.insurance_fund.bump];
+        token::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.insurance_vault.to_account_info(),
+                    to: ctx.accounts.market_token_account.to_account_info(),
+                    authority: ctx.accounts.insurance_fund.to_account_info(),
+                },
+                &[&[b"insurance_fund", mint.as_ref(), &bump]],
+            ),
+            amount,
+        )?;
+
+        let fund = &mut ctx.accounts.insurance_fund;
+        fund.total_covered += amount;
+
+        emit!(ShortfallCovered {
+            market_id: market.market_id,
+            mint,
+            amount,
+            total_covered: fund.total_covered,
+        });
+
+        Ok(())
+    }
+
+    /// Permissionless crank for TWAP markets: reads the price from the market's TWAP source
+    /// and adds it to the running average. Samples are taken within the window before
+    /// `resolution_time`, at least `min_spacing` seconds apart.
+    pub fn sample_twap(ctx: Context<SampleTwap>) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        let now = Clock::get()?.unix_timestamp;
+
+        let twap = market.twap.ok_or(ErrorCode::WrongMarketType)?;
+        require!(ctx.accounts.source_account.key() == twap.source, ErrorCode::InvalidResolutionSource);
+        require!(!market.resolved, ErrorCode::MarketAlreadyResolved);
+        require!(!market.voided, ErrorCode::MarketVoided);
+        require!(
+            now >= market.resolution_time - twap.window && now <= market.resolution_time,
+            ErrorCode::OutsideTwapWindow
+        );
+        require!(
+            market.twap_samples == 0 || now - market.twap_last_at >= twap.min_spacing,
+            ErrorCode::TwapSampleTooSoon
+        );
+
+        let data = ctx.accounts.source_account.try_borrow_data()?;
+        let start = twap.offset as usize;
+        let bytes = data.get(start..start + 8).ok_or(ErrorCode::ResolutionDataOutOfRange)?;
+        let price = i64::from_le_bytes(bytes.try_into().unwrap());
+        drop(data);
+
+        if market.twap_samples == 0 {
+            market.twap_first_at = now;
+        } else {
+            let elapsed = (now - market.twap_last_at) as i128;
+            market.twap_cumulative = market
+                .twap_cumulative
+                .checked_add(market.twap_last_price as i128 * elapsed)
+                .ok_or(ErrorCode::MathOverflow)?;
+        }
+        market.twap_last_price = price;
+        market.twap_last_at = now;
+        market.twap_samples += 1;
+
+        emit!(TwapSampled {
+            market_id: market.market_id,
+            price,
+            sampled_at: now,
+            samples: market.twap_samples,
+        });
+
+        Ok(())
+    }
+
+    /// Permissionlessly settles a TWAP market once `resolution_time` has passed and enough
+    /// samples were taken, on their time-weighted average; see `twap_value`.
+    pub fn resolve_from_twap(ctx: Context<ResolveFromTwap>) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+
+        let twap = market.twap.ok_or(ErrorCode::WrongMarketType)?;
+        require!(!market.resolved, ErrorCode::MarketAlreadyResolved);
+        require!(!market.voided, ErrorCode::MarketVoided);
+        require!(deadline_passed(market, &Clock::get()?), ErrorCode::TooEarlyToResolve);
+        require!(market.twap_samples >= twap.min_samples, ErrorCode::NotEnoughTwapSamples);
+        require!(
+            funded_outcomes(market) >= market.min_diverse_outcomes as usize,
+            ErrorCode::InsufficientDiversity
+        );
+
+        let value = twap_value(market)?;
+        emit!(TwapResolved {
+            market_id: market.market_id,
+            twap: value,
+            samples: market.twap_samples,
+            first_sample_at: market.twap_first_at,
+            last_sample_at: market.twap_last_at,
+        });
+
+        // The samples stand in for the resolver's signature
+        let caller = ctx.accounts.caller.key();
+        if market.market_type == MarketType::Pivot {
+            settle_pivot(market, value, caller, Clock::get()?.unix_timestamp)?;
+        } else {
+            settle_scalar(market, value, caller, Clock::get()?.unix_timestamp)?;
+        }
+        write_resolved_outcome(&mut ctx.accounts.resolved_outcome, market, Clock::get()?.unix_timestamp);
+
+        Ok(())
+    }
+
+    /// Whitelists a yield vault's receipt mint for betting, reading its exchange rate from
+    /// `rate_source` at `rate_offset`. The source's current owner is recorded and checked on
+    /// every later read.
+    pub fn register_receipt_mint(ctx: Context<RegisterReceiptMint>, rate_offset: u32) -> Result<()> {
+        require!(ctx.accounts.authority.key() == ctx.accounts.global_state.authority, ErrorCode::Unauthorized);
+
+        let vault = ReceiptVault {
+            mint: ctx.accounts.mint.key(),
+            rate_source: ctx.accounts.rate_source.key(),
+            rate_source_owner: *ctx.accounts.rate_source.owner,
+            rate_offset,
+        };
+        let rate = read_receipt_rate(&ctx.accounts.rate_source, &vault)?;
+        ctx.accounts.receipt_mint_entry.vault = vault;
+
+        emit!(ReceiptMintRegistered {
+            mint: vault.mint,
+            rate_source: vault.rate_source,
+            rate,
+        });
+
+        Ok(())
+    }
+
+    /// Switches a market without bets, created in a whitelisted receipt mint, to valuing its
+    /// stakes as that vault's receipts. Stakes keep earning the vault's yield until
+    /// resolution, and payouts are made in the receipt token.
+    pub fn enable_receipt_betting(ctx: Context<EnableReceiptBetting>) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+
+        require!(ctx.accounts.authority.key() == market.authority, ErrorCode::Unauthorized);
+        require!(!market.immutable, ErrorCode::MarketImmutable);
+        require!(!market.resolved && !market.voided, ErrorCode::MarketResolved);
+        require!(market.total_pool == 0, ErrorCode::MarketAlreadyFunded);
+
+        market.receipt = Some(ctx.accounts.receipt_mint_entry.vault);
+        market.receipt_rate = 0;
+        market.receipt_rate_at = 0;
+
+        Ok(())
+    }
+
+    /// Reads a receipt market's exchange rate from its vault. Permissionless; betting needs a
+    /// rate no older than `MAX_RECEIPT_RATE_AGE`.
+    pub fn update_receipt_rate(ctx: Context<UpdateReceiptRate>) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        let now = Clock::get()?.unix_timestamp;
+
+        let vault = market.receipt.ok_or(ErrorCode::WrongMarketType)?;
+        market.receipt_rate = read_receipt_rate(&ctx.accounts.rate_source, &vault)?;
+        market.receipt_rate_at = now;
+
+        emit!(ReceiptRateUpdated {
+            market_id: market.market_id,
+            rate: market.receipt_rate,
+            updated_at: now,
+        });
+
+        Ok(())
+    }
+}
+
+/// Time-weighted average of a TWAP market's samples from the first one to `resolution_time`,
+/// with the latest price standing until the close.
+fn twap_value(market: &Market) -> Result<i64> {
+    let carried = market.twap_last_price as i128 * (market.resolution_time - market.twap_last_at) as i128;
+    let total = market.twap_cumulative.checked_add(carried).ok_or(ErrorCode::MathOverflow)?;
+    // Two samples at least `min_spacing` apart make the span positive
+    let span = (market.resolution_time - market.twap_first_at) as i128;
+    i64::try_from(total / span).map_err(|_| error!(ErrorCode::MathOverflow))
+}
+
+/// Reads a receipt token's exchange rate from `source`, which must be the vault's registered
+/// rate account under its registered owner.
+fn read_receipt_rate(source: &AccountInfo, vault: &ReceiptVault) -> Result<u64> {
+    require!(
+        source.key() == vault.rate_source && *source.owner == vault.rate_source_owner,
+        ErrorCode::InvalidReceiptRate
+    );
+    let data = source.try_borrow_data()?;
+    let start = vault.rate_offset as usize;
+    let bytes = data.get(start..start + 8).ok_or(ErrorCode::ResolutionDataOutOfRange)?;
+    let rate = u64::from_le_bytes(bytes.try_into().unwrap());
+    require!(rate > 0, ErrorCode::InvalidReceiptRate);
+    Ok(rate)
+}
+
+/// Underlying tokens `amount` receipt tokens redeem for at `rate`.
+fn receipt_underlying(amount: u64, rate: u64) -> Result<u64> {
+    u64::try_from(amount as u128 * rate as u128 / RECEIPT_RATE_SCALE as u128).map_err(|_| error!(ErrorCode::MathOverflow))
+}
+
+/// Underlying value of a new `amount` stake on `market`: the amount itself, or on a receipt
+/// market its value at a rate read within `MAX_RECEIPT_RATE_AGE`.
+fn underlying_value(market: &Market, amount: u64, now: i64) -> Result<u64> {
+    if market.receipt.is_none() {
+        return Ok(amount);
+    }
+    require!(
+        market.receipt_rate > 0 && now - market.receipt_rate_at <= MAX_RECEIPT_RATE_AGE,
+        ErrorCode::StaleReceiptRate
+    );
+    receipt_underlying(amount, market.receipt_rate)
+}
+
+/// Question length limit a freshly initialized platform starts with.
+pub const DEFAULT_MAX_QUESTION_LEN: u16 = 200;
+/// Upper bound for `GlobalState::max_question_len`, keeping markets well inside account size limits.
+pub const MAX_QUESTION_LEN_LIMIT: u16 = 1000;
+
+/// Highest creator fee a market can charge, 10% of each payout.
+pub const MAX_CREATOR_FEE_BPS: u16 = 1_000;
+
+/// Maximum number of bets `refund_batch` will process in one call.
+pub const MAX_REFUND_BATCH: usize = 10;
+
+/// Largest multiple of `min_bet` a graduated minimum can demand.
+pub const GRADUATED_MIN_BET_MAX_MULTIPLIER: u64 = 10;
+
+/// Most markets a basket can span.
+pub const MAX_BASKET_LEGS: usize = 5;
+
+/// Longest byte string an account-resolved market can compare against.
+pub const MAX_EXPECTED_BYTES: usize = 32;
+
+/// Highest accuracy score `resolve_market_scored` accepts for an outcome.
+pub const MAX_OUTCOME_SCORE: u8 = 100;
+
+/// Most outcomes a ranked market can order.
+pub const MAX_RANKED_OUTCOMES: usize = 6;
+
+/// Maximum number of bets `claim_payouts_batch` will claim in one call.
+pub const MAX_CLAIM_BATCH: usize = 10;
+
+/// Maximum number of winning bets `resolve_and_settle_top` will pay in one call.
+pub const MAX_SETTLE_BATCH: usize = 10;
+
+/// Maximum number of markets `get_platform_tvl` will sum in one call.
+pub const MAX_TVL_BATCH: usize = 20;
+
+/// Longest outcome label the market account reserves space for.
+pub const MAX_OUTCOME_LABEL_LEN: usize = 50;
+
+/// Most discovery tags a market can carry.
+pub const MAX_TAGS: usize = 5;
+
+/// Longest tag the market account reserves space for.
+pub const MAX_TAG_LEN: usize = 24;
+
+/// Maximum number of approved source token accounts per market.
+pub const MAX_ALLOWED_TOKEN_ACCOUNTS: usize = 10;
+
+/// Largest resolution committee a market can name.
+pub const MAX_COMMITTEE_SIZE: usize = 10;
+
+/// Most prompt-resolution fee tiers a market can set.
+pub const MAX_PROMPT_FEE_TIERS: usize = 4;
+
+/// Fixed-point scale of receipt token exchange rates: underlying tokens per receipt token.
+pub const RECEIPT_RATE_SCALE: u64 = 1_000_000_000;
+/// Fixed-point scale of a `FeeToken` conversion rate.
+pub const FEE_RATE_SCALE: u64 = 1_000_000;
+
+/// Oldest a receipt market's exchange rate may be, in seconds, for it to take new stakes.
+pub const MAX_RECEIPT_RATE_AGE: i64 = 300;
+
+/// Oldest a fee token's rate may be, in seconds, for a fee to be charged at it.
+pub const MAX_FEE_RATE_AGE: i64 = 300;
+
+/// Outcome index paid when a pivot market's actual value lands above the pivot.
+pub const PIVOT_OVER: u8 = 0;
+/// Outcome index paid when a pivot market's actual value lands below the pivot.
+pub const PIVOT_UNDER: u8 = 1;
+
+/// Outcome index of a scalar market's long side, paid more as the value nears the upper bound.
+pub const SCALAR_LONG: u8 = 0;
+/// Outcome index of a scalar market's short side, paid more as the value nears the lower bound.
+pub const SCALAR_SHORT: u8 = 1;
+
+/// Checks outcome labels fit the account and can be told apart: none blank, too long, or equal
+/// to another once trimmed and lowercased.
+fn validate_outcome_labels(labels: &[String]) -> Result<()> {
+    let normalized: Vec<String> = labels.iter().map(|label| label.trim().to_lowercase()).collect();
+    for (i, label) in labels.iter().enumerate() {
+        require!(label.len() <= MAX_OUTCOME_LABEL_LEN, ErrorCode::OutcomeLabelTooLong);
+        require!(!normalized[i].is_empty(), ErrorCode::EmptyOutcome);
+        require!(!normalized[..i].contains(&normalized[i]), ErrorCode::DuplicateOutcome);
+    }
+    Ok(())
+}
+
+/// Seed bytes of the id the next market takes. Creations race on `market_count`, so a caller
+/// naming the id it derived its market address from gets `MarketIdTaken` if another creation
+/// claimed it first, rather than a failed seeds check. Runs while the accounts are validated,
+/// before `init` would trip over the existing account.
+fn next_market_seed(global_state: &GlobalState, expected_market_id: Option<u64>) -> Result<[u8; 8]> {
+    if let Some(market_id) = expected_market_id {
+        require!(market_id == global_state.market_count, ErrorCode::MarketIdTaken);
+    }
+    Ok(global_state.market_count.to_le_bytes())
+}
+
+/// Exact size of a market account for `question` and `outcomes`. `INIT_SPACE` counts only
+/// their length prefixes, so the question's bytes and each label's prefix and bytes are added
+/// here. Blind markets reserve full-length labels, since `reveal_outcomes` replaces their
+/// placeholders after creation.
+fn market_space(question: &str, outcomes: &[String], blind: bool) -> usize {
+    let labels: usize = if blind {
+        outcomes.len() * (4 + MAX_OUTCOME_LABEL_LEN)
+    } else {
+        outcomes.iter().map(|label| 4 + label.len()).sum()
+    };
+    8 + Market::INIT_SPACE + question.len() + labels
+}
+
+/// Checks shared by every path that settles a standard market on `winning_outcome_index`.
+fn validate_standard_resolution(
+    market: &Market,
+    winning_outcome_index: u8,
+    resolver: Pubkey,
+    evidence: &[[u8; 32]],
+    clock: &Clock,
+) -> Result<()> {
+    require!((winning_outcome_index as usize) < market.outcomes.len(), ErrorCode::InvalidOutcome);
+    require!(resolver == outcome_resolver(market, winning_outcome_index), ErrorCode::Unauthorized);
+    // Committee markets resolve only through `submit_resolution_vote`
+    require!(market.resolvers.is_empty(), ErrorCode::CommitteeMarket);
+    validate_resolvable(market, winning_outcome_index, evidence, clock)
+}
+
+/// State checks for settling `market` on `winning_outcome_index`, whoever the resolver is.
+fn validate_resolvable(market: &Market, winning_outcome_index: u8, evidence: &[[u8; 32]], clock: &Clock) -> Result<()> {
+    require!((winning_outcome_index as usize) < market.outcomes.len(), ErrorCode::InvalidOutcome);
+    require!(market.merged_into[winning_outcome_index as usize].is_none(), ErrorCode::OutcomeMerged);
+    require!(!market.resolved, ErrorCode::MarketAlreadyResolved);
+    require!(!market.voided, ErrorCode::MarketVoided);
+    require!(deadline_passed(market, clock), ErrorCode::TooEarlyToResolve);
+    // Fixed-odds markets also settle on a named outcome; only pivot and scalar markets resolve
+    // from a value
+    require!(
+        !matches!(market.market_type, MarketType::Pivot | MarketType::Scalar),
+        ErrorCode::WrongMarketType
+    );
+    require!(
+        market.outcome_commitments.is_empty() || market.outcomes_revealed,
+        ErrorCode::OutcomesNotRevealed
+    );
+    require!(
+        funded_outcomes(market) >= market.min_diverse_outcomes as usize,
+        ErrorCode::InsufficientDiversity
+    );
+    require!(
+        evidence.len() == market.outcomes.len() || (evidence.is_empty() && !market.require_outcome_evidence),
+        ErrorCode::InvalidOutcomeEvidence
+    );
+    // A half-split vault can't pay winners proportionally; finish splitting first
+    require!(
+        market.outcome_vaults.is_empty() || market.outcome_vaults.len() == market.outcomes.len(),
+        ErrorCode::OutcomeVaultsIncomplete
+    );
+    Ok(())
+}
+
+/// Number of outcomes holding any stake.
+fn funded_outcomes(market: &Market) -> usize {
+    market.outcome_pools.iter().filter(|pool| **pool > 0).count()
+}
+
+/// Fails while any bet or seed on the winner or runner-up of a resolved market is unpaid.
+fn require_claims_settled(market: &Market) -> Result<()> {
+    let bets_on = |outcome: u8| market.bet_counts[outcome as usize];
+    let paying_bets = bets_on(market.winning_outcome.unwrap()) + market.runner_up.map_or(0, bets_on);
+    require!(market.claimed_count >= paying_bets, ErrorCode::ClaimsOutstanding);
+    let seed_on = |outcome: u8| market.seed_amounts.get(outcome as usize).copied().unwrap_or(0);
+    let paying_seed = seed_on(market.winning_outcome.unwrap()) + market.runner_up.map_or(0, seed_on);
+    require!(paying_seed == 0 || market.seed_reclaimed, ErrorCode::ClaimsOutstanding);
+    Ok(())
+}
+
+/// Checks shared by every path that adds a new stake to `market`.
+fn validate_new_stake(market: &Market, outcome_index: u8, amount: u64, clock: &Clock) -> Result<()> {
+    require!(!market.quarantined, ErrorCode::MarketQuarantined);
+    require!(!market.cancelled, ErrorCode::MarketCancelled);
+    require!(!market.resolved, ErrorCode::MarketResolved);
+    require!(!market.voided, ErrorCode::MarketVoided);
+    require!(!market.circuit_breaker_tripped, ErrorCode::CircuitBreakerTripped);
+    require!(!market.betting_closed, ErrorCode::BettingClosed);
+    require!(!deadline_passed(market, clock), ErrorCode::BettingClosed);
+    require!(!cutoff_passed(market, clock), ErrorCode::BettingCutoffPassed);
+    require!((outcome_index as usize) < market.outcomes.len(), ErrorCode::InvalidOutcome);
+    require!(amount >= effective_min_bet(market, outcome_index), ErrorCode::BetTooSmall);
+    if let Some(max_bet) = market.max_bet {
+        require!(amount <= max_bet, ErrorCode::BetTooLarge);
+    }
+    if let Some(max_total_pool) = market.max_total_pool {
+        require!(market.total_pool.saturating_add(amount) <= max_total_pool, ErrorCode::PoolCapReached);
+    }
+    require!(market.merged_into[outcome_index as usize].is_none(), ErrorCode::OutcomeMerged);
+    Ok(())
+}
+
+/// Whether betting has closed and resolution may begin, measured in slots or seconds
+/// depending on the market's mode.
+fn deadline_passed(market: &Market, clock: &Clock) -> bool {
+    if market.use_slots {
+        clock.slot >= market.resolution_slot
+    } else {
+        clock.unix_timestamp >= market.resolution_time
+    }
+}
+
+/// Whether the market's betting cutoff has passed. Slot-based markets have no separate cutoff.
+fn cutoff_passed(market: &Market, clock: &Clock) -> bool {
+    !market.use_slots && clock.unix_timestamp >= market.betting_cutoff
+}
+
+/// Minimum stake on `outcome_index`. With `graduated_min_bet`, `min_bet` is scaled by the
+/// outcome's fair share over its implied probability, `total_pool / (outcomes * pool)`, so an
+/// outcome priced at an even split keeps `min_bet` and long shots need proportionally more.
+/// The scaled minimum never drops below `min_bet` and is capped at
+/// `GRADUATED_MIN_BET_MAX_MULTIPLIER` times it, which is also what an unbacked outcome requires.
+fn effective_min_bet(market: &Market, outcome_index: u8) -> u64 {
+    if !market.graduated_min_bet || market.total_pool == 0 {
+        return market.min_bet;
+    }
+
+    let ceiling = market.min_bet as u128 * GRADUATED_MIN_BET_MAX_MULTIPLIER as u128;
+    let pool = market.outcome_pools[outcome_index as usize] as u128;
+    if pool == 0 {
+        return ceiling as u64;
+    }
+
+    let scaled = market.min_bet as u128 * market.total_pool as u128 / (market.outcomes.len() as u128 * pool);
+    scaled.clamp(market.min_bet as u128, ceiling) as u64
+}
+
+/// Rejects a bettor whose freeze record exists. Only the program can create data at that PDA.
+fn require_not_frozen(bettor_freeze: &AccountInfo) -> Result<()> {
+    require!(bettor_freeze.data_is_empty(), ErrorCode::FrozenBettor);
+    Ok(())
+}
+
+/// Rejects stakes on a whitelist market from bettors without a whitelist entry.
+fn require_whitelisted(market: &Market, whitelist_entry: &AccountInfo) -> Result<()> {
+    require!(
+        market.access_mode == AccessMode::Public || !whitelist_entry.data_is_empty(),
+        ErrorCode::NotWhitelisted
+    );
+    Ok(())
+}
+
+/// Holds back payouts on a guarded market until its guardian can no longer veto the
+/// resolution.
+fn require_veto_window_closed(market: &Market, now: i64) -> Result<()> {
+    require!(
+        market.guardian.is_none() || now >= market.resolved_at + market.veto_window,
+        ErrorCode::VetoWindowOpen
+    );
+    Ok(())
+}
+
+/// When winning claims on `market` close, if it has a claim deadline. Counted from
+/// `resolution_time`, or from when claims open if that came later: the final resolution plus
+/// its veto window. A late resolution never shortens the window winners get, and a vetoed one
+/// restarts it, so time spent in dispute doesn't count.
+fn claim_deadline_at(market: &Market) -> Option<i64> {
+    (market.claim_deadline > 0)
+        .then(|| market.resolution_time.max(market.resolved_at + market.veto_window) + market.claim_deadline)
+}
+
+/// Errors once `market`'s claim deadline has passed; `reclaim_unclaimed` owns what's left.
+fn require_claim_deadline_open(market: &Market, now: i64) -> Result<()> {
+    if let Some(deadline) = claim_deadline_at(market) {
+        require!(now < deadline, ErrorCode::ClaimDeadlinePassed);
+    }
+    Ok(())
+}
+
+/// Errors on markets whose stakes have been split across per-outcome vaults, for instructions
+/// that only know how to move funds through the combined vault.
+fn require_combined_vault(market: &Market) -> Result<()> {
+    require!(market.outcome_vaults.is_empty(), ErrorCode::VaultSplit);
+    Ok(())
+}
+
+fn set_paused(global_state: &mut GlobalState, authority: Pubkey, paused: bool) -> Result<()> {
+    require!(authority == global_state.authority, ErrorCode::Unauthorized);
+
+    global_state.paused = paused;
+
+    emit!(PlatformPauseSet { paused, authority });
+
+    Ok(())
+}
+
+/// Rejects token instructions on a native SOL market, whose stakes sit in its `sol_vault`.
+fn require_token_market(market: &Market) -> Result<()> {
+    require!(!market.is_native, ErrorCode::NativeMarket);
+    Ok(())
+}
+
+/// Pays `amount` lamports out of a native market's vault, never dipping into the rent-exempt
+/// minimum it was funded with at creation.
+fn transfer_from_sol_vault<'info>(
+    sol_vault: &SystemAccount<'info>,
+    to: &AccountInfo<'info>,
+    system_program: &Program<'info, System>,
+    market: Pubkey,
+    bump: u8,
+    amount: u64,
+) -> Result<()> {
+    let spendable = sol_vault.lamports().saturating_sub(Rent::get()?.minimum_balance(0));
+    require!(amount <= spendable, ErrorCode::SolVaultShortfall);
+
+    system_program::transfer(
+        CpiContext::new_with_signer(
+            system_program.to_account_info(),
+            system_program::Transfer {
+                from: sol_vault.to_account_info(),
+                to: to.clone(),
+            },
+            &[&[b"sol_vault", market.as_ref(), &[bump]]],
+        ),
+        amount,
+    )
+}
+
+/// Checks that stakes on `outcome_index` move through `vault`: the outcome's own sub-vault once
+/// it has one, and never another outcome's sub-vault otherwise.
+fn require_stake_vault(market: &Market, outcome_index: u8, vault: Pubkey) -> Result<()> {
+    match market.outcome_vaults.get(outcome_index as usize) {
+        Some(outcome_vault) => require!(vault == *outcome_vault, ErrorCode::InvalidOutcomeVaults),
+        None => require!(!market.outcome_vaults.contains(&vault), ErrorCode::InvalidOutcomeVaults),
+    }
+    Ok(())
+}
+
+/// Deducts fees from a winning payout and books them against the market: the protocol fee
+/// comes off the gross payout first, at the market's resolution-time share of `fee_bps` and
+/// never past its fee cap, then the creator fee off what remains. Returns the net payout, the
+/// protocol fee and the creator fee.
+fn take_fees(market: &mut Market, fee_bps: u16, payout: u64) -> (u64, u64, u64) {
+    let (net, fee, creator_fee) = payout_fees(market, fee_bps, payout);
+    market.creator_fees_accrued += creator_fee;
+    market.creator_fees_collected += creator_fee;
+    // A fee token market's platform fee is booked by `charge_fee_token` instead
+    if market.fee_token.is_none() {
+        market.fees_accrued += fee;
+        market.fees_collected += fee;
+        if fee > 0 && market.fee_cap == Some(market.fees_collected) {
+            emit!(FeeCapReached {
+                market_id: market.market_id,
+                fee_cap: market.fees_collected,
+            });
+        }
+    }
+    (net, fee, creator_fee)
+}
+
+/// `split_fees` at `market`'s fee rates and cap. A fee token market's winner pays the platform
+/// fee separately, so it stays in the net payout.
+fn payout_fees(market: &Market, fee_bps: u16, payout: u64) -> (u64, u64, u64) {
+    let (net, fee, creator_fee) =
+        split_fees(payout, platform_fee_bps(market, fee_bps), market.creator_fee_bps, fee_room(market));
+    if market.fee_token.is_some() {
+        (net + fee, fee, creator_fee)
+    } else {
+        (net, fee, creator_fee)
+    }
+}
+
+/// Charges a fee token market's platform fee of `fee_amount` payout tokens to the bettor's fee
+/// token account, converted at the rate source's current rate, and books it in
+/// `fee_token_fees_collected`. Does nothing on other markets.
+fn charge_fee_token<'info>(
+    market: &mut Market,
+    fee_amount: u64,
+    fee_payer_token_account: Option<&Account<'info, TokenAccount>>,
+    fee_treasury: Option<&Account<'info, TokenAccount>>,
+    fee_rate_source: Option<&UncheckedAccount<'info>>,
+    bettor: &Signer<'info>,
+    token_program: &Program<'info, Token>,
+) -> Result<()> {
+    let Some(fee_token) = market.fee_token else {
+        return Ok(());
+    };
+    if fee_amount == 0 {
+        return Ok(());
+    }
+    let (Some(fee_payer_token_account), Some(fee_treasury), Some(fee_rate_source)) =
+        (fee_payer_token_account, fee_treasury, fee_rate_source)
+    else {
+        return err!(ErrorCode::FeeTokenAccountsRequired);
+    };
+    require!(fee_treasury.key() == fee_token.treasury, ErrorCode::InvalidFeeTreasury);
+    require!(
+        fee_payer_token_account.mint == fee_token.mint && fee_treasury.mint == fee_token.mint,
+        ErrorCode::MintMismatch
+    );
+    require!(fee_payer_token_account.owner == bettor.key(), ErrorCode::InvalidTokenAccountOwner);
+
+    let rate = read_fee_rate(&fee_token, fee_rate_source, Clock::get()?.unix_timestamp)?;
+    let fee_token_amount = to_u64(fee_amount as u128 * rate as u128 / FEE_RATE_SCALE as u128)?;
+    token::transfer(
+        CpiContext::new(
+            token_program.to_account_info(),
+            Transfer {
+                from: fee_payer_token_account.to_account_info(),
+                to: fee_treasury.to_account_info(),
+                authority: bettor.to_account_info(),
+            },
+        ),
+        fee_token_amount,
+    )?;
+    market.fee_token_fees_collected += fee_token_amount;
+
+    emit!(FeeTokenCharged {
+        bettor: bettor.key(),
+        market_id: market.market_id,
+        fee_amount,
+        rate,
+        fee_token_amount,
+    });
+
+    Ok(())
+}
+
+/// Reads a fee token's conversion rate from `source`, which must be its rate source under the
+/// configured owner and updated within `MAX_FEE_RATE_AGE` of `now`. A zero rate would waive
+/// the fee, so it's refused.
+fn read_fee_rate(fee_token: &FeeToken, source: &AccountInfo, now: i64) -> Result<u64> {
+    require!(
+        source.key() == fee_token.rate_source && *source.owner == fee_token.rate_source_owner,
+        ErrorCode::InvalidFeeRate
+    );
+    let data = source.try_borrow_data()?;
+    let word = |offset: u32| -> Result<[u8; 8]> {
+        let start = offset as usize;
+        let bytes = data.get(start..start + 8).ok_or(ErrorCode::ResolutionDataOutOfRange)?;
+        Ok(bytes.try_into().unwrap())
+    };
+    let rate = u64::from_le_bytes(word(fee_token.rate_offset)?);
+    let updated_at = i64::from_le_bytes(word(fee_token.updated_at_offset)?);
+    require!(rate > 0, ErrorCode::InvalidFeeRate);
+    require!(now - updated_at <= MAX_FEE_RATE_AGE, ErrorCode::StaleFeeRate);
+    Ok(rate)
+}
+
+/// What's left to pay on a winning bet, with the fees booked by this call. The first claim on
+/// a bet, whole or partial, takes fees on its full payout, fixes the net in `payout_due` and
+/// counts the winner in `winners_paid` unless a preliminary tranche already did; later claims
+/// just draw down what `claimed_amount` hasn't covered.
+fn settle_bet_payout(market: &mut Market, bet: &mut Bet, fee_bps: u16, now: i64) -> Result<(u64, u64, u64)> {
+    if bet.claimed_amount > 0 {
+        return Ok((bet.payout_due - bet.claimed_amount, 0, 0));
+    }
+    // A preliminary tranche already paid is part of the bet's payout, not on top of it
+    let gross = winning_payout(market, bet, now)?.saturating_sub(bet.tranche_payout);
+    let (net, fee, creator_fee) = take_fees(market, fee_bps, gross);
+    if !bet.tranche_claimed {
+        market.winners_paid += 1;
+    }
+    bet.payout_due = net;
+    Ok((net, fee, creator_fee))
+}
+
+/// The fee arithmetic behind `take_fees`, without booking anything. The platform fee stops at
+/// `max_fee`, leaving the rest with the winner.
+fn split_fees(payout: u64, fee_bps: u16, creator_fee_bps: u16, max_fee: u64) -> (u64, u64, u64) {
+    let fee = ((payout as u128 * fee_bps as u128 / 10_000) as u64).min(max_fee);
+    let creator_fee = ((payout - fee) as u128 * creator_fee_bps as u128 / 10_000) as u64;
+    (payout - fee - creator_fee, fee, creator_fee)
+}
+
+/// Gross payout `amount` on `outcome_index` would earn if it won, with the stake added to the
+/// current pools. Follows `stake_payout` for uncapped markets.
+fn projected_payout(market: &Market, outcome_index: u8, amount: u64, now: i64) -> Result<u64> {
+    let idx = outcome_index as usize;
+    if market.market_type == MarketType::FixedOdds {
+        return to_u64(amount as u128 * market.fixed_odds_bps[idx] as u128 / 10_000);
+    }
+
+    let total_pool = market.total_pool as u128 + amount as u128;
+    if market.weighted {
+        let weight = stake_weight(market, amount, now)? as u128;
+        let weighted_pool = market.weighted_pools[idx] as u128 + weight;
+        if weighted_pool == 0 {
+            return Ok(0);
+        }
+        return to_u64(weight * total_pool / weighted_pool);
+    }
+    let winning_pool = market.outcome_pools[idx] as u128 + amount as u128;
+    to_u64(amount as u128 * total_pool / winning_pool)
+}
+
+/// Platform fee rate `market` charges on payouts: `fee_bps` scaled by the share fixed at
+/// resolution.
+fn platform_fee_bps(market: &Market, fee_bps: u16) -> u16 {
+    (fee_bps as u32 * market.platform_fee_share_bps as u32 / 10_000) as u16
+}
+
+/// Platform fee `market` may still take before reaching its fee cap.
+fn fee_room(market: &Market) -> u64 {
+    market.fee_cap.map_or(u64::MAX, |cap| cap.saturating_sub(market.fees_collected))
+}
+
+/// Pays `amount` out of a split market's outcome vaults, passed in outcome order, in
+/// proportion to their balances.
+fn transfer_from_outcome_vaults<'info>(
+    market: &Account<'info, Market>,
+    outcome_vaults: &'info [AccountInfo<'info>],
+    to: &AccountInfo<'info>,
+    token_program: &AccountInfo<'info>,
+    signer_seeds: &[&[u8]],
+    amount: u64,
+) -> Result<()> {
+    require!(
+        market.outcome_vaults.len() == market.outcomes.len(),
+        ErrorCode::OutcomeVaultsIncomplete
+    );
+    require!(outcome_vaults.len() == market.outcome_vaults.len(), ErrorCode::InvalidOutcomeVaults);
+
+    let mut balances = Vec::with_capacity(outcome_vaults.len());
+    for (info, expected) in outcome_vaults.iter().zip(market.outcome_vaults.iter()) {
+        require!(info.key() == *expected, ErrorCode::InvalidOutcomeVaults);
+        balances.push(Account::<TokenAccount>::try_from(info)?.amount);
+    }
+
+    let shares = proportional_shares(amount, &balances)?;
+    for (info, share) in outcome_vaults.iter().zip(shares) {
+        if share == 0 {
+            continue;
+        }
+        token::transfer(
+            CpiContext::new_with_signer(
+                token_program.clone(),
+                Transfer {
+                    from: info.clone(),
+                    to: to.clone(),
+                    authority: market.to_account_info(),
+                },
+                &[signer_seeds],
+            ),
+            share,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Splits `amount` across vaults in proportion to `balances`. Rounding dust is taken from
+/// whichever vaults still have room, in order.
+fn proportional_shares(amount: u64, balances: &[u64]) -> Result<Vec<u64>> {
+    let total: u64 = balances.iter().sum();
+    require!(total >= amount, ErrorCode::InvalidOutcomeVaults);
+
+    let mut shares: Vec<u64> = balances
+        .iter()
+        .map(|balance| (amount as u128 * *balance as u128 / total.max(1) as u128) as u64)
+        .collect();
+    let mut remaining = amount - shares.iter().sum::<u64>();
+    for (share, balance) in shares.iter_mut().zip(balances) {
+        let extra = remaining.min(balance - *share);
+        *share += extra;
+        remaining -= extra;
+    }
+
+    Ok(shares)
+}
+
+/// The outcome a bet on `outcome_index` now pays out on, following any merges.
+fn effective_outcome(market: &Market, outcome_index: u8) -> u8 {
+    market
+        .merged_into
+        .get(outcome_index as usize)
+        .copied()
+        .flatten()
+        .unwrap_or(outcome_index)
+}
+
+/// Adds a stake that has already been transferred into the vault to the market's pools.
+fn credit_stake(market: &mut Market, bettor: Pubkey, outcome_index: u8, amount: u64, now: i64) -> Result<u64> {
+    let outcome = outcome_index as usize;
+    let weight = stake_weight(market, amount, now)?;
+    if market.weighted {
+        market.weighted_pools[outcome] = market.weighted_pools[outcome]
+            .checked_add(weight)
+            .ok_or(ErrorCode::MathOverflow)?;
+    }
+    market.outcome_pools[outcome] = market.outcome_pools[outcome]
+        .checked_add(amount)
+        .ok_or(ErrorCode::MathOverflow)?;
+    market.total_pool = market.total_pool.checked_add(amount).ok_or(ErrorCode::MathOverflow)?;
+    market.total_staked = market.total_staked.checked_add(amount).ok_or(ErrorCode::MathOverflow)?;
+    if bettor == market.authority {
+        market.authority_stakes[outcome] = market.authority_stakes[outcome]
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+    }
+    if market.circuit_breaker_bps > 0 {
+        update_circuit_breaker(market, amount, now);
+    }
+    refresh_active(market);
+    Ok(weight)
+}
+
+/// Payout weight of `amount` staked at `now`. On time-weighted markets the stake decays
+/// linearly from its full amount at creation to zero at `resolution_time`; elsewhere the
+/// weight is the stake.
+fn stake_weight(market: &Market, amount: u64, now: i64) -> Result<u64> {
+    if !market.weighted {
+        return Ok(amount);
+    }
+    let window = (market.resolution_time - market.created_at).max(1);
+    let remaining = (market.resolution_time - now).clamp(0, window);
+    to_u64(amount as u128 * remaining as u128 / window as u128)
+}
+
+/// Share of the whole pool owed to `bet` on a resolved time-weighted market, by its weight
+/// against the winning outcome's `weighted_pools` total. Zero for losing bets.
+fn weighted_payout(market: &Market, bet: &Bet) -> Result<u64> {
+    let winning_outcome = market.winning_outcome.unwrap();
+    let weighted_pool = market.weighted_pools[winning_outcome as usize];
+    if effective_outcome(market, bet.outcome_index) != winning_outcome || weighted_pool == 0 {
+        return Ok(0);
+    }
+    to_u64(bet.weight as u128 * market.total_pool as u128 / weighted_pool as u128)
+}
+
+/// Marks the market active while every unmerged outcome's pool holds at least
+/// `min_liquidity_per_outcome`. Rerun on every pool change, since `cancel_bet` can take a
+/// market back below the threshold.
+fn refresh_active(market: &mut Market) {
+    let was_active = market.active;
+    market.active = market
+        .outcome_pools
+        .iter()
+        .zip(market.merged_into.iter())
+        .all(|(pool, merged)| merged.is_some() || *pool >= market.min_liquidity_per_outcome);
+
+    if market.active && !was_active && market.min_liquidity_per_outcome > 0 {
+        emit!(MarketActivated {
+            market_id: market.market_id,
+            outcome_pools: market.outcome_pools.clone(),
+        });
+    }
+}
+
+/// Records a newly opened bet on `outcome_index` in the market's bet counts.
+fn count_bet(market: &mut Market, outcome_index: u8) {
+    market.bet_counts[outcome_index as usize] += 1;
+    market.total_bets += 1;
+}
+
+fn settlement_report(market: &Market) -> SettlementReport {
+    SettlementReport {
+        market_id: market.market_id,
+        total_volume: market.total_staked,
+        platform_fees: market.fees_collected,
+        creator_fees: market.creator_fees_collected,
+        winners_paid: market.winners_paid,
+        total_paid_out: market.winnings_paid,
+        dust_swept: market.dust_swept,
+        unclaimed_forfeited: market.unclaimed_forfeited,
+        fee_token_fees: market.fee_token_fees_collected,
+    }
+}
+
+/// Token account a market's dust and forfeited winnings are swept to. Token markets fix theirs
+/// at creation, falling back to the treasury's account at the time.
+fn residual_destination(market: &Market, global_state: &GlobalState) -> Pubkey {
+    market.residual_destination.unwrap_or(global_state.treasury)
+}
+
+/// Payout owed to a winning `bet` on a resolved market.
+fn winning_payout(market: &Market, bet: &Bet, now: i64) -> Result<u64> {
+    if !market.final_ranking.is_empty() {
+        let payout = ranked_payout(market, bet)?;
+        require!(payout > 0, ErrorCode::LosingBet);
+        return Ok(payout);
+    }
+
+    if market.weighted {
+        let payout = weighted_payout(market, bet)?;
+        require!(payout > 0, ErrorCode::LosingBet);
+        return Ok(payout);
+    }
+
+    outcome_payout(market, bet.outcome_index, bet.amount, bet.payout_capped, now)
+}
+
+/// Gross payout owed to `amount` staked on `outcome_index` of a resolved, unranked market.
+fn outcome_payout(market: &Market, outcome_index: u8, amount: u64, payout_capped: bool, now: i64) -> Result<u64> {
+    if market.market_type == MarketType::Scalar {
+        let payout = scalar_payout(market, effective_outcome(market, outcome_index), amount)?;
+        require!(payout > 0, ErrorCode::LosingBet);
+        return Ok(payout);
+    }
+
+    if !market.outcome_scores.is_empty() {
+        let payout = scored_payout(market, effective_outcome(market, outcome_index), amount);
+        require!(payout > 0, ErrorCode::LosingBet);
+        return Ok(payout);
+    }
+
+    let outcome = Some(effective_outcome(market, outcome_index));
+    if outcome == market.winning_outcome {
+        stake_payout(market, amount, payout_capped, now)
+    } else if outcome == market.runner_up {
+        consolation_payout(market, amount)
+    } else {
+        err!(ErrorCode::LosingBet)
+    }
+}
+
+/// Payout owed to `amount` staked on `outcome` of a resolved scalar market: its side's share of
+/// the pool, split pro rata.
+fn scalar_payout(market: &Market, outcome: u8, amount: u64) -> Result<u64> {
+    let pool = market.outcome_pools[outcome as usize];
+    if pool == 0 {
+        return Ok(0);
+    }
+    let share = if outcome == SCALAR_LONG {
+        market.scalar_long_share
+    } else {
+        market.total_pool - market.scalar_long_share
+    };
+    to_u64(amount as u128 * share as u128 / pool as u128)
+}
+
+/// Long holders' part of `pool` at `value`, already clamped: `pool * (value - lower) / (upper -
+/// lower)`, or all or none of it when one side has no stake.
+fn scalar_long_share(market: &Market, pool: u64, value: i64) -> Result<u64> {
+    if market.outcome_pools[SCALAR_LONG as usize] == 0 {
+        return Ok(0);
+    }
+    if market.outcome_pools[SCALAR_SHORT as usize] == 0 {
+        return Ok(pool);
+    }
+    let range = (market.scalar_upper_bound as i128 - market.scalar_lower_bound as i128) as u128;
+    let offset = (value as i128 - market.scalar_lower_bound as i128) as u128;
+    to_u64(pool as u128 * offset / range)
+}
+
+/// Part of `total_pool` settled on the preliminary value; zero unless the market pays in
+/// tranches.
+fn preliminary_tranche_pool(market: &Market) -> u64 {
+    (market.total_pool as u128 * market.preliminary_tranche_bps as u128 / 10_000) as u64
+}
+
+/// Errors once a preliminary tranche has been paid out. Those payouts can't be taken back, so
+/// the market has to settle rather than refund.
+fn require_no_tranche_paid(market: &Market) -> Result<()> {
+    require!(market.preliminary_value.is_none(), ErrorCode::PreliminaryTranchePaid);
+    Ok(())
+}
+
+/// Sum of `pool * score` across outcomes on a scored market; the denominator of every
+/// scored payout.
+fn scored_weight(market: &Market) -> u128 {
+    market
+        .outcome_pools
+        .iter()
+        .zip(market.outcome_scores.iter())
+        .map(|(pool, score)| *pool as u128 * *score as u128)
+        .sum()
+}
+
+/// Payout owed to `amount` staked on `outcome` of a scored market.
+fn scored_payout(market: &Market, outcome: u8, amount: u64) -> u64 {
+    let weight = scored_weight(market);
+    if weight == 0 {
+        return 0;
+    }
+    let score = market.outcome_scores[outcome as usize] as u128;
+    (amount as u128 * score * market.total_pool as u128 / weight) as u64
+}
+
+/// Whether `ranking` orders every outcome of `market` exactly once.
+fn is_ranking(market: &Market, ranking: &[u8]) -> bool {
+    let outcomes = market.outcomes.len();
+    let mut seen = vec![false; outcomes];
+    ranking.len() == outcomes
+        && ranking.iter().all(|outcome| {
+            let outcome = *outcome as usize;
+            outcome < outcomes && !std::mem::replace(&mut seen[outcome], true)
+        })
+}
+
+/// Largest footrule distance between two rankings of the market's outcomes, `n² / 2`.
+/// A ranked bet this far from the final ranking scores nothing.
+fn max_rank_distance(market: &Market) -> u64 {
+    let outcomes = market.outcomes.len() as u64;
+    outcomes * outcomes / 2
+}
+
+/// Footrule distance: how many places each outcome sits from its actual rank, summed.
+fn rank_distance(predicted: &[u8], actual: &[u8]) -> u64 {
+    predicted
+        .iter()
+        .enumerate()
+        .map(|(position, outcome)| {
+            let actual_position = actual.iter().position(|o| o == outcome).unwrap();
+            position.abs_diff(actual_position) as u64
+        })
+        .sum()
+}
+
+/// Sum of `stake * score` across every ranked bet, built from `rank_stakes` rather than the
+/// bets themselves; the denominator of every ranked payout.
+fn ranked_weight(market: &Market) -> u128 {
+    let outcomes = market.outcomes.len();
+    // Every bet ranks outcome 0 somewhere, so its row holds the whole ranked stake
+    let ranked_stake: u128 = market.rank_stakes[..outcomes].iter().map(|stake| *stake as u128).sum();
+    let mut distance: u128 = 0;
+    for (actual_position, outcome) in market.final_ranking.iter().enumerate() {
+        for position in 0..outcomes {
+            let stake = market.rank_stakes[*outcome as usize * outcomes + position] as u128;
+            distance += stake * position.abs_diff(actual_position) as u128;
+        }
+    }
+    max_rank_distance(market) as u128 * ranked_stake - distance
+}
+
+/// Payout owed to a ranked `bet` on a resolved ranked market.
+fn ranked_payout(market: &Market, bet: &Bet) -> Result<u64> {
+    let weight = ranked_weight(market);
+    if weight == 0 {
+        return Ok(0);
+    }
+    let score = max_rank_distance(market) - rank_distance(&bet.ranking, &market.final_ranking);
+    to_u64(bet.amount as u128 * score as u128 * market.total_pool as u128 / weight)
+}
+
+/// Payout owed to `amount` staked on the winning outcome of a resolved market.
+fn stake_payout(market: &Market, amount: u64, payout_capped: bool, now: i64) -> Result<u64> {
+    let winning_outcome = market.winning_outcome.unwrap() as usize;
+    let winning_pool = market.outcome_pools[winning_outcome];
+    let payout = if market.market_type == MarketType::FixedOdds {
+        to_u64(amount as u128 * market.fixed_odds_bps[winning_outcome] as u128 / 10_000)?
+    } else if market.max_payout_share_bps > 0 {
+        require!(now >= market.cap_registration_ends, ErrorCode::CapRegistrationOpen);
+        capped_payout(market, amount, payout_capped)
+    } else if winning_pool > 0 {
+        let winners_pool = market.total_pool - market.consolation_pool;
+        to_u64(amount as u128 * winners_pool as u128 / winning_pool as u128)?
+    } else {
+        0
+    };
+
+    require!(payout > 0, ErrorCode::NoPayoutAvailable);
+    Ok(payout)
+}
+
+/// Sum of a seed, which must put something into the pools.
+fn seed_total(amounts: &[u64]) -> Result<u64> {
+    let total = amounts
+        .iter()
+        .try_fold(0u64, |sum, amount| sum.checked_add(*amount))
+        .ok_or(ErrorCode::MathOverflow)?;
+    require!(total > 0, ErrorCode::InvalidSeedAmounts);
+    Ok(total)
+}
+
+/// Adds a seed, already in the vault, to an unfunded market's pools and records it for
+/// `reclaim_seed`.
+fn seed_pools(market: &mut Market, amounts: Vec<u64>, total: u64) {
+    for (pool, amount) in market.outcome_pools.iter_mut().zip(amounts.iter()) {
+        *pool += amount;
+    }
+    market.total_pool += total;
+    market.seed_amounts = amounts;
+    refresh_active(market);
+}
+
+/// Narrows a u128 payout intermediate, failing instead of truncating.
+fn to_u64(value: u128) -> Result<u64> {
+    u64::try_from(value).map_err(|_| error!(ErrorCode::MathOverflow))
+}
+
+/// On fixed-odds markets, commits house liquidity to the profit `amount` would win on
+/// `outcome_index`, rejecting the stake if the house can't cover it. No-op elsewhere.
+fn reserve_house_cover(market: &mut Market, outcome_index: u8, amount: u64) -> Result<()> {
+    if market.market_type != MarketType::FixedOdds {
+        return Ok(());
+    }
+
+    let odds = market.fixed_odds_bps[outcome_index as usize] as u128;
+    // Round the house's exposure up so it is never short by a unit
+    let profit = (amount as u128 * (odds - 10_000)).div_ceil(10_000) as u64;
+    require!(
+        market.house_locked + profit <= market.house_liquidity,
+        ErrorCode::HouseOvercommitted
+    );
+
+    market.house_locked += profit;
+    Ok(())
+}
+
+/// Share of the consolation pool owed to `amount` staked on the runner-up.
+fn consolation_payout(market: &Market, amount: u64) -> Result<u64> {
+    let runner_up_pool = market.outcome_pools[market.runner_up.unwrap() as usize];
+    let payout = (amount as u128 * market.consolation_pool as u128 / runner_up_pool as u128) as u64;
+
+    require!(payout > 0, ErrorCode::NoPayoutAvailable);
+    Ok(payout)
+}
+
+/// Trips the breaker when `total_pool` has grown by more than `circuit_breaker_bps`
+/// since the start of the current window. Called after `amount` is added to the pools.
+fn update_circuit_breaker(market: &mut Market, amount: u64, now: i64) {
+    if now - market.breaker_window_start >= market.circuit_breaker_window {
+        market.breaker_window_start = now;
+        market.breaker_window_pool = market.total_pool - amount;
+    }
+
+    // Growth from an empty pool is unbounded, so the first bet only seeds the window
+    if market.breaker_window_pool == 0 {
+        market.breaker_window_start = now;
+        market.breaker_window_pool = market.total_pool;
+        return;
+    }
+
+    let growth = market.total_pool - market.breaker_window_pool;
+    let limit = market.breaker_window_pool as u128 * market.circuit_breaker_bps as u128 / 10_000;
+    if growth as u128 > limit {
+        market.circuit_breaker_tripped = true;
+
+        emit!(CircuitBreakerTripped {
+            market_id: market.market_id,
+            window_start_pool: market.breaker_window_pool,
+            total_pool: market.total_pool,
+        });
+    }
+}
+
+/// Kelly fraction `(b*p - q) / b` in bps, with net odds `b = (total - pool) / pool`. Simplifies
+/// to `(p*total - pool) / (total - pool)`; negative-EV bets return zero.
+fn kelly_fraction_bps(outcome_pool: u64, total_pool: u64, probability_bps: u16) -> u16 {
+    // Nobody on this outcome yet: odds are unbounded and Kelly tends to staking `p`
+    if outcome_pool == 0 {
+        return probability_bps;
+    }
+    if total_pool <= outcome_pool {
+        return 0;
+    }
+
+    let edge = probability_bps as i128 * total_pool as i128 - 10_000 * outcome_pool as i128;
+    if edge <= 0 {
+        return 0;
+    }
+    (edge / (total_pool - outcome_pool) as i128) as u16
+}
+
+fn market_status(market: &Market, clock: &Clock) -> MarketStatus {
+    if market.cancelled {
+        MarketStatus::Cancelled
+    } else if market.voided {
+        MarketStatus::Voided
+    } else if market.resolved {
+        MarketStatus::Resolved
+    } else if deadline_passed(market, clock) {
+        MarketStatus::AwaitingResolution
+    } else if market.betting_closed || cutoff_passed(market, clock) {
+        MarketStatus::BettingClosed
+    } else if market.circuit_breaker_tripped {
+        MarketStatus::Paused
+    } else {
+        MarketStatus::Open
+    }
+}
+
+/// Each outcome's implied probability in basis points: its share of the staked pools, or on
+/// fixed-odds markets the inverse of its odds. All zero before any stake on a pool market.
+fn implied_probabilities_bps(market: &Market) -> Vec<u16> {
+    if market.market_type == MarketType::FixedOdds {
+        return market.fixed_odds_bps.iter().map(|odds| 100_000_000u64.checked_div(*odds as u64).unwrap_or(0) as u16).collect();
+    }
+    let staked: u128 = market.outcome_pools.iter().map(|pool| *pool as u128).sum();
+    market
+        .outcome_pools
+        .iter()
+        .map(|pool| (*pool as u128 * 10_000).checked_div(staked).unwrap_or(0) as u16)
+        .collect()
+}
+
+/// The account allowed to declare `outcome_index` the winner: its designated oracle if it
+/// has one, otherwise the market's resolver.
+fn outcome_resolver(market: &Market, outcome_index: u8) -> Pubkey {
+    market
+        .outcome_oracles
+        .get(outcome_index as usize)
+        .copied()
+        .flatten()
+        .unwrap_or_else(|| market_resolver(market))
+}
+
+/// Who reports a market's result: its `oracle` when set, otherwise the market authority.
+fn market_resolver(market: &Market) -> Pubkey {
+    market.oracle.unwrap_or(market.authority)
+}
+
+/// Payout under a `max_payout_share_bps` cap. Registered capped bets receive exactly the cap;
+/// everything they would have received above it is shared pro-rata among the remaining winning
+/// stake. This is a single redistribution round, so an uncapped bet is still clipped at the cap
+/// and any such overflow stays in the vault.
+fn capped_payout(market: &Market, amount: u64, payout_capped: bool) -> u64 {
+    let cap = market.total_pool as u128 * market.max_payout_share_bps as u128 / 10_000;
+    if payout_capped {
+        return cap as u64;
+    }
+
+    let winning_pool = market.outcome_pools[market.winning_outcome.unwrap() as usize];
+    let remaining_pool = market.total_pool as u128 - cap * market.capped_count as u128;
+    let remaining_stake = (winning_pool - market.capped_stake) as u128;
+    if remaining_stake == 0 {
+        return 0;
+    }
+
+    (amount as u128 * remaining_pool / remaining_stake).min(cap) as u64
+}
+
+fn write_resolved_outcome(record: &mut ResolvedOutcome, market: &Account<Market>, now: i64) {
+    record.market = market.key();
+    record.market_id = market.market_id;
+    record.winning_outcome = market.winning_outcome;
+    record.resolved_at = now;
+}
+
+/// Pays over or under against the pivot, or voids the market on an exact hit.
+fn settle_pivot(market: &mut Market, actual_value: i64, resolved_by: Pubkey, now: i64) -> Result<()> {
+    msg!("Pivot {} resolved against actual value {}", market.pivot_value, actual_value);
+
+    if actual_value > market.pivot_value {
+        apply_resolution(market, PIVOT_OVER, resolved_by, now)
+    } else if actual_value < market.pivot_value {
+        apply_resolution(market, PIVOT_UNDER, resolved_by, now)
+    } else {
+        require!(market.total_pool > 0, ErrorCode::EmptyMarket);
+        // A push: nobody wins, so every bettor gets their stake back
+        market.voided = true;
+
+        emit!(MarketVoided {
+            market_id: market.market_id,
+            total_pool: market.total_pool,
+        });
+        Ok(())
+    }
+}
+
+/// Splits a scalar market's pool on `value`, clamped to its bounds. The value settles
+/// whatever a preliminary tranche didn't.
+fn settle_scalar(market: &mut Market, value: i64, resolved_by: Pubkey, now: i64) -> Result<()> {
+    let tranche_pool = preliminary_tranche_pool(market);
+    require!(tranche_pool == 0 || market.preliminary_value.is_some(), ErrorCode::PreliminaryValueRequired);
+    let value = value.clamp(market.scalar_lower_bound, market.scalar_upper_bound);
+    let long_share = market.preliminary_long_share + scalar_long_share(market, market.total_pool - tranche_pool, value)?;
+    market.scalar_value = value;
+    market.scalar_long_share = long_share;
+
+    emit!(ScalarResolved {
+        market_id: market.market_id,
+        value,
+        long_share,
+        short_share: market.total_pool - long_share,
+    });
+
+    // The side taking the larger share stands as the winner for single-outcome readers
+    let winning_outcome_index = if long_share >= market.total_pool - long_share {
+        SCALAR_LONG
+    } else {
+        SCALAR_SHORT
+    };
+    apply_resolution(market, winning_outcome_index, resolved_by, now)
+}
+
+/// Share of the platform fee a market resolved at `now` charges, from the first prompt fee
+/// tier whose window it resolved in, and the index of that tier. On a market with a fee decay
+/// the share then shrinks by the part of `fee_decay_period` the resolution was late.
+fn resolution_fee_share(market: &Market, now: i64) -> (Option<u8>, u16) {
+    let latency = (now - market.resolution_time).max(0);
+    let (tier, share) = match market.prompt_fee_tiers.iter().position(|tier| latency <= tier.within) {
+        Some(tier) => (Some(tier as u8), market.prompt_fee_tiers[tier].fee_share_bps),
+        None => (None, 10_000),
+    };
+    if market.fee_decay_period == 0 {
+        return (tier, share);
+    }
+    let remaining = (market.fee_decay_period - latency).max(0);
+    (tier, (share as i128 * remaining as i128 / market.fee_decay_period as i128) as u16)
+}
+
+/// Settles `market` on `winning_outcome_index`, or voids it when nobody can be paid. Every
+/// resolution path ends here, so this is where empty markets are turned away.
+fn apply_resolution(market: &mut Market, winning_outcome_index: u8, resolved_by: Pubkey, now: i64) -> Result<()> {
+    require!(market.total_pool > 0, ErrorCode::EmptyMarket);
+
+    // Nobody backed the winner, so the pool has no one to go to: refund every stake instead.
+    // Fixed-odds markets are exempt since the house keeps losing stakes, as are scored markets
+    // where other outcomes share the pool. Ranked markets pay any ranking that scores.
+    let unclaimable = if !market.final_ranking.is_empty() {
+        ranked_weight(market) == 0
+    } else {
+        market.outcome_pools[winning_outcome_index as usize] == 0 && market.outcome_scores.is_empty()
+    };
+    // A market that never reached its minimum liquidity refunds rather than settle on thin odds
+    let inactive = !market.active;
+    if inactive || (unclaimable && market.market_type != MarketType::FixedOdds) {
+        if inactive {
+            msg!("Market never reached {} per outcome; voiding", market.min_liquidity_per_outcome);
+        } else {
+            msg!("No stake on winning outcome {}; voiding market", winning_outcome_index);
+        }
+        market.voided = true;
+        market.runner_up = None;
+        market.consolation_pool = 0;
+
+        emit!(MarketVoided {
+            market_id: market.market_id,
+            total_pool: market.total_pool,
+        });
+        return Ok(());
+    }
+
+    market.resolved = true;
+    market.winning_outcome = Some(winning_outcome_index);
+    market.resolved_at = now;
+    market.resolved_by = resolved_by;
+    // What the pools implied just before resolution, for calibration after the fact
+    market.final_pools = market.outcome_pools.clone();
+    let winning_probability_bps =
+        (market.outcome_pools[winning_outcome_index as usize] as u128 * 10_000 / market.total_pool as u128) as u16;
+    market.cap_registration_ends = now + market.cap_registration_period;
+    // Flag, but don't block, resolutions that pay out to the market authority's own bets
+    market.authority_had_winning_stake = market.authority_stakes[winning_outcome_index as usize] > 0;
+    // The fee is fixed by how promptly this resolution came
+    let (tier, fee_share_bps) = resolution_fee_share(market, now);
+    market.platform_fee_share_bps = fee_share_bps;
+    if !market.prompt_fee_tiers.is_empty() || market.fee_decay_period > 0 {
+        emit!(ResolutionFeeTier {
+            market_id: market.market_id,
+            latency: (now - market.resolution_time).max(0),
+            tier,
+            fee_share_bps,
+        });
+    }
+
+    emit!(MarketResolved {
+        market_id: market.market_id,
+        winning_outcome: winning_outcome_index,
+        winning_outcome_name: market.outcomes[winning_outcome_index as usize].clone(),
+        authority_had_winning_stake: market.authority_had_winning_stake,
+        resolved_by,
+        resolved_at: now,
+        outcome_evidence: market.outcome_evidence.clone(),
+        final_pools: market.final_pools.clone(),
+        winning_probability_bps,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct Initialize<'info> {
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + GlobalState::INIT_SPACE,
+        seeds = [b"global_state"],
+        bump
+    )]
+    pub global_state: Account<'info, GlobalState>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateGlobalState<'info> {
+    #[account(
+        mut,
+        seeds = [b"global_state"],
+        bump
+    )]
+    pub global_state: Account<'info, GlobalState>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetFeeToken<'info> {
+    #[account(
+        mut,
+        seeds = [b"global_state"],
+        bump
+    )]
+    pub global_state: Account<'info, GlobalState>,
+    pub authority: Signer<'info>,
+    /// CHECK: only read; required when setting a fee token, and must be its `rate_source`
+    pub rate_source: Option<UncheckedAccount<'info>>,
+}
+
+#[derive(Accounts)]
+#[instruction(question: String, outcomes: Vec<String>, resolution_time: i64, min_bet: u64, config: MarketConfig)]
+pub struct CreateMarket<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = market_space(&question, &outcomes, !config.outcome_commitments.is_empty()),
+        seeds = [b"market", next_market_seed(&global_state, config.market_id)?.as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+    #[account(mut)]
+    pub global_state: Account<'info, GlobalState>,
+    /// Required when `config.external_id` is set; its existence blocks duplicate ids.
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + ExternalIdEntry::INIT_SPACE,
+        seeds = [b"external_id", config.external_id.unwrap_or_default().as_ref()],
+        bump
+    )]
+    pub external_id_entry: Option<Account<'info, ExternalIdEntry>>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    /// CHECK: the creator's fee waiver record; the creation fee applies unless it exists
+    #[account(seeds = [b"fee_waiver", authority.key().as_ref()], bump)]
+    pub fee_waiver: UncheckedAccount<'info>,
+    /// Token every stake and payout on the market is denominated in.
+    pub mint: Account<'info, Mint>,
+    /// Required, with `treasury` and `token_program`, when a creation fee is due, and with
+    /// `market_token_account` and `token_program` to fund `initial_liquidity`.
+    #[account(mut)]
+    pub creator_token_account: Option<Account<'info, TokenAccount>>,
+    #[account(mut)]
+    pub treasury: Option<Account<'info, TokenAccount>>,
+    /// The market's vault, receiving `initial_liquidity`.
+    #[account(mut)]
+    pub market_token_account: Option<Account<'info, TokenAccount>>,
+    /// Where the market's dust and forfeited winnings will go: `config.residual_destination`,
+    /// or else the treasury. Required on token markets, so its mint can be checked.
+    pub residual_destination: Option<Account<'info, TokenAccount>>,
+    pub token_program: Option<Program<'info, Token>>,
+    /// Lamport vault of a native SOL market; required when `is_native` is set.
+    #[account(mut, seeds = [b"sol_vault", market.key().as_ref()], bump)]
+    pub sol_vault: Option<SystemAccount<'info>>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(outcome_index: u8, amount: u64, bet_nonce: u64)]
+pub struct PlaceBet<'info> {
+    #[account(
+        init,
+        payer = bettor,
+        space = 8 + Bet::INIT_SPACE,
+        seeds = [b"bet", market.key().as_ref(), bettor.key().as_ref(), bet_nonce.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub bet: Account<'info, Bet>,
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+    #[account(seeds = [b"global_state"], bump)]
+    pub global_state: Account<'info, GlobalState>,
+    #[account(mut)]
+    pub bettor: Signer<'info>,
+    /// CHECK: the bettor's freeze record on this market; must not exist
+    #[account(seeds = [b"bettor_freeze", market.key().as_ref(), bettor.key().as_ref()], bump)]
+    pub bettor_freeze: UncheckedAccount<'info>,
+    /// CHECK: the bettor's whitelist entry on this market; must exist on whitelist markets
+    #[account(seeds = [b"whitelist", market.key().as_ref(), bettor.key().as_ref()], bump)]
+    pub whitelist_entry: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        constraint = bettor_token_account.owner == bettor.key() @ ErrorCode::InvalidTokenAccountOwner,
+        constraint = bettor_token_account.mint == market.mint @ ErrorCode::MintMismatch
+    )]
+    pub bettor_token_account: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = market_token_account.owner == market.key() @ ErrorCode::InvalidMarketVault,
+        constraint = market_token_account.mint == market.mint @ ErrorCode::MintMismatch
+    )]
+    pub market_token_account: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(ranking: Vec<u8>, amount: u64, bet_nonce: u64)]
+pub struct PlaceRankedBet<'info> {
+    #[account(
+        init,
+        payer = bettor,
+        space = 8 + Bet::INIT_SPACE,
+        seeds = [b"bet", market.key().as_ref(), bettor.key().as_ref(), bet_nonce.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub bet: Account<'info, Bet>,
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+    #[account(seeds = [b"global_state"], bump)]
+    pub global_state: Account<'info, GlobalState>,
+    #[account(mut)]
+    pub bettor: Signer<'info>,
+    /// CHECK: the bettor's freeze record on this market; must not exist
+    #[account(seeds = [b"bettor_freeze", market.key().as_ref(), bettor.key().as_ref()], bump)]
+    pub bettor_freeze: UncheckedAccount<'info>,
+    /// CHECK: the bettor's whitelist entry on this market; must exist on whitelist markets
+    #[account(seeds = [b"whitelist", market.key().as_ref(), bettor.key().as_ref()], bump)]
+    pub whitelist_entry: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        constraint = bettor_token_account.owner == bettor.key() @ ErrorCode::InvalidTokenAccountOwner,
+        constraint = bettor_token_account.mint == market.mint @ ErrorCode::MintMismatch
+    )]
+    pub bettor_token_account: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = market_token_account.owner == market.key() @ ErrorCode::InvalidMarketVault,
+        constraint = market_token_account.mint == market.mint @ ErrorCode::MintMismatch
+    )]
+    pub market_token_account: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(outcome_index: u8, amount: u64, bet_nonce: u64)]
+pub struct PlaceBetSol<'info> {
+    #[account(
+        init,
+        payer = bettor,
+        space = 8 + Bet::INIT_SPACE,
+        seeds = [b"bet", market.key().as_ref(), bettor.key().as_ref(), bet_nonce.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub bet: Account<'info, Bet>,
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+    #[account(seeds = [b"global_state"], bump)]
+    pub global_state: Account<'info, GlobalState>,
+    #[account(mut)]
+    pub bettor: Signer<'info>,
+    /// CHECK: the bettor's freeze record on this market; must not exist
+    #[account(seeds = [b"bettor_freeze", market.key().as_ref(), bettor.key().as_ref()], bump)]
+    pub bettor_freeze: UncheckedAccount<'info>,
+    /// CHECK: the bettor's whitelist entry on this market; must exist on whitelist markets
+    #[account(seeds = [b"whitelist", market.key().as_ref(), bettor.key().as_ref()], bump)]
+    pub whitelist_entry: UncheckedAccount<'info>,
+    #[account(mut, seeds = [b"sol_vault", market.key().as_ref()], bump)]
+    pub sol_vault: SystemAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(outcome_index: u8)]
+pub struct PlacePositionBet<'info> {
+    #[account(
+        init_if_needed,
+        payer = bettor,
+        space = 8 + UserPosition::INIT_SPACE,
+        seeds = [b"position", market.key().as_ref(), bettor.key().as_ref(), outcome_index.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub position: Account<'info, UserPosition>,
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+    #[account(seeds = [b"global_state"], bump)]
+    pub global_state: Account<'info, GlobalState>,
+    #[account(mut)]
+    pub bettor: Signer<'info>,
+    /// CHECK: the bettor's freeze record on this market; must not exist
+    #[account(seeds = [b"bettor_freeze", market.key().as_ref(), bettor.key().as_ref()], bump)]
+    pub bettor_freeze: UncheckedAccount<'info>,
+    /// CHECK: the bettor's whitelist entry on this market; must exist on whitelist markets
+    #[account(seeds = [b"whitelist", market.key().as_ref(), bettor.key().as_ref()], bump)]
+    pub whitelist_entry: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        constraint = bettor_token_account.owner == bettor.key() @ ErrorCode::InvalidTokenAccountOwner,
+        constraint = bettor_token_account.mint == market.mint @ ErrorCode::MintMismatch
+    )]
+    pub bettor_token_account: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = market_token_account.owner == market.key() @ ErrorCode::InvalidMarketVault,
+        constraint = market_token_account.mint == market.mint @ ErrorCode::MintMismatch
+    )]
+    pub market_token_account: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimPosition<'info> {
+    /// Closed once settled, returning its rent to the bettor.
+    #[account(
+        mut,
+        seeds = [
+            b"position",
+            market.key().as_ref(),
+            bettor.key().as_ref(),
+            position.outcome_index.to_le_bytes().as_ref()
+        ],
+        bump,
+        close = bettor
+    )]
+    pub position: Account<'info, UserPosition>,
+    #[account(
+        mut,
+        seeds = [b"market", market.market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+    #[account(seeds = [b"global_state"], bump)]
+    pub global_state: Account<'info, GlobalState>,
+    #[account(mut)]
+    pub bettor: Signer<'info>,
+    /// CHECK: the bettor's freeze record on this market; must not exist
+    #[account(seeds = [b"bettor_freeze", market.key().as_ref(), bettor.key().as_ref()], bump)]
+    pub bettor_freeze: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        constraint = bettor_token_account.owner == bettor.key() @ ErrorCode::InvalidTokenAccountOwner,
+        constraint = bettor_token_account.mint == market.mint @ ErrorCode::MintMismatch
+    )]
+    pub bettor_token_account: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = market_token_account.owner == market.key() @ ErrorCode::InvalidMarketVault,
+        constraint = market_token_account.mint == market.mint @ ErrorCode::MintMismatch
+    )]
+    pub market_token_account: Account<'info, TokenAccount>,
+    /// Required, with `fee_treasury` and `fee_rate_source`, on a fee token market: the
+    /// bettor's account the platform fee is paid from.
+    #[account(mut)]
+    pub fee_payer_token_account: Option<Account<'info, TokenAccount>>,
+    #[account(mut)]
+    pub fee_treasury: Option<Account<'info, TokenAccount>>,
+    /// CHECK: only read; must be the fee token's `rate_source`
+    pub fee_rate_source: Option<UncheckedAccount<'info>>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct CancelBet<'info> {
+    #[account(
+        mut,
+        seeds = [b"bet", market.key().as_ref(), bettor.key().as_ref(), bet.nonce.to_le_bytes().as_ref()],
+        bump,
+        close = bettor
+    )]
+    pub bet: Account<'info, Bet>,
+    #[account(
+        mut,
+        seeds = [b"market", market.market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+    #[account(mut)]
+    pub bettor: Signer<'info>,
+    /// CHECK: the bettor's freeze record on this market; must not exist
+    #[account(seeds = [b"bettor_freeze", market.key().as_ref(), bettor.key().as_ref()], bump)]
+    pub bettor_freeze: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        constraint = bettor_token_account.owner == bettor.key() @ ErrorCode::InvalidTokenAccountOwner,
+        constraint = bettor_token_account.mint == market.mint @ ErrorCode::MintMismatch
+    )]
+    pub bettor_token_account: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = market_token_account.owner == market.key() @ ErrorCode::InvalidMarketVault,
+        constraint = market_token_account.mint == market.mint @ ErrorCode::MintMismatch
+    )]
+    pub market_token_account: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct AddToBet<'info> {
+    #[account(mut)]
+    pub bet: Account<'info, Bet>,
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+    #[account(seeds = [b"global_state"], bump)]
+    pub global_state: Account<'info, GlobalState>,
+    pub bettor: Signer<'info>,
+    /// CHECK: the bettor's freeze record on this market; must not exist
+    #[account(seeds = [b"bettor_freeze", market.key().as_ref(), bettor.key().as_ref()], bump)]
+    pub bettor_freeze: UncheckedAccount<'info>,
+    /// CHECK: the bettor's whitelist entry on this market; must exist on whitelist markets
+    #[account(seeds = [b"whitelist", market.key().as_ref(), bettor.key().as_ref()], bump)]
+    pub whitelist_entry: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        constraint = bettor_token_account.owner == bettor.key() @ ErrorCode::InvalidTokenAccountOwner,
+        constraint = bettor_token_account.mint == market.mint @ ErrorCode::MintMismatch
+    )]
+    pub bettor_token_account: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = market_token_account.owner == market.key() @ ErrorCode::InvalidMarketVault,
+        constraint = market_token_account.mint == market.mint @ ErrorCode::MintMismatch
+    )]
+    pub market_token_account: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct ResolveMarket<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + ResolvedOutcome::INIT_SPACE,
+        seeds = [b"resolved_outcome", market.market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub resolved_outcome: Account<'info, ResolvedOutcome>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ProposeResolution<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ReportPreliminaryValue<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct FinalizeResolution<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+    #[account(
+        init,
+        payer = caller,
+        space = 8 + ResolvedOutcome::INIT_SPACE,
+        seeds = [b"resolved_outcome", market.market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub resolved_outcome: Account<'info, ResolvedOutcome>,
+    #[account(mut)]
+    pub caller: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SubmitResolutionVote<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+    /// Created by the first vote and filled in by the one that resolves the market
+    #[account(
+        init_if_needed,
+        payer = voter,
+        space = 8 + ResolvedOutcome::INIT_SPACE,
+        seeds = [b"resolved_outcome", market.market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub resolved_outcome: Account<'info, ResolvedOutcome>,
+    #[account(mut)]
+    pub voter: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ResolveFromAccount<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+    #[account(
+        init,
+        payer = caller,
+        space = 8 + ResolvedOutcome::INIT_SPACE,
+        seeds = [b"resolved_outcome", market.market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub resolved_outcome: Account<'info, ResolvedOutcome>,
+    /// CHECK: only read; must be the market's configured `resolution_account`
+    pub source_account: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub caller: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ResolveAndSettle<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", market.market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+    #[account(seeds = [b"global_state"], bump)]
+    pub global_state: Account<'info, GlobalState>,
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + ResolvedOutcome::INIT_SPACE,
+        seeds = [b"resolved_outcome", market.market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub resolved_outcome: Account<'info, ResolvedOutcome>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(mut)]
+    pub market_token_account: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimPayoutPartial<'info> {
+    /// Closed by the claim that pays the last of `payout_due`.
+    #[account(
+        mut,
+        seeds = [b"bet", market.key().as_ref(), bettor.key().as_ref(), bet.nonce.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub bet: Account<'info, Bet>,
+    #[account(
+        mut,
+        seeds = [b"market", market.market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+    #[account(seeds = [b"global_state"], bump)]
+    pub global_state: Account<'info, GlobalState>,
+    #[account(mut)]
+    pub bettor: Signer<'info>,
+    /// CHECK: the bettor's freeze record on this market; must not exist
+    #[account(seeds = [b"bettor_freeze", market.key().as_ref(), bettor.key().as_ref()], bump)]
+    pub bettor_freeze: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        constraint = bettor_token_account.owner == bettor.key() @ ErrorCode::InvalidTokenAccountOwner,
+        constraint = bettor_token_account.mint == market.mint @ ErrorCode::MintMismatch
+    )]
+    pub bettor_token_account: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = market_token_account.owner == market.key() @ ErrorCode::InvalidMarketVault,
+        constraint = market_token_account.mint == market.mint @ ErrorCode::MintMismatch
+    )]
+    pub market_token_account: Account<'info, TokenAccount>,
+    /// Required, with `fee_treasury` and `fee_rate_source`, on a fee token market: the
+    /// bettor's account the platform fee is paid from.
+    #[account(mut)]
+    pub fee_payer_token_account: Option<Account<'info, TokenAccount>>,
+    #[account(mut)]
+    pub fee_treasury: Option<Account<'info, TokenAccount>>,
+    /// CHECK: only read; must be the fee token's `rate_source`
+    pub fee_rate_source: Option<UncheckedAccount<'info>>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimPayout<'info> {
+    /// Closed once the payout is sent or escrowed, returning its rent to the bettor.
+    #[account(
+        mut,
+        seeds = [b"bet", market.key().as_ref(), bettor.key().as_ref(), bet.nonce.to_le_bytes().as_ref()],
+        bump,
+        close = bettor
+    )]
+    pub bet: Account<'info, Bet>,
+    #[account(
+        mut,
+        seeds = [b"market", market.market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+    #[account(seeds = [b"global_state"], bump)]
+    pub global_state: Account<'info, GlobalState>,
+    #[account(mut)]
+    pub bettor: Signer<'info>,
+    /// CHECK: the bettor's freeze record on this market; must not exist
+    #[account(seeds = [b"bettor_freeze", market.key().as_ref(), bettor.key().as_ref()], bump)]
+    pub bettor_freeze: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        constraint = bettor_token_account.owner == bettor.key() @ ErrorCode::InvalidTokenAccountOwner,
+        constraint = bettor_token_account.mint == market.mint @ ErrorCode::MintMismatch
+    )]
+    pub bettor_token_account: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = market_token_account.owner == market.key() @ ErrorCode::InvalidMarketVault,
+        constraint = market_token_account.mint == market.mint @ ErrorCode::MintMismatch
+    )]
+    pub market_token_account: Account<'info, TokenAccount>,
+    /// Required when the market has a withdrawal delay.
+    #[account(
+        init_if_needed,
+        payer = bettor,
+        space = 8 + PayoutEscrow::INIT_SPACE,
+        seeds = [b"escrow", market.key().as_ref(), bettor.key().as_ref()],
+        bump
+    )]
+    pub escrow: Option<Account<'info, PayoutEscrow>>,
+    /// Required, with `fee_treasury` and `fee_rate_source`, on a fee token market: the
+    /// bettor's account the platform fee is paid from.
+    #[account(mut)]
+    pub fee_payer_token_account: Option<Account<'info, TokenAccount>>,
+    #[account(mut)]
+    pub fee_treasury: Option<Account<'info, TokenAccount>>,
+    /// CHECK: only read; must be the fee token's `rate_source`
+    pub fee_rate_source: Option<UncheckedAccount<'info>>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimPayoutsBatch<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", market.market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+    #[account(seeds = [b"global_state"], bump)]
+    pub global_state: Account<'info, GlobalState>,
+    #[account(mut)]
+    pub bettor: Signer<'info>,
+    /// CHECK: the bettor's freeze record on this market; must not exist
+    #[account(seeds = [b"bettor_freeze", market.key().as_ref(), bettor.key().as_ref()], bump)]
+    pub bettor_freeze: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        constraint = bettor_token_account.owner == bettor.key() @ ErrorCode::InvalidTokenAccountOwner,
+        constraint = bettor_token_account.mint == market.mint @ ErrorCode::MintMismatch
+    )]
+    pub bettor_token_account: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = market_token_account.owner == market.key() @ ErrorCode::InvalidMarketVault,
+        constraint = market_token_account.mint == market.mint @ ErrorCode::MintMismatch
+    )]
+    pub market_token_account: Account<'info, TokenAccount>,
+    /// Required, with `fee_treasury` and `fee_rate_source`, on a fee token market: the
+    /// bettor's account the platform fee is paid from.
+    #[account(mut)]
+    pub fee_payer_token_account: Option<Account<'info, TokenAccount>>,
+    #[account(mut)]
+    pub fee_treasury: Option<Account<'info, TokenAccount>>,
+    /// CHECK: only read; must be the fee token's `rate_source`
+    pub fee_rate_source: Option<UncheckedAccount<'info>>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimPayoutSol<'info> {
+    #[account(
+        mut,
+        seeds = [b"bet", market.key().as_ref(), bettor.key().as_ref(), bet.nonce.to_le_bytes().as_ref()],
+        bump,
+        close = bettor
+    )]
+    pub bet: Account<'info, Bet>,
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+    #[account(seeds = [b"global_state"], bump)]
+    pub global_state: Account<'info, GlobalState>,
+    #[account(mut)]
+    pub bettor: Signer<'info>,
+    /// CHECK: the bettor's freeze record on this market; must not exist
+    #[account(seeds = [b"bettor_freeze", market.key().as_ref(), bettor.key().as_ref()], bump)]
+    pub bettor_freeze: UncheckedAccount<'info>,
+    #[account(mut, seeds = [b"sol_vault", market.key().as_ref()], bump)]
+    pub sol_vault: SystemAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimRefundSol<'info> {
+    #[account(mut, close = bettor)]
+    pub bet: Account<'info, Bet>,
+    pub market: Account<'info, Market>,
+    #[account(mut)]
+    pub bettor: Signer<'info>,
+    #[account(mut, seeds = [b"sol_vault", market.key().as_ref()], bump)]
+    pub sol_vault: SystemAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawFeesSol<'info> {
+    #[account(seeds = [b"global_state"], bump)]
+    pub global_state: Account<'info, GlobalState>,
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+    pub authority: Signer<'info>,
+    #[account(mut, seeds = [b"sol_vault", market.key().as_ref()], bump)]
+    pub sol_vault: SystemAccount<'info>,
+    #[account(mut)]
+    pub destination: SystemAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawFees<'info> {
+    #[account(seeds = [b"global_state"], bump)]
+    pub global_state: Account<'info, GlobalState>,
+    #[account(
+        mut,
+        seeds = [b"market", market.market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+    pub authority: Signer<'info>,
+    #[account(mut)]
+    pub market_token_account: Account<'info, TokenAccount>,
+    #[account(mut)]
+    pub destination: Account<'info, TokenAccount>,
+    /// Required, with `insurance_vault`, while `GlobalState::insurance_fee_bps` is set: the
+    /// fund for the market's mint its share of the fees goes to.
+    #[account(
+        mut,
+        seeds = [b"insurance_fund", market.mint.as_ref()],
+        bump = insurance_fund.bump
+    )]
+    pub insurance_fund: Option<Account<'info, InsuranceFund>>,
+    #[account(mut)]
+    pub insurance_vault: Option<Account<'info, TokenAccount>>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawCreatorFees<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", market.market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+    pub authority: Signer<'info>,
+    #[account(mut)]
+    pub market_token_account: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = destination.owner == authority.key() @ ErrorCode::InvalidTokenAccountOwner
+    )]
+    pub destination: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct SweepDust<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", market.market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+    #[account(seeds = [b"global_state"], bump)]
+    pub global_state: Account<'info, GlobalState>,
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        constraint = market_token_account.owner == market.key() @ ErrorCode::InvalidMarketVault,
+        constraint = market_token_account.mint == market.mint @ ErrorCode::MintMismatch
+    )]
+    pub market_token_account: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = destination.key() == residual_destination(&market, &global_state)
+            @ ErrorCode::InvalidResidualDestination,
+        constraint = destination.mint == market.mint @ ErrorCode::MintMismatch
+    )]
+    pub destination: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct ReclaimUnclaimed<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", market.market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+    #[account(seeds = [b"global_state"], bump)]
+    pub global_state: Account<'info, GlobalState>,
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        constraint = market_token_account.owner == market.key() @ ErrorCode::InvalidMarketVault,
+        constraint = market_token_account.mint == market.mint @ ErrorCode::MintMismatch
+    )]
+    pub market_token_account: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = destination.key() == residual_destination(&market, &global_state)
+            @ ErrorCode::InvalidResidualDestination,
+        constraint = destination.mint == market.mint @ ErrorCode::MintMismatch
+    )]
+    pub destination: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct CloseMarket<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", market.market_id.to_le_bytes().as_ref()],
+        bump,
+        has_one = authority @ ErrorCode::Unauthorized,
+        close = authority
+    )]
+    pub market: Account<'info, Market>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        constraint = market_token_account.owner == market.key() @ ErrorCode::InvalidMarketVault,
+        constraint = market_token_account.mint == market.mint @ ErrorCode::MintMismatch
+    )]
+    pub market_token_account: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawEscrow<'info> {
+    #[account(
+        seeds = [b"market", market.market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+    #[account(
+        mut,
+        seeds = [b"escrow", market.key().as_ref(), bettor.key().as_ref()],
+        bump,
+        close = bettor,
+        has_one = market,
+        has_one = bettor,
+    )]
+    pub escrow: Account<'info, PayoutEscrow>,
+    #[account(mut)]
+    pub bettor: Signer<'info>,
+    /// CHECK: the bettor's freeze record on this market; must not exist
+    #[account(seeds = [b"bettor_freeze", market.key().as_ref(), bettor.key().as_ref()], bump)]
+    pub bettor_freeze: UncheckedAccount<'info>,
+    #[account(mut, constraint = bettor_token_account.mint == market.mint @ ErrorCode::MintMismatch)]
+    pub bettor_token_account: Account<'info, TokenAccount>,
+    #[account(mut, constraint = market_token_account.mint == market.mint @ ErrorCode::MintMismatch)]
+    pub market_token_account: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct LockMarket<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateAllowlist<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RevealOutcomes<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct MergeOutcomes<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SeedLiquidity<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+    pub authority: Signer<'info>,
+    #[account(mut, constraint = authority_token_account.mint == market.mint @ ErrorCode::MintMismatch)]
+    pub authority_token_account: Account<'info, TokenAccount>,
+    #[account(mut, constraint = market_token_account.mint == market.mint @ ErrorCode::MintMismatch)]
+    pub market_token_account: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct ReclaimSeed<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", market.market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+    pub authority: Signer<'info>,
+    #[account(mut)]
+    pub authority_token_account: Account<'info, TokenAccount>,
+    #[account(mut)]
+    pub market_token_account: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct FundHouse<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+    pub authority: Signer<'info>,
+    #[account(mut, constraint = authority_token_account.mint == market.mint @ ErrorCode::MintMismatch)]
+    pub authority_token_account: Account<'info, TokenAccount>,
+    #[account(mut, constraint = market_token_account.mint == market.mint @ ErrorCode::MintMismatch)]
+    pub market_token_account: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawHouseProfit<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", market.market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+    pub authority: Signer<'info>,
+    #[account(mut)]
+    pub authority_token_account: Account<'info, TokenAccount>,
+    #[account(mut)]
+    pub market_token_account: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+#[instruction(outcome_index: u8, bet_nonce: u64)]
+pub struct RollPayout<'info> {
+    #[account(mut)]
+    pub source_bet: Account<'info, Bet>,
+    #[account(
+        mut,
+        seeds = [b"market", source_market.market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub source_market: Account<'info, Market>,
+    #[account(seeds = [b"global_state"], bump)]
+    pub global_state: Account<'info, GlobalState>,
+    #[account(mut, constraint = source_market_token_account.mint == source_market.mint @ ErrorCode::MintMismatch)]
+    pub source_market_token_account: Account<'info, TokenAccount>,
+    #[account(
+        init,
+        payer = bettor,
+        space = 8 + Bet::INIT_SPACE,
+        seeds = [b"bet", target_market.key().as_ref(), bettor.key().as_ref(), bet_nonce.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub new_bet: Account<'info, Bet>,
+    #[account(mut)]
+    pub target_market: Account<'info, Market>,
+    #[account(mut, constraint = target_market_token_account.mint == target_market.mint @ ErrorCode::MintMismatch)]
+    pub target_market_token_account: Account<'info, TokenAccount>,
+    #[account(mut)]
+    pub bettor: Signer<'info>,
+    /// CHECK: the bettor's freeze record on the source market; must not exist
+    #[account(seeds = [b"bettor_freeze", source_market.key().as_ref(), bettor.key().as_ref()], bump)]
+    pub source_bettor_freeze: UncheckedAccount<'info>,
+    /// CHECK: the bettor's freeze record on the target market; must not exist
+    #[account(seeds = [b"bettor_freeze", target_market.key().as_ref(), bettor.key().as_ref()], bump)]
+    pub target_bettor_freeze: UncheckedAccount<'info>,
+    /// CHECK: the bettor's whitelist entry on the target market; must exist on whitelist markets
+    #[account(seeds = [b"whitelist", target_market.key().as_ref(), bettor.key().as_ref()], bump)]
+    pub target_whitelist_entry: UncheckedAccount<'info>,
+    /// Required, with `fee_treasury` and `fee_rate_source`, on a fee token market: the
+    /// bettor's account the platform fee is paid from.
+    #[account(mut)]
+    pub fee_payer_token_account: Option<Account<'info, TokenAccount>>,
+    #[account(mut)]
+    pub fee_treasury: Option<Account<'info, TokenAccount>>,
+    /// CHECK: only read; must be the fee token's `rate_source`
+    pub fee_rate_source: Option<UncheckedAccount<'info>>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(basket_id: u64)]
+pub struct CreateBasket<'info> {
+    #[account(
+        init,
+        payer = creator,
+        space = 8 + Basket::INIT_SPACE,
+        seeds = [b"basket", creator.key().as_ref(), basket_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub basket: Account<'info, Basket>,
+    #[account(
+        init,
+        payer = creator,
+        seeds = [b"basket_vault", basket.key().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = basket
+    )]
+    pub basket_vault: Account<'info, TokenAccount>,
+    pub mint: Account<'info, Mint>,
+    #[account(mut)]
+    pub creator: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct FundBasket<'info> {
+    #[account(has_one = creator)]
+    pub basket: Account<'info, Basket>,
+    #[account(mut, address = basket.vault)]
+    pub basket_vault: Account<'info, TokenAccount>,
+    pub creator: Signer<'info>,
+    #[account(mut)]
+    pub creator_token_account: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+#[instruction(picks: Vec<u8>, amount: u64, bet_nonce: u64)]
+pub struct CreateBasketBet<'info> {
+    #[account(mut)]
+    pub basket: Account<'info, Basket>,
+    #[account(
+        init,
+        payer = bettor,
+        space = 8 + BasketBet::INIT_SPACE,
+        seeds = [b"basket_bet", basket.key().as_ref(), bettor.key().as_ref(), bet_nonce.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub basket_bet: Account<'info, BasketBet>,
+    #[account(mut, address = basket.vault)]
+    pub basket_vault: Account<'info, TokenAccount>,
+    #[account(mut)]
+    pub bettor: Signer<'info>,
+    #[account(
+        mut,
+        constraint = bettor_token_account.owner == bettor.key() @ ErrorCode::InvalidTokenAccountOwner,
+        constraint = bettor_token_account.mint == basket.mint @ ErrorCode::MintMismatch
+    )]
+    pub bettor_token_account: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimBasket<'info> {
+    #[account(mut)]
+    pub basket: Account<'info, Basket>,
+    #[account(mut, has_one = basket, close = bettor)]
+    pub basket_bet: Account<'info, BasketBet>,
+    #[account(mut, address = basket.vault)]
+    pub basket_vault: Account<'info, TokenAccount>,
+    #[account(mut)]
+    pub bettor: Signer<'info>,
+    #[account(
+        mut,
+        constraint = bettor_token_account.owner == bettor.key() @ ErrorCode::InvalidTokenAccountOwner,
+        constraint = bettor_token_account.mint == basket.mint @ ErrorCode::MintMismatch
+    )]
+    pub bettor_token_account: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct RegisterCappedBet<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+    #[account(mut)]
+    pub bet: Account<'info, Bet>,
+}
+
+#[derive(Accounts)]
+pub struct ReleaseExternalId<'info> {
+    pub market: Account<'info, Market>,
+    #[account(
+        mut,
+        close = authority,
+        has_one = market,
+    )]
+    pub external_id_entry: Account<'info, ExternalIdEntry>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(creator: Pubkey)]
+pub struct WaiveCreationFee<'info> {
+    #[account(seeds = [b"global_state"], bump)]
+    pub global_state: Account<'info, GlobalState>,
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + FeeWaiver::INIT_SPACE,
+        seeds = [b"fee_waiver", creator.as_ref()],
+        bump
+    )]
+    pub fee_waiver: Account<'info, FeeWaiver>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RevokeFeeWaiver<'info> {
+    #[account(seeds = [b"global_state"], bump)]
+    pub global_state: Account<'info, GlobalState>,
+    #[account(
+        mut,
+        close = authority,
+        seeds = [b"fee_waiver", fee_waiver.creator.as_ref()],
+        bump
+    )]
+    pub fee_waiver: Account<'info, FeeWaiver>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct VetoResolution<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+    /// Closed so the resolver can record a fresh resolution.
+    #[account(
+        mut,
+        close = market_authority,
+        seeds = [b"resolved_outcome", market.market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub resolved_outcome: Account<'info, ResolvedOutcome>,
+    /// CHECK: receives the resolution record's rent
+    #[account(mut, address = market.authority)]
+    pub market_authority: UncheckedAccount<'info>,
+    pub guardian: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ExtendResolution<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct MarkBettingClosed<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+    pub caller: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct TransferMarketAuthority<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CancelMarket<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RefundCreationFee<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+    #[account(
+        seeds = [b"global_state"],
+        bump
+    )]
+    pub global_state: Account<'info, GlobalState>,
+    #[account(
+        mut,
+        constraint = treasury.key() == global_state.treasury @ ErrorCode::InvalidTreasury,
+        constraint = treasury.owner == treasury_authority.key() @ ErrorCode::Unauthorized
+    )]
+    pub treasury: Account<'info, TokenAccount>,
+    pub treasury_authority: Signer<'info>,
+    #[account(
+        mut,
+        constraint = creator_token_account.key() == market.creation_fee_account @ ErrorCode::Unauthorized
+    )]
+    pub creator_token_account: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct QuarantineMarket<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+    #[account(
+        seeds = [b"global_state"],
+        bump
+    )]
+    pub global_state: Account<'info, GlobalState>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeInsuranceFund<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + InsuranceFund::INIT_SPACE,
+        seeds = [b"insurance_fund", mint.key().as_ref()],
+        bump
+    )]
+    pub insurance_fund: Account<'info, InsuranceFund>,
+    #[account(
+        init,
+        payer = authority,
+        token::mint = mint,
+        token::authority = insurance_fund,
+        seeds = [b"insurance_vault", mint.key().as_ref()],
+        bump
+    )]
+    pub insurance_vault: Account<'info, TokenAccount>,
+    pub mint: Account<'info, Mint>,
+    #[account(
+        seeds = [b"global_state"],
+        bump
+    )]
+    pub global_state: Account<'info, GlobalState>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ContributeInsurance<'info> {
+    #[account(
+        mut,
+        seeds = [b"insurance_fund", insurance_fund.mint.as_ref()],
+        bump = insurance_fund.bump
+    )]
+    pub insurance_fund: Account<'info, InsuranceFund>,
+    #[account(mut, address = insurance_fund.vault)]
+    pub insurance_vault: Account<'info, TokenAccount>,
+    pub contributor: Signer<'info>,
+    #[account(mut)]
+    pub contributor_token_account: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct CoverShortfall<'info> {
+    #[account(
+        mut,
+        seeds = [b"insurance_fund", insurance_fund.mint.as_ref()],
+        bump = insurance_fund.bump
+    )]
+    pub insurance_fund: Account<'info, InsuranceFund>,
+    #[account(mut, address = insurance_fund.vault)]
+    pub insurance_vault: Account<'info, TokenAccount>,
+    pub market: Account<'info, Market>,
+    #[account(mut)]
+    pub market_token_account: Account<'info, TokenAccount>,
+    #[account(
+        seeds = [b"global_state"],
+        bump
+    )]
+    pub global_state: Account<'info, GlobalState>,
+    pub authority: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+#[instruction(bettor: Pubkey)]
+pub struct FreezeBettor<'info> {
+    pub market: Account<'info, Market>,
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + BettorFreeze::INIT_SPACE,
+        seeds = [b"bettor_freeze", market.key().as_ref(), bettor.as_ref()],
+        bump
+    )]
+    pub bettor_freeze: Account<'info, BettorFreeze>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UnfreezeBettor<'info> {
+    pub market: Account<'info, Market>,
+    #[account(
+        mut,
+        close = authority,
+        has_one = market,
+    )]
+    pub bettor_freeze: Account<'info, BettorFreeze>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(bettor: Pubkey)]
+pub struct AddToWhitelist<'info> {
+    pub market: Account<'info, Market>,
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + WhitelistEntry::INIT_SPACE,
+        seeds = [b"whitelist", market.key().as_ref(), bettor.as_ref()],
+        bump
+    )]
+    pub whitelist_entry: Account<'info, WhitelistEntry>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RemoveFromWhitelist<'info> {
+    pub market: Account<'info, Market>,
+    #[account(
+        mut,
+        close = authority,
+        has_one = market,
+    )]
+    pub whitelist_entry: Account<'info, WhitelistEntry>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(outcome_index: u8)]
+pub struct SplitVault<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", market.market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(mut)]
+    pub market_token_account: Account<'info, TokenAccount>,
+    #[account(address = market_token_account.mint)]
+    pub mint: Account<'info, Mint>,
+    #[account(
+        init,
+        payer = authority,
+        seeds = [b"outcome_vault", market.key().as_ref(), &[outcome_index]],
+        bump,
+        token::mint = mint,
+        token::authority = market
+    )]
+    pub outcome_vault: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct FundKeeperReserve<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+    pub authority: Signer<'info>,
+    #[account(mut)]
+    pub authority_token_account: Account<'info, TokenAccount>,
+    #[account(mut)]
+    pub market_token_account: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct ProcessStaleMarket<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", market.market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+    pub keeper: Signer<'info>,
+    #[account(mut)]
+    pub keeper_token_account: Account<'info, TokenAccount>,
+    #[account(mut)]
+    pub market_token_account: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct ReclaimKeeperReserve<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", market.market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+    pub authority: Signer<'info>,
+    #[account(mut)]
+    pub authority_token_account: Account<'info, TokenAccount>,
+    #[account(mut)]
+    pub market_token_account: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct CheckAndVoidSingleSided<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+}
+
+#[derive(Accounts)]
+pub struct ValidateDiversity<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimRefund<'info> {
+    #[account(mut, close = bettor)]
+    pub bet: Account<'info, Bet>,
+    #[account(
+        seeds = [b"market", market.market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+    #[account(mut)]
+    pub bettor: Signer<'info>,
+    #[account(
+        mut,
+        constraint = bettor_token_account.owner == bettor.key() @ ErrorCode::InvalidTokenAccountOwner,
+        constraint = bettor_token_account.mint == market.mint @ ErrorCode::MintMismatch
+    )]
+    pub bettor_token_account: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = market_token_account.owner == market.key() @ ErrorCode::InvalidMarketVault,
+        constraint = market_token_account.mint == market.mint @ ErrorCode::MintMismatch
+    )]
+    pub market_token_account: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct Donate<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", market.market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+    #[account(seeds = [b"global_state"], bump)]
+    pub global_state: Account<'info, GlobalState>,
+    #[account(
+        init_if_needed,
+        payer = donor,
+        space = 8 + Donation::INIT_SPACE,
+        seeds = [b"donation", market.key().as_ref(), donor.key().as_ref()],
+        bump
+    )]
+    pub donation: Account<'info, Donation>,
+    #[account(mut)]
+    pub donor: Signer<'info>,
+    #[account(
+        mut,
+        constraint = donor_token_account.owner == donor.key() @ ErrorCode::InvalidTokenAccountOwner,
+        constraint = donor_token_account.mint == market.mint @ ErrorCode::MintMismatch
+    )]
+    pub donor_token_account: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = market_token_account.owner == market.key() @ ErrorCode::InvalidMarketVault,
+        constraint = market_token_account.mint == market.mint @ ErrorCode::MintMismatch
+    )]
+    pub market_token_account: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ReclaimDonation<'info> {
+    #[account(
+        seeds = [b"market", market.market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+    #[account(
+        mut,
+        seeds = [b"donation", market.key().as_ref(), donor.key().as_ref()],
+        bump,
+        close = donor
+    )]
+    pub donation: Account<'info, Donation>,
+    #[account(mut)]
+    pub donor: Signer<'info>,
+    #[account(
+        mut,
+        constraint = donor_token_account.owner == donor.key() @ ErrorCode::InvalidTokenAccountOwner,
+        constraint = donor_token_account.mint == market.mint @ ErrorCode::MintMismatch
+    )]
+    pub donor_token_account: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = market_token_account.owner == market.key() @ ErrorCode::InvalidMarketVault,
+        constraint = market_token_account.mint == market.mint @ ErrorCode::MintMismatch
+    )]
+    pub market_token_account: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct RefundBatch<'info> {
+    #[account(
+        seeds = [b"market", market.market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+    pub authority: Signer<'info>,
+    #[account(mut)]
+    pub market_token_account: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct GetPlatformTvl<'info> {
+    #[account(seeds = [b"global_state"], bump)]
+    pub global_state: Account<'info, GlobalState>,
+}
+
+#[derive(Accounts)]
+pub struct ResetCircuitBreaker<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ReadMarket<'info> {
+    pub market: Account<'info, Market>,
+}
+
+#[derive(Accounts)]
+pub struct QuotePayout<'info> {
+    pub market: Account<'info, Market>,
+    #[account(seeds = [b"global_state"], bump)]
+    pub global_state: Account<'info, GlobalState>,
+}
+
+#[derive(Accounts)]
+pub struct GetPayoutBreakdown<'info> {
+    pub market: Account<'info, Market>,
+    pub bet: Account<'info, Bet>,
+    #[account(seeds = [b"global_state"], bump)]
+    pub global_state: Account<'info, GlobalState>,
+}
+
+#[derive(Accounts)]
+pub struct MarketHealthCheck<'info> {
+    pub market: Account<'info, Market>,
+    pub market_token_account: Account<'info, TokenAccount>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default)]
+pub struct MarketConfig {
+    /// Void the market and refund everyone if only one outcome has bets at close.
+    pub void_if_single_sided: bool,
+    pub market_type: MarketType,
+    /// Over/under line for `Pivot` markets, in the same fixed-point scale as the reported value.
+    pub pivot_value: i64,
+    /// Pause betting once `total_pool` grows by more than this within one window. Zero disables.
+    pub circuit_breaker_bps: u16,
+    /// Length in seconds of the circuit breaker's measurement window.
+    pub circuit_breaker_window: i64,
+    /// Optional oracle per outcome that must sign to declare that outcome the winner.
+    /// Empty, or `None` entries, fall back to the market authority.
+    pub outcome_oracles: Vec<Option<Pubkey>>,
+    /// Cap any single bet's payout at this share of the pool, redistributing the excess to
+    /// other winners. Zero disables.
+    pub max_payout_share_bps: u16,
+    /// Seconds after resolution during which capped bets can be registered; claims wait for it.
+    pub cap_registration_period: i64,
+    /// Optional id in an off-chain question catalog, e.g. a hash of the canonical question.
+    /// Only one active market may hold a given id.
+    pub external_id: Option<[u8; 32]>,
+    /// Seconds winnings are held in escrow after a claim before they can be withdrawn.
+    /// Zero pays claims out directly.
+    pub withdrawal_delay: i64,
+    /// Close betting and open resolution at `resolution_slot` instead of `resolution_time`,
+    /// for short markets where validator clock drift matters.
+    pub use_slots: bool,
+    pub resolution_slot: u64,
+    /// Only accept bets funded from token accounts the authority has approved, e.g. KYC'd
+    /// custody accounts. The list starts empty and is managed with `allow_token_account`.
+    pub restrict_token_accounts: bool,
+    /// Makes a blind market: `outcomes` are placeholders and these are `hash(label || salt)`
+    /// of the real labels, one per outcome, revealed after betting closes. Empty for an
+    /// ordinary market.
+    pub outcome_commitments: Vec<[u8; 32]>,
+    /// Commits a pivot market to the reading `scheduled_oracle` reports between
+    /// `scheduled_time` and `scheduled_time + scheduled_window`; the authority can't resolve it.
+    pub scheduled_oracle: Option<Pubkey>,
+    pub scheduled_time: i64,
+    pub scheduled_window: i64,
+    /// Fewest outcomes that must hold stake at close for the market to resolve; below it the
+    /// market can only be voided through `validate_diversity`. 0 or 1 leaves resolution as is.
+    pub min_diverse_outcomes: u8,
+    /// Decimal odds per outcome in bps for `MarketType::FixedOdds` (25_000 pays 2.5x the stake).
+    /// Must be empty for other market types.
+    pub fixed_odds_bps: Vec<u32>,
+    /// Raise the minimum bet on outcomes the pools price as long shots; see `effective_min_bet`.
+    pub graduated_min_bet: bool,
+    /// Resolution must carry an evidence hash for every outcome, recording why the winner won
+    /// and each other outcome lost. Not available on pivot markets.
+    pub require_outcome_evidence: bool,
+    /// Seconds past `resolution_time` after which anyone may void an unresolved market through
+    /// `process_stale_market`. Zero disables keeper processing.
+    pub stale_grace_period: i64,
+    /// Resolve with per-outcome accuracy scores through `resolve_market_scored` instead of a
+    /// single winner. Standard markets only.
+    pub scored_resolution: bool,
+    /// Account whose data decides a two-outcome market through `resolve_from_account`.
+    pub resolution_account: Option<Pubkey>,
+    /// Byte offset into `resolution_account`'s data where `expected_bytes` is compared.
+    pub resolution_offset: u32,
+    pub expected_bytes: Vec<u8>,
+    /// Who may veto a resolution through `veto_resolution`; never chooses the outcome.
+    pub guardian: Option<Pubkey>,
+    /// Seconds after resolution during which the guardian may veto and payouts are held.
+    pub veto_window: i64,
+    /// Most vetoes the guardian may issue on this market.
+    pub max_vetoes: u8,
+    /// Take bets on a full ordering of the outcomes through `place_ranked_bet` and resolve
+    /// with `resolve_ranked`. Standard markets of up to `MAX_RANKED_OUTCOMES` outcomes.
+    pub ranked: bool,
+    /// Seconds a `propose_resolution` must stand before `finalize_resolution` settles it.
+    /// Zero keeps single-step `resolve_market`.
+    pub challenge_period: i64,
+    /// Stake native SOL through the `_sol` instructions instead of an SPL token.
+    pub is_native: bool,
+    /// Seconds before `resolution_time` that betting closes, e.g. an hour before kickoff.
+    /// Resolution still opens at `resolution_time`. Time-based markets only.
+    pub betting_cutoff_offset: i64,
+    /// Share of each winning payout, after the protocol fee, that accrues to the market
+    /// authority for `withdraw_creator_fees`. At most `MAX_CREATOR_FEE_BPS`; token markets only.
+    pub creator_fee_bps: u16,
+    /// Opening seed per outcome, funded by the creator at creation; empty for none. Treated
+    /// exactly like `seed_liquidity`: seed on losing outcomes goes to winners, and the seed on
+    /// the winner earns its pro-rata share back through `reclaim_seed`.
+    pub initial_liquidity: Vec<u64>,
+    /// Who may bet; `Whitelist` limits staking to bettors with a whitelist entry.
+    pub access_mode: AccessMode,
+    /// Cap on any one bet, counting top-ups. `None` leaves bets unlimited.
+    pub max_bet: Option<u64>,
+    /// Cap on `total_pool`; stakes that would push past it are rejected. `None` is unlimited.
+    pub max_total_pool: Option<u64>,
+    /// Committee that resolves the market by vote in place of the authority. Empty keeps
+    /// authority resolution.
+    pub resolvers: Vec<Pubkey>,
+    /// Matching votes an outcome needs from `resolvers` to win. Zero without a committee.
+    pub resolution_threshold: u8,
+    /// Seconds after resolution before `close_market` may close the market. Zero allows it
+    /// as soon as every winner is paid.
+    pub close_grace_period: i64,
+    /// Stake every outcome needs before the market goes active. Bets are taken either way,
+    /// but a market still inactive at resolution is voided for refunds. Zero starts active.
+    pub min_liquidity_per_outcome: u64,
+    /// Pay winners by time-decayed weight instead of raw stake, so earlier bets earn more of
+    /// the pool; see `stake_weight`.
+    pub weighted: bool,
+    /// Share of the stake kept in the pool when a bettor calls `cancel_bet`. `None` disables
+    /// cancelling.
+    pub cancel_penalty_bps: Option<u16>,
+    /// Discovery metadata for browsing and filtering markets.
+    pub category: MarketCategory,
+    /// Up to `MAX_TAGS` free-form tags of at most `MAX_TAG_LEN` bytes each.
+    pub tags: Vec<String>,
+    /// Account that resolves the market in place of the authority, so the creator doesn't also
+    /// decide the result. `outcome_oracles` entries still take precedence for their outcomes.
+    pub oracle: Option<Pubkey>,
+    /// Range a `Scalar` market's value is read against: at the lower bound short holders take
+    /// the whole pool, at the upper bound long holders do. Both zero on other market types.
+    pub scalar_lower_bound: i64,
+    pub scalar_upper_bound: i64,
+    /// Id the caller expects the market to get, i.e. the `market_count` its address was derived
+    /// from. Checked against the counter so a losing concurrent creation fails with
+    /// `MarketIdTaken`; `None` skips the check.
+    pub market_id: Option<u64>,
+    /// Seconds after `resolution_time` that winners have to claim before the authority may
+    /// `reclaim_unclaimed` the rest; see `claim_deadline_at` for how resolution delays and
+    /// disputes push it back. Zero leaves claims open indefinitely.
+    pub claim_deadline: i64,
+    /// Resolve through `resolve_from_twap` on a time-weighted average of the prices
+    /// `sample_twap` reads over the market's final window, rather than a reported spot value
+    /// that a single trade could move. Pivot and scalar markets only.
+    pub twap: Option<TwapConfig>,
+    /// Platform fee discounts for resolving soon after `resolution_time`, tightest window
+    /// first; see `PromptFeeTier`. Empty charges the full fee. Time-based markets only.
+    pub prompt_fee_tiers: Vec<PromptFeeTier>,
+    /// Seconds past `resolution_time` over which the platform fee falls linearly to zero, so
+    /// bettors pay less the longer resolution is late. Zero keeps the fee. Time-based markets only.
+    pub fee_decay_period: i64,
+    /// Most platform fee the market will take over its lifetime; once claims have paid this
+    /// much, later ones pay none. `None` leaves the fee uncapped.
+    pub fee_cap: Option<u64>,
+    /// Token account, e.g. a charity's, that receives the market's swept dust and reclaimed
+    /// unclaimed winnings. `None` sends them to the platform treasury.
+    pub residual_destination: Option<Pubkey>,
+    /// On a scalar market, the share of the pool in bps settled on a preliminary value by
+    /// `report_preliminary_value`; the rest settles on the final value. Zero settles in one go.
+    pub preliminary_tranche_bps: u16,
+}
+
+/// Resolving within `within` seconds of `resolution_time` charges `fee_share_bps` of the
+/// platform fee on the market's payouts.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub struct PromptFeeTier {
+    pub within: i64,
+    pub fee_share_bps: u16,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Default, InitSpace)]
+pub enum MarketType {
+    #[default]
+    Standard,
+    /// Two-outcome over/under market resolved from a reported value.
+    /// Outcome 0 is "over", outcome 1 is "under", and an exact tie voids the market.
+    Pivot,
+    /// Bets are paid at the odds fixed in `fixed_odds_bps` rather than from the pool, with the
+    /// market authority as the house taking the other side of every bet.
+    FixedOdds,
+    /// Two-outcome long/short market resolved from a reported value. The pool is split between
+    /// the sides by where the value lands between `scalar_lower_bound` and `scalar_upper_bound`;
+    /// outcome 0 is "long", outcome 1 is "short".
+    Scalar,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Default, InitSpace)]
+pub enum MarketCategory {
+    Sports,
+    Politics,
+    Crypto,
+    Finance,
+    Entertainment,
+    Science,
+    #[default]
+    Other,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Default, InitSpace)]
+pub enum AccessMode {
+    #[default]
+    Public,
+    /// Only bettors the authority admits through `add_to_whitelist` may bet.
+    Whitelist,
+}
+
+/// Return data of `create_market`, for programs creating markets over CPI.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct MarketReceipt {
+    pub market: Pubkey,
+    pub market_id: u64,
+}
+
+/// Return data of `get_market_state`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct MarketState {
+    pub market_id: u64,
+    pub total_pool: u64,
+    /// Per outcome, summing to roughly 10,000 on pool markets
+    pub implied_probabilities_bps: Vec<u16>,
+    pub time_until_cutoff: i64,
+    pub time_until_resolution: i64,
+    pub status: MarketStatus,
+}
+
+/// Return data of `place_bet`: the bet account and the stake credited to the pool.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct BetReceipt {
+    pub bet: Pubkey,
+    pub amount: u64,
+}
+
+/// Lifecycle state derived from the clock and stored market flags.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum MarketStatus {
+    Open,
+    Paused,
+    AwaitingResolution,
+    Resolved,
+    Voided,
+    /// Past the betting cutoff, or marked closed, but not yet at `resolution_time`
+    BettingClosed,
+    /// Called off by `cancel_market`; refunds are open
+    Cancelled,
+}
+
+/// Triage classification reported by `market_health`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum MarketHealthStatus {
+    Healthy,
+    OverdueResolution,
+    StuckFunds,
+    Settled,
+}
+
+#[derive(Accounts)]
+pub struct SampleTwap<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+    /// CHECK: only read; must be the market's configured TWAP source
+    pub source_account: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ResolveFromTwap<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+    #[account(
+        init,
+        payer = caller,
+        space = 8 + ResolvedOutcome::INIT_SPACE,
+        seeds = [b"resolved_outcome", market.market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub resolved_outcome: Account<'info, ResolvedOutcome>,
+    #[account(mut)]
+    pub caller: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RegisterReceiptMint<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + ReceiptMintEntry::INIT_SPACE,
+        seeds = [b"receipt_mint", mint.key().as_ref()],
+        bump
+    )]
+    pub receipt_mint_entry: Account<'info, ReceiptMintEntry>,
+    pub mint: Account<'info, Mint>,
+    /// CHECK: only read; the vault account publishing the receipt token's exchange rate
+    pub rate_source: UncheckedAccount<'info>,
+    pub global_state: Account<'info, GlobalState>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct EnableReceiptBetting<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+    #[account(
+        seeds = [b"receipt_mint", market.mint.as_ref()],
+        bump
+    )]
+    pub receipt_mint_entry: Account<'info, ReceiptMintEntry>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateReceiptRate<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+    /// CHECK: only read; must be the market's registered rate source
+    pub rate_source: UncheckedAccount<'info>,
+}
+
+/// Where and how a TWAP market samples its price.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub struct TwapConfig {
+    /// Account holding the price as a little-endian `i64` at `offset`, in the same scale as
+    /// the market's pivot.
+    pub source: Pubkey,
+    pub offset: u32,
+    /// Seconds before `resolution_time` that sampling opens.
+    pub window: i64,
+    /// Fewest seconds between two samples.
+    pub min_spacing: i64,
+    /// Samples the market needs before it can resolve; at least two.
+    pub min_samples: u16,
+}
+
+/// A yield vault's receipt token and where its exchange rate is published.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub struct ReceiptVault {
+    pub mint: Pubkey,
+    /// Account holding the rate as a little-endian `u64` at `rate_offset`, in underlying
+    /// tokens per receipt token scaled by `RECEIPT_RATE_SCALE`.
+    pub rate_source: Pubkey,
+    /// Program `rate_source` belonged to when the mint was registered.
+    pub rate_source_owner: Pubkey,
+    pub rate_offset: u32,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct GlobalState {
+    pub authority: Pubkey,
+    pub market_count: u64,
+    pub max_question_len: u16,
+    /// Protocol fee on winning payouts, in basis points.
+    pub fee_bps: u16,
+    /// Flat fee charged by `create_market`, in the treasury's mint.
+    pub creation_fee: u64,
+    /// Token account creation fees are paid into.
+    pub treasury: Pubkey,
+    /// Set by `pause` to halt market creation and new stakes.
+    pub paused: bool,
+    /// Lowest `min_bet` a new market may set, keeping dust bets from bloating markets.
+    pub global_min_bet: u64,
+    /// Token new markets charge the platform fee in, if not the payout currency.
+    pub fee_token: Option<FeeToken>,
+    /// Share of withdrawn platform fees paid into the insurance fund, in basis points.
+    pub insurance_fee_bps: u16,
+}
+
+/// Platform fee paid from the winner's own account in a separate token, converted from the
+/// fee in payout tokens at the rate `rate_source` holds.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub struct FeeToken {
+    pub mint: Pubkey,
+    /// Token account of `mint` the fees are paid into.
+    pub treasury: Pubkey,
+    /// Account holding a little-endian `u64` at `rate_offset`: fee token base units per
+    /// `FEE_RATE_SCALE` payout token base units.
+    pub rate_source: Pubkey,
+    /// Program `rate_source` must belong to.
+    pub rate_source_owner: Pubkey,
+    pub rate_offset: u32,
+    /// Offset of the little-endian `i64` unix time `rate_source` was last updated at; a rate
+    /// older than `MAX_FEE_RATE_AGE` is refused.
+    pub updated_at_offset: u32,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Market {
+    pub authority: Pubkey,
+    pub market_id: u64,
+    // Question bytes are sized per market from `question.len()` in `CreateMarket`,
+    // bounded by `GlobalState::max_question_len`
+    #[max_len(0)]
+    pub question: String,
+    // labels are sized per market by `market_space`
+    #[max_len(0, 0)]
+    pub outcomes: Vec<String>,
+    #[max_len(10)]
+    pub outcome_pools: Vec<u64>,
+    /// Bet accounts opened on each outcome, and in total. Counts bets, not unique bettors:
+    /// one wallet can hold several bets, and topping one up with `add_to_bet` adds none.
+    #[max_len(10)]
+    pub bet_counts: Vec<u32>,
+    pub total_bets: u64,
+    /// Winning and runner-up bets paid out so far, by any claim or settlement path.
+    pub claimed_count: u32,
+    pub close_grace_period: i64,
+    /// Claim window after resolution; see `claim_deadline_at`. Zero is unlimited.
+    pub claim_deadline: i64,
+    /// Set by `reclaim_unclaimed`; unclaimed winnings have gone to the residual destination.
+    pub unclaimed_reclaimed: bool,
+    /// Seconds resolutions on this market stood before the guardian vetoed them, summed.
+    pub dispute_duration: i64,
+    pub min_liquidity_per_outcome: u64,
+    /// Whether every outcome meets `min_liquidity_per_outcome`; see `refresh_active`.
+    pub active: bool,
+    pub weighted: bool,
+    /// Sum of bet weights per outcome on time-weighted markets; empty elsewhere.
+    #[max_len(10)]
+    pub weighted_pools: Vec<u64>,
+    pub cancel_penalty_bps: Option<u16>,
+    pub category: MarketCategory,
+    #[max_len(5, 24)]
+    pub tags: Vec<String>,
+    /// Reports the result instead of `authority` when set; see `market_resolver`.
+    pub oracle: Option<Pubkey>,
+    pub scalar_lower_bound: i64,
+    pub scalar_upper_bound: i64,
+    /// Reported value clamped to the bounds, set by `resolve_market_scalar`.
+    pub scalar_value: i64,
+    /// Part of `total_pool` long holders split; short holders split the rest. On tranche
+    /// markets this is both tranches together.
+    pub scalar_long_share: u64,
+    pub preliminary_tranche_bps: u16,
+    /// Value `report_preliminary_value` settled the first tranche on; `scalar_value` is the
+    /// final one.
+    pub preliminary_value: Option<i64>,
+    /// Part of the first tranche long holders split.
+    pub preliminary_long_share: u64,
+    pub access_mode: AccessMode,
+    /// Token stakes are denominated in; every vault and bettor account must use it. Unused on
+    /// native SOL markets.
+    pub mint: Pubkey,
+    pub resolution_time: i64,
+    /// New stakes are refused from this time on; equals `resolution_time` unless the market
+    /// was created with a `betting_cutoff_offset`.
+    pub betting_cutoff: i64,
+    /// Set by `mark_betting_closed`; new stakes are refused even if the clock reads earlier.
+    pub betting_closed: bool,
+    /// Part of `total_pool` that came from `donate` rather than stakes.
+    pub donated_total: u64,
+    pub min_bet: u64,
+    /// Largest single bet, and largest the pool may grow through stakes. `None` is unlimited.
+    pub max_bet: Option<u64>,
+    pub max_total_pool: Option<u64>,
+    /// M-of-N resolution committee; `resolution_votes[i]` is the outcome `resolvers[i]` voted for.
+    #[max_len(10)]
+    pub resolvers: Vec<Pubkey>,
+    pub resolution_threshold: u8,
+    #[max_len(10)]
+    pub resolution_votes: Vec<Option<u8>>,
+    pub resolved: bool,
+    pub winning_outcome: Option<u8>,
+    pub total_pool: u64,
+    pub created_at: i64,
+    pub void_if_single_sided: bool,
+    pub voided: bool,
+    /// Stakes placed by whoever held `authority` at the time of each bet.
+    #[max_len(10)]
+    pub authority_stakes: Vec<u64>,
+    /// Whether the authority's own bets back the winner. Says nothing about an oracle or
+    /// committee member who resolved; see `resolved_by` for who that was.
+    pub authority_had_winning_stake: bool,
+    pub market_type: MarketType,
+    pub pivot_value: i64,
+    pub circuit_breaker_bps: u16,
+    pub circuit_breaker_window: i64,
+    pub circuit_breaker_tripped: bool,
+    pub breaker_window_start: i64,
+    pub breaker_window_pool: u64,
+    #[max_len(10)]
+    pub outcome_oracles: Vec<Option<Pubkey>>,
+    pub max_payout_share_bps: u16,
+    pub cap_registration_period: i64,
+    pub cap_registration_ends: i64,
+    pub capped_stake: u64,
+    pub capped_count: u32,
+    pub external_id: Option<[u8; 32]>,
+    #[max_len(10)]
+    pub seed_amounts: Vec<u64>,
+    pub seed_reclaimed: bool,
+    pub quarantined: bool,
+    pub cancelled: bool,
+    /// Creation fee the creator paid to list this market; zeroed once refunded.
+    pub creation_fee_paid: u64,
+    pub guardian: Option<Pubkey>,
+    pub veto_window: i64,
+    pub max_vetoes: u8,
+    pub veto_count: u8,
+    /// When the current resolution was applied; starts the guardian's veto window.
+    pub resolved_at: i64,
+    /// Who applied the current resolution; the default key while unresolved.
+    pub resolved_by: Pubkey,
+    /// `outcome_pools` as they stood when the current resolution was applied.
+    #[max_len(10)]
+    pub final_pools: Vec<u64>,
+    /// Token account the creation fee was paid from, and is refunded to on cancellation.
+    pub creation_fee_account: Pubkey,
+    pub immutable: bool,
+    #[max_len(10)]
+    pub merged_into: Vec<Option<u8>>,
+    pub twap: Option<TwapConfig>,
+    /// Samples taken by `sample_twap`, when the first and latest were taken, and the latest
+    /// price.
+    pub twap_samples: u16,
+    pub twap_first_at: i64,
+    pub twap_last_at: i64,
+    pub twap_last_price: i64,
+    /// Sum of each sampled price times the seconds it stood until the next sample.
+    pub twap_cumulative: i128,
+    #[max_len(4)]
+    pub prompt_fee_tiers: Vec<PromptFeeTier>,
+    pub fee_decay_period: i64,
+    pub fee_cap: Option<u64>,
+    /// Where dust and forfeited winnings go, in the market's mint; see `residual_destination`.
+    pub residual_destination: Option<Pubkey>,
+    /// Share of the platform fee charged on payouts, set at resolution by
+    /// `resolution_fee_share`. The full fee until then.
+    pub platform_fee_share_bps: u16,
+    /// Receipt token staked on this market instead of a plain token; see `enable_receipt_betting`.
+    pub receipt: Option<ReceiptVault>,
+    /// Latest exchange rate read by `update_receipt_rate`, and when.
+    pub receipt_rate: u64,
+    pub receipt_rate_at: i64,
+    /// Platform fee token, fixed at creation from `GlobalState::fee_token`. When set, winners
+    /// pay the platform fee from their own fee token account and it's never held in the vault.
+    pub fee_token: Option<FeeToken>,
+    /// Fee tokens charged on this market, in the fee token's base units. Kept apart from
+    /// `fees_collected`, which only counts fees taken from payouts.
+    pub fee_token_fees_collected: u64,
+    pub withdrawal_delay: i64,
+    pub use_slots: bool,
+    pub resolution_slot: u64,
+    pub restrict_token_accounts: bool,
+    #[max_len(10)]
+    pub allowed_token_accounts: Vec<Pubkey>,
+    #[max_len(10)]
+    pub outcome_commitments: Vec<[u8; 32]>,
+    pub outcomes_revealed: bool,
+    pub scheduled_oracle: Option<Pubkey>,
+    pub scheduled_time: i64,
+    pub scheduled_window: i64,
+    pub min_diverse_outcomes: u8,
+    #[max_len(10)]
+    pub fixed_odds_bps: Vec<u32>,
+    /// House liquidity deposited through `fund_house` and the part of it committed to bets.
+    pub house_liquidity: u64,
+    pub house_locked: u64,
+    pub house_withdrawn: bool,
+    pub graduated_min_bet: bool,
+    pub require_outcome_evidence: bool,
+    /// Per-outcome evidence hashes submitted at resolution, empty if none were given.
+    #[max_len(10)]
+    pub outcome_evidence: Vec<[u8; 32]>,
+    pub stale_grace_period: i64,
+    /// Tokens in the vault set aside to reward the keeper, outside `total_pool`.
+    pub keeper_reserve: u64,
+    /// Per-outcome sub-vaults created by `split_vault`, indexed by outcome. Empty while all
+    /// stakes sit in the combined vault.
+    #[max_len(10)]
+    pub outcome_vaults: Vec<Pubkey>,
+    /// Protocol fees deducted from payouts, held in the vault until `withdraw_fees`.
+    pub fees_accrued: u64,
+    pub creator_fee_bps: u16,
+    /// Creator fees collected from payouts and not yet withdrawn.
+    pub creator_fees_accrued: u64,
+    /// Every protocol fee ever taken; `withdraw_fees` doesn't lower it. Counts toward `fee_cap`.
+    pub fees_collected: u64,
+    /// Every creator fee ever taken; `withdraw_creator_fees` doesn't lower it.
+    pub creator_fees_collected: u64,
+    pub scored_resolution: bool,
+    /// Scores set by `resolve_market_scored`; empty on markets resolved to a single winner.
+    #[max_len(10)]
+    pub outcome_scores: Vec<u8>,
+    pub ranked: bool,
+    /// Ranked stake per predicted placing, indexed `outcome * outcomes.len() + position`.
+    #[max_len(36)]
+    pub rank_stakes: Vec<u64>,
+    /// Ranking set by `resolve_ranked`, best first; empty on other markets.
+    #[max_len(6)]
+    pub final_ranking: Vec<u8>,
+    pub challenge_period: i64,
+    /// Outcome awaiting `finalize_resolution` on markets with a challenge period.
+    pub proposed_outcome: Option<u8>,
+    pub proposed_at: i64,
+    /// Stakes are lamports held by the `[b"sol_vault", market]` PDA rather than a token vault.
+    pub is_native: bool,
+    pub resolution_account: Option<Pubkey>,
+    pub resolution_offset: u32,
+    #[max_len(32)]
+    pub expected_bytes: Vec<u8>,
+    pub runner_up: Option<u8>,
+    /// Slice of `total_pool` reserved for bets on `runner_up`, fixed at resolution.
+    pub consolation_pool: u64,
+    /// Lifetime totals behind `SettlementReport`; refunds and withdrawals don't lower them.
+    /// Every stake ever credited to the pools, excluding seed liquidity.
+    pub total_staked: u64,
+    /// Winning bets paid, counted at their first claim, whole or partial.
+    pub winners_paid: u32,
+    /// Net winnings paid to bettors, whether sent, escrowed or rolled into another market.
+    pub winnings_paid: u64,
+    /// Rounding dust sent to the residual destination by `sweep_dust`.
+    pub dust_swept: u64,
+    /// Unclaimed winnings `reclaim_unclaimed` forfeited to the residual destination.
+    pub unclaimed_forfeited: u64,
+}
+
+/// A composite position product over several markets, paying by how many legs hit.
+#[account]
+#[derive(InitSpace)]
+pub struct Basket {
+    pub creator: Pubkey,
+    pub basket_id: u64,
+    pub mint: Pubkey,
+    pub vault: Pubkey,
+    #[max_len(5)]
+    pub markets: Vec<Pubkey>,
+    /// Payout multiplier for 0..=markets.len() correct legs, in basis points of the stake.
+    #[max_len(6)]
+    pub payout_bps: Vec<u32>,
+    /// Best-case payouts owed to open basket bets; liquidity above this can be withdrawn.
+    pub locked: u64,
+    pub bump: u8,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct BasketBet {
+    pub bettor: Pubkey,
+    pub basket: Pubkey,
+    /// Chosen outcome for each of the basket's markets, in order.
+    #[max_len(5)]
+    pub picks: Vec<u8>,
+    pub amount: u64,
+    pub reserved: u64,
+    pub claimed: bool,
+    pub timestamp: i64,
+    /// Caller-chosen nonce in the basket bet's PDA seeds.
+    pub nonce: u64,
+}
+
+/// Exempts `creator` from the creation fee while it exists.
+#[account]
+#[derive(InitSpace)]
+pub struct FeeWaiver {
+    pub creator: Pubkey,
+    pub granted_at: i64,
+}
+
+/// Marks a bettor as frozen on one market; its existence is the freeze.
+#[account]
+#[derive(InitSpace)]
+pub struct BettorFreeze {
+    pub market: Pubkey,
+    pub bettor: Pubkey,
+    pub frozen_at: i64,
+}
+
+/// Admits one bettor to a whitelist market.
+#[account]
+#[derive(InitSpace)]
+pub struct WhitelistEntry {
+    pub market: Pubkey,
+    pub bettor: Pubkey,
+    pub added_at: i64,
+}
+
+/// Platform backstop for markets whose vault can't cover their winners, one per mint.
+#[account]
+#[derive(InitSpace)]
+pub struct InsuranceFund {
+    pub mint: Pubkey,
+    pub vault: Pubkey,
+    pub total_contributed: u64,
+    pub total_covered: u64,
+    pub bump: u8,
+}
+
+/// Winnings a bettor has claimed from a market but cannot withdraw until `release_at`.
+/// Each new claim adds to the balance and restarts the delay.
+#[account]
+#[derive(InitSpace)]
+pub struct PayoutEscrow {
+    pub bettor: Pubkey,
+    pub market: Pubkey,
+    pub amount: u64,
+    pub release_at: i64,
+}
+
+/// Claims an external id for one market at a time.
+#[account]
+#[derive(InitSpace)]
+pub struct ExternalIdEntry {
+    pub external_id: [u8; 32],
+    pub market: Pubkey,
+}
+
+/// Compact record of a market's resolution for other programs to read cheaply.
+#[account]
+#[derive(InitSpace)]
+pub struct ResolvedOutcome {
+    pub market: Pubkey,
+    pub market_id: u64,
+    /// `None` when resolution voided the market instead of picking a winner.
+    pub winning_outcome: Option<u8>,
+    pub resolved_at: i64,
+}
+
+/// Whitelists a receipt mint for betting; one per mint.
+#[account]
+#[derive(InitSpace)]
+pub struct ReceiptMintEntry {
+    pub vault: ReceiptVault,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Bet {
+    pub bettor: Pubkey,
+    pub market: Pubkey,
+    pub outcome_index: u8,
+    /// Tokens the bettor deposited. A bet's exposure is always exactly this: winners are paid
+    /// from deposited stakes only, so nothing could fund notional beyond the collateral.
+    pub amount: u64,
+    pub claimed: bool,
+    pub timestamp: i64,
+    pub payout_capped: bool,
+    /// Caller-chosen nonce in the bet's PDA seeds, distinguishing one bettor's bets on a market.
+    pub nonce: u64,
+    /// Predicted ranking of a ranked bet, best first; empty on every other bet.
+    #[max_len(6)]
+    pub ranking: Vec<u8>,
+    /// Payout weight from `stake_weight`; equal to `amount` unless the market is time-weighted.
+    pub weight: u64,
+    /// Net payout fixed by the bet's first claim; zero until then.
+    pub payout_due: u64,
+    /// Part of `payout_due` already sent by `claim_payout_partial`. `claimed` is set once it
+    /// reaches `payout_due`, or when a single claim pays everything.
+    pub claimed_amount: u64,
+    /// Underlying-token value of `amount` when staked; equal to `amount` except on receipt markets.
+    pub underlying_amount: u64,
+    /// Set once `claim_preliminary_tranche` has paid this bet's share of the first tranche.
+    pub tranche_claimed: bool,
+    /// Gross first-tranche payout, deducted from the bet's payout at final settlement.
+    pub tranche_payout: u64,
+}
+
+/// One donor's running donations to a market, kept so they can be returned if it's voided.
+#[account]
+#[derive(InitSpace)]
+pub struct Donation {
+    pub market: Pubkey,
+    pub donor: Pubkey,
+    pub amount: u64,
+}
+
+/// A bettor's running stake on one outcome, accumulated across `place_position_bet` calls
+/// and claimed once.
+#[account]
+#[derive(InitSpace)]
+pub struct UserPosition {
+    pub market: Pubkey,
+    pub bettor: Pubkey,
+    pub outcome_index: u8,
+    pub total_amount: u64,
+    /// Stakes folded into this position
+    pub bet_count: u32,
+    pub last_bet_at: i64,
+}
+
+#[event]
+pub struct MarketCreated {
+    pub market_id: u64,
     pub authority: Pubkey,
+    pub question: String,
+    pub outcomes: Vec<String>,
+    pub resolution_time: i64,
+    pub external_id: Option<[u8; 32]>,
+    pub outcome_commitments: Vec<[u8; 32]>,
+    pub outcome_pools: Vec<u64>,
+    pub total_pool: u64,
+    pub category: MarketCategory,
+    pub tags: Vec<String>,
+}
+
+#[event]
+pub struct BetPlaced {
+    pub bettor: Pubkey,
+    pub market_id: u64,
+    pub outcome_index: u8,
+    pub amount: u64,
+    /// Pools and bet counts after this stake was credited. At most 10 outcomes, so the full
+    /// vectors stay small.
+    pub outcome_pools: Vec<u64>,
+    pub total_pool: u64,
+    pub bet_counts: Vec<u32>,
+    pub total_bets: u64,
+}
+
+#[event]
+pub struct PayoutsBatchClaimed {
+    pub bettor: Pubkey,
+    pub market_id: u64,
+    pub claimed_count: u32,
+    pub total_payout: u64,
+}
+
+#[event]
+pub struct MarketResolvedAndSettled {
+    pub market_id: u64,
+    pub winning_outcome: u8,
+    pub settled_count: u32,
+    pub total_paid: u64,
+}
+
+#[event]
+pub struct ConsolationAwarded {
+    pub market_id: u64,
+    pub runner_up: u8,
+    pub consolation_pool: u64,
+}
+
+#[event]
+pub struct MarketHealth {
+    pub market_id: u64,
+    pub health: MarketHealthStatus,
+    pub vault_balance: u64,
+    pub total_pool: u64,
+}
+
+#[event]
+pub struct BetCancelled {
+    pub bettor: Pubkey,
+    pub market_id: u64,
+    pub outcome_index: u8,
+    pub amount: u64,
+    pub refund: u64,
+    /// Left in the pool for the remaining bettors
+    pub penalty: u64,
+    pub outcome_pools: Vec<u64>,
+    pub total_pool: u64,
+}
+
+#[event]
+pub struct BetIncreased {
+    pub bettor: Pubkey,
+    pub market_id: u64,
+    pub outcome_index: u8,
+    pub added: u64,
+    pub new_amount: u64,
+}
+
+#[event]
+pub struct MarketResolved {
+    pub market_id: u64,
+    pub winning_outcome: u8,
+    pub winning_outcome_name: String,
+    pub authority_had_winning_stake: bool,
+    pub resolved_by: Pubkey,
+    pub resolved_at: i64,
+    pub outcome_evidence: Vec<[u8; 32]>,
+    pub final_pools: Vec<u64>,
+    /// Winning outcome's share of `total_pool` at resolution, in basis points
+    pub winning_probability_bps: u16,
+}
+
+#[event]
+pub struct PayoutClaimed {
+    pub bettor: Pubkey,
+    pub market_id: u64,
+    pub bet_amount: u64,
+    pub payout_amount: u64,
+    pub fee_amount: u64,
+    pub creator_fee_amount: u64,
+}
+
+#[event]
+pub struct MarketDonation {
+    pub market_id: u64,
+    pub donor: Pubkey,
+    pub amount: u64,
+    pub donated_total: u64,
+    pub total_pool: u64,
+}
+
+#[event]
+pub struct DonationReclaimed {
+    pub market_id: u64,
+    pub donor: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct BettingClosed {
+    pub market_id: u64,
+    pub betting_cutoff: i64,
+    pub closed_at: i64,
+    pub caller: Pubkey,
+}
+
+#[event]
+pub struct ScalarResolved {
+    pub market_id: u64,
+    /// Reported value after clamping to the market's bounds
+    pub value: i64,
+    pub long_share: u64,
+    pub short_share: u64,
+}
+
+#[event]
+pub struct PayoutPartiallyClaimed {
+    pub bettor: Pubkey,
+    pub market_id: u64,
+    pub bet_amount: u64,
+    pub amount: u64,
+    pub claimed_amount: u64,
+    pub payout_due: u64,
+    /// Fees on the whole payout, reported by the first claim only
+    pub fee_amount: u64,
+    pub creator_fee_amount: u64,
+}
+
+#[event]
+pub struct MarketActivated {
+    pub market_id: u64,
+    pub outcome_pools: Vec<u64>,
+}
+
+#[event]
+pub struct MarketVoided {
+    pub market_id: u64,
+    pub total_pool: u64,
+}
+
+#[event]
+pub struct RefundClaimed {
+    pub bettor: Pubkey,
+    pub market_id: u64,
+    pub amount: u64,
+}
+
+#[event]
+pub struct RefundBatchProcessed {
+    pub market_id: u64,
+    pub refunded_count: u32,
+    pub total_refunded: u64,
+}
+
+#[event]
+pub struct OutcomesRevealed {
+    pub market_id: u64,
+    pub outcomes: Vec<String>,
+}
+
+#[event]
+pub struct TokenAccountAllowed {
+    pub market_id: u64,
+    pub token_account: Pubkey,
+}
+
+#[event]
+pub struct TokenAccountDisallowed {
+    pub market_id: u64,
+    pub token_account: Pubkey,
+}
+
+#[event]
+pub struct InsuranceContributed {
+    pub contributor: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub total_contributed: u64,
+}
+
+#[event]
+pub struct ShortfallCovered {
+    pub market_id: u64,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub total_covered: u64,
+}
+
+#[event]
+pub struct PlatformTvl {
+    pub mint: Pubkey,
+    pub market_count: u32,
+    pub tvl: u64,
+    pub total_pool: u64,
+}
+
+#[event]
+pub struct CircuitBreakerTripped {
+    pub market_id: u64,
+    pub window_start_pool: u64,
+    pub total_pool: u64,
+}
+
+#[event]
+pub struct CircuitBreakerReset {
+    pub market_id: u64,
+    pub total_pool: u64,
+}
+
+#[event]
+pub struct MarketTiming {
+    pub market_id: u64,
+    /// Until the betting cutoff, which is `resolution_time` unless the market sets an offset.
+    pub time_until_close: i64,
+    pub time_until_resolution: i64,
+    /// Zero for markets on timestamp deadlines.
+    pub slots_until_resolution: u64,
+    pub status: MarketStatus,
+}
+
+#[event]
+pub struct BetPayoutCapped {
     pub market_id: u64,
-    #[max_len(200)]
-    pub question: String,
-    #[max_len(10, 50)]
-    pub outcomes: Vec<String>,
-    #[max_len(10)]
-    pub outcome_pools: Vec<u64>,
-    pub resolution_time: i64,
-    pub min_bet: u64,
-    pub resolved: bool,
-    pub winning_outcome: Option<u8>,
+    pub bet: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct LiquiditySeeded {
+    pub market_id: u64,
+    pub amounts: Vec<u64>,
     pub total_pool: u64,
-    pub created_at: i64,
 }
 
-#[account]
-#[derive(InitSpace)]
-pub struct Bet {
+#[event]
+pub struct BasketCreated {
+    pub basket: Pubkey,
+    pub creator: Pubkey,
+    pub markets: Vec<Pubkey>,
+    pub payout_bps: Vec<u32>,
+}
+
+#[event]
+pub struct BasketBetPlaced {
+    pub basket: Pubkey,
     pub bettor: Pubkey,
-    pub market: Pubkey,
+    pub picks: Vec<u8>,
+    pub amount: u64,
+}
+
+#[event]
+pub struct BasketClaimed {
+    pub basket: Pubkey,
+    pub bettor: Pubkey,
+    pub hits: u8,
+    pub refunded: u64,
+    pub payout: u64,
+}
+
+#[event]
+pub struct MarketListed {
+    pub market_id: u64,
+    pub creator: Pubkey,
+    pub creation_fee: u64,
+    pub fee_waived: bool,
+}
+
+#[event]
+pub struct CreationFeeWaived {
+    pub creator: Pubkey,
+}
+
+#[event]
+pub struct FeeWaiverRevoked {
+    pub creator: Pubkey,
+}
+
+#[event]
+pub struct ResolutionVoteSubmitted {
+    pub market_id: u64,
+    pub voter: Pubkey,
     pub outcome_index: u8,
+    /// Votes `outcome_index` now holds, counting this one
+    pub votes: u8,
+    pub threshold: u8,
+}
+
+#[event]
+pub struct ResolutionVetoed {
+    pub market_id: u64,
+    pub guardian: Pubkey,
+    pub vetoed_outcome: u8,
+    pub veto_count: u8,
+    pub dispute_duration: i64,
+}
+
+#[event]
+pub struct PlatformPauseSet {
+    pub paused: bool,
+    pub authority: Pubkey,
+}
+
+#[event]
+pub struct ResolutionExtended {
+    pub market_id: u64,
+    pub old_resolution_time: i64,
+    pub new_resolution_time: i64,
+    pub betting_cutoff: i64,
+}
+
+#[event]
+pub struct AuthorityChanged {
+    pub market_id: Option<u64>,
+    pub old_authority: Pubkey,
+    pub new_authority: Pubkey,
+}
+
+#[event]
+pub struct MarketCancelled {
+    pub market_id: u64,
+    pub total_pool: u64,
+}
+
+#[event]
+pub struct PreliminaryValueReported {
+    pub market_id: u64,
+    pub value: i64,
+    pub tranche_pool: u64,
+    pub long_share: u64,
+    pub short_share: u64,
+}
+
+#[event]
+pub struct PreliminaryTrancheClaimed {
+    pub bettor: Pubkey,
+    pub market_id: u64,
+    pub bet_amount: u64,
+    pub payout_amount: u64,
+    pub fee_amount: u64,
+    pub creator_fee_amount: u64,
+}
+
+#[event]
+pub struct CreationFeeRefunded {
+    pub market_id: u64,
     pub amount: u64,
-    pub claimed: bool,
-    pub timestamp: i64,
+    pub destination: Pubkey,
 }
 
 #[event]
-pub struct MarketCreated {
+pub struct FeesWithdrawn {
+    pub market_id: u64,
+    pub amount: u64,
+    pub destination: Pubkey,
+}
+
+#[event]
+pub struct MarketClosed {
     pub market_id: u64,
     pub authority: Pubkey,
-    pub question: String,
-    pub outcomes: Vec<String>,
-    pub resolution_time: i64,
+    pub claimed_count: u32,
 }
 
 #[event]
-pub struct BetPlaced {
+pub struct UnclaimedReclaimed {
+    pub market_id: u64,
+    pub amount: u64,
+    pub claim_deadline: i64,
+    pub destination: Pubkey,
+}
+
+#[event]
+pub struct DustSwept {
+    pub market_id: u64,
+    pub amount: u64,
+    pub destination: Pubkey,
+}
+
+#[event]
+pub struct ResidualDonated {
+    pub market_id: u64,
+    pub amount: u64,
+    pub destination: Pubkey,
+}
+
+#[event]
+pub struct CreatorFeesWithdrawn {
+    pub market_id: u64,
+    pub amount: u64,
+    pub destination: Pubkey,
+}
+
+#[event]
+pub struct AccountResolutionRead {
+    pub market_id: u64,
+    pub source_account: Pubkey,
+    pub matched: bool,
+}
+
+#[event]
+pub struct MarketScored {
+    pub market_id: u64,
+    pub scores: Vec<u8>,
+}
+
+#[event]
+pub struct ResolutionProposed {
+    pub market_id: u64,
+    pub proposed_outcome: u8,
+    pub proposed_by: Pubkey,
+    pub finalizes_at: i64,
+}
+
+#[event]
+pub struct MarketRanked {
+    pub market_id: u64,
+    pub ranking: Vec<u8>,
+}
+
+#[event]
+pub struct OutcomeVaultSplit {
+    pub market_id: u64,
+    pub outcome_index: u8,
+    pub outcome_vault: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct KeeperReserveFunded {
+    pub market_id: u64,
+    pub amount: u64,
+    pub keeper_reserve: u64,
+}
+
+#[event]
+pub struct StaleMarketProcessed {
+    pub market_id: u64,
+    pub keeper: Pubkey,
+    pub reward: u64,
+}
+
+#[event]
+pub struct KeeperReserveReclaimed {
+    pub market_id: u64,
+    pub amount: u64,
+}
+
+#[event]
+pub struct BettorFrozen {
+    pub market_id: u64,
+    pub bettor: Pubkey,
+}
+
+#[event]
+pub struct BettorUnfrozen {
+    pub market_id: u64,
+    pub bettor: Pubkey,
+}
+
+#[event]
+pub struct BettorWhitelisted {
+    pub market_id: u64,
+    pub bettor: Pubkey,
+}
+
+#[event]
+pub struct BettorRemovedFromWhitelist {
+    pub market_id: u64,
     pub bettor: Pubkey,
+}
+
+#[event]
+pub struct HouseFunded {
+    pub market_id: u64,
+    pub amount: u64,
+    pub house_liquidity: u64,
+}
+
+#[event]
+pub struct HouseProfitWithdrawn {
+    pub market_id: u64,
+    pub amount: u64,
+}
+
+#[event]
+pub struct SeedReclaimed {
+    pub market_id: u64,
+    pub amount: u64,
+}
+
+#[event]
+pub struct MarketQuarantined {
+    pub market_id: u64,
+    pub authority: Pubkey,
+    pub total_pool: u64,
+}
+
+#[event]
+pub struct PayoutQuote {
     pub market_id: u64,
     pub outcome_index: u8,
     pub amount: u64,
+    pub gross_payout: u64,
+    pub fee_amount: u64,
+    pub creator_fee_amount: u64,
+    pub net_payout: u64,
 }
 
 #[event]
-pub struct MarketResolved {
+pub struct KellyStake {
+    pub market_id: u64,
+    pub outcome_index: u8,
+    pub probability_bps: u16,
+    pub bankroll: u64,
+    pub kelly_fraction_bps: u16,
+    pub suggested_stake: u64,
+}
+
+#[event]
+pub struct MarketLocked {
+    pub market_id: u64,
+    pub authority: Pubkey,
+}
+
+#[event]
+pub struct PayoutBreakdown {
     pub market_id: u64,
+    pub bet: Pubkey,
+    pub bet_amount: u64,
+    pub outcome_index: u8,
     pub winning_outcome: u8,
-    pub winning_outcome_name: String,
+    pub winning_pool: u64,
+    pub total_pool: u64,
+    pub max_payout_share_bps: u16,
+    pub payout_capped: bool,
+    pub capped_stake: u64,
+    pub capped_count: u32,
+    pub runner_up: Option<u8>,
+    pub consolation_pool: u64,
+    /// Seeded liquidity in `total_pool`, shared out like stakes.
+    pub bonus_pool: u64,
+    /// Gross payout, before fees.
+    pub payout: u64,
+    pub platform_fee_share_bps: u16,
+    /// `GlobalState::fee_bps` scaled by `platform_fee_share_bps`; the rate `payout` is charged.
+    pub platform_fee_bps: u16,
+    pub creator_fee_bps: u16,
+    /// Platform fee the market may still take before its fee cap; `u64::MAX` when uncapped.
+    pub fee_cap_room: u64,
+    pub fee_amount: u64,
+    pub creator_fee_amount: u64,
+    /// What a claim made now would pay, the same split `claim_payout` makes.
+    pub net_payout: u64,
 }
 
 #[event]
-pub struct PayoutClaimed {
+pub struct OutcomesMerged {
+    pub market_id: u64,
+    pub from: u8,
+    pub into: u8,
+    pub merged_pool: u64,
+    pub into_pool: u64,
+}
+
+#[event]
+pub struct PayoutEscrowed {
     pub bettor: Pubkey,
     pub market_id: u64,
-    pub bet_amount: u64,
+    pub amount: u64,
+    pub escrow_balance: u64,
+    pub release_at: i64,
+}
+
+#[event]
+pub struct EscrowWithdrawn {
+    pub bettor: Pubkey,
+    pub market_id: u64,
+    pub amount: u64,
+}
+
+#[event]
+pub struct FeeTokenCharged {
+    pub bettor: Pubkey,
+    pub market_id: u64,
+    /// Platform fee in payout tokens, before conversion
+    pub fee_amount: u64,
+    pub rate: u64,
+    pub fee_token_amount: u64,
+}
+
+#[event]
+pub struct TwapSampled {
+    pub market_id: u64,
+    pub price: i64,
+    pub sampled_at: i64,
+    pub samples: u16,
+}
+
+#[event]
+pub struct TwapResolved {
+    pub market_id: u64,
+    /// Average the market settled on
+    pub twap: i64,
+    pub samples: u16,
+    pub first_sample_at: i64,
+    pub last_sample_at: i64,
+}
+
+#[event]
+pub struct ResolutionFeeTier {
+    pub market_id: u64,
+    /// Seconds past `resolution_time` the market resolved.
+    pub latency: i64,
+    /// Prompt fee tier the resolution fell in; `None` past every tier.
+    pub tier: Option<u8>,
+    pub fee_share_bps: u16,
+}
+
+#[event]
+pub struct FeeCapReached {
+    pub market_id: u64,
+    pub fee_cap: u64,
+}
+
+#[event]
+pub struct SettlementReport {
+    pub market_id: u64,
+    /// Everything bettors staked over the market's life.
+    pub total_volume: u64,
+    pub platform_fees: u64,
+    pub creator_fees: u64,
+    /// Winning bets paid.
+    pub winners_paid: u32,
+    /// Net winnings paid out.
+    pub total_paid_out: u64,
+    pub dust_swept: u64,
+    pub unclaimed_forfeited: u64,
+    /// Platform fees charged in the fee token, in its base units.
+    pub fee_token_fees: u64,
+}
+
+#[event]
+pub struct ReceiptMintRegistered {
+    pub mint: Pubkey,
+    pub rate_source: Pubkey,
+    pub rate: u64,
+}
+
+#[event]
+pub struct ReceiptRateUpdated {
+    pub market_id: u64,
+    pub rate: u64,
+    pub updated_at: i64,
+}
+
+#[event]
+pub struct ReceiptStakeValued {
+    pub market_id: u64,
+    pub bettor: Pubkey,
+    /// Receipt tokens staked
+    pub amount: u64,
+    pub rate: u64,
+    pub underlying_amount: u64,
+}
+
+#[event]
+pub struct ReceiptPayoutValued {
+    pub market_id: u64,
+    pub bettor: Pubkey,
+    /// Receipt tokens paid
     pub payout_amount: u64,
+    pub rate: u64,
+    pub rate_updated_at: i64,
+    pub underlying_amount: u64,
+    /// Underlying value of the bet's stake when it was placed
+    pub staked_underlying: u64,
 }
 
 #[error_code]
@@ -349,4 +8737,324 @@ pub enum ErrorCode {
     LosingBet,
     #[msg("No payout available")]
     NoPayoutAvailable,
+    #[msg("Market has been voided")]
+    MarketVoided,
+    #[msg("Market is not voided")]
+    MarketNotVoided,
+    #[msg("Single-sided voiding is not enabled for this market")]
+    SingleSidedVoidDisabled,
+    #[msg("Betting period has not ended yet")]
+    BettingStillOpen,
+    #[msg("More than one outcome received bets")]
+    MarketNotSingleSided,
+    #[msg("Bet does not belong to this market")]
+    InvalidBet,
+    #[msg("Pivot markets must have exactly two outcomes")]
+    PivotRequiresTwoOutcomes,
+    #[msg("Instruction does not apply to this market type")]
+    WrongMarketType,
+    #[msg("Batch accounts must be passed as bet, token account, bettor triples")]
+    InvalidBatchAccounts,
+    #[msg("Too many entries in one batch")]
+    BatchTooLarge,
+    #[msg("Betting is paused by the circuit breaker")]
+    CircuitBreakerTripped,
+    #[msg("Circuit breaker is not tripped")]
+    CircuitBreakerNotTripped,
+    #[msg("Circuit breaker needs a positive window")]
+    InvalidCircuitBreaker,
+    #[msg("Outcome oracles must match the outcomes and are only supported on standard markets")]
+    InvalidOutcomeOracles,
+    #[msg("Payout cap must be at most 10000 bps with a non-negative registration period")]
+    InvalidPayoutCap,
+    #[msg("Payout cap is not enabled for this market")]
+    PayoutCapDisabled,
+    #[msg("Capped bets can still be registered; claims open afterwards")]
+    CapRegistrationOpen,
+    #[msg("Capped bet registration has closed")]
+    CapRegistrationClosed,
+    #[msg("Bet is already registered as capped")]
+    BetAlreadyCapped,
+    #[msg("Bet's payout does not exceed the cap")]
+    BetBelowPayoutCap,
+    #[msg("A payout can only be rolled into a different market")]
+    InvalidRollTarget,
+    #[msg("Token accounts use different mints")]
+    MintMismatch,
+    #[msg("External id entry must be passed exactly when an external id is set")]
+    ExternalIdEntryMismatch,
+    #[msg("Market is still active")]
+    MarketStillActive,
+    #[msg("Liquidity can only be seeded before any bets")]
+    MarketAlreadyFunded,
+    #[msg("Seed amounts must cover every outcome and total more than zero")]
+    InvalidSeedAmounts,
+    #[msg("Question exceeds the platform's maximum length")]
+    QuestionTooLong,
+    #[msg("Question length limit must be between 1 and 1000")]
+    InvalidQuestionLimit,
+    #[msg("Market has been quarantined")]
+    MarketQuarantined,
+    #[msg("Market has been cancelled")]
+    MarketCancelled,
+    #[msg("Probability must be at most 10000 bps")]
+    InvalidProbability,
+    #[msg("Market is locked and can no longer be changed")]
+    MarketImmutable,
+    #[msg("Outcome has been merged into another outcome")]
+    OutcomeMerged,
+    #[msg("Merging would leave fewer than two outcomes")]
+    TooFewOutcomesToMerge,
+    #[msg("Withdrawal delay cannot be negative")]
+    InvalidWithdrawalDelay,
+    #[msg("This market holds winnings in escrow; pass the escrow account")]
+    EscrowAccountRequired,
+    #[msg("Escrowed winnings are still within the withdrawal delay")]
+    EscrowLocked,
+    #[msg("Winnings from this market must go through the withdrawal delay")]
+    WithdrawalDelayActive,
+    #[msg("TVL accounts must be distinct market, vault pairs")]
+    InvalidTvlAccounts,
+    #[msg("Insurance amount must be greater than zero")]
+    InvalidInsuranceAmount,
+    #[msg("Token account is not owned by this market")]
+    InvalidMarketVault,
+    #[msg("Bets on this market must come from an approved token account")]
+    TokenAccountNotAllowed,
+    #[msg("Token account is already approved")]
+    TokenAccountAlreadyAllowed,
+    #[msg("Token account allowlist is full")]
+    AllowlistFull,
+    #[msg("Outcome commitments must match the outcomes one to one")]
+    InvalidOutcomeCommitments,
+    #[msg("Market does not hide its outcomes")]
+    NotBlindMarket,
+    #[msg("Outcomes have already been revealed")]
+    OutcomesAlreadyRevealed,
+    #[msg("Blind market outcomes must be revealed before resolution")]
+    OutcomesNotRevealed,
+    #[msg("Revealed label does not match its commitment")]
+    CommitmentMismatch,
+    #[msg("Outcome label is too long")]
+    OutcomeLabelTooLong,
+    #[msg("Scheduled resolution needs a pivot market, a timestamp deadline, and a window after betting closes")]
+    InvalidSchedule,
+    #[msg("Market resolves only through its scheduled oracle reading")]
+    ScheduledResolutionOnly,
+    #[msg("Market has no scheduled resolution")]
+    NoScheduledResolution,
+    #[msg("Outside the scheduled resolution window")]
+    OutsideScheduledWindow,
+    #[msg("Consolation needs a funded runner-up outcome and must leave winners their stake")]
+    InvalidConsolation,
+    #[msg("Settlement accounts must be passed as bet, token account, freeze record triples")]
+    InvalidSettleAccounts,
+    #[msg("Minimum diverse outcomes exceeds the number of outcomes")]
+    InvalidDiversity,
+    #[msg("Too few outcomes hold stake to resolve this market")]
+    InsufficientDiversity,
+    #[msg("Enough outcomes hold stake; market cannot be voided for diversity")]
+    MarketSufficientlyDiverse,
+    #[msg("Fixed odds must be given for every outcome, above 1x, and only on fixed-odds markets")]
+    InvalidFixedOdds,
+    #[msg("House liquidity can't cover this bet's potential winnings")]
+    HouseOvercommitted,
+    #[msg("House amount must be greater than zero")]
+    InvalidHouseAmount,
+    #[msg("Bettor is frozen on this market")]
+    FrozenBettor,
+    #[msg("Evidence must have one hash per outcome")]
+    InvalidOutcomeEvidence,
+    #[msg("Market has no stale grace period")]
+    StaleProcessingDisabled,
+    #[msg("Market is not past its stale grace period")]
+    MarketNotStale,
+    #[msg("Keeper amounts must be positive")]
+    InvalidKeeperAmount,
+    #[msg("Market funds are split across per-outcome vaults")]
+    VaultSplit,
+    #[msg("Not every outcome has its own vault yet")]
+    OutcomeVaultsIncomplete,
+    #[msg("Outcome vault accounts do not match the market")]
+    InvalidOutcomeVaults,
+    #[msg("Scores must be 0-100 per outcome and pay out at least one bet")]
+    InvalidScores,
+    #[msg("Scored markets resolve through resolve_market_scored")]
+    ScoredMarket,
+    #[msg("Market resolves from on-chain account state")]
+    AccountResolvedMarket,
+    #[msg("Invalid on-chain resolution source")]
+    InvalidResolutionSource,
+    #[msg("Resolution offset is outside the account data")]
+    ResolutionDataOutOfRange,
+    #[msg("Fee cannot exceed 10000 bps")]
+    InvalidFee,
+    #[msg("Creation fee accounts are required")]
+    CreationFeeRequired,
+    #[msg("Initial liquidity needs the creator's token account, the market vault and the token program")]
+    SeedAccountsRequired,
+    #[msg("Creation fee must go to the platform treasury")]
+    InvalidTreasury,
+    #[msg("Baskets need 2-5 distinct markets and one payout entry per hit count")]
+    InvalidBasket,
+    #[msg("Basket legs do not match the basket's markets")]
+    InvalidBasketLegs,
+    #[msg("Basket liquidity can't cover this bet's best-case payout")]
+    BasketUnderfunded,
+    #[msg("Not every basket leg has settled")]
+    BasketLegOpen,
+    #[msg("A guardian needs a veto window and veto cap, and can't guard capped markets")]
+    InvalidGuardian,
+    #[msg("The guardian's veto window has closed")]
+    VetoWindowClosed,
+    #[msg("Payouts wait until the guardian's veto window closes")]
+    VetoWindowOpen,
+    #[msg("The guardian has used all their vetoes")]
+    VetoLimitReached,
+    #[msg("Arithmetic overflow")]
+    MathOverflow,
+    #[msg("A ranking must order every outcome exactly once, on a ranked market of at most 6 outcomes")]
+    InvalidRanking,
+    #[msg("Ranked markets bet through place_ranked_bet and resolve through resolve_ranked")]
+    RankedMarket,
+    #[msg("Token account is not owned by the signer")]
+    InvalidTokenAccountOwner,
+    #[msg("Challenge period must be zero, or positive on a standard single-winner market")]
+    InvalidChallengePeriod,
+    #[msg("Markets with a challenge period resolve through propose_resolution")]
+    ProposalRequired,
+    #[msg("No resolution has been proposed")]
+    NoResolutionProposed,
+    #[msg("The challenge period on the proposed resolution is still running")]
+    ChallengePeriodActive,
+    #[msg("The challenge period has ended; the proposal can only be finalized")]
+    ChallengePeriodEnded,
+    #[msg("Native SOL markets are standard markets without escrow, allowlists or ranked bets")]
+    InvalidNativeMarket,
+    #[msg("Native SOL markets move stakes through the _sol instructions")]
+    NativeMarket,
+    #[msg("A native SOL market needs its sol_vault")]
+    SolVaultRequired,
+    #[msg("The SOL vault can't cover this transfer above its rent-exempt minimum")]
+    SolVaultShortfall,
+    #[msg("The platform is paused; only claims and refunds are open")]
+    PlatformPaused,
+    #[msg("Betting cutoff must be a non-negative offset that leaves the market open for betting")]
+    InvalidBettingCutoff,
+    #[msg("Betting has closed ahead of resolution")]
+    BettingCutoffPassed,
+    #[msg("Creator fee cannot exceed 1000 bps")]
+    InvalidCreatorFee,
+    #[msg("Winning bets or seed on this market are still unclaimed")]
+    ClaimsOutstanding,
+    #[msg("Market vault still holds tokens")]
+    VaultNotEmpty,
+    #[msg("Market can't be closed until its grace period after resolution ends")]
+    CloseGracePeriodActive,
+    #[msg("Close grace period can't be negative")]
+    InvalidCloseGracePeriod,
+    #[msg("Minimum outcome liquidity doesn't apply to fixed-odds markets")]
+    InvalidMinLiquidity,
+    #[msg("Time weighting requires a plain time-based parimutuel market")]
+    InvalidWeighting,
+    #[msg("Bets on this market can't be cancelled")]
+    CancellationDisabled,
+    #[msg("Cancel penalty must be at most 10000 bps on a parimutuel token market")]
+    InvalidCancelPenalty,
+    #[msg("Too many tags, or a tag is empty or too long")]
+    InvalidTags,
+    #[msg("Bettor is not on this market's whitelist")]
+    NotWhitelisted,
+    #[msg("Bet exceeds the market's maximum bet")]
+    BetTooLarge,
+    #[msg("Bet would push the pool past the market's cap")]
+    PoolCapReached,
+    #[msg("Bet and pool caps must be at least the minimum bet")]
+    InvalidBetLimits,
+    #[msg("Invalid resolution committee or threshold")]
+    InvalidCommittee,
+    #[msg("Signer is not on this market's resolution committee")]
+    NotCommitteeMember,
+    #[msg("Committee member has already voted")]
+    AlreadyVoted,
+    #[msg("Market is resolved by its committee")]
+    CommitteeMarket,
+    #[msg("Positions aren't available on payout-capped or time-weighted markets")]
+    PositionsUnsupported,
+    #[msg("Market has no stake to resolve")]
+    EmptyMarket,
+    #[msg("Minimum bet is below the platform's global minimum")]
+    MinBetBelowFloor,
+    #[msg("Scalar markets need two outcomes, an upper bound above the lower, and no other settlement rules")]
+    InvalidScalarMarket,
+    #[msg("Platform is already initialized")]
+    AlreadyInitialized,
+    #[msg("Donation amount must be positive")]
+    InvalidDonation,
+    #[msg("Another market already took this id; re-read market_count and retry")]
+    MarketIdTaken,
+    #[msg("Outcome labels must be distinct, ignoring case and surrounding whitespace")]
+    DuplicateOutcome,
+    #[msg("Outcome labels can't be empty or whitespace")]
+    EmptyOutcome,
+    #[msg("Claim deadline can't be negative, and needs a pool-betting token market without a withdrawal delay")]
+    InvalidClaimDeadline,
+    #[msg("Claim deadline has passed")]
+    ClaimDeadlinePassed,
+    #[msg("Claim deadline hasn't passed yet")]
+    ClaimDeadlineNotReached,
+    #[msg("Market has no claim deadline")]
+    NoClaimDeadline,
+    #[msg("TWAP markets need a pivot or scalar market on timestamps and a window that fits their samples")]
+    InvalidTwapConfig,
+    #[msg("This market resolves from its TWAP through resolve_from_twap")]
+    TwapResolutionOnly,
+    #[msg("Outside the market's TWAP sampling window")]
+    OutsideTwapWindow,
+    #[msg("Too soon after the previous TWAP sample")]
+    TwapSampleTooSoon,
+    #[msg("Not enough TWAP samples to resolve")]
+    NotEnoughTwapSamples,
+    #[msg("Prompt fee tiers must widen and never get cheaper, on a time-based market")]
+    InvalidFeeTiers,
+    #[msg("Receipt rate source is not the registered one or holds no rate")]
+    InvalidReceiptRate,
+    #[msg("Receipt token exchange rate is stale; call update_receipt_rate")]
+    StaleReceiptRate,
+    #[msg("A fee token needs a mint, treasury and rate source")]
+    InvalidFeeToken,
+    #[msg("A fee decay period must be positive, on a time-based market")]
+    InvalidFeeDecay,
+    #[msg("A fee cap must be above zero")]
+    InvalidFeeCap,
+    #[msg("Residuals must go to the market's residual destination")]
+    InvalidResidualDestination,
+    #[msg("Market has not been cancelled")]
+    MarketNotCancelled,
+    #[msg("No creation fee to refund")]
+    NoCreationFeePaid,
+    #[msg("The preliminary value has already been reported")]
+    PreliminaryValueReported,
+    #[msg("No preliminary value has been reported")]
+    NoPreliminaryValue,
+    #[msg("Tranche markets settle on a preliminary value first")]
+    PreliminaryValueRequired,
+    #[msg("A preliminary tranche has been paid; the market must settle")]
+    PreliminaryTranchePaid,
+    #[msg("Fee token markets need the fee payer account, fee treasury and rate source")]
+    FeeTokenAccountsRequired,
+    #[msg("Fee token rate source is not the configured one or holds no rate")]
+    InvalidFeeRate,
+    #[msg("Fee token rate is too old to charge a fee at")]
+    StaleFeeRate,
+    #[msg("Fee token fees must go to the fee token's treasury")]
+    InvalidFeeTreasury,
+    #[msg("Fee token markets can't cap the platform fee")]
+    FeeTokenFeeCap,
+    #[msg("Fee token markets' winners pay their own fee, so their bets can't be settled for them")]
+    FeeTokenMarket,
+    #[msg("Token markets need a residual destination in their mint unless the treasury holds it")]
+    ResidualDestinationRequired,
+    #[msg("Withdrawing fees needs the insurance fund and vault for the market's mint")]
+    InsuranceFundRequired,
 }
@@ -9,6 +9,7 @@ import {
   TOKEN_PROGRAM_ID 
 } from "@solana/spl-token";
 import { expect } from "chai";
+import { createHash, randomBytes } from "crypto";
 
 describe("betting-markets", () => {
   // Configure the client to use the local cluster.
@@ -25,6 +26,239 @@ describe("betting-markets", () => {
   let user1TokenAccount: anchor.web3.PublicKey;
   let user2TokenAccount: anchor.web3.PublicKey;
   let marketTokenAccount: anchor.web3.PublicKey;
+  let authorityTokenAccount: anchor.web3.PublicKey;
+
+  const sleep = (ms: number) => new Promise((resolve) => setTimeout(resolve, ms));
+
+  const getMarketAddress = (marketId: number) =>
+    anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("market"), new anchor.BN(marketId).toArrayLike(Buffer, "le", 8)],
+      program.programId
+    )[0];
+
+  const defaultConfig = () => ({
+    voidIfSingleSided: false,
+    marketType: { standard: {} } as any,
+    pivotValue: new anchor.BN(0),
+    circuitBreakerBps: 0,
+    circuitBreakerWindow: new anchor.BN(0),
+    outcomeOracles: [] as (anchor.web3.PublicKey | null)[],
+    maxPayoutShareBps: 0,
+    capRegistrationPeriod: new anchor.BN(0),
+    externalId: null as number[] | null,
+    withdrawalDelay: new anchor.BN(0),
+    useSlots: false,
+    resolutionSlot: new anchor.BN(0),
+    restrictTokenAccounts: false,
+    outcomeCommitments: [] as number[][],
+    scheduledOracle: null as anchor.web3.PublicKey | null,
+    scheduledTime: new anchor.BN(0),
+    scheduledWindow: new anchor.BN(0),
+    minDiverseOutcomes: 1,
+    fixedOddsBps: [] as number[],
+    graduatedMinBet: false,
+    requireOutcomeEvidence: false,
+    staleGracePeriod: new anchor.BN(0),
+    scoredResolution: false,
+    resolutionAccount: null as anchor.web3.PublicKey | null,
+    resolutionOffset: 0,
+    expectedBytes: Buffer.alloc(0),
+    guardian: null as anchor.web3.PublicKey | null,
+    vetoWindow: new anchor.BN(0),
+    maxVetoes: 0,
+    ranked: false,
+    challengePeriod: new anchor.BN(0),
+    isNative: false,
+    bettingCutoffOffset: new anchor.BN(0),
+    creatorFeeBps: 0,
+    initialLiquidity: [],
+    accessMode: { public: {} },
+    maxBet: null,
+    maxTotalPool: null,
+    resolvers: [],
+    resolutionThreshold: 0,
+    closeGracePeriod: new anchor.BN(0),
+    minLiquidityPerOutcome: new anchor.BN(0),
+    weighted: false,
+    cancelPenaltyBps: null,
+    category: { other: {} },
+    tags: [],
+    oracle: null,
+    scalarLowerBound: new anchor.BN(0),
+    scalarUpperBound: new anchor.BN(0),
+    marketId: null,
+    claimDeadline: new anchor.BN(0),
+    twap: null,
+    promptFeeTiers: [],
+    feeDecayPeriod: new anchor.BN(0),
+    feeCap: null,
+    residualDestination: null,
+    preliminaryTrancheBps: 0,
+  });
+
+  const getBettorFreeze = (market: anchor.web3.PublicKey, bettor: anchor.web3.PublicKey) =>
+    anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("bettor_freeze"), market.toBuffer(), bettor.toBuffer()],
+      program.programId
+    )[0];
+
+  // Each test bet takes a fresh nonce so its PDA never collides with an earlier bet
+  let nextBetNonce = 0;
+  const getSolVault = (market: anchor.web3.PublicKey) =>
+    anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("sol_vault"), market.toBuffer()],
+      program.programId
+    )[0];
+
+  const getBetAddress = (
+    market: anchor.web3.PublicKey,
+    bettor: anchor.web3.PublicKey,
+    nonce: anchor.BN
+  ) =>
+    anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("bet"), market.toBuffer(), bettor.toBuffer(), nonce.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    )[0];
+
+  const getExternalIdEntry = (externalId: number[] | null) =>
+    externalId
+      ? anchor.web3.PublicKey.findProgramAddressSync(
+          [Buffer.from("external_id"), Buffer.from(externalId)],
+          program.programId
+        )[0]
+      : null;
+
+  // Creates a market at the next market id along with its token vault
+  const createTestMarket = async (
+    resolutionDelaySecs: number,
+    config: ReturnType<typeof defaultConfig> = defaultConfig(),
+    outcomes: string[] = ["Yes", "No"],
+    question: string = "Test market",
+    marketMint: anchor.web3.PublicKey = mint
+  ) => {
+    const globalStateAccount = await program.account.globalState.fetch(globalState);
+    const market = getMarketAddress(globalStateAccount.marketCount.toNumber());
+    const vault = await createAccount(
+      provider.connection,
+      authority,
+      marketMint,
+      market,
+      anchor.web3.Keypair.generate()
+    );
+    const seeded = config.initialLiquidity.length > 0;
+    // Dust and forfeited winnings go to the config's destination, or else the treasury
+    const residualDestination = config.residualDestination ?? globalStateAccount.treasury;
+
+    await program.methods
+      .createMarket(
+        question,
+        outcomes,
+        new anchor.BN(Math.floor(Date.now() / 1000) + resolutionDelaySecs),
+        new anchor.BN(1e6),
+        config
+      )
+      .accountsPartial({
+        market,
+        globalState,
+        externalIdEntry: getExternalIdEntry(config.externalId),
+        authority: authority.publicKey,
+        mint: marketMint,
+        creatorTokenAccount: seeded ? authorityTokenAccount : null,
+        treasury: null,
+        marketTokenAccount: seeded ? vault : null,
+        residualDestination:
+          config.isNative || residualDestination.equals(anchor.web3.PublicKey.default) ? null : residualDestination,
+        tokenProgram: seeded ? TOKEN_PROGRAM_ID : null,
+        solVault: config.isNative ? getSolVault(market) : null,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([authority])
+      .rpc();
+
+    return { market, vault };
+  };
+
+  const resolveTestMarket = (market: anchor.web3.PublicKey, winningOutcome: number) =>
+    program.methods
+      .resolveMarket(winningOutcome, null, 0, [])
+      .accountsPartial({ market, authority: authority.publicKey })
+      .signers([authority])
+      .rpc();
+
+  const claimTestPayout = (
+    bettor: anchor.web3.Keypair,
+    bettorTokenAccount: anchor.web3.PublicKey,
+    market: anchor.web3.PublicKey,
+    vault: anchor.web3.PublicKey,
+    bet: anchor.web3.PublicKey,
+    escrow: anchor.web3.PublicKey | null = null,
+    outcomeVaults: anchor.web3.PublicKey[] = [],
+    feeAccounts: {
+      feePayerTokenAccount: anchor.web3.PublicKey;
+      feeTreasury: anchor.web3.PublicKey;
+      feeRateSource: anchor.web3.PublicKey;
+    } = { feePayerTokenAccount: null, feeTreasury: null, feeRateSource: null }
+  ) =>
+    program.methods
+      .claimPayout()
+      .accountsPartial({
+        bet,
+        market,
+        bettor: bettor.publicKey,
+        bettorTokenAccount,
+        marketTokenAccount: vault,
+        escrow,
+        ...feeAccounts,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .remainingAccounts(outcomeVaults.map((pubkey) => ({ pubkey, isSigner: false, isWritable: true })))
+      .signers([bettor])
+      .rpc();
+
+  const tokenBalance = async (tokenAccount: anchor.web3.PublicKey) =>
+    Number((await getAccount(provider.connection, tokenAccount)).amount);
+
+  const placeTestBet = async (
+    bettor: anchor.web3.Keypair,
+    bettorTokenAccount: anchor.web3.PublicKey,
+    market: anchor.web3.PublicKey,
+    vault: anchor.web3.PublicKey,
+    outcomeIndex: number,
+    amount: number
+  ) => {
+    const nonce = new anchor.BN(nextBetNonce++);
+    const bet = getBetAddress(market, bettor.publicKey, nonce);
+    await program.methods
+      .placeBet(outcomeIndex, new anchor.BN(amount), nonce)
+      .accountsPartial({
+        bet,
+        market,
+        bettor: bettor.publicKey,
+        bettorTokenAccount,
+        marketTokenAccount: vault,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([bettor])
+      .rpc();
+    return bet;
+  };
+
+  // A bet on a throwaway market stands in for a fee token rate feed: its amount is the rate and
+  // its placement time the feed's last update
+  const feeRateFeed = async (rate: number) => {
+    const { market, vault } = await createTestMarket(60);
+    const bet = await placeTestBet(user2, user2TokenAccount, market, vault, 0, rate);
+    return { rateSource: bet, rateSourceOwner: program.programId, rateOffset: 73, updatedAtOffset: 82 };
+  };
+
+  const setFeeToken = (feeToken) =>
+    program.methods
+      .setFeeToken(feeToken)
+      .accountsPartial({ globalState, authority: authority.publicKey, rateSource: feeToken ? feeToken.rateSource : null })
+      .signers([authority])
+      .rpc();
 
   before(async () => {
     // Airdrop SOL to users
@@ -83,6 +317,22 @@ describe("betting-markets", () => {
       authority,
       1000 * 1e6 // 1000 tokens
     );
+
+    authorityTokenAccount = await createAccount(
+      provider.connection,
+      authority,
+      mint,
+      authority.publicKey
+    );
+
+    await mintTo(
+      provider.connection,
+      authority,
+      mint,
+      authorityTokenAccount,
+      authority,
+      1000 * 1e6 // 1000 tokens
+    );
   });
 
   it("Initialize the platform", async () => {
@@ -92,7 +342,7 @@ describe("betting-markets", () => {
     );
 
     const tx = await program.methods
-      .initialize()
+      .initialize(0, new anchor.BN(0))
       .accountsPartial({
         globalState,
         authority: authority.publicKey,
@@ -107,6 +357,8 @@ describe("betting-markets", () => {
     const globalStateAccount = await program.account.globalState.fetch(globalState);
     expect(globalStateAccount.authority.toString()).to.equal(authority.publicKey.toString());
     expect(globalStateAccount.marketCount.toNumber()).to.equal(0);
+    expect(globalStateAccount.maxQuestionLen).to.equal(200);
+    expect(globalStateAccount.globalMinBet.toNumber()).to.equal(0);
   });
 
   it("Create a prediction market", async () => {
@@ -129,16 +381,39 @@ describe("betting-markets", () => {
       authority
     );
 
-    const tx = await program.methods
-      .createMarket(question, outcomes, new anchor.BN(resolutionTime), new anchor.BN(minBet))
-      .accountsPartial({
-        market,
-        globalState,
-        authority: authority.publicKey,
-        systemProgram: anchor.web3.SystemProgram.programId,
-      })
+    const createMarket = (residualDestination: anchor.web3.PublicKey | null) =>
+      program.methods
+        .createMarket(question, outcomes, new anchor.BN(resolutionTime), new anchor.BN(minBet), defaultConfig())
+        .accountsPartial({
+          market,
+          globalState,
+          externalIdEntry: null,
+          authority: authority.publicKey,
+          mint,
+          creatorTokenAccount: null,
+          treasury: null,
+          marketTokenAccount: null,
+          residualDestination,
+          tokenProgram: null,
+          solVault: null,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([authority])
+        .rpc();
+
+    // Residuals default to the treasury, which isn't set yet
+    try {
+      await createMarket(null);
+      expect.fail("the market's dust would have nowhere to go");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("ResidualDestinationRequired");
+    }
+    await program.methods
+      .setCreationFee(new anchor.BN(0), authorityTokenAccount)
+      .accountsPartial({ globalState, authority: authority.publicKey })
       .signers([authority])
       .rpc();
+    const tx = await createMarket(authorityTokenAccount);
 
     console.log("Create market transaction signature:", tx);
 
@@ -148,6 +423,7 @@ describe("betting-markets", () => {
     expect(marketAccount.outcomes).to.deep.equal(outcomes);
     expect(marketAccount.resolved).to.be.false;
     expect(marketAccount.totalPool.toNumber()).to.equal(0);
+    expect(marketAccount.residualDestination.toString()).to.equal(authorityTokenAccount.toString());
 
     // Verify global state updated
     const globalStateAccount = await program.account.globalState.fetch(globalState);
@@ -161,13 +437,13 @@ describe("betting-markets", () => {
     );
 
     // User 1 bets on "Yes" (outcome 0)
-    const bet1 = anchor.web3.Keypair.generate();
+    const bet1 = getBetAddress(market, user1.publicKey, new anchor.BN(0));
     const betAmount1 = 10 * 1e6; // 10 tokens
 
     const tx1 = await program.methods
-      .placeBet(0, new anchor.BN(betAmount1))
+      .placeBet(0, new anchor.BN(betAmount1), new anchor.BN(0))
       .accountsPartial({
-        bet: bet1.publicKey,
+        bet: bet1,
         market,
         bettor: user1.publicKey,
         bettorTokenAccount: user1TokenAccount,
@@ -175,19 +451,19 @@ describe("betting-markets", () => {
         tokenProgram: TOKEN_PROGRAM_ID,
         systemProgram: anchor.web3.SystemProgram.programId,
       })
-      .signers([user1, bet1])
+      .signers([user1])
       .rpc();
 
     console.log("User1 bet transaction signature:", tx1);
 
     // User 2 bets on "No" (outcome 1)
-    const bet2 = anchor.web3.Keypair.generate();
+    const bet2 = getBetAddress(market, user2.publicKey, new anchor.BN(0));
     const betAmount2 = 5 * 1e6; // 5 tokens
 
     const tx2 = await program.methods
-      .placeBet(1, new anchor.BN(betAmount2))
+      .placeBet(1, new anchor.BN(betAmount2), new anchor.BN(0))
       .accountsPartial({
-        bet: bet2.publicKey,
+        bet: bet2,
         market,
         bettor: user2.publicKey,
         bettorTokenAccount: user2TokenAccount,
@@ -195,19 +471,19 @@ describe("betting-markets", () => {
         tokenProgram: TOKEN_PROGRAM_ID,
         systemProgram: anchor.web3.SystemProgram.programId,
       })
-      .signers([user2, bet2])
+      .signers([user2])
       .rpc();
 
     console.log("User2 bet transaction signature:", tx2);
 
     // Verify bets
-    const bet1Account = await program.account.bet.fetch(bet1.publicKey);
+    const bet1Account = await program.account.bet.fetch(bet1);
     expect(bet1Account.bettor.toString()).to.equal(user1.publicKey.toString());
     expect(bet1Account.outcomeIndex).to.equal(0);
     expect(bet1Account.amount.toNumber()).to.equal(betAmount1);
     expect(bet1Account.claimed).to.be.false;
 
-    const bet2Account = await program.account.bet.fetch(bet2.publicKey);
+    const bet2Account = await program.account.bet.fetch(bet2);
     expect(bet2Account.bettor.toString()).to.equal(user2.publicKey.toString());
     expect(bet2Account.outcomeIndex).to.equal(1);
     expect(bet2Account.amount.toNumber()).to.equal(betAmount2);
@@ -230,6 +506,4512 @@ describe("betting-markets", () => {
     expect(Number(marketBalance.amount)).to.equal(15 * 1e6); // 10 + 5
   });
 
+  it("Voids a single-sided market and refunds the stake", async () => {
+    const { market, vault } = await createTestMarket(3, { ...defaultConfig(), voidIfSingleSided: true });
+    const bet = await placeTestBet(user1, user1TokenAccount, market, vault, 0, 4 * 1e6);
+    const balanceBefore = Number((await getAccount(provider.connection, user1TokenAccount)).amount);
+
+    await sleep(4000);
+
+    await program.methods
+      .checkAndVoidSingleSided()
+      .accountsPartial({ market })
+      .rpc();
+
+    const marketAccount = await program.account.market.fetch(market);
+    expect(marketAccount.voided).to.be.true;
+
+    await program.methods
+      .claimRefund()
+      .accountsPartial({
+        bet,
+        market,
+        bettor: user1.publicKey,
+        bettorTokenAccount: user1TokenAccount,
+        marketTokenAccount: vault,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([user1])
+      .rpc();
+
+    const balanceAfter = Number((await getAccount(provider.connection, user1TokenAccount)).amount);
+    expect(balanceAfter - balanceBefore).to.equal(4 * 1e6);
+  });
+
+  it("Does not void a market with bets on several outcomes", async () => {
+    const { market, vault } = await createTestMarket(3, { ...defaultConfig(), voidIfSingleSided: true });
+    await placeTestBet(user1, user1TokenAccount, market, vault, 0, 2 * 1e6);
+    await placeTestBet(user2, user2TokenAccount, market, vault, 1, 2 * 1e6);
+
+    await sleep(4000);
+
+    try {
+      await program.methods.checkAndVoidSingleSided().accountsPartial({ market }).rpc();
+      expect.fail("two-sided market should not be voidable");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("MarketNotSingleSided");
+    }
+  });
+
+  it("Flags a resolution that pays out to the authority's own bet", async () => {
+    const { market, vault } = await createTestMarket(3);
+    await placeTestBet(authority, authorityTokenAccount, market, vault, 0, 2 * 1e6);
+    await placeTestBet(user1, user1TokenAccount, market, vault, 1, 2 * 1e6);
+
+    await sleep(4000);
+
+    await program.methods
+      .resolveMarket(0, null, 0, [])
+      .accountsPartial({ market, authority: authority.publicKey })
+      .signers([authority])
+      .rpc();
+
+    const marketAccount = await program.account.market.fetch(market);
+    expect(marketAccount.authorityHadWinningStake).to.be.true;
+  });
+
+  it("Resolves a pivot market over the line", async () => {
+    const { market, vault } = await createTestMarket(3, {
+      ...defaultConfig(),
+      marketType: { pivot: {} },
+      pivotValue: new anchor.BN(505),
+    }, ["Over 50.5", "Under 50.5"]);
+    await placeTestBet(user1, user1TokenAccount, market, vault, 0, 2 * 1e6);
+    await placeTestBet(user2, user2TokenAccount, market, vault, 1, 2 * 1e6);
+
+    await sleep(4000);
+
+    await program.methods
+      .resolvePivotMarket(new anchor.BN(560))
+      .accountsPartial({ market, authority: authority.publicKey })
+      .signers([authority])
+      .rpc();
+
+    const marketAccount = await program.account.market.fetch(market);
+    expect(marketAccount.resolved).to.be.true;
+    expect(marketAccount.winningOutcome).to.equal(0);
+  });
+
+  it("Voids a pivot market on an exact push", async () => {
+    const { market, vault } = await createTestMarket(3, {
+      ...defaultConfig(),
+      marketType: { pivot: {} },
+      pivotValue: new anchor.BN(50),
+    }, ["Over 50", "Under 50"]);
+    await placeTestBet(user1, user1TokenAccount, market, vault, 0, 2 * 1e6);
+
+    await sleep(4000);
+
+    await program.methods
+      .resolvePivotMarket(new anchor.BN(50))
+      .accountsPartial({ market, authority: authority.publicKey })
+      .signers([authority])
+      .rpc();
+
+    const marketAccount = await program.account.market.fetch(market);
+    expect(marketAccount.resolved).to.be.false;
+    expect(marketAccount.voided).to.be.true;
+  });
+
+  it("Voids a market resolved to an outcome nobody backed", async () => {
+    const { market, vault } = await createTestMarket(3);
+    const aliceBet = await placeTestBet(user1, user1TokenAccount, market, vault, 0, 3 * 1e6);
+    const bobBet = await placeTestBet(user2, user2TokenAccount, market, vault, 0, 1e6);
+
+    await sleep(4000);
+    await resolveTestMarket(market, 1);
+
+    const marketAccount = await program.account.market.fetch(market);
+    expect(marketAccount.resolved).to.be.false;
+    expect(marketAccount.voided).to.be.true;
+
+    const refund = (bettor: anchor.web3.Keypair, bettorTokenAccount: anchor.web3.PublicKey, bet: anchor.web3.PublicKey) =>
+      program.methods
+        .claimRefund()
+        .accountsPartial({
+          bet,
+          market,
+          bettor: bettor.publicKey,
+          bettorTokenAccount,
+          marketTokenAccount: vault,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([bettor])
+        .rpc();
+
+    const user1Before = await tokenBalance(user1TokenAccount);
+    const user2Before = await tokenBalance(user2TokenAccount);
+    await refund(user1, user1TokenAccount, aliceBet);
+    await refund(user2, user2TokenAccount, bobBet);
+    expect((await tokenBalance(user1TokenAccount)) - user1Before).to.equal(3 * 1e6);
+    expect((await tokenBalance(user2TokenAccount)) - user2Before).to.equal(1e6);
+    expect(await tokenBalance(vault)).to.equal(0);
+  });
+
+  it("Refunds a voided market in one batch and closes the bets", async () => {
+    const { market, vault } = await createTestMarket(3, { ...defaultConfig(), voidIfSingleSided: true });
+    const bet1 = await placeTestBet(user1, user1TokenAccount, market, vault, 0, 3 * 1e6);
+    const bet2 = await placeTestBet(user2, user2TokenAccount, market, vault, 0, 5 * 1e6);
+    const user1Before = Number((await getAccount(provider.connection, user1TokenAccount)).amount);
+    const user2Before = Number((await getAccount(provider.connection, user2TokenAccount)).amount);
+
+    await sleep(4000);
+    await program.methods.checkAndVoidSingleSided().accountsPartial({ market }).rpc();
+
+    await program.methods
+      .refundBatch()
+      .accountsPartial({
+        market,
+        authority: authority.publicKey,
+        marketTokenAccount: vault,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .remainingAccounts([
+        { pubkey: bet1, isWritable: true, isSigner: false },
+        { pubkey: user1TokenAccount, isWritable: true, isSigner: false },
+        { pubkey: user1.publicKey, isWritable: true, isSigner: false },
+        { pubkey: bet2, isWritable: true, isSigner: false },
+        { pubkey: user2TokenAccount, isWritable: true, isSigner: false },
+        { pubkey: user2.publicKey, isWritable: true, isSigner: false },
+      ])
+      .signers([authority])
+      .rpc();
+
+    const user1After = Number((await getAccount(provider.connection, user1TokenAccount)).amount);
+    const user2After = Number((await getAccount(provider.connection, user2TokenAccount)).amount);
+    expect(user1After - user1Before).to.equal(3 * 1e6);
+    expect(user2After - user2Before).to.equal(5 * 1e6);
+    expect(await program.account.bet.fetchNullable(bet1)).to.be.null;
+    expect(await program.account.bet.fetchNullable(bet2)).to.be.null;
+  });
+
+  it("Trips the circuit breaker on a rapid surge and resets after review", async () => {
+    const { market, vault } = await createTestMarket(3600, {
+      ...defaultConfig(),
+      circuitBreakerBps: 5000, // 50% growth per window
+      circuitBreakerWindow: new anchor.BN(3600),
+    });
+    await placeTestBet(user1, user1TokenAccount, market, vault, 0, 2 * 1e6);
+    await placeTestBet(user2, user2TokenAccount, market, vault, 1, 10 * 1e6);
+
+    let marketAccount = await program.account.market.fetch(market);
+    expect(marketAccount.circuitBreakerTripped).to.be.true;
+
+    try {
+      await placeTestBet(user1, user1TokenAccount, market, vault, 0, 1e6);
+      expect.fail("betting should be paused");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("CircuitBreakerTripped");
+    }
+
+    await program.methods
+      .resetCircuitBreaker()
+      .accountsPartial({ market, authority: authority.publicKey })
+      .signers([authority])
+      .rpc();
+
+    marketAccount = await program.account.market.fetch(market);
+    expect(marketAccount.circuitBreakerTripped).to.be.false;
+    await placeTestBet(user1, user1TokenAccount, market, vault, 0, 1e6);
+  });
+
+  it("Requires an outcome's designated oracle to resolve to it", async () => {
+    const oracle = anchor.web3.Keypair.generate();
+    // The resolver pays for the ResolvedOutcome record
+    await provider.connection.confirmTransaction(
+      await provider.connection.requestAirdrop(oracle.publicKey, anchor.web3.LAMPORTS_PER_SOL)
+    );
+    const { market, vault } = await createTestMarket(3, {
+      ...defaultConfig(),
+      outcomeOracles: [oracle.publicKey, null],
+    });
+    await placeTestBet(user1, user1TokenAccount, market, vault, 0, 1e6);
+
+    await sleep(4000);
+
+    try {
+      await program.methods
+        .resolveMarket(0, null, 0, [])
+        .accountsPartial({ market, authority: authority.publicKey })
+        .signers([authority])
+        .rpc();
+      expect.fail("authority should not resolve an oracle-backed outcome");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("Unauthorized");
+    }
+
+    await program.methods
+      .resolveMarket(0, null, 0, [])
+      .accountsPartial({ market, authority: oracle.publicKey })
+      .signers([oracle])
+      .rpc();
+
+    const marketAccount = await program.account.market.fetch(market);
+    expect(marketAccount.winningOutcome).to.equal(0);
+  });
+
+  it("Reports market timing and lifecycle status", async () => {
+    const { market } = await createTestMarket(3);
+
+    let timing = (await program.methods.getMarketTiming().accountsPartial({ market }).simulate())
+      .events.find((e) => e.name === "marketTiming").data;
+    expect(timing.status).to.deep.equal({ open: {} });
+    expect(timing.timeUntilClose.toNumber()).to.be.greaterThan(0);
+
+    await sleep(4000);
+
+    timing = (await program.methods.getMarketTiming().accountsPartial({ market }).simulate())
+      .events.find((e) => e.name === "marketTiming").data;
+    expect(timing.status).to.deep.equal({ awaitingResolution: {} });
+    expect(timing.timeUntilClose.toNumber()).to.equal(0);
+    expect(timing.timeUntilResolution.toNumber()).to.equal(0);
+
+    // Betting closes at the cutoff, half a minute before resolution
+    const { market: cutoffMarket } = await createTestMarket(60, {
+      ...defaultConfig(),
+      bettingCutoffOffset: new anchor.BN(30),
+    });
+    timing = (await program.methods.getMarketTiming().accountsPartial({ market: cutoffMarket }).simulate())
+      .events.find((e) => e.name === "marketTiming").data;
+    expect(timing.timeUntilClose.toNumber()).to.be.within(25, 30);
+    expect(timing.timeUntilResolution.toNumber()).to.be.within(55, 60);
+  });
+
+  it("Writes a compact resolved-outcome record on resolution", async () => {
+    const { market, vault } = await createTestMarket(3);
+    await placeTestBet(user1, user1TokenAccount, market, vault, 1, 2 * 1e6);
+    const marketId = (await program.account.market.fetch(market)).marketId;
+    const [resolvedOutcome] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("resolved_outcome"), marketId.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+
+    await sleep(4000);
+
+    await program.methods
+      .resolveMarket(1, null, 0, [])
+      .accountsPartial({ market, resolvedOutcome, authority: authority.publicKey })
+      .signers([authority])
+      .rpc();
+
+    const marketAccount = await program.account.market.fetch(market);
+    const record = await program.account.resolvedOutcome.fetch(resolvedOutcome);
+    expect(record.market.toString()).to.equal(market.toString());
+    expect(record.marketId.toNumber()).to.equal(marketAccount.marketId.toNumber());
+    expect(record.winningOutcome).to.equal(marketAccount.winningOutcome);
+  });
+
+  it("Caps a whale's payout and redistributes the excess", async () => {
+    const { market, vault } = await createTestMarket(3, {
+      ...defaultConfig(),
+      maxPayoutShareBps: 5000, // no bet may take more than half the pool
+      capRegistrationPeriod: new anchor.BN(2),
+    });
+    const whaleBet = await placeTestBet(user1, user1TokenAccount, market, vault, 0, 8 * 1e6);
+    const smallBet = await placeTestBet(user2, user2TokenAccount, market, vault, 0, 2 * 1e6);
+    await placeTestBet(authority, authorityTokenAccount, market, vault, 1, 10 * 1e6);
+
+    await sleep(4000);
+    await resolveTestMarket(market, 0);
+
+    await program.methods
+      .registerCappedBet()
+      .accountsPartial({ market, bet: whaleBet })
+      .rpc();
+
+    await sleep(3000);
+
+    const user1Before = await tokenBalance(user1TokenAccount);
+    const user2Before = await tokenBalance(user2TokenAccount);
+    await claimTestPayout(user1, user1TokenAccount, market, vault, whaleBet);
+    await claimTestPayout(user2, user2TokenAccount, market, vault, smallBet);
+
+    // Uncapped, the whale would take 16 of the 20 pool; capped at 10, the small bettor gets the rest
+    expect((await tokenBalance(user1TokenAccount)) - user1Before).to.equal(10 * 1e6);
+    expect((await tokenBalance(user2TokenAccount)) - user2Before).to.equal(10 * 1e6);
+  });
+
+  it("Rolls a winning payout into a bet on another market", async () => {
+    const source = await createTestMarket(3);
+    const winningBet = await placeTestBet(user1, user1TokenAccount, source.market, source.vault, 0, 4 * 1e6);
+    await placeTestBet(user2, user2TokenAccount, source.market, source.vault, 1, 6 * 1e6);
+    const target = await createTestMarket(3600);
+
+    await sleep(4000);
+    await resolveTestMarket(source.market, 0);
+
+    const rollNonce = new anchor.BN(nextBetNonce++);
+    const newBet = getBetAddress(target.market, user1.publicKey, rollNonce);
+    await program.methods
+      .rollPayout(1, rollNonce)
+      .accountsPartial({
+        sourceBet: winningBet,
+        sourceMarket: source.market,
+        sourceMarketTokenAccount: source.vault,
+        newBet,
+        targetMarket: target.market,
+        targetMarketTokenAccount: target.vault,
+        bettor: user1.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([user1])
+      .rpc();
+
+    const rolled = await program.account.bet.fetch(newBet);
+    expect(rolled.amount.toNumber()).to.equal(10 * 1e6);
+    expect(rolled.outcomeIndex).to.equal(1);
+    expect((await program.account.bet.fetch(winningBet)).claimed).to.be.true;
+
+    const targetAccount = await program.account.market.fetch(target.market);
+    expect(targetAccount.outcomePools[1].toNumber()).to.equal(10 * 1e6);
+    expect(await tokenBalance(target.vault)).to.equal(10 * 1e6);
+    expect(await tokenBalance(source.vault)).to.equal(0);
+  });
+
+  it("Rejects a second active market with the same external id", async () => {
+    const externalId = Array.from(Buffer.alloc(32, 7));
+    const { market } = await createTestMarket(3600, { ...defaultConfig(), externalId });
+
+    const marketAccount = await program.account.market.fetch(market);
+    expect(marketAccount.externalId).to.deep.equal(externalId);
+    const entry = await program.account.externalIdEntry.fetch(getExternalIdEntry(externalId));
+    expect(entry.market.toString()).to.equal(market.toString());
+
+    try {
+      await createTestMarket(3600, { ...defaultConfig(), externalId });
+      expect.fail("duplicate external id should be rejected");
+    } catch (err) {
+      expect(err.toString()).to.match(/already in use/);
+    }
+  });
+
+  it("Seeds asymmetric starting odds and lets the creator reclaim the winning seed", async () => {
+    const { market, vault } = await createTestMarket(3);
+
+    await program.methods
+      .seedLiquidity([new anchor.BN(3 * 1e6), new anchor.BN(1e6)])
+      .accountsPartial({
+        market,
+        authority: authority.publicKey,
+        authorityTokenAccount,
+        marketTokenAccount: vault,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([authority])
+      .rpc();
+
+    // 3:1 prior, so outcome 0 starts at 75% implied probability
+    let marketAccount = await program.account.market.fetch(market);
+    expect(marketAccount.outcomePools.map((p) => p.toNumber())).to.deep.equal([3 * 1e6, 1e6]);
+    expect(marketAccount.totalPool.toNumber()).to.equal(4 * 1e6);
+
+    const bet = await placeTestBet(user1, user1TokenAccount, market, vault, 1, 1e6);
+
+    await sleep(4000);
+    await resolveTestMarket(market, 1);
+
+    // Outcome 1 pool is 1 seed + 1 bet out of 5 total, so each side takes 2.5
+    const user1Before = await tokenBalance(user1TokenAccount);
+    await claimTestPayout(user1, user1TokenAccount, market, vault, bet);
+    expect((await tokenBalance(user1TokenAccount)) - user1Before).to.equal(2.5 * 1e6);
+
+    const authorityBefore = await tokenBalance(authorityTokenAccount);
+    await program.methods
+      .reclaimSeed()
+      .accountsPartial({
+        market,
+        authority: authority.publicKey,
+        authorityTokenAccount,
+        marketTokenAccount: vault,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([authority])
+      .rpc();
+    expect((await tokenBalance(authorityTokenAccount)) - authorityBefore).to.equal(2.5 * 1e6);
+
+    marketAccount = await program.account.market.fetch(market);
+    expect(marketAccount.seedReclaimed).to.be.true;
+  });
+
+  it("Enforces the platform's configurable question length", async () => {
+    await program.methods
+      .setMaxQuestionLen(250)
+      .accountsPartial({ globalState, authority: authority.publicKey })
+      .signers([authority])
+      .rpc();
+
+    const { market } = await createTestMarket(3600, defaultConfig(), ["Yes", "No"], "q".repeat(250));
+    expect((await program.account.market.fetch(market)).question.length).to.equal(250);
+
+    try {
+      await createTestMarket(3600, defaultConfig(), ["Yes", "No"], "q".repeat(251));
+      expect.fail("question over the limit should be rejected");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("QuestionTooLong");
+    }
+
+    await program.methods
+      .setMaxQuestionLen(200)
+      .accountsPartial({ globalState, authority: authority.publicKey })
+      .signers([authority])
+      .rpc();
+  });
+
+  it("Quarantines a market, blocking bets but keeping refunds open", async () => {
+    const { market, vault } = await createTestMarket(3600);
+    const bet = await placeTestBet(user1, user1TokenAccount, market, vault, 0, 3 * 1e6);
+
+    await program.methods
+      .quarantineMarket()
+      .accountsPartial({ market, globalState, authority: authority.publicKey })
+      .signers([authority])
+      .rpc();
+
+    try {
+      await placeTestBet(user2, user2TokenAccount, market, vault, 1, 1e6);
+      expect.fail("quarantined market should reject bets");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("MarketQuarantined");
+    }
+
+    const before = await tokenBalance(user1TokenAccount);
+    await program.methods
+      .claimRefund()
+      .accountsPartial({
+        bet,
+        market,
+        bettor: user1.publicKey,
+        bettorTokenAccount: user1TokenAccount,
+        marketTokenAccount: vault,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([user1])
+      .rpc();
+    expect((await tokenBalance(user1TokenAccount)) - before).to.equal(3 * 1e6);
+  });
+
+  it("Suggests a Kelly stake against pool-implied odds", async () => {
+    const { market, vault } = await createTestMarket(3600);
+    await placeTestBet(user1, user1TokenAccount, market, vault, 0, 3 * 1e6);
+    await placeTestBet(user2, user2TokenAccount, market, vault, 1, 1e6);
+
+    const kelly = async (outcomeIndex: number, probabilityBps: number) =>
+      (
+        await program.methods
+          .kellyStake(outcomeIndex, probabilityBps, new anchor.BN(100 * 1e6))
+          .accountsPartial({ market })
+          .simulate()
+      ).events.find((e) => e.name === "kellyStake").data;
+
+    // Outcome 1 pays 4x; believing 40% gives f = (0.4 * 4 - 1) / 3 = 20%
+    const positive = await kelly(1, 4000);
+    expect(positive.kellyFractionBps).to.equal(2000);
+    expect(positive.suggestedStake.toNumber()).to.equal(20 * 1e6);
+
+    // Outcome 0 is implied at 75%, so believing 60% is -EV
+    const negative = await kelly(0, 6000);
+    expect(negative.suggestedStake.toNumber()).to.equal(0);
+  });
+
+  it("Rejects admin changes once a market is locked", async () => {
+    const { market, vault } = await createTestMarket(3600);
+
+    await program.methods
+      .lockMarket()
+      .accountsPartial({ market, authority: authority.publicKey })
+      .signers([authority])
+      .rpc();
+    expect((await program.account.market.fetch(market)).immutable).to.be.true;
+
+    try {
+      await program.methods
+        .seedLiquidity([new anchor.BN(1e6), new anchor.BN(1e6)])
+        .accountsPartial({
+          market,
+          authority: authority.publicKey,
+          authorityTokenAccount,
+          marketTokenAccount: vault,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([authority])
+        .rpc();
+      expect.fail("locked market should reject admin changes");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("MarketImmutable");
+    }
+    try {
+      await program.methods
+        .transferMarketAuthority(user1.publicKey)
+        .accountsPartial({ market, authority: authority.publicKey })
+        .signers([authority])
+        .rpc();
+      expect.fail("locked market should keep its authority");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("MarketImmutable");
+    }
+
+    // Betting is unaffected
+    await placeTestBet(user1, user1TokenAccount, market, vault, 0, 1e6);
+  });
+
+  it("Exposes the payout formula inputs for a resolved bet", async () => {
+    const { market, vault } = await createTestMarket(3, { ...defaultConfig(), creatorFeeBps: 500 });
+    const winner = await placeTestBet(user1, user1TokenAccount, market, vault, 0, 2 * 1e6);
+    const loser = await placeTestBet(user2, user2TokenAccount, market, vault, 1, 6 * 1e6);
+
+    const breakdown = async (bet: anchor.web3.PublicKey) =>
+      (await program.methods.getPayoutBreakdown().accountsPartial({ market, bet }).simulate())
+        .events.find((e) => e.name === "payoutBreakdown").data;
+
+    try {
+      await breakdown(winner);
+      expect.fail("unresolved market has no payout breakdown");
+    } catch (err) {
+      expect(err.toString()).to.match(/MarketNotResolved/);
+    }
+
+    await sleep(4000);
+    await resolveTestMarket(market, 0);
+
+    const win = await breakdown(winner);
+    expect(win.betAmount.toNumber()).to.equal(2 * 1e6);
+    expect(win.winningPool.toNumber()).to.equal(2 * 1e6);
+    expect(win.totalPool.toNumber()).to.equal(8 * 1e6);
+    expect(win.payout.toNumber()).to.equal(8 * 1e6);
+    expect(win.bonusPool.toNumber()).to.equal(0);
+    expect(win.platformFeeBps).to.equal(0);
+    expect(win.creatorFeeBps).to.equal(500);
+    // 5% of the 8 token payout goes to the creator
+    expect(win.creatorFeeAmount.toNumber()).to.equal(0.4 * 1e6);
+    expect(win.netPayout.toNumber()).to.equal(7.6 * 1e6);
+
+    expect((await breakdown(loser)).payout.toNumber()).to.equal(0);
+  });
+
+  it("Merges two outcomes and pays bets on the merged outcome", async () => {
+    const { market, vault } = await createTestMarket(3, defaultConfig(), ["Alice", "Bob", "Carol"]);
+    const aliceBet = await placeTestBet(user1, user1TokenAccount, market, vault, 0, 2 * 1e6);
+    const bobBet = await placeTestBet(user2, user2TokenAccount, market, vault, 1, 2 * 1e6);
+    await placeTestBet(authority, authorityTokenAccount, market, vault, 2, 4 * 1e6);
+
+    // Bob drops out and endorses Alice
+    await program.methods
+      .mergeOutcomes(1, 0)
+      .accountsPartial({ market, authority: authority.publicKey })
+      .signers([authority])
+      .rpc();
+
+    const marketAccount = await program.account.market.fetch(market);
+    expect(marketAccount.outcomePools.map((p) => p.toNumber())).to.deep.equal([4 * 1e6, 0, 4 * 1e6]);
+    expect(marketAccount.totalPool.toNumber()).to.equal(8 * 1e6);
+    expect(marketAccount.mergedInto).to.deep.equal([null, 0, null]);
+
+    try {
+      await placeTestBet(user1, user1TokenAccount, market, vault, 1, 1e6);
+      expect.fail("merged outcome should not take bets");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("OutcomeMerged");
+    }
+
+    await sleep(4000);
+    await resolveTestMarket(market, 0);
+
+    const user1Before = await tokenBalance(user1TokenAccount);
+    const user2Before = await tokenBalance(user2TokenAccount);
+    await claimTestPayout(user1, user1TokenAccount, market, vault, aliceBet);
+    await claimTestPayout(user2, user2TokenAccount, market, vault, bobBet);
+    expect((await tokenBalance(user1TokenAccount)) - user1Before).to.equal(4 * 1e6);
+    expect((await tokenBalance(user2TokenAccount)) - user2Before).to.equal(4 * 1e6);
+  });
+
+  it("Holds winnings in escrow until the withdrawal delay passes", async () => {
+    const { market, vault } = await createTestMarket(3, {
+      ...defaultConfig(),
+      withdrawalDelay: new anchor.BN(3),
+    });
+    const winningBet = await placeTestBet(user1, user1TokenAccount, market, vault, 0, 2 * 1e6);
+    await placeTestBet(user2, user2TokenAccount, market, vault, 1, 2 * 1e6);
+
+    await sleep(4000);
+    await resolveTestMarket(market, 0);
+
+    const [escrow] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("escrow"), market.toBuffer(), user1.publicKey.toBuffer()],
+      program.programId
+    );
+    const before = await tokenBalance(user1TokenAccount);
+    await claimTestPayout(user1, user1TokenAccount, market, vault, winningBet, escrow);
+    expect(await tokenBalance(user1TokenAccount)).to.equal(before);
+    expect((await program.account.payoutEscrow.fetch(escrow)).amount.toNumber()).to.equal(4 * 1e6);
+
+    const withdraw = () =>
+      program.methods
+        .withdrawEscrow()
+        .accountsPartial({
+          market,
+          escrow,
+          bettor: user1.publicKey,
+          bettorTokenAccount: user1TokenAccount,
+          marketTokenAccount: vault,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([user1])
+        .rpc();
+
+    try {
+      await withdraw();
+      expect.fail("withdrawal should wait for the delay");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("EscrowLocked");
+    }
+
+    await sleep(4000);
+    await withdraw();
+    expect((await tokenBalance(user1TokenAccount)) - before).to.equal(4 * 1e6);
+    expect(await provider.connection.getAccountInfo(escrow)).to.be.null;
+  });
+
+  it("Sums vault balances across markets sharing a mint", async () => {
+    const first = await createTestMarket(3600);
+    const second = await createTestMarket(3600);
+    await placeTestBet(user1, user1TokenAccount, first.market, first.vault, 0, 2 * 1e6);
+    await placeTestBet(user2, user2TokenAccount, second.market, second.vault, 1, 3 * 1e6);
+
+    const tvl = async (pairs: { market: anchor.web3.PublicKey; vault: anchor.web3.PublicKey }[]) =>
+      program.methods
+        .getPlatformTvl(mint)
+        .accountsPartial({ globalState })
+        .remainingAccounts(
+          pairs.flatMap(({ market, vault }) => [
+            { pubkey: market, isWritable: false, isSigner: false },
+            { pubkey: vault, isWritable: false, isSigner: false },
+          ])
+        )
+        .simulate();
+
+    const snapshot = (await tvl([first, second])).events.find((e) => e.name === "platformTvl").data;
+    expect(snapshot.marketCount).to.equal(2);
+    expect(snapshot.tvl.toNumber()).to.equal(
+      (await tokenBalance(first.vault)) + (await tokenBalance(second.vault))
+    );
+    expect(snapshot.totalPool.toNumber()).to.equal(5 * 1e6);
+
+    try {
+      await tvl([first, first]);
+      expect.fail("duplicate markets should be rejected");
+    } catch (err) {
+      expect(err.toString()).to.match(/InvalidTvlAccounts/);
+    }
+  });
+
+  it("Closes betting and opens resolution on a slot deadline", async () => {
+    const resolutionSlot = (await provider.connection.getSlot()) + 15;
+    const { market, vault } = await createTestMarket(3600, {
+      ...defaultConfig(),
+      useSlots: true,
+      resolutionSlot: new anchor.BN(resolutionSlot),
+    });
+    await placeTestBet(user1, user1TokenAccount, market, vault, 0, 2 * 1e6);
+
+    try {
+      await resolveTestMarket(market, 0);
+      expect.fail("resolution should wait for the slot");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("TooEarlyToResolve");
+    }
+
+    while ((await provider.connection.getSlot()) < resolutionSlot) {
+      await sleep(400);
+    }
+
+    // The timestamp deadline is an hour out, but the slot deadline governs
+    try {
+      await placeTestBet(user2, user2TokenAccount, market, vault, 1, 1e6);
+      expect.fail("betting should close at the slot");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("BettingClosed");
+    }
+
+    await resolveTestMarket(market, 0);
+    expect((await program.account.market.fetch(market)).resolved).to.be.true;
+  });
+
+  it("Covers a market vault shortfall from the insurance fund", async () => {
+    const [insuranceFund] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("insurance_fund"), mint.toBuffer()],
+      program.programId
+    );
+    const [insuranceVault] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("insurance_vault"), mint.toBuffer()],
+      program.programId
+    );
+
+    await program.methods
+      .initializeInsuranceFund()
+      .accountsPartial({
+        insuranceFund,
+        insuranceVault,
+        mint,
+        globalState,
+        authority: authority.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([authority])
+      .rpc();
+
+    await program.methods
+      .contributeInsurance(new anchor.BN(10 * 1e6))
+      .accountsPartial({
+        insuranceFund,
+        insuranceVault,
+        contributor: authority.publicKey,
+        contributorTokenAccount: authorityTokenAccount,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([authority])
+      .rpc();
+    expect(await tokenBalance(insuranceVault)).to.equal(10 * 1e6);
+
+    const { market, vault } = await createTestMarket(3);
+    const winningBet = await placeTestBet(user1, user1TokenAccount, market, vault, 0, 2 * 1e6);
+    await placeTestBet(user2, user2TokenAccount, market, vault, 1, 2 * 1e6);
+    await sleep(4000);
+    await resolveTestMarket(market, 0);
+
+    // Stand in for a drained vault with an empty one the market also owns
+    const shortVault = await createAccount(
+      provider.connection,
+      authority,
+      mint,
+      market,
+      anchor.web3.Keypair.generate()
+    );
+    try {
+      await claimTestPayout(user1, user1TokenAccount, market, shortVault, winningBet);
+      expect.fail("an empty vault cannot pay the winner");
+    } catch (err) {
+      // SPL token's InsufficientFunds
+      expect(err.toString()).to.match(/custom program error: 0x1\b/);
+    }
+    expect((await program.account.bet.fetch(winningBet)).claimed).to.be.false;
+
+    await program.methods
+      .coverShortfall(new anchor.BN(4 * 1e6))
+      .accountsPartial({
+        insuranceFund,
+        insuranceVault,
+        market,
+        marketTokenAccount: shortVault,
+        globalState,
+        authority: authority.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([authority])
+      .rpc();
+
+    const before = await tokenBalance(user1TokenAccount);
+    await claimTestPayout(user1, user1TokenAccount, market, shortVault, winningBet);
+    expect((await tokenBalance(user1TokenAccount)) - before).to.equal(4 * 1e6);
+
+    const fund = await program.account.insuranceFund.fetch(insuranceFund);
+    expect(fund.totalContributed.toNumber()).to.equal(10 * 1e6);
+    expect(fund.totalCovered.toNumber()).to.equal(4 * 1e6);
+  });
+
+  it("Only accepts bets from approved token accounts when restricted", async () => {
+    const { market, vault } = await createTestMarket(3600, {
+      ...defaultConfig(),
+      restrictTokenAccounts: true,
+    });
+
+    const updateAllowlist = (method: "allowTokenAccount" | "disallowTokenAccount", tokenAccount: anchor.web3.PublicKey) =>
+      program.methods[method](tokenAccount)
+        .accountsPartial({ market, authority: authority.publicKey })
+        .signers([authority])
+        .rpc();
+
+    try {
+      await placeTestBet(user1, user1TokenAccount, market, vault, 0, 1e6);
+      expect.fail("unapproved token account should be rejected");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("TokenAccountNotAllowed");
+    }
+
+    await updateAllowlist("allowTokenAccount", user1TokenAccount);
+    await placeTestBet(user1, user1TokenAccount, market, vault, 0, 1e6);
+    expect((await program.account.market.fetch(market)).totalPool.toNumber()).to.equal(1e6);
+
+    try {
+      await placeTestBet(user2, user2TokenAccount, market, vault, 1, 1e6);
+      expect.fail("other token accounts stay blocked");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("TokenAccountNotAllowed");
+    }
+
+    await updateAllowlist("disallowTokenAccount", user1TokenAccount);
+    try {
+      await placeTestBet(user1, user1TokenAccount, market, vault, 0, 1e6);
+      expect.fail("removed token account should be rejected");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("TokenAccountNotAllowed");
+    }
+  });
+
+  it("Hides outcome labels behind commitments until betting closes", async () => {
+    const labels = ["Red team", "Blue team"];
+    const salts = labels.map(() => randomBytes(32));
+    const commitments = labels.map((label, i) =>
+      Array.from(createHash("sha256").update(Buffer.concat([Buffer.from(label), salts[i]])).digest())
+    );
+    const { market, vault } = await createTestMarket(
+      3,
+      { ...defaultConfig(), outcomeCommitments: commitments },
+      ["A", "B"]
+    );
+    await placeTestBet(user1, user1TokenAccount, market, vault, 0, 1e6);
+
+    const reveal = (revealed: string[]) =>
+      program.methods
+        .revealOutcomes(revealed, salts.map((salt) => Array.from(salt)))
+        .accountsPartial({ market, authority: authority.publicKey })
+        .signers([authority])
+        .rpc();
+
+    try {
+      await reveal(labels);
+      expect.fail("labels stay hidden while betting is open");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("BettingStillOpen");
+    }
+
+    await sleep(4000);
+
+    try {
+      await resolveTestMarket(market, 0);
+      expect.fail("blind market must be revealed first");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("OutcomesNotRevealed");
+    }
+
+    try {
+      await reveal(["Blue team", "Red team"]);
+      expect.fail("swapped labels should not match the commitments");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("CommitmentMismatch");
+    }
+
+    await reveal(labels);
+    const marketAccount = await program.account.market.fetch(market);
+    expect(marketAccount.outcomes).to.deep.equal(labels);
+    expect(marketAccount.outcomesRevealed).to.be.true;
+
+    await resolveTestMarket(market, 0);
+  });
+
+  it("Resolves a scheduled market only from its oracle inside the window", async () => {
+    const oracle = anchor.web3.Keypair.generate();
+    await provider.connection.confirmTransaction(
+      await provider.connection.requestAirdrop(oracle.publicKey, anchor.web3.LAMPORTS_PER_SOL)
+    );
+
+    const { market, vault } = await createTestMarket(
+      2,
+      {
+        ...defaultConfig(),
+        marketType: { pivot: {} },
+        pivotValue: new anchor.BN(100),
+        scheduledOracle: oracle.publicKey,
+        scheduledTime: new anchor.BN(Math.floor(Date.now() / 1000) + 5),
+        scheduledWindow: new anchor.BN(60),
+      },
+      ["Over", "Under"]
+    );
+    await placeTestBet(user1, user1TokenAccount, market, vault, 0, 1e6);
+    await placeTestBet(user2, user2TokenAccount, market, vault, 1, 1e6);
+
+    const resolveScheduled = (signer: anchor.web3.Keypair, reading: number) =>
+      program.methods
+        .resolveAtScheduledTime(new anchor.BN(reading))
+        .accountsPartial({ market, authority: signer.publicKey })
+        .signers([signer])
+        .rpc();
+
+    // Betting has closed, but the scheduled reading hasn't arrived yet
+    await sleep(3000);
+    try {
+      await resolveScheduled(oracle, 120);
+      expect.fail("reading before the scheduled time should be rejected");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("OutsideScheduledWindow");
+    }
+
+    try {
+      await program.methods
+        .resolvePivotMarket(new anchor.BN(120))
+        .accountsPartial({ market, authority: authority.publicKey })
+        .signers([authority])
+        .rpc();
+      expect.fail("the authority cannot resolve a scheduled market");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("ScheduledResolutionOnly");
+    }
+
+    await sleep(3000);
+    try {
+      await resolveScheduled(authority, 120);
+      expect.fail("only the committed oracle can report");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("Unauthorized");
+    }
+
+    await resolveScheduled(oracle, 120);
+    const marketAccount = await program.account.market.fetch(market);
+    expect(marketAccount.resolved).to.be.true;
+    expect(marketAccount.winningOutcome).to.equal(0);
+  });
+
+  it("Tops up an existing bet instead of opening a new one", async () => {
+    const { market, vault } = await createTestMarket(3600);
+    const bet = await placeTestBet(user1, user1TokenAccount, market, vault, 1, 2 * 1e6);
+
+    const addToBet = (bettor: anchor.web3.Keypair, bettorTokenAccount: anchor.web3.PublicKey, amount: number) =>
+      program.methods
+        .addToBet(new anchor.BN(amount))
+        .accountsPartial({
+          bet,
+          market,
+          bettor: bettor.publicKey,
+          bettorTokenAccount,
+          marketTokenAccount: vault,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([bettor])
+        .rpc();
+
+    await addToBet(user1, user1TokenAccount, 3 * 1e6);
+
+    expect((await program.account.bet.fetch(bet)).amount.toNumber()).to.equal(5 * 1e6);
+    const marketAccount = await program.account.market.fetch(market);
+    expect(marketAccount.outcomePools[1].toNumber()).to.equal(5 * 1e6);
+    expect(marketAccount.totalPool.toNumber()).to.equal(5 * 1e6);
+    expect(await tokenBalance(vault)).to.equal(5 * 1e6);
+
+    try {
+      await addToBet(user2, user2TokenAccount, 1e6);
+      expect.fail("only the bettor can add to their bet");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("Unauthorized");
+    }
+  });
+
+  it("Classifies markets for health triage", async () => {
+    const health = async (market: anchor.web3.PublicKey, vault: anchor.web3.PublicKey, gracePeriod = 0) =>
+      (
+        await program.methods
+          .marketHealth(new anchor.BN(gracePeriod))
+          .accountsPartial({ market, marketTokenAccount: vault })
+          .simulate()
+      ).events.find((e) => e.name === "marketHealth").data.health;
+
+    const open = await createTestMarket(3600);
+    expect(await health(open.market, open.vault)).to.deep.equal({ healthy: {} });
+
+    const unresolved = await createTestMarket(2);
+    const settled = await createTestMarket(2);
+    const winningBet = await placeTestBet(user1, user1TokenAccount, settled.market, settled.vault, 0, 2 * 1e6);
+    await placeTestBet(user2, user2TokenAccount, settled.market, settled.vault, 1, 2 * 1e6);
+    await sleep(3000);
+
+    expect(await health(unresolved.market, unresolved.vault)).to.deep.equal({ overdueResolution: {} });
+    expect(await health(unresolved.market, unresolved.vault, 3600)).to.deep.equal({ healthy: {} });
+
+    await resolveTestMarket(settled.market, 0);
+    expect(await health(settled.market, settled.vault)).to.deep.equal({ stuckFunds: {} });
+
+    await claimTestPayout(user1, user1TokenAccount, settled.market, settled.vault, winningBet);
+    expect(await health(settled.market, settled.vault)).to.deep.equal({ settled: {} });
+  });
+
+  it("Pays a consolation slice to bettors on the runner-up", async () => {
+    const { market, vault } = await createTestMarket(3, defaultConfig(), ["Gold", "Silver", "Bronze"]);
+    const winnerBet = await placeTestBet(user1, user1TokenAccount, market, vault, 0, 2 * 1e6);
+    const runnerUpBet = await placeTestBet(user2, user2TokenAccount, market, vault, 1, 2 * 1e6);
+    const loserBet = await placeTestBet(authority, authorityTokenAccount, market, vault, 2, 6 * 1e6);
+    await sleep(4000);
+
+    const resolve = (runnerUp: number | null, consolationBps: number) =>
+      program.methods
+        .resolveMarket(0, runnerUp, consolationBps, [])
+        .accountsPartial({ market, authority: authority.publicKey })
+        .signers([authority])
+        .rpc();
+
+    try {
+      // 90% of the pool would leave the winners less than their stake
+      await resolve(1, 9000);
+      expect.fail("consolation must leave winners their stake");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("InvalidConsolation");
+    }
+
+    // 10% of the 10 token pool goes to Silver, the other 9 to Gold
+    await resolve(1, 1000);
+
+    const user1Before = await tokenBalance(user1TokenAccount);
+    const user2Before = await tokenBalance(user2TokenAccount);
+    await claimTestPayout(user1, user1TokenAccount, market, vault, winnerBet);
+    await claimTestPayout(user2, user2TokenAccount, market, vault, runnerUpBet);
+    expect((await tokenBalance(user1TokenAccount)) - user1Before).to.equal(9 * 1e6);
+    expect((await tokenBalance(user2TokenAccount)) - user2Before).to.equal(1e6);
+
+    try {
+      await claimTestPayout(authority, authorityTokenAccount, market, vault, loserBet);
+      expect.fail("third place gets nothing");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("LosingBet");
+    }
+  });
+
+  it("Resolves and pushes payouts to the top winners in one transaction", async () => {
+    const { market, vault } = await createTestMarket(3);
+    const user1Bet = await placeTestBet(user1, user1TokenAccount, market, vault, 0, 3 * 1e6);
+    const user2Bet = await placeTestBet(user2, user2TokenAccount, market, vault, 0, 1e6);
+    const losingBet = await placeTestBet(authority, authorityTokenAccount, market, vault, 1, 4 * 1e6);
+    await sleep(4000);
+
+    const settle = (positions: [anchor.web3.PublicKey, anchor.web3.PublicKey, anchor.web3.PublicKey][]) =>
+      program.methods
+        .resolveAndSettleTop(0, [])
+        .accountsPartial({
+          market,
+          authority: authority.publicKey,
+          marketTokenAccount: vault,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .remainingAccounts(
+          positions.flatMap(([bet, tokenAccount, bettor]) => [
+            { pubkey: bet, isWritable: true, isSigner: false },
+            { pubkey: tokenAccount, isWritable: true, isSigner: false },
+            { pubkey: getBettorFreeze(market, bettor), isWritable: false, isSigner: false },
+            { pubkey: bettor, isWritable: true, isSigner: false },
+          ])
+        )
+        .signers([authority])
+        .rpc();
+
+    try {
+      await settle([[losingBet, authorityTokenAccount, authority.publicKey]]);
+      expect.fail("losing positions cannot be pushed a payout");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("LosingBet");
+    }
+    expect((await program.account.market.fetch(market)).resolved).to.be.false;
+
+    const user1Before = await tokenBalance(user1TokenAccount);
+    const user2Before = await tokenBalance(user2TokenAccount);
+    const rent = await provider.connection.getBalance(user1Bet);
+    const user1Lamports = await provider.connection.getBalance(user1.publicKey);
+    await settle([
+      [user1Bet, user1TokenAccount, user1.publicKey],
+      [user2Bet, user2TokenAccount, user2.publicKey],
+    ]);
+
+    expect((await program.account.market.fetch(market)).resolved).to.be.true;
+    expect((await tokenBalance(user1TokenAccount)) - user1Before).to.equal(6 * 1e6);
+    expect((await tokenBalance(user2TokenAccount)) - user2Before).to.equal(2 * 1e6);
+    // Settled bets are closed with their rent returned, as a claim would
+    expect(await program.account.bet.fetchNullable(user1Bet)).to.be.null;
+    expect(await program.account.bet.fetchNullable(user2Bet)).to.be.null;
+    expect((await provider.connection.getBalance(user1.publicKey)) - user1Lamports).to.equal(rent);
+  });
+
+  it("Voids a market when too few outcomes hold stake at close", async () => {
+    const { market, vault } = await createTestMarket(
+      3,
+      { ...defaultConfig(), minDiverseOutcomes: 3 },
+      ["Alice", "Bob", "Carol"]
+    );
+    const bet = await placeTestBet(user1, user1TokenAccount, market, vault, 0, 2 * 1e6);
+    await placeTestBet(user2, user2TokenAccount, market, vault, 1, 2 * 1e6);
+
+    try {
+      await program.methods.validateDiversity().accountsPartial({ market }).rpc();
+      expect.fail("diversity is only judged at close");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("BettingStillOpen");
+    }
+
+    await sleep(4000);
+
+    try {
+      await resolveTestMarket(market, 0);
+      expect.fail("two of three outcomes funded is below the threshold");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("InsufficientDiversity");
+    }
+
+    await program.methods.validateDiversity().accountsPartial({ market }).rpc();
+    expect((await program.account.market.fetch(market)).voided).to.be.true;
+
+    const before = await tokenBalance(user1TokenAccount);
+    await program.methods
+      .claimRefund()
+      .accountsPartial({
+        bet,
+        market,
+        bettor: user1.publicKey,
+        bettorTokenAccount: user1TokenAccount,
+        marketTokenAccount: vault,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([user1])
+      .rpc();
+    expect((await tokenBalance(user1TokenAccount)) - before).to.equal(2 * 1e6);
+  });
+
+  it("Settles a fixed-odds market against house liquidity", async () => {
+    // Outcome 0 pays 2x, outcome 1 pays 3x
+    const { market, vault } = await createTestMarket(3, {
+      ...defaultConfig(),
+      marketType: { fixedOdds: {} },
+      fixedOddsBps: [20_000, 30_000],
+    });
+
+    await program.methods
+      .fundHouse(new anchor.BN(3 * 1e6))
+      .accountsPartial({
+        market,
+        authority: authority.publicKey,
+        authorityTokenAccount,
+        marketTokenAccount: vault,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([authority])
+      .rpc();
+
+    try {
+      // 2 tokens at 3x would need 4 tokens of house cover
+      await placeTestBet(user1, user1TokenAccount, market, vault, 1, 2 * 1e6);
+      expect.fail("bet beyond the house's liquidity should be rejected");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("HouseOvercommitted");
+    }
+
+    const winningBet = await placeTestBet(user1, user1TokenAccount, market, vault, 1, 1e6);
+    await placeTestBet(user2, user2TokenAccount, market, vault, 0, 1e6);
+    let marketAccount = await program.account.market.fetch(market);
+    expect(marketAccount.houseLocked.toNumber()).to.equal(3 * 1e6);
+
+    try {
+      await placeTestBet(user2, user2TokenAccount, market, vault, 0, 1e6);
+      expect.fail("house is fully committed");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("HouseOvercommitted");
+    }
+
+    await sleep(4000);
+    await resolveTestMarket(market, 1);
+
+    const user1Before = await tokenBalance(user1TokenAccount);
+    await claimTestPayout(user1, user1TokenAccount, market, vault, winningBet);
+    expect((await tokenBalance(user1TokenAccount)) - user1Before).to.equal(3 * 1e6);
+
+    // House put in 3, took the losing 1, and paid 2 of profit
+    const authorityBefore = await tokenBalance(authorityTokenAccount);
+    await program.methods
+      .withdrawHouseProfit()
+      .accountsPartial({
+        market,
+        authority: authority.publicKey,
+        authorityTokenAccount,
+        marketTokenAccount: vault,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([authority])
+      .rpc();
+    expect((await tokenBalance(authorityTokenAccount)) - authorityBefore).to.equal(2 * 1e6);
+    expect(await tokenBalance(vault)).to.equal(0);
+  });
+
+  it("Raises the minimum bet on long-shot outcomes", async () => {
+    // min_bet is 1 token; an unbacked outcome needs the 10x ceiling
+    const { market, vault } = await createTestMarket(3600, { ...defaultConfig(), graduatedMinBet: true });
+    await placeTestBet(user1, user1TokenAccount, market, vault, 0, 2 * 1e6);
+
+    try {
+      await placeTestBet(user2, user2TokenAccount, market, vault, 1, 9 * 1e6);
+      expect.fail("an empty outcome requires the ceiling");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("BetTooSmall");
+    }
+    await placeTestBet(user2, user2TokenAccount, market, vault, 1, 10 * 1e6);
+
+    // Pools 2/10: outcome 0 now needs 1 * 12 / (2 * 2) = 3 tokens, outcome 1 stays at 1
+    try {
+      await placeTestBet(user1, user1TokenAccount, market, vault, 0, 2 * 1e6);
+      expect.fail("the long shot's minimum should have risen");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("BetTooSmall");
+    }
+    await placeTestBet(user1, user1TokenAccount, market, vault, 0, 3 * 1e6);
+    await placeTestBet(user2, user2TokenAccount, market, vault, 1, 1e6);
+  });
+
+  it("Blocks a frozen bettor while others keep betting", async () => {
+    const { market, vault } = await createTestMarket(3);
+    const frozenBet = await placeTestBet(user2, user2TokenAccount, market, vault, 0, 1e6);
+
+    await program.methods
+      .freezeBettor(user2.publicKey)
+      .accountsPartial({ market, authority: authority.publicKey })
+      .signers([authority])
+      .rpc();
+
+    try {
+      await placeTestBet(user2, user2TokenAccount, market, vault, 0, 1e6);
+      expect.fail("frozen bettor should not be able to bet");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("FrozenBettor");
+    }
+    const otherBet = await placeTestBet(user1, user1TokenAccount, market, vault, 0, 1e6);
+    await placeTestBet(authority, authorityTokenAccount, market, vault, 1, 2 * 1e6);
+
+    await sleep(4000);
+    await resolveTestMarket(market, 0);
+
+    try {
+      await claimTestPayout(user2, user2TokenAccount, market, vault, frozenBet);
+      expect.fail("frozen bettor should not be able to claim");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("FrozenBettor");
+    }
+    await claimTestPayout(user1, user1TokenAccount, market, vault, otherBet);
+
+    await program.methods
+      .unfreezeBettor()
+      .accountsPartial({
+        market,
+        bettorFreeze: getBettorFreeze(market, user2.publicKey),
+        authority: authority.publicKey,
+      })
+      .signers([authority])
+      .rpc();
+    await claimTestPayout(user2, user2TokenAccount, market, vault, frozenBet);
+  });
+
+  it("Requires one evidence hash per outcome when configured", async () => {
+    const { market, vault } = await createTestMarket(
+      3,
+      { ...defaultConfig(), requireOutcomeEvidence: true },
+      ["Alice", "Bob", "Carol"]
+    );
+    await placeTestBet(user1, user1TokenAccount, market, vault, 0, 1e6);
+    await sleep(4000);
+
+    const evidence = ["alice-won", "bob-lost", "carol-lost"].map((note) =>
+      Array.from(createHash("sha256").update(note).digest())
+    );
+    const resolve = (hashes: number[][]) =>
+      program.methods
+        .resolveMarket(0, null, 0, hashes)
+        .accountsPartial({ market, authority: authority.publicKey })
+        .signers([authority])
+        .rpc();
+
+    for (const hashes of [[], evidence.slice(0, 2)]) {
+      try {
+        await resolve(hashes);
+        expect.fail("evidence must cover every outcome");
+      } catch (err) {
+        expect(err.error.errorCode.code).to.equal("InvalidOutcomeEvidence");
+      }
+    }
+
+    await resolve(evidence);
+    expect((await program.account.market.fetch(market)).outcomeEvidence).to.deep.equal(evidence);
+  });
+
+  it("Lets a keeper void a stale market for the keeper reward", async () => {
+    const { market, vault } = await createTestMarket(2, {
+      ...defaultConfig(),
+      staleGracePeriod: new anchor.BN(2),
+    });
+    const bet = await placeTestBet(user2, user2TokenAccount, market, vault, 0, 2 * 1e6);
+
+    await program.methods
+      .fundKeeperReserve(new anchor.BN(1e6))
+      .accountsPartial({
+        market,
+        authority: authority.publicKey,
+        authorityTokenAccount,
+        marketTokenAccount: vault,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([authority])
+      .rpc();
+
+    const processStale = () =>
+      program.methods
+        .processStaleMarket()
+        .accountsPartial({
+          market,
+          keeper: user1.publicKey,
+          keeperTokenAccount: user1TokenAccount,
+          marketTokenAccount: vault,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([user1])
+        .rpc();
+
+    // Past resolution_time but still inside the grace period
+    await sleep(3000);
+    try {
+      await processStale();
+      expect.fail("market is not stale yet");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("MarketNotStale");
+    }
+
+    await sleep(2000);
+    const keeperBefore = await tokenBalance(user1TokenAccount);
+    await processStale();
+    expect((await tokenBalance(user1TokenAccount)) - keeperBefore).to.equal(1e6);
+    expect((await program.account.market.fetch(market)).voided).to.be.true;
+
+    try {
+      await processStale();
+      expect.fail("a stale market is processed only once");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("MarketVoided");
+    }
+
+    const bettorBefore = await tokenBalance(user2TokenAccount);
+    await program.methods
+      .claimRefund()
+      .accountsPartial({
+        bet,
+        market,
+        bettor: user2.publicKey,
+        bettorTokenAccount: user2TokenAccount,
+        marketTokenAccount: vault,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([user2])
+      .rpc();
+    expect((await tokenBalance(user2TokenAccount)) - bettorBefore).to.equal(2 * 1e6);
+  });
+
+  it("Splits stakes into per-outcome vaults and settles across them", async () => {
+    const { market, vault } = await createTestMarket(3);
+    await placeTestBet(user1, user1TokenAccount, market, vault, 0, 3 * 1e6);
+    await placeTestBet(user2, user2TokenAccount, market, vault, 1, 1e6);
+
+    const outcomeVaults = [0, 1].map(
+      (index) =>
+        anchor.web3.PublicKey.findProgramAddressSync(
+          [Buffer.from("outcome_vault"), market.toBuffer(), Buffer.from([index])],
+          program.programId
+        )[0]
+    );
+    for (const index of [0, 1]) {
+      await program.methods
+        .splitVault(index)
+        .accountsPartial({
+          market,
+          authority: authority.publicKey,
+          marketTokenAccount: vault,
+          mint,
+          outcomeVault: outcomeVaults[index],
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([authority])
+        .rpc();
+    }
+
+    expect(await tokenBalance(vault)).to.equal(0);
+    expect(await tokenBalance(outcomeVaults[0])).to.equal(3 * 1e6);
+    expect(await tokenBalance(outcomeVaults[1])).to.equal(1e6);
+
+    // New stakes on outcome 1 must go to its own sub-vault
+    try {
+      await placeTestBet(user2, user2TokenAccount, market, outcomeVaults[0], 1, 1e6);
+      expect.fail("stake routed to the wrong outcome vault");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("InvalidOutcomeVaults");
+    }
+    const bet = await placeTestBet(user1, user1TokenAccount, market, outcomeVaults[0], 0, 1e6);
+    expect(await tokenBalance(outcomeVaults[0])).to.equal(4 * 1e6);
+
+    await sleep(4000);
+    await resolveTestMarket(market, 0);
+
+    // The other outcome-0 bet would take the rest; this one is a quarter of the winning pool
+    const before = await tokenBalance(user1TokenAccount);
+    await claimTestPayout(user1, user1TokenAccount, market, vault, bet, null, outcomeVaults);
+    expect((await tokenBalance(user1TokenAccount)) - before).to.equal(1.25 * 1e6);
+    expect((await tokenBalance(outcomeVaults[0])) + (await tokenBalance(outcomeVaults[1]))).to.equal(
+      3.75 * 1e6
+    );
+    expect(await tokenBalance(outcomeVaults[1])).to.equal(0.75 * 1e6);
+  });
+
+  it("Pays scored markets in proportion to stake times score", async () => {
+    const { market, vault } = await createTestMarket(
+      3,
+      { ...defaultConfig(), scoredResolution: true },
+      ["Close", "Near", "Far"]
+    );
+    const closeBet = await placeTestBet(user1, user1TokenAccount, market, vault, 0, 2 * 1e6);
+    const nearBet = await placeTestBet(user2, user2TokenAccount, market, vault, 1, 2 * 1e6);
+    const farBet = await placeTestBet(user2, user2TokenAccount, market, vault, 2, 4 * 1e6);
+
+    await sleep(4000);
+    try {
+      await resolveTestMarket(market, 0);
+      expect.fail("scored markets can't resolve to a single winner");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("ScoredMarket");
+    }
+    try {
+      await program.methods
+        .resolveMarketScored(Buffer.from([101, 50, 0]), [])
+        .accountsPartial({ market, authority: authority.publicKey })
+        .signers([authority])
+        .rpc();
+      expect.fail("scores above 100 are rejected");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("InvalidScores");
+    }
+
+    await program.methods
+      .resolveMarketScored(Buffer.from([100, 50, 0]), [])
+      .accountsPartial({ market, authority: authority.publicKey })
+      .signers([authority])
+      .rpc();
+
+    // Weight is 2*100 + 2*50 = 300 over an 8 token pool
+    const closeBefore = await tokenBalance(user1TokenAccount);
+    await claimTestPayout(user1, user1TokenAccount, market, vault, closeBet);
+    const closePayout = (await tokenBalance(user1TokenAccount)) - closeBefore;
+
+    const nearBefore = await tokenBalance(user2TokenAccount);
+    await claimTestPayout(user2, user2TokenAccount, market, vault, nearBet);
+    const nearPayout = (await tokenBalance(user2TokenAccount)) - nearBefore;
+
+    expect(closePayout).to.equal(5333333);
+    expect(nearPayout).to.equal(2666666);
+    expect(closePayout + nearPayout).to.be.at.most(8 * 1e6);
+    expect(await tokenBalance(vault)).to.equal(8 * 1e6 - closePayout - nearPayout);
+
+    try {
+      await claimTestPayout(user2, user2TokenAccount, market, vault, farBet);
+      expect.fail("a zero score pays nothing");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("LosingBet");
+    }
+  });
+
+  it("Resolves from another program's account state", async () => {
+    // SPL token accounts store their owner at byte 32; stand in for a target program's account
+    const { market, vault } = await createTestMarket(3, {
+      ...defaultConfig(),
+      resolutionAccount: user1TokenAccount,
+      resolutionOffset: 32,
+      expectedBytes: user1.publicKey.toBuffer(),
+    });
+    const yesBet = await placeTestBet(user1, user1TokenAccount, market, vault, 0, 1e6);
+    await placeTestBet(user2, user2TokenAccount, market, vault, 1, 1e6);
+
+    await sleep(4000);
+    try {
+      await resolveTestMarket(market, 1);
+      expect.fail("account-resolved markets can't be resolved by hand");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("AccountResolvedMarket");
+    }
+
+    const resolveFrom = (sourceAccount: anchor.web3.PublicKey) =>
+      program.methods
+        .resolveFromAccount()
+        .accountsPartial({ market, sourceAccount, caller: user2.publicKey })
+        .signers([user2])
+        .rpc();
+
+    try {
+      await resolveFrom(user2TokenAccount);
+      expect.fail("source account must match the market");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("InvalidResolutionSource");
+    }
+
+    await resolveFrom(user1TokenAccount);
+    const resolved = await program.account.market.fetch(market);
+    expect(resolved.resolved).to.be.true;
+    expect(resolved.winningOutcome).to.equal(0);
+
+    const before = await tokenBalance(user1TokenAccount);
+    await claimTestPayout(user1, user1TokenAccount, market, vault, yesBet);
+    expect((await tokenBalance(user1TokenAccount)) - before).to.equal(2 * 1e6);
+  });
+
+  it("Deducts the protocol fee from payouts for withdrawal", async () => {
+    const setFee = (feeBps: number) =>
+      program.methods
+        .setFee(feeBps)
+        .accountsPartial({ globalState, authority: authority.publicKey })
+        .signers([authority])
+        .rpc();
+
+    try {
+      await setFee(10_001);
+      expect.fail("fee above 100% is rejected");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("InvalidFee");
+    }
+
+    await setFee(500);
+    try {
+      const { market, vault } = await createTestMarket(3);
+      const bet = await placeTestBet(user1, user1TokenAccount, market, vault, 0, 2 * 1e6);
+      await placeTestBet(user2, user2TokenAccount, market, vault, 1, 2 * 1e6);
+
+      await sleep(4000);
+      await resolveTestMarket(market, 0);
+
+      const before = await tokenBalance(user1TokenAccount);
+      await claimTestPayout(user1, user1TokenAccount, market, vault, bet);
+      // 5% of the 4 token payout stays behind as the fee
+      expect((await tokenBalance(user1TokenAccount)) - before).to.equal(3.8 * 1e6);
+      expect((await program.account.market.fetch(market)).feesAccrued.toNumber()).to.equal(0.2 * 1e6);
+
+      const treasuryBefore = await tokenBalance(authorityTokenAccount);
+      await program.methods
+        .withdrawFees()
+        .accountsPartial({
+          globalState,
+          market,
+          authority: authority.publicKey,
+          marketTokenAccount: vault,
+          destination: authorityTokenAccount,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([authority])
+        .rpc();
+      expect((await tokenBalance(authorityTokenAccount)) - treasuryBefore).to.equal(0.2 * 1e6);
+      const withdrawn = await program.account.market.fetch(market);
+      expect(withdrawn.feesAccrued.toNumber()).to.equal(0);
+      expect(withdrawn.feesCollected.toNumber()).to.equal(0.2 * 1e6);
+      expect(await tokenBalance(vault)).to.equal(0);
+    } finally {
+      await setFee(0);
+    }
+  });
+
+  it("Cancels a market and refunds every stake regardless of outcome", async () => {
+    const { market, vault } = await createTestMarket(60);
+    const yesBet = await placeTestBet(user1, user1TokenAccount, market, vault, 0, 3 * 1e6);
+    const noBet = await placeTestBet(user2, user2TokenAccount, market, vault, 1, 1e6);
+
+    try {
+      await program.methods
+        .cancelMarket()
+        .accountsPartial({ market, authority: user1.publicKey })
+        .signers([user1])
+        .rpc();
+      expect.fail("only the market authority can cancel");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("Unauthorized");
+    }
+
+    await program.methods
+      .cancelMarket()
+      .accountsPartial({ market, authority: authority.publicKey })
+      .signers([authority])
+      .rpc();
+
+    const cancelled = await program.account.market.fetch(market);
+    expect(cancelled.cancelled).to.be.true;
+    expect(cancelled.voided).to.be.true;
+
+    try {
+      await placeTestBet(user2, user2TokenAccount, market, vault, 1, 1e6);
+      expect.fail("cancelled market should reject bets");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("MarketCancelled");
+    }
+
+    for (const [bettor, tokenAccount, bet, stake] of [
+      [user1, user1TokenAccount, yesBet, 3 * 1e6],
+      [user2, user2TokenAccount, noBet, 1e6],
+    ] as const) {
+      const before = await tokenBalance(tokenAccount);
+      await program.methods
+        .claimRefund()
+        .accountsPartial({
+          bet,
+          market,
+          bettor: bettor.publicKey,
+          bettorTokenAccount: tokenAccount,
+          marketTokenAccount: vault,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([bettor])
+        .rpc();
+      expect((await tokenBalance(tokenAccount)) - before).to.equal(stake);
+    }
+    expect(await tokenBalance(vault)).to.equal(0);
+  });
+
+  it("Charges the creation fee unless the creator holds a waiver", async () => {
+    const setCreationFee = (fee: number) =>
+      program.methods
+        .setCreationFee(new anchor.BN(fee), authorityTokenAccount)
+        .accountsPartial({ globalState, authority: authority.publicKey })
+        .signers([authority])
+        .rpc();
+
+    const createAsUser1 = async (payFee: boolean) => {
+      const { marketCount } = await program.account.globalState.fetch(globalState);
+      const market = getMarketAddress(marketCount.toNumber());
+      await program.methods
+        .createMarket(
+          "Listed market",
+          ["Yes", "No"],
+          new anchor.BN(Math.floor(Date.now() / 1000) + 60),
+          new anchor.BN(1e6),
+          defaultConfig()
+        )
+        .accountsPartial({
+          market,
+          globalState,
+          externalIdEntry: null,
+          authority: user1.publicKey,
+          mint,
+          creatorTokenAccount: payFee ? user1TokenAccount : null,
+          treasury: payFee ? authorityTokenAccount : null,
+          marketTokenAccount: null,
+          residualDestination: authorityTokenAccount,
+          tokenProgram: payFee ? TOKEN_PROGRAM_ID : null,
+          solVault: null,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([user1])
+        .rpc();
+      return market;
+    };
+
+    const [feeWaiver] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("fee_waiver"), user1.publicKey.toBuffer()],
+      program.programId
+    );
+
+    await setCreationFee(1e6);
+    try {
+      try {
+        await createAsUser1(false);
+        expect.fail("listing without the fee should fail");
+      } catch (err) {
+        expect(err.error.errorCode.code).to.equal("CreationFeeRequired");
+      }
+
+      const treasuryBefore = await tokenBalance(authorityTokenAccount);
+      const paid = await createAsUser1(true);
+      expect((await tokenBalance(authorityTokenAccount)) - treasuryBefore).to.equal(1e6);
+      expect((await program.account.market.fetch(paid)).creationFeePaid.toNumber()).to.equal(1e6);
+
+      // Cancelling the listing hands the fee back to the account that paid it
+      await program.methods
+        .cancelMarket()
+        .accountsPartial({ market: paid, authority: user1.publicKey })
+        .signers([user1])
+        .rpc();
+      const creatorBefore = await tokenBalance(user1TokenAccount);
+      await program.methods
+        .refundCreationFee()
+        .accountsPartial({
+          market: paid,
+          globalState,
+          treasury: authorityTokenAccount,
+          treasuryAuthority: authority.publicKey,
+          creatorTokenAccount: user1TokenAccount,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([authority])
+        .rpc();
+      expect((await tokenBalance(user1TokenAccount)) - creatorBefore).to.equal(1e6);
+      expect((await program.account.market.fetch(paid)).creationFeePaid.toNumber()).to.equal(0);
+
+      await program.methods
+        .waiveCreationFee(user1.publicKey)
+        .accountsPartial({ globalState, feeWaiver, authority: authority.publicKey })
+        .signers([authority])
+        .rpc();
+      const waived = await createAsUser1(false);
+      expect((await program.account.market.fetch(waived)).creationFeePaid.toNumber()).to.equal(0);
+
+      await program.methods
+        .revokeFeeWaiver()
+        .accountsPartial({ globalState, feeWaiver, authority: authority.publicKey })
+        .signers([authority])
+        .rpc();
+    } finally {
+      await setCreationFee(0);
+    }
+  });
+
+  it("Pays basket bets from the payout table by legs hit", async () => {
+    const legs = [];
+    for (let i = 0; i < 3; i++) {
+      const leg = await createTestMarket(3);
+      // An empty market can't be resolved
+      await placeTestBet(user1, user1TokenAccount, leg.market, leg.vault, 0, 1e6);
+      legs.push(leg);
+    }
+    const legMarkets = legs.map(({ market }) => market);
+    const legAccounts = legMarkets.map((pubkey) => ({ pubkey, isSigner: false, isWritable: false }));
+
+    const basketId = new anchor.BN(1);
+    const [basket] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("basket"), authority.publicKey.toBuffer(), basketId.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    const [basketVault] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("basket_vault"), basket.toBuffer()],
+      program.programId
+    );
+
+    // 0 or 1 hits pay nothing, 2 hits pay 2x and 3 hits pay 5x
+    await program.methods
+      .createBasket(basketId, legMarkets, [0, 0, 20_000, 50_000])
+      .accountsPartial({
+        basket,
+        basketVault,
+        mint,
+        creator: authority.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([authority])
+      .rpc();
+
+    const basketFunds = {
+      basket,
+      basketVault,
+      creator: authority.publicKey,
+      creatorTokenAccount: authorityTokenAccount,
+      tokenProgram: TOKEN_PROGRAM_ID,
+    };
+    await program.methods
+      .fundBasket(new anchor.BN(40 * 1e6))
+      .accountsPartial(basketFunds)
+      .signers([authority])
+      .rpc();
+
+    const placeBasketBet = async (
+      bettor: anchor.web3.Keypair,
+      bettorTokenAccount: anchor.web3.PublicKey,
+      picks: number[]
+    ) => {
+      const nonce = new anchor.BN(nextBetNonce++);
+      const [basketBet] = anchor.web3.PublicKey.findProgramAddressSync(
+        [Buffer.from("basket_bet"), basket.toBuffer(), bettor.publicKey.toBuffer(), nonce.toArrayLike(Buffer, "le", 8)],
+        program.programId
+      );
+      await program.methods
+        .createBasketBet(Buffer.from(picks), new anchor.BN(3 * 1e6), nonce)
+        .accountsPartial({
+          basket,
+          basketBet,
+          basketVault,
+          bettor: bettor.publicKey,
+          bettorTokenAccount,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .remainingAccounts(legAccounts)
+        .signers([bettor])
+        .rpc();
+      return basketBet;
+    };
+    const claimBasket = (
+      bettor: anchor.web3.Keypair,
+      bettorTokenAccount: anchor.web3.PublicKey,
+      basketBet: anchor.web3.PublicKey
+    ) =>
+      program.methods
+        .claimBasket()
+        .accountsPartial({
+          basket,
+          basketBet,
+          basketVault,
+          bettor: bettor.publicKey,
+          bettorTokenAccount,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .remainingAccounts(legAccounts)
+        .signers([bettor])
+        .rpc();
+
+    try {
+      await placeBasketBet(user1, user2TokenAccount, [0, 0, 0]);
+      expect.fail("the stake must come from the bettor's own account");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("InvalidTokenAccountOwner");
+    }
+
+    const allRight = await placeBasketBet(user1, user1TokenAccount, [0, 0, 0]);
+    const oneRight = await placeBasketBet(user2, user2TokenAccount, [0, 1, 1]);
+
+    try {
+      await claimBasket(user1, user1TokenAccount, allRight);
+      expect.fail("legs are still open");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("BasketLegOpen");
+    }
+
+    // The third leg is cancelled, so a third of each stake comes back as a refund
+    await program.methods
+      .cancelMarket()
+      .accountsPartial({ market: legMarkets[2], authority: authority.publicKey })
+      .signers([authority])
+      .rpc();
+    await sleep(4000);
+    await resolveTestMarket(legMarkets[0], 0);
+    await resolveTestMarket(legMarkets[1], 0);
+
+    let before = await tokenBalance(user1TokenAccount);
+    await claimBasket(user1, user1TokenAccount, allRight);
+    expect((await tokenBalance(user1TokenAccount)) - before).to.equal(5 * 1e6);
+
+    before = await tokenBalance(user2TokenAccount);
+    await claimBasket(user2, user2TokenAccount, oneRight);
+    expect((await tokenBalance(user2TokenAccount)) - before).to.equal(1e6);
+
+    // Claiming closes the basket bet, so it can't be claimed again
+    expect(await program.account.basketBet.fetchNullable(oneRight)).to.be.null;
+    try {
+      await claimBasket(user2, user2TokenAccount, oneRight);
+      expect.fail("basket bets are claimed once");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("AccountNotInitialized");
+    }
+
+    // Nothing is locked any more, so the creator can take back everything left
+    expect((await program.account.basket.fetch(basket)).locked.toNumber()).to.equal(0);
+    expect(await tokenBalance(basketVault)).to.equal(40 * 1e6);
+    await program.methods
+      .withdrawBasketLiquidity(new anchor.BN(40 * 1e6))
+      .accountsPartial(basketFunds)
+      .signers([authority])
+      .rpc();
+    expect(await tokenBalance(basketVault)).to.equal(0);
+  });
+
+  it("Pays two bets from the same wallet on one market independently", async () => {
+    const { market, vault } = await createTestMarket(3);
+    const firstBet = await placeTestBet(user1, user1TokenAccount, market, vault, 0, 1e6);
+    const secondBet = await placeTestBet(user1, user1TokenAccount, market, vault, 0, 3 * 1e6);
+    await placeTestBet(user2, user2TokenAccount, market, vault, 1, 4 * 1e6);
+
+    // Both bets live at PDAs derived from the bettor, market and their own nonce
+    expect(firstBet.toString()).to.not.equal(secondBet.toString());
+    const first = await program.account.bet.fetch(firstBet);
+    expect(getBetAddress(market, user1.publicKey, first.nonce).toString()).to.equal(firstBet.toString());
+
+    await sleep(4000);
+    await resolveTestMarket(market, 0);
+
+    let before = await tokenBalance(user1TokenAccount);
+    await claimTestPayout(user1, user1TokenAccount, market, vault, firstBet);
+    expect((await tokenBalance(user1TokenAccount)) - before).to.equal(2 * 1e6);
+    expect((await program.account.bet.fetch(secondBet)).claimed).to.be.false;
+
+    before = await tokenBalance(user1TokenAccount);
+    await claimTestPayout(user1, user1TokenAccount, market, vault, secondBet);
+    expect((await tokenBalance(user1TokenAccount)) - before).to.equal(6 * 1e6);
+  });
+
+  it("Lets the guardian veto a resolution for the resolver to redo", async () => {
+    const { market, vault } = await createTestMarket(3, {
+      ...defaultConfig(),
+      guardian: user2.publicKey,
+      vetoWindow: new anchor.BN(5),
+      maxVetoes: 1,
+    });
+    const bet = await placeTestBet(user1, user1TokenAccount, market, vault, 0, 2 * 1e6);
+    await placeTestBet(user2, user2TokenAccount, market, vault, 1, 2 * 1e6);
+
+    const { marketId } = await program.account.market.fetch(market);
+    const [resolvedOutcome] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("resolved_outcome"), marketId.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    const veto = (guardian: anchor.web3.Keypair) =>
+      program.methods
+        .vetoResolution()
+        .accountsPartial({ market, resolvedOutcome, marketAuthority: authority.publicKey, guardian: guardian.publicKey })
+        .signers([guardian])
+        .rpc();
+
+    await sleep(4000);
+    await resolveTestMarket(market, 1);
+
+    try {
+      await veto(user1);
+      expect.fail("only the guardian can veto");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("Unauthorized");
+    }
+
+    await veto(user2);
+    const reopened = await program.account.market.fetch(market);
+    expect(reopened.resolved).to.be.false;
+    expect(reopened.winningOutcome).to.be.null;
+    expect(reopened.vetoCount).to.equal(1);
+
+    // The resolver settles it again; the guardian's single veto is spent
+    await resolveTestMarket(market, 0);
+    try {
+      await veto(user2);
+      expect.fail("veto cap reached");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("VetoLimitReached");
+    }
+
+    try {
+      await claimTestPayout(user1, user1TokenAccount, market, vault, bet);
+      expect.fail("payouts wait for the veto window");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("VetoWindowOpen");
+    }
+
+    await sleep(5000);
+    const before = await tokenBalance(user1TokenAccount);
+    await claimTestPayout(user1, user1TokenAccount, market, vault, bet);
+    expect((await tokenBalance(user1TokenAccount)) - before).to.equal(4 * 1e6);
+  });
+
+  it("Returns instruction results for CPI callers", async () => {
+    // Reads back what `set_return_data` left on the transaction, as a calling program would
+    const returnData = async (signature: string) => {
+      const tx = await provider.connection.getTransaction(signature, {
+        commitment: "confirmed",
+        maxSupportedTransactionVersion: 0,
+      });
+      expect(tx.meta.returnData.programId).to.equal(program.programId.toString());
+      return Buffer.from(tx.meta.returnData.data[0], "base64");
+    };
+
+    const { marketCount } = await program.account.globalState.fetch(globalState);
+    const market = getMarketAddress(marketCount.toNumber());
+    const vault = await createAccount(provider.connection, authority, mint, market, anchor.web3.Keypair.generate());
+    const createSig = await program.methods
+      .createMarket("Return data market", ["Yes", "No"], new anchor.BN(Math.floor(Date.now() / 1000) + 3), new anchor.BN(1e6), defaultConfig())
+      .accountsPartial({
+        market,
+        globalState,
+        externalIdEntry: null,
+        authority: authority.publicKey,
+        mint,
+        creatorTokenAccount: null,
+        treasury: null,
+        marketTokenAccount: null,
+        residualDestination: authorityTokenAccount,
+        tokenProgram: null,
+        solVault: null,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([authority])
+      .rpc({ commitment: "confirmed" });
+    const receipt = program.coder.types.decode("marketReceipt", await returnData(createSig));
+    expect(receipt.market.toString()).to.equal(market.toString());
+    expect(receipt.marketId.toNumber()).to.equal(marketCount.toNumber());
+
+    const nonce = new anchor.BN(nextBetNonce++);
+    const bet = getBetAddress(market, user1.publicKey, nonce);
+    const betSig = await program.methods
+      .placeBet(0, new anchor.BN(3 * 1e6), nonce)
+      .accountsPartial({
+        bet,
+        market,
+        bettor: user1.publicKey,
+        bettorTokenAccount: user1TokenAccount,
+        marketTokenAccount: vault,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([user1])
+      .rpc({ commitment: "confirmed" });
+    const betReceipt = program.coder.types.decode("betReceipt", await returnData(betSig));
+    expect(betReceipt.bet.toString()).to.equal(bet.toString());
+    expect(betReceipt.amount.toNumber()).to.equal(3 * 1e6);
+    await placeTestBet(user2, user2TokenAccount, market, vault, 1, 1e6);
+
+    await sleep(4000);
+    await resolveTestMarket(market, 0);
+
+    const claimSig = await program.methods
+      .claimPayout()
+      .accountsPartial({
+        bet,
+        market,
+        bettor: user1.publicKey,
+        bettorTokenAccount: user1TokenAccount,
+        marketTokenAccount: vault,
+        escrow: null,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([user1])
+      .rpc({ commitment: "confirmed" });
+    expect(Number((await returnData(claimSig)).readBigUInt64LE(0))).to.equal(4 * 1e6);
+  });
+
+  it("Fails cleanly when a stake would overflow the pool", async () => {
+    const { market, vault } = await createTestMarket(3);
+    const u64Max = new anchor.BN("18446744073709551615");
+
+    try {
+      await program.methods
+        .seedLiquidity([u64Max, new anchor.BN(1)])
+        .accountsPartial({
+          market,
+          authority: authority.publicKey,
+          authorityTokenAccount,
+          marketTokenAccount: vault,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([authority])
+        .rpc();
+      expect.fail("seed total past u64::MAX should be rejected");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("MathOverflow");
+    }
+
+    const marketAccount = await program.account.market.fetch(market);
+    expect(marketAccount.totalPool.toNumber()).to.equal(0);
+  });
+
+  it("Pays ranked bets by rank distance from the final ranking", async () => {
+    const { market, vault } = await createTestMarket(3, { ...defaultConfig(), ranked: true }, ["Ana", "Ben", "Cal"]);
+
+    const placeRanked = async (
+      bettor: anchor.web3.Keypair,
+      bettorTokenAccount: anchor.web3.PublicKey,
+      ranking: number[],
+      amount: number
+    ) => {
+      const nonce = new anchor.BN(nextBetNonce++);
+      const bet = getBetAddress(market, bettor.publicKey, nonce);
+      await program.methods
+        .placeRankedBet(Buffer.from(ranking), new anchor.BN(amount), nonce)
+        .accountsPartial({
+          bet,
+          market,
+          bettor: bettor.publicKey,
+          bettorTokenAccount,
+          marketTokenAccount: vault,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([bettor])
+        .rpc();
+      return bet;
+    };
+
+    try {
+      await placeRanked(user1, user1TokenAccount, [0, 0, 1], 1e6);
+      expect.fail("a ranking must be a permutation");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("InvalidRanking");
+    }
+    try {
+      await placeTestBet(user1, user1TokenAccount, market, vault, 0, 1e6);
+      expect.fail("ranked markets only take rankings");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("RankedMarket");
+    }
+
+    // With 3 outcomes the maximum distance is 4: exact scores 4, one swap scores 2, reversed 0
+    const exact = await placeRanked(user1, user1TokenAccount, [0, 1, 2], 1e6);
+    const reversed = await placeRanked(user2, user2TokenAccount, [2, 1, 0], 1e6);
+    const swapped = await placeRanked(user2, user2TokenAccount, [0, 2, 1], 2 * 1e6);
+
+    await sleep(4000);
+    await program.methods
+      .resolveRanked(Buffer.from([0, 1, 2]), [])
+      .accountsPartial({ market, authority: authority.publicKey })
+      .signers([authority])
+      .rpc();
+
+    const marketAccount = await program.account.market.fetch(market);
+    expect(Array.from(marketAccount.finalRanking)).to.deep.equal([0, 1, 2]);
+    expect(marketAccount.winningOutcome).to.equal(0);
+
+    // Weights are 1*4 + 2*2 = 8, so each scoring bet takes half of the 4 token pool
+    const user1Before = await tokenBalance(user1TokenAccount);
+    await claimTestPayout(user1, user1TokenAccount, market, vault, exact);
+    expect((await tokenBalance(user1TokenAccount)) - user1Before).to.equal(2 * 1e6);
+
+    const user2Before = await tokenBalance(user2TokenAccount);
+    await claimTestPayout(user2, user2TokenAccount, market, vault, swapped);
+    expect((await tokenBalance(user2TokenAccount)) - user2Before).to.equal(2 * 1e6);
+
+    try {
+      await claimTestPayout(user2, user2TokenAccount, market, vault, reversed);
+      expect.fail("a fully reversed ranking scores nothing");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("LosingBet");
+    }
+  });
+
+  it("Rejects bets and claims against a vault the market doesn't hold", async () => {
+    const { market, vault } = await createTestMarket(3);
+    const foreignVault = await createAccount(provider.connection, user2, mint, user2.publicKey, anchor.web3.Keypair.generate());
+
+    try {
+      await placeTestBet(user1, user1TokenAccount, market, foreignVault, 0, 1e6);
+      expect.fail("stake must land in the market's vault");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("InvalidMarketVault");
+    }
+
+    try {
+      await placeTestBet(user1, user2TokenAccount, market, vault, 0, 1e6);
+      expect.fail("bettor must own the source token account");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("InvalidTokenAccountOwner");
+    }
+
+    const bet = await placeTestBet(user1, user1TokenAccount, market, vault, 0, 1e6);
+    await placeTestBet(user2, user2TokenAccount, market, vault, 1, 1e6);
+    await sleep(4000);
+    await resolveTestMarket(market, 0);
+
+    try {
+      await claimTestPayout(user1, user1TokenAccount, market, foreignVault, bet);
+      expect.fail("payouts must come from the market's vault");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("InvalidMarketVault");
+    }
+    await claimTestPayout(user1, user1TokenAccount, market, vault, bet);
+  });
+
+  it("Closes a claimed bet and returns its rent to the bettor", async () => {
+    const { market, vault } = await createTestMarket(3);
+    const bet = await placeTestBet(user1, user1TokenAccount, market, vault, 0, 1e6);
+    await placeTestBet(user2, user2TokenAccount, market, vault, 1, 1e6);
+
+    await sleep(4000);
+    await resolveTestMarket(market, 0);
+
+    const rent = await provider.connection.getBalance(bet);
+    expect(rent).to.be.greaterThan(0);
+    // The provider wallet pays the fee, so the bettor's lamports change by the rent alone
+    const before = await provider.connection.getBalance(user1.publicKey);
+    await claimTestPayout(user1, user1TokenAccount, market, vault, bet);
+    expect((await provider.connection.getBalance(user1.publicKey)) - before).to.equal(rent);
+    expect(await program.account.bet.fetchNullable(bet)).to.be.null;
+
+    try {
+      await claimTestPayout(user1, user1TokenAccount, market, vault, bet);
+      expect.fail("a closed bet can't be claimed again");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("AccountNotInitialized");
+    }
+  });
+
+  it("Resolves through a proposal that stands for the challenge period", async () => {
+    const { market, vault } = await createTestMarket(3, { ...defaultConfig(), challengePeriod: new anchor.BN(4) });
+    const bet = await placeTestBet(user1, user1TokenAccount, market, vault, 0, 1e6);
+    await placeTestBet(user2, user2TokenAccount, market, vault, 1, 1e6);
+
+    const propose = (outcomeIndex: number) =>
+      program.methods
+        .proposeResolution(outcomeIndex, [])
+        .accountsPartial({ market, authority: authority.publicKey })
+        .signers([authority])
+        .rpc();
+    const finalize = () =>
+      program.methods
+        .finalizeResolution()
+        .accountsPartial({ market, caller: user2.publicKey })
+        .signers([user2])
+        .rpc();
+
+    await sleep(4000);
+    try {
+      await resolveTestMarket(market, 0);
+      expect.fail("challenge markets resolve by proposal");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("ProposalRequired");
+    }
+    try {
+      await finalize();
+      expect.fail("nothing proposed yet");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("NoResolutionProposed");
+    }
+
+    // The authority corrects its proposal inside the window, restarting it
+    await propose(1);
+    await propose(0);
+    let marketAccount = await program.account.market.fetch(market);
+    expect(marketAccount.proposedOutcome).to.equal(0);
+    expect(marketAccount.resolved).to.be.false;
+
+    try {
+      await finalize();
+      expect.fail("challenge period still running");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("ChallengePeriodActive");
+    }
+    try {
+      await claimTestPayout(user1, user1TokenAccount, market, vault, bet);
+      expect.fail("a proposal pays nothing");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("MarketNotResolved");
+    }
+
+    await sleep(5000);
+    await finalize();
+    marketAccount = await program.account.market.fetch(market);
+    expect(marketAccount.resolved).to.be.true;
+    expect(marketAccount.winningOutcome).to.equal(0);
+
+    const before = await tokenBalance(user1TokenAccount);
+    await claimTestPayout(user1, user1TokenAccount, market, vault, bet);
+    expect((await tokenBalance(user1TokenAccount)) - before).to.equal(2 * 1e6);
+  });
+
+  it("Settles a native SOL market the same as its SPL twin", async () => {
+    const { market: splMarket, vault } = await createTestMarket(3);
+    const { market: solMarket } = await createTestMarket(3, { ...defaultConfig(), isNative: true });
+    const solVault = getSolVault(solMarket);
+    const vaultRent = await provider.connection.getMinimumBalanceForRentExemption(0);
+    expect(await provider.connection.getBalance(solVault)).to.equal(vaultRent);
+
+    const placeSolBet = async (bettor: anchor.web3.Keypair, outcomeIndex: number, lamports: number) => {
+      const nonce = new anchor.BN(nextBetNonce++);
+      const bet = getBetAddress(solMarket, bettor.publicKey, nonce);
+      await program.methods
+        .placeBetSol(outcomeIndex, new anchor.BN(lamports), nonce)
+        .accountsPartial({
+          bet,
+          market: solMarket,
+          bettor: bettor.publicKey,
+          solVault,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([bettor])
+        .rpc();
+      return bet;
+    };
+
+    try {
+      await placeTestBet(user1, user1TokenAccount, solMarket, vault, 0, 1e6);
+      expect.fail("token bets don't go into a native market");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("NativeMarket");
+    }
+
+    const splBet = await placeTestBet(user1, user1TokenAccount, splMarket, vault, 0, 2 * 1e6);
+    await placeTestBet(user2, user2TokenAccount, splMarket, vault, 1, 2 * 1e6);
+    const solBet = await placeSolBet(user1, 0, 2 * 1e6);
+    await placeSolBet(user2, 1, 2 * 1e6);
+    expect(await provider.connection.getBalance(solVault)).to.equal(vaultRent + 4 * 1e6);
+
+    await sleep(4000);
+    await resolveTestMarket(splMarket, 0);
+    await resolveTestMarket(solMarket, 0);
+
+    const tokensBefore = await tokenBalance(user1TokenAccount);
+    await claimTestPayout(user1, user1TokenAccount, splMarket, vault, splBet);
+    expect((await tokenBalance(user1TokenAccount)) - tokensBefore).to.equal(4 * 1e6);
+
+    // The bettor also gets the closed bet's rent back; the vault keeps only its own rent
+    const betRent = await provider.connection.getBalance(solBet);
+    const lamportsBefore = await provider.connection.getBalance(user1.publicKey);
+    await program.methods
+      .claimPayoutSol()
+      .accountsPartial({
+        bet: solBet,
+        market: solMarket,
+        bettor: user1.publicKey,
+        solVault,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([user1])
+      .rpc();
+    expect((await provider.connection.getBalance(user1.publicKey)) - lamportsBefore).to.equal(4 * 1e6 + betRent);
+    expect(await provider.connection.getBalance(solVault)).to.equal(vaultRent);
+  });
+
+  it("Rotates the platform and market authorities", async () => {
+    const setFee = (signer: anchor.web3.Keypair) =>
+      program.methods
+        .setFee(0)
+        .accountsPartial({ globalState, authority: signer.publicKey })
+        .signers([signer])
+        .rpc();
+    const setAuthority = (signer: anchor.web3.Keypair, newAuthority: anchor.web3.PublicKey) =>
+      program.methods
+        .setAuthority(newAuthority)
+        .accountsPartial({ globalState, authority: signer.publicKey })
+        .signers([signer])
+        .rpc();
+
+    await setAuthority(authority, user2.publicKey);
+    try {
+      try {
+        await setFee(authority);
+        expect.fail("the old platform authority is locked out");
+      } catch (err) {
+        expect(err.error.errorCode.code).to.equal("Unauthorized");
+      }
+      await setFee(user2);
+    } finally {
+      await setAuthority(user2, authority.publicKey);
+    }
+
+    const { market } = await createTestMarket(3600);
+    await program.methods
+      .transferMarketAuthority(user1.publicKey)
+      .accountsPartial({ market, authority: authority.publicKey })
+      .signers([authority])
+      .rpc();
+    expect((await program.account.market.fetch(market)).authority.toString()).to.equal(user1.publicKey.toString());
+
+    const cancel = (signer: anchor.web3.Keypair) =>
+      program.methods
+        .cancelMarket()
+        .accountsPartial({ market, authority: signer.publicKey })
+        .signers([signer])
+        .rpc();
+    try {
+      await cancel(authority);
+      expect.fail("the old market authority is locked out");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("Unauthorized");
+    }
+    await cancel(user1);
+    expect((await program.account.market.fetch(market)).cancelled).to.be.true;
+  });
+
+  it("Blocks new markets and bets while paused but keeps claims open", async () => {
+    const { market, vault } = await createTestMarket(2);
+    const bet = await placeTestBet(user1, user1TokenAccount, market, vault, 0, 1e6);
+
+    await program.methods
+      .pause()
+      .accountsPartial({ globalState, authority: authority.publicKey })
+      .signers([authority])
+      .rpc();
+    try {
+      expect((await program.account.globalState.fetch(globalState)).paused).to.be.true;
+      try {
+        await placeTestBet(user2, user2TokenAccount, market, vault, 1, 1e6);
+        expect.fail("betting is halted while paused");
+      } catch (err) {
+        expect(err.error.errorCode.code).to.equal("PlatformPaused");
+      }
+      try {
+        await createTestMarket(3600);
+        expect.fail("market creation is halted while paused");
+      } catch (err) {
+        expect(err.error.errorCode.code).to.equal("PlatformPaused");
+      }
+
+      await sleep(3000);
+      await resolveTestMarket(market, 0);
+      const before = await tokenBalance(user1TokenAccount);
+      await claimTestPayout(user1, user1TokenAccount, market, vault, bet);
+      expect((await tokenBalance(user1TokenAccount)) - before).to.equal(1e6);
+    } finally {
+      await program.methods
+        .unpause()
+        .accountsPartial({ globalState, authority: authority.publicKey })
+        .signers([authority])
+        .rpc();
+    }
+    expect((await program.account.globalState.fetch(globalState)).paused).to.be.false;
+  });
+
+  it("Closes betting at the cutoff ahead of resolution_time", async () => {
+    const { market, vault } = await createTestMarket(6, { ...defaultConfig(), bettingCutoffOffset: new anchor.BN(4) });
+    const marketAccount = await program.account.market.fetch(market);
+    expect(marketAccount.bettingCutoff.toNumber()).to.equal(marketAccount.resolutionTime.toNumber() - 4);
+
+    await placeTestBet(user1, user1TokenAccount, market, vault, 0, 1e6);
+
+    // Past the cutoff but still short of resolution_time
+    await sleep(3000);
+    try {
+      await placeTestBet(user2, user2TokenAccount, market, vault, 1, 1e6);
+      expect.fail("bets inside the cutoff window are rejected");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("BettingCutoffPassed");
+    }
+    try {
+      await resolveTestMarket(market, 0);
+      expect.fail("resolution still waits for resolution_time");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("TooEarlyToResolve");
+    }
+  });
+
+  it("Pays the creator fee on top of the protocol fee", async () => {
+    try {
+      await createTestMarket(3, { ...defaultConfig(), creatorFeeBps: 1001 });
+      expect.fail("creator fee above 10% is rejected");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("InvalidCreatorFee");
+    }
+
+    const setFee = (feeBps: number) =>
+      program.methods
+        .setFee(feeBps)
+        .accountsPartial({ globalState, authority: authority.publicKey })
+        .signers([authority])
+        .rpc();
+
+    await setFee(100);
+    try {
+      const { market, vault } = await createTestMarket(3, { ...defaultConfig(), creatorFeeBps: 500 });
+      const user1Bet = await placeTestBet(user1, user1TokenAccount, market, vault, 0, 2 * 1e6);
+      const user2Bet = await placeTestBet(user2, user2TokenAccount, market, vault, 0, 1e6);
+      await placeTestBet(user2, user2TokenAccount, market, vault, 1, 1e6);
+
+      await sleep(4000);
+      await resolveTestMarket(market, 0);
+
+      const user1Before = await tokenBalance(user1TokenAccount);
+      const user2Before = await tokenBalance(user2TokenAccount);
+      await claimTestPayout(user1, user1TokenAccount, market, vault, user1Bet);
+      await claimTestPayout(user2, user2TokenAccount, market, vault, user2Bet);
+      const user1Net = (await tokenBalance(user1TokenAccount)) - user1Before;
+      const user2Net = (await tokenBalance(user2TokenAccount)) - user2Before;
+
+      // 2.666666 gross: 1% protocol fee first, then 5% of the remaining 2.64
+      expect(user1Net).to.equal(2_666_666 - 26_666 - 132_000);
+
+      const marketAccount = await program.account.market.fetch(market);
+      const protocolFees = marketAccount.feesAccrued.toNumber();
+      const creatorFees = marketAccount.creatorFeesAccrued.toNumber();
+      const distributed = user1Net + user2Net + protocolFees + creatorFees;
+      // Each winner's pro-rata share rounds down by under one unit
+      expect(distributed).to.be.at.most(marketAccount.totalPool.toNumber());
+      expect(distributed).to.be.at.least(marketAccount.totalPool.toNumber() - 2);
+
+      try {
+        await program.methods
+          .withdrawCreatorFees()
+          .accountsPartial({
+            market,
+            authority: user1.publicKey,
+            marketTokenAccount: vault,
+            destination: user1TokenAccount,
+            tokenProgram: TOKEN_PROGRAM_ID,
+          })
+          .signers([user1])
+          .rpc();
+        expect.fail("only the market authority collects creator fees");
+      } catch (err) {
+        expect(err.error.errorCode.code).to.equal("Unauthorized");
+      }
+
+      const authorityBefore = await tokenBalance(authorityTokenAccount);
+      await program.methods
+        .withdrawCreatorFees()
+        .accountsPartial({
+          market,
+          authority: authority.publicKey,
+          marketTokenAccount: vault,
+          destination: authorityTokenAccount,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([authority])
+        .rpc();
+      expect((await tokenBalance(authorityTokenAccount)) - authorityBefore).to.equal(creatorFees);
+      expect((await program.account.market.fetch(market)).creatorFeesAccrued.toNumber()).to.equal(0);
+      expect(await tokenBalance(vault)).to.equal(marketAccount.totalPool.toNumber() - distributed + protocolFees);
+    } finally {
+      await setFee(0);
+    }
+  });
+
+  it("Claims several winning bets in one batch", async () => {
+    const { market, vault } = await createTestMarket(3);
+    const bets = [];
+    for (let i = 0; i < 5; i++) {
+      bets.push(await placeTestBet(user1, user1TokenAccount, market, vault, 0, 1e6));
+    }
+    await placeTestBet(user2, user2TokenAccount, market, vault, 1, 2 * 1e6);
+
+    await sleep(4000);
+    await resolveTestMarket(market, 0);
+
+    const claimBatch = (batch: anchor.web3.PublicKey[]) =>
+      program.methods
+        .claimPayoutsBatch()
+        .accountsPartial({
+          market,
+          bettor: user1.publicKey,
+          bettorTokenAccount: user1TokenAccount,
+          marketTokenAccount: vault,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .remainingAccounts(batch.map((pubkey) => ({ pubkey, isSigner: false, isWritable: true })))
+        .signers([user1])
+        .rpc();
+
+    const before = await tokenBalance(user1TokenAccount);
+    await claimBatch(bets);
+    expect((await tokenBalance(user1TokenAccount)) - before).to.equal(7 * 1e6);
+    for (const bet of bets) {
+      expect(await provider.connection.getAccountInfo(bet)).to.be.null;
+    }
+
+    // Resending part of the batch skips the closed bets instead of failing
+    await claimBatch(bets.slice(0, 3));
+    expect((await tokenBalance(user1TokenAccount)) - before).to.equal(7 * 1e6);
+  });
+
+  it("Carries post-bet pools in BetPlaced events", async () => {
+    const { market, vault } = await createTestMarket(3600);
+    await placeTestBet(user1, user1TokenAccount, market, vault, 0, 3 * 1e6);
+
+    const nonce = new anchor.BN(nextBetNonce++);
+    const signature = await program.methods
+      .placeBet(1, new anchor.BN(1e6), nonce)
+      .accountsPartial({
+        bet: getBetAddress(market, user2.publicKey, nonce),
+        market,
+        bettor: user2.publicKey,
+        bettorTokenAccount: user2TokenAccount,
+        marketTokenAccount: vault,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([user2])
+      .rpc({ commitment: "confirmed" });
+
+    const tx = await provider.connection.getTransaction(signature, {
+      commitment: "confirmed",
+      maxSupportedTransactionVersion: 0,
+    });
+    const parser = new anchor.EventParser(program.programId, program.coder);
+    const events = [...parser.parseLogs(tx.meta.logMessages)];
+    const betPlaced = events.find((event) => event.name === "betPlaced");
+    expect(betPlaced.data.outcomePools.map((pool) => pool.toNumber())).to.deep.equal([3 * 1e6, 1e6]);
+    expect(betPlaced.data.totalPool.toNumber()).to.equal(4 * 1e6);
+  });
+
+  it("Rejects stakes in a token other than the market's mint", async () => {
+    const { market, vault } = await createTestMarket(3600);
+    expect((await program.account.market.fetch(market)).mint.toString()).to.equal(mint.toString());
+
+    const otherMint = await createMint(provider.connection, authority, authority.publicKey, null, 6);
+    const otherTokenAccount = await createAccount(provider.connection, user1, otherMint, user1.publicKey);
+    await mintTo(provider.connection, authority, otherMint, otherTokenAccount, authority, 10 * 1e6);
+    const otherVault = await createAccount(
+      provider.connection,
+      authority,
+      otherMint,
+      market,
+      anchor.web3.Keypair.generate()
+    );
+
+    try {
+      await placeTestBet(user1, otherTokenAccount, market, otherVault, 0, 1e6);
+      expect.fail("a bet in another token is rejected");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("MintMismatch");
+    }
+    expect((await program.account.market.fetch(market)).totalPool.toNumber()).to.equal(0);
+  });
+
+  it("Quotes the payout a claim later pays", async () => {
+    const { market, vault } = await createTestMarket(3, { ...defaultConfig(), creatorFeeBps: 500 });
+    await placeTestBet(user2, user2TokenAccount, market, vault, 1, 3 * 1e6);
+
+    const quote = await program.methods
+      .quotePayout(0, new anchor.BN(1e6))
+      .accountsPartial({ market })
+      .view();
+    // 1 token joins a 3 token pool and takes all 4, less the 5% creator fee
+    expect(quote.toNumber()).to.equal(3.8 * 1e6);
+
+    const bet = await placeTestBet(user1, user1TokenAccount, market, vault, 0, 1e6);
+    await sleep(4000);
+    await resolveTestMarket(market, 0);
+
+    const before = await tokenBalance(user1TokenAccount);
+    await claimTestPayout(user1, user1TokenAccount, market, vault, bet);
+    expect((await tokenBalance(user1TokenAccount)) - before).to.equal(quote.toNumber());
+  });
+
+  it("Funds initial liquidity when the market is created", async () => {
+    const authorityBefore = await tokenBalance(authorityTokenAccount);
+    const { market, vault } = await createTestMarket(3, {
+      ...defaultConfig(),
+      initialLiquidity: [new anchor.BN(1e6), new anchor.BN(1e6)],
+    });
+    expect(authorityBefore - (await tokenBalance(authorityTokenAccount))).to.equal(2 * 1e6);
+    expect(await tokenBalance(vault)).to.equal(2 * 1e6);
+    let marketAccount = await program.account.market.fetch(market);
+    expect(marketAccount.outcomePools.map((p) => p.toNumber())).to.deep.equal([1e6, 1e6]);
+    expect(marketAccount.seedAmounts.map((p) => p.toNumber())).to.deep.equal([1e6, 1e6]);
+
+    // Without the seed a lone first bettor could only ever win back their stake
+    const bet = await placeTestBet(user1, user1TokenAccount, market, vault, 0, 1e6);
+    await sleep(4000);
+    await resolveTestMarket(market, 0);
+
+    const user1Before = await tokenBalance(user1TokenAccount);
+    await claimTestPayout(user1, user1TokenAccount, market, vault, bet);
+    expect((await tokenBalance(user1TokenAccount)) - user1Before).to.equal(1.5 * 1e6);
+
+    const reclaimBefore = await tokenBalance(authorityTokenAccount);
+    await program.methods
+      .reclaimSeed()
+      .accountsPartial({
+        market,
+        authority: authority.publicKey,
+        authorityTokenAccount,
+        marketTokenAccount: vault,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([authority])
+      .rpc();
+    expect((await tokenBalance(authorityTokenAccount)) - reclaimBefore).to.equal(1.5 * 1e6);
+    expect(await tokenBalance(vault)).to.equal(0);
+  });
+
+  it("Counts bets per outcome and in total", async () => {
+    const { market, vault } = await createTestMarket(3600, defaultConfig(), ["A", "B", "C"]);
+    await placeTestBet(user1, user1TokenAccount, market, vault, 0, 1e6);
+    await placeTestBet(user1, user1TokenAccount, market, vault, 0, 1e6);
+    await placeTestBet(user2, user2TokenAccount, market, vault, 0, 1e6);
+    await placeTestBet(user2, user2TokenAccount, market, vault, 2, 1e6);
+
+    // Each bet account counts, including a second bet from the same wallet
+    const marketAccount = await program.account.market.fetch(market);
+    expect(marketAccount.betCounts).to.deep.equal([3, 0, 1]);
+    expect(marketAccount.totalBets.toNumber()).to.equal(4);
+  });
+
+  it("Sweeps rounding dust once every winner has claimed", async () => {
+    const { market, vault } = await createTestMarket(3, {
+      ...defaultConfig(),
+      residualDestination: authorityTokenAccount,
+    });
+    const winners = [
+      await placeTestBet(user1, user1TokenAccount, market, vault, 0, 1e6),
+      await placeTestBet(user1, user1TokenAccount, market, vault, 0, 1e6),
+    ];
+    const user2Winner = await placeTestBet(user2, user2TokenAccount, market, vault, 0, 1e6);
+    await placeTestBet(user2, user2TokenAccount, market, vault, 1, 1e6 + 1);
+
+    await sleep(4000);
+    await resolveTestMarket(market, 0);
+
+    const sweep = () =>
+      program.methods
+        .sweepDust()
+        .accountsPartial({
+          market,
+          authority: authority.publicKey,
+          marketTokenAccount: vault,
+          destination: authorityTokenAccount,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([authority])
+        .rpc();
+
+    // Each of the three winners takes floor(1 * 4.000001 / 3) = 1.333333
+    for (const bet of winners) {
+      await claimTestPayout(user1, user1TokenAccount, market, vault, bet);
+    }
+    try {
+      await sweep();
+      expect.fail("dust can't be swept while a winner is unpaid");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("ClaimsOutstanding");
+    }
+    await claimTestPayout(user2, user2TokenAccount, market, vault, user2Winner);
+    expect(await tokenBalance(vault)).to.equal(2);
+
+    // Dust only goes to the market's residual destination
+    try {
+      await program.methods
+        .sweepDust()
+        .accountsPartial({
+          market,
+          authority: authority.publicKey,
+          marketTokenAccount: vault,
+          destination: user2TokenAccount,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([authority])
+        .rpc();
+      expect.fail("dust must go to the residual destination");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("InvalidResidualDestination");
+    }
+
+    const { events } = await program.methods
+      .sweepDust()
+      .accountsPartial({
+        market,
+        authority: authority.publicKey,
+        marketTokenAccount: vault,
+        destination: authorityTokenAccount,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([authority])
+      .simulate();
+    const donated = events.find((e) => e.name === "residualDonated").data;
+    expect(donated.amount.toNumber()).to.equal(2);
+    expect(donated.destination.toBase58()).to.equal(authorityTokenAccount.toBase58());
+
+    const before = await tokenBalance(authorityTokenAccount);
+    await sweep();
+    expect((await tokenBalance(authorityTokenAccount)) - before).to.equal(2);
+    expect(await tokenBalance(vault)).to.equal(0);
+    expect((await program.account.market.fetch(market)).dustSwept.toNumber()).to.equal(2);
+  });
+
+  it("Stops charging the protocol fee once a market's fee cap is reached", async () => {
+    const setFee = (feeBps: number) =>
+      program.methods
+        .setFee(feeBps)
+        .accountsPartial({ globalState, authority: authority.publicKey })
+        .signers([authority])
+        .rpc();
+
+    await setFee(1000);
+    try {
+      const { market, vault } = await createTestMarket(2, { ...defaultConfig(), feeCap: new anchor.BN(0.3e6) });
+      const winners = [
+        await placeTestBet(user1, user1TokenAccount, market, vault, 0, 1e6),
+        await placeTestBet(user1, user1TokenAccount, market, vault, 0, 1e6),
+        await placeTestBet(user1, user1TokenAccount, market, vault, 0, 1e6),
+      ];
+      await placeTestBet(user2, user2TokenAccount, market, vault, 1, 3e6);
+      await sleep(3000);
+      await resolveTestMarket(market, 0);
+
+      // Each 2 token payout owes a 0.2 token fee; the second only has 0.1 of room left
+      const payouts: number[] = [];
+      for (const bet of winners) {
+        if (payouts.length === 1) {
+          const { events } = await program.methods
+            .claimPayout()
+            .accountsPartial({
+              bet,
+              market,
+              bettor: user1.publicKey,
+              bettorTokenAccount: user1TokenAccount,
+              marketTokenAccount: vault,
+              escrow: null,
+              tokenProgram: TOKEN_PROGRAM_ID,
+              systemProgram: anchor.web3.SystemProgram.programId,
+            })
+            .signers([user1])
+            .simulate();
+          expect(events.find((e) => e.name === "feeCapReached").data.feeCap.toNumber()).to.equal(0.3e6);
+        }
+        const before = await tokenBalance(user1TokenAccount);
+        await claimTestPayout(user1, user1TokenAccount, market, vault, bet);
+        payouts.push((await tokenBalance(user1TokenAccount)) - before);
+      }
+      expect(payouts).to.deep.equal([1.8e6, 1.9e6, 2e6]);
+      expect((await program.account.market.fetch(market)).feesCollected.toNumber()).to.equal(0.3e6);
+    } finally {
+      await setFee(0);
+    }
+  });
+
+  it("Resolves a pivot market on the time-weighted average of sampled prices", async () => {
+    // SPL token accounts store their amount at byte 64; minting to one moves the "price"
+    const priceFeed = await createAccount(provider.connection, authority, mint, anchor.web3.Keypair.generate().publicKey);
+    await mintTo(provider.connection, authority, mint, priceFeed, authority, 100);
+    const twapConfig = {
+      ...defaultConfig(),
+      marketType: { pivot: {} },
+      pivotValue: new anchor.BN(150),
+      twap: { source: priceFeed, offset: 64, window: new anchor.BN(14), minSpacing: new anchor.BN(3), minSamples: 2 },
+    };
+
+    try {
+      await createTestMarket(3600, { ...twapConfig, twap: { ...twapConfig.twap, minSamples: 1 } });
+      expect.fail("one sample is no average");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("InvalidTwapConfig");
+    }
+
+    const { market, vault } = await createTestMarket(12, twapConfig);
+    const { market: sparse } = await createTestMarket(12, twapConfig);
+    await placeTestBet(user1, user1TokenAccount, market, vault, 0, 1e6);
+    await placeTestBet(user2, user2TokenAccount, market, vault, 1, 1e6);
+
+    const sample = (target: anchor.web3.PublicKey) =>
+      program.methods.sampleTwap().accountsPartial({ market: target, sourceAccount: priceFeed }).rpc();
+    const resolveFromTwap = (target: anchor.web3.PublicKey) =>
+      program.methods
+        .resolveFromTwap()
+        .accountsPartial({ market: target, caller: user2.publicKey })
+        .signers([user2])
+        .rpc({ commitment: "confirmed" });
+
+    await sample(market);
+    await sample(sparse);
+    // The price jumps to 400 for the rest of the window
+    await mintTo(provider.connection, authority, mint, priceFeed, authority, 300);
+    try {
+      await sample(market);
+      expect.fail("samples must be three seconds apart");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("TwapSampleTooSoon");
+    }
+    await sleep(3500);
+    await sample(market);
+    await sleep(7000);
+
+    try {
+      await sample(market);
+      expect.fail("sampling closes at resolution_time");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("OutsideTwapWindow");
+    }
+    try {
+      await program.methods
+        .resolvePivotMarket(new anchor.BN(100))
+        .accountsPartial({ market, authority: authority.publicKey })
+        .signers([authority])
+        .rpc();
+      expect.fail("the average decides, not a reported value");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("TwapResolutionOnly");
+    }
+    try {
+      await resolveFromTwap(sparse);
+      expect.fail("one sample is below the minimum");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("NotEnoughTwapSamples");
+    }
+
+    const signature = await resolveFromTwap(market);
+    const tx = await provider.connection.getTransaction(signature, {
+      commitment: "confirmed",
+      maxSupportedTransactionVersion: 0,
+    });
+    const parser = new anchor.EventParser(program.programId, program.coder);
+    const resolved = [...parser.parseLogs(tx.meta.logMessages)].find((event) => event.name === "twapResolved");
+    // Weighted by how long each stood, the average lands strictly between the two prices
+    expect(resolved.data.samples).to.equal(2);
+    expect(resolved.data.twap.toNumber()).to.be.greaterThan(150).and.lessThan(400);
+    expect((await program.account.market.fetch(market)).winningOutcome).to.equal(0);
+  });
+
+  it("Snapshots a cheaper platform fee share on markets resolved promptly", async () => {
+    // Free within 5 seconds of resolution_time, half price within the hour, full after that
+    const tieredConfig = {
+      ...defaultConfig(),
+      promptFeeTiers: [
+        { within: new anchor.BN(5), feeShareBps: 0 },
+        { within: new anchor.BN(3600), feeShareBps: 5000 },
+      ],
+    };
+
+    try {
+      await createTestMarket(3600, { ...tieredConfig, promptFeeTiers: [...tieredConfig.promptFeeTiers].reverse() });
+      expect.fail("tiers must widen");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("InvalidFeeTiers");
+    }
+
+    const { market: prompt } = await createTestMarket(2, tieredConfig);
+    const { market: late } = await createTestMarket(2, tieredConfig);
+    const { market: untiered } = await createTestMarket(2);
+    await sleep(3000);
+
+    const signature = await program.methods
+      .resolveMarket(0)
+      .accountsPartial({ market: prompt, authority: authority.publicKey })
+      .signers([authority])
+      .rpc({ commitment: "confirmed" });
+    const tx = await provider.connection.getTransaction(signature, {
+      commitment: "confirmed",
+      maxSupportedTransactionVersion: 0,
+    });
+    const parser = new anchor.EventParser(program.programId, program.coder);
+    const applied = [...parser.parseLogs(tx.meta.logMessages)].find((event) => event.name === "resolutionFeeTier");
+    expect(applied.data.tier).to.equal(0);
+    expect(applied.data.feeShareBps).to.equal(0);
+    expect((await program.account.market.fetch(prompt)).platformFeeShareBps).to.equal(0);
+
+    await sleep(6000);
+    await resolveTestMarket(late, 0);
+    await resolveTestMarket(untiered, 0);
+    expect((await program.account.market.fetch(late)).platformFeeShareBps).to.equal(5000);
+    expect((await program.account.market.fetch(untiered)).platformFeeShareBps).to.equal(10000);
+  });
+
+  it("Decays the platform fee share the later a market resolves", async () => {
+    const decayConfig = { ...defaultConfig(), feeDecayPeriod: new anchor.BN(8) };
+
+    try {
+      await createTestMarket(3600, { ...decayConfig, feeDecayPeriod: new anchor.BN(-1) });
+      expect.fail("the decay period can't be negative");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("InvalidFeeDecay");
+    }
+
+    const { market: early } = await createTestMarket(2, decayConfig);
+    const { market: late } = await createTestMarket(2, decayConfig);
+    await sleep(3000);
+
+    // A few seconds late keeps most of the fee; past the 8 second period none is left
+    await resolveTestMarket(early, 0);
+    const earlyShare = (await program.account.market.fetch(early)).platformFeeShareBps;
+    expect(earlyShare).to.be.greaterThan(0).and.lessThan(10_000);
+    await sleep(8000);
+    await resolveTestMarket(late, 0);
+    expect((await program.account.market.fetch(late)).platformFeeShareBps).to.equal(0);
+  });
+
+  it("Stakes vault receipt tokens and values them at the vault's exchange rate", async () => {
+    const receiptMint = await createMint(provider.connection, authority, authority.publicKey, null, 6);
+    // A token account's balance stands in for the vault's published rate, read at byte 64
+    const rateMint = await createMint(provider.connection, authority, authority.publicKey, null, 0);
+    const rateSource = await createAccount(provider.connection, authority, rateMint, authority.publicKey);
+    await mintTo(provider.connection, authority, rateMint, rateSource, authority, 1e9);
+    const getReceiptMintEntry = (receiptMintKey: anchor.web3.PublicKey) =>
+      anchor.web3.PublicKey.findProgramAddressSync(
+        [Buffer.from("receipt_mint"), receiptMintKey.toBuffer()],
+        program.programId
+      )[0];
+
+    await program.methods
+      .registerReceiptMint(64)
+      .accountsPartial({
+        receiptMintEntry: getReceiptMintEntry(receiptMint),
+        mint: receiptMint,
+        rateSource,
+        globalState,
+        authority: authority.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([authority])
+      .rpc();
+
+    const enableReceiptBetting = (market: anchor.web3.PublicKey, receiptMintEntry: anchor.web3.PublicKey) =>
+      program.methods
+        .enableReceiptBetting()
+        .accountsPartial({ market, receiptMintEntry, authority: authority.publicKey })
+        .signers([authority])
+        .rpc();
+
+    const { market: plainMarket, vault: plainVault } = await createTestMarket(3600);
+    try {
+      await enableReceiptBetting(plainMarket, getReceiptMintEntry(mint));
+      expect.fail("the plain mint is not whitelisted");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("AccountNotInitialized");
+    }
+
+    const user1Receipts = await createAccount(provider.connection, user1, receiptMint, user1.publicKey);
+    const user2Receipts = await createAccount(provider.connection, user2, receiptMint, user2.publicKey);
+    const resolvesAt = Date.now() + 15000;
+    // The treasury doesn't hold receipts, so user2's receipt account stands in for a charity's
+    const { market, vault } = await createTestMarket(
+      15,
+      { ...defaultConfig(), residualDestination: user2Receipts },
+      ["Yes", "No"],
+      "Test market",
+      receiptMint
+    );
+    await enableReceiptBetting(market, getReceiptMintEntry(receiptMint));
+
+    await mintTo(provider.connection, authority, receiptMint, user1Receipts, authority, 10e6);
+    await mintTo(provider.connection, authority, receiptMint, user2Receipts, authority, 10e6);
+
+    try {
+      await placeTestBet(user1, user1Receipts, market, vault, 0, 2e6);
+      expect.fail("no rate has been read yet");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("StaleReceiptRate");
+    }
+
+    const updateRate = () =>
+      program.methods.updateReceiptRate().accountsPartial({ market, rateSource }).rpc();
+    await updateRate();
+
+    try {
+      await placeTestBet(user1, user1TokenAccount, market, plainVault, 0, 2e6);
+      expect.fail("the market only takes its receipt token");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("MintMismatch");
+    }
+
+    const bet = await placeTestBet(user1, user1Receipts, market, vault, 0, 2e6);
+    expect((await program.account.bet.fetch(bet)).underlyingAmount.toNumber()).to.equal(2e6);
+
+    // The vault earns 10% before the second bet lands
+    await mintTo(provider.connection, authority, rateMint, rateSource, authority, 0.1e9);
+    await updateRate();
+    const secondBet = await placeTestBet(user2, user2Receipts, market, vault, 1, 2e6);
+    expect((await program.account.bet.fetch(secondBet)).underlyingAmount.toNumber()).to.equal(2.2e6);
+
+    await sleep(Math.max(0, resolvesAt - Date.now()) + 1000);
+    await resolveTestMarket(market, 0);
+
+    const signature = await program.methods
+      .claimPayout()
+      .accountsPartial({
+        bet,
+        market,
+        bettor: user1.publicKey,
+        bettorTokenAccount: user1Receipts,
+        marketTokenAccount: vault,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([user1])
+      .rpc({ commitment: "confirmed" });
+    const tx = await provider.connection.getTransaction(signature, {
+      commitment: "confirmed",
+      maxSupportedTransactionVersion: 0,
+    });
+    const parser = new anchor.EventParser(program.programId, program.coder);
+    const valued = [...parser.parseLogs(tx.meta.logMessages)].find((event) => event.name === "receiptPayoutValued");
+    expect(valued.data.payoutAmount.toNumber()).to.equal(4e6);
+    expect(valued.data.underlyingAmount.toNumber()).to.equal(4.4e6);
+    expect(valued.data.stakedUnderlying.toNumber()).to.equal(2e6);
+    expect(await tokenBalance(user1Receipts)).to.equal(12e6);
+  });
+
+  it("Snapshots the platform fee token onto new markets", async () => {
+    const feeMint = await createMint(provider.connection, authority, authority.publicKey, null, 6);
+    const feeTreasury = await createAccount(
+      provider.connection,
+      authority,
+      feeMint,
+      authority.publicKey,
+      anchor.web3.Keypair.generate()
+    );
+    const feed = await feeRateFeed(1e6);
+
+    try {
+      await setFeeToken({ mint: feeMint, treasury: anchor.web3.PublicKey.default, ...feed });
+      expect.fail("fees need somewhere to go");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("InvalidFeeToken");
+    }
+    try {
+      await setFeeToken({ mint: feeMint, treasury: feeTreasury, ...feed, rateSourceOwner: TOKEN_PROGRAM_ID });
+      expect.fail("the rate source belongs to another program");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("InvalidFeeRate");
+    }
+
+    await setFeeToken({ mint: feeMint, treasury: feeTreasury, ...feed });
+    let feeTokenMarket: anchor.web3.PublicKey;
+    try {
+      ({ market: feeTokenMarket } = await createTestMarket(60));
+      try {
+        await createTestMarket(60, { ...defaultConfig(), feeCap: new anchor.BN(1e6) });
+        expect.fail("fee token fees aren't capped");
+      } catch (err) {
+        expect(err.error.errorCode.code).to.equal("FeeTokenFeeCap");
+      }
+    } finally {
+      await setFeeToken(null);
+    }
+    const { market: plainMarket } = await createTestMarket(60);
+
+    // Clearing the setting leaves existing markets alone
+    const snapshot = (await program.account.market.fetch(feeTokenMarket)).feeToken;
+    expect(snapshot.mint.toString()).to.equal(feeMint.toString());
+    expect(snapshot.treasury.toString()).to.equal(feeTreasury.toString());
+    expect(snapshot.rateOffset).to.equal(73);
+    expect((await program.account.market.fetch(plainMarket)).feeToken).to.be.null;
+  });
+
+  it("Charges the platform fee in a separate fee token", async () => {
+    const feeMint = await createMint(provider.connection, authority, authority.publicKey, null, 6);
+    const feePayerTokenAccount = await createAccount(provider.connection, user1, feeMint, user1.publicKey);
+    await mintTo(provider.connection, authority, feeMint, feePayerTokenAccount, authority, 1e6);
+    const feeTreasury = await createAccount(
+      provider.connection,
+      authority,
+      feeMint,
+      authority.publicKey,
+      anchor.web3.Keypair.generate()
+    );
+    // 2 fee tokens per payout token
+    const feed = await feeRateFeed(2e6);
+    const setFee = (feeBps: number) =>
+      program.methods
+        .setFee(feeBps)
+        .accountsPartial({ globalState, authority: authority.publicKey })
+        .signers([authority])
+        .rpc();
+
+    await setFee(500);
+    await setFeeToken({ mint: feeMint, treasury: feeTreasury, ...feed });
+    try {
+      const { market, vault } = await createTestMarket(3);
+      const bet = await placeTestBet(user1, user1TokenAccount, market, vault, 0, 2 * 1e6);
+      await placeTestBet(user2, user2TokenAccount, market, vault, 1, 2 * 1e6);
+      await sleep(4000);
+      await resolveTestMarket(market, 0);
+
+      try {
+        await claimTestPayout(user1, user1TokenAccount, market, vault, bet);
+        expect.fail("the fee token accounts are required");
+      } catch (err) {
+        expect(err.error.errorCode.code).to.equal("FeeTokenAccountsRequired");
+      }
+
+      const before = await tokenBalance(user1TokenAccount);
+      await claimTestPayout(user1, user1TokenAccount, market, vault, bet, null, [], {
+        feePayerTokenAccount,
+        feeTreasury,
+        feeRateSource: feed.rateSource,
+      });
+      // The whole 4 token payout arrives; the 0.2 token fee is paid as 0.4 fee tokens
+      expect((await tokenBalance(user1TokenAccount)) - before).to.equal(4 * 1e6);
+      expect(await tokenBalance(feeTreasury)).to.equal(0.4 * 1e6);
+      expect(await tokenBalance(feePayerTokenAccount)).to.equal(0.6 * 1e6);
+      const marketAccount = await program.account.market.fetch(market);
+      expect(marketAccount.feesAccrued.toNumber()).to.equal(0);
+      expect(marketAccount.feesCollected.toNumber()).to.equal(0);
+      expect(marketAccount.feeTokenFeesCollected.toNumber()).to.equal(0.4 * 1e6);
+      expect(await tokenBalance(vault)).to.equal(0);
+
+      const report = (await program.methods.getSettlementReport().accountsPartial({ market }).simulate()).events.find(
+        (e) => e.name === "settlementReport"
+      ).data;
+      expect(report.platformFees.toNumber()).to.equal(0);
+      expect(report.feeTokenFees.toNumber()).to.equal(0.4 * 1e6);
+    } finally {
+      await setFeeToken(null);
+      await setFee(0);
+    }
+  });
+
+  it("Reports a settled market's volume and payouts from lifetime counters", async () => {
+    const { market, vault } = await createTestMarket(3);
+    const report = async () =>
+      (await program.methods.getSettlementReport().accountsPartial({ market }).simulate()).events.find(
+        (e) => e.name === "settlementReport"
+      ).data;
+
+    // Seed liquidity deepens the pools but isn't bettor volume
+    await program.methods
+      .seedLiquidity([new anchor.BN(1e6), new anchor.BN(1e6)])
+      .accountsPartial({
+        market,
+        authority: authority.publicKey,
+        authorityTokenAccount,
+        marketTokenAccount: vault,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([authority])
+      .rpc();
+    const bets = [
+      await placeTestBet(user1, user1TokenAccount, market, vault, 0, 1e6),
+      await placeTestBet(user1, user1TokenAccount, market, vault, 0, 1e6),
+    ];
+    await placeTestBet(user2, user2TokenAccount, market, vault, 1, 1e6);
+
+    try {
+      await report();
+      expect.fail("an open market has nothing to report");
+    } catch (err) {
+      expect(err.toString()).to.match(/MarketStillActive/);
+    }
+
+    await sleep(4000);
+    await resolveTestMarket(market, 0);
+    // Each 1 token bet takes 1/3 of the 5 token pool
+    for (const bet of bets) {
+      await claimTestPayout(user1, user1TokenAccount, market, vault, bet);
+    }
+
+    const settled = await report();
+    expect(settled.totalVolume.toNumber()).to.equal(3e6);
+    expect(settled.winnersPaid).to.equal(2);
+    expect(settled.totalPaidOut.toNumber()).to.equal(2 * 1_666_666);
+  });
+
+  it("Fixes a market's lifetime platform fee cap at creation", async () => {
+    try {
+      await createTestMarket(3600, { ...defaultConfig(), feeCap: new anchor.BN(0) });
+      expect.fail("a zero cap is just no fee");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("InvalidFeeCap");
+    }
+
+    const { market: capped } = await createTestMarket(3600, { ...defaultConfig(), feeCap: new anchor.BN(0.3e6) });
+    const { market: uncapped } = await createTestMarket(3600);
+    expect((await program.account.market.fetch(capped)).feeCap.toNumber()).to.equal(0.3e6);
+    expect((await program.account.market.fetch(uncapped)).feeCap).to.be.null;
+  });
+
+  it("Records a market's residual destination at creation", async () => {
+    try {
+      await createTestMarket(3600, { ...defaultConfig(), residualDestination: anchor.web3.PublicKey.default });
+      expect.fail("residuals need a real destination");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("InvalidResidualDestination");
+    }
+
+    // user2's token account stands in for a charity's
+    const { market } = await createTestMarket(3600, { ...defaultConfig(), residualDestination: user2TokenAccount });
+    expect((await program.account.market.fetch(market)).residualDestination.toBase58()).to.equal(
+      user2TokenAccount.toBase58()
+    );
+
+    const otherMint = await createMint(provider.connection, authority, authority.publicKey, null, 6);
+    const otherTokenAccount = await createAccount(provider.connection, user2, otherMint, user2.publicKey);
+    try {
+      await createTestMarket(3600, { ...defaultConfig(), residualDestination: otherTokenAccount });
+      expect.fail("residuals are swept in the market's mint");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("MintMismatch");
+    }
+
+    // Without a destination of its own a market takes the treasury's
+    const { market: defaulted } = await createTestMarket(3600);
+    expect((await program.account.market.fetch(defaulted)).residualDestination.toBase58()).to.equal(
+      authorityTokenAccount.toBase58()
+    );
+  });
+
+  it("Takes bets on a whitelist market only from admitted bettors", async () => {
+    const { market, vault } = await createTestMarket(3600, { ...defaultConfig(), accessMode: { whitelist: {} } });
+    const whitelistEntry = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("whitelist"), market.toBuffer(), user1.publicKey.toBuffer()],
+      program.programId
+    )[0];
+
+    await program.methods
+      .addToWhitelist(user1.publicKey)
+      .accountsPartial({ market, whitelistEntry, authority: authority.publicKey })
+      .signers([authority])
+      .rpc();
+
+    await placeTestBet(user1, user1TokenAccount, market, vault, 0, 1e6);
+    try {
+      await placeTestBet(user2, user2TokenAccount, market, vault, 1, 1e6);
+      expect.fail("a bettor without an entry is rejected");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("NotWhitelisted");
+    }
+
+    await program.methods
+      .removeFromWhitelist()
+      .accountsPartial({ market, whitelistEntry, authority: authority.publicKey })
+      .signers([authority])
+      .rpc();
+    expect(await provider.connection.getAccountInfo(whitelistEntry)).to.be.null;
+    try {
+      await placeTestBet(user1, user1TokenAccount, market, vault, 0, 1e6);
+      expect.fail("a removed bettor is rejected");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("NotWhitelisted");
+    }
+  });
+
+  it("Extends resolution_time and reopens betting until the new cutoff", async () => {
+    const { market, vault } = await createTestMarket(2);
+    await placeTestBet(user1, user1TokenAccount, market, vault, 0, 1e6);
+    await sleep(3000);
+
+    const { resolutionTime } = await program.account.market.fetch(market);
+    await program.methods
+      .extendResolution(resolutionTime.addn(6))
+      .accountsPartial({ market, authority: authority.publicKey })
+      .signers([authority])
+      .rpc();
+    const extended = await program.account.market.fetch(market);
+    expect(extended.resolutionTime.toNumber()).to.equal(resolutionTime.toNumber() + 6);
+    expect(extended.bettingCutoff.toNumber()).to.equal(extended.resolutionTime.toNumber());
+
+    await placeTestBet(user2, user2TokenAccount, market, vault, 1, 1e6);
+    try {
+      await resolveTestMarket(market, 0);
+      expect.fail("resolution waits for the extended time");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("TooEarlyToResolve");
+    }
+
+    await sleep(6000);
+    await resolveTestMarket(market, 0);
+    expect((await program.account.market.fetch(market)).resolved).to.be.true;
+  });
+
+  it("Rejects bets above max_bet and past max_total_pool", async () => {
+    const { market, vault } = await createTestMarket(3600, {
+      ...defaultConfig(),
+      maxBet: new anchor.BN(3 * 1e6),
+      maxTotalPool: new anchor.BN(5 * 1e6),
+    });
+
+    try {
+      await placeTestBet(user1, user1TokenAccount, market, vault, 0, 4 * 1e6);
+      expect.fail("a bet over max_bet is rejected");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("BetTooLarge");
+    }
+
+    await placeTestBet(user1, user1TokenAccount, market, vault, 0, 3 * 1e6);
+    try {
+      await placeTestBet(user2, user2TokenAccount, market, vault, 1, 3 * 1e6);
+      expect.fail("3 + 3 tokens would pass the 5 token pool cap");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("PoolCapReached");
+    }
+
+    // Filling the pool exactly to the cap is allowed
+    await placeTestBet(user2, user2TokenAccount, market, vault, 1, 2 * 1e6);
+    expect((await program.account.market.fetch(market)).totalPool.toNumber()).to.equal(5 * 1e6);
+  });
+
+  it("Resolves a market once 2 of its 3 committee members agree", async () => {
+    const committee = [user1.publicKey, user2.publicKey, anchor.web3.Keypair.generate().publicKey];
+    const { market, vault } = await createTestMarket(2, {
+      ...defaultConfig(),
+      resolvers: committee,
+      resolutionThreshold: 2,
+    });
+    await placeTestBet(user1, user1TokenAccount, market, vault, 0, 1e6);
+    await placeTestBet(user2, user2TokenAccount, market, vault, 1, 1e6);
+    await sleep(3000);
+
+    const vote = (voter: anchor.web3.Keypair, outcomeIndex: number) =>
+      program.methods
+        .submitResolutionVote(outcomeIndex)
+        .accountsPartial({ market, voter: voter.publicKey })
+        .signers([voter])
+        .rpc();
+
+    try {
+      await resolveTestMarket(market, 0);
+      expect.fail("the authority can't bypass the committee");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("CommitteeMarket");
+    }
+
+    await vote(user1, 0);
+    expect((await program.account.market.fetch(market)).resolved).to.be.false;
+    try {
+      await vote(user1, 1);
+      expect.fail("a member votes once");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("AlreadyVoted");
+    }
+
+    await vote(user2, 0);
+    const marketAccount = await program.account.market.fetch(market);
+    expect(marketAccount.resolved).to.be.true;
+    expect(marketAccount.winningOutcome).to.equal(0);
+    expect(marketAccount.resolutionVotes).to.deep.equal([0, 0, null]);
+  });
+
+  it("Rejects resolution votes from outside the committee", async () => {
+    const { market, vault } = await createTestMarket(2, {
+      ...defaultConfig(),
+      resolvers: [user1.publicKey, user2.publicKey],
+      resolutionThreshold: 2,
+    });
+    await placeTestBet(user1, user1TokenAccount, market, vault, 0, 1e6);
+    await sleep(3000);
+
+    try {
+      await program.methods
+        .submitResolutionVote(0)
+        .accountsPartial({ market, voter: authority.publicKey })
+        .signers([authority])
+        .rpc();
+      expect.fail("the authority isn't a member");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("NotCommitteeMember");
+    }
+    expect((await program.account.market.fetch(market)).resolutionVotes).to.deep.equal([null, null]);
+  });
+
+  it("Accumulates repeat bets into one position and claims it once", async () => {
+    const { market, vault } = await createTestMarket(3);
+    const position = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("position"), market.toBuffer(), user1.publicKey.toBuffer(), Buffer.from([0])],
+      program.programId
+    )[0];
+    const placePositionBet = (amount: number) =>
+      program.methods
+        .placePositionBet(0, new anchor.BN(amount))
+        .accountsPartial({
+          position,
+          market,
+          bettor: user1.publicKey,
+          bettorTokenAccount: user1TokenAccount,
+          marketTokenAccount: vault,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([user1])
+        .rpc();
+
+    await placePositionBet(1e6);
+    await placePositionBet(2e6);
+    await placePositionBet(3e6);
+    await placeTestBet(user2, user2TokenAccount, market, vault, 1, 6e6);
+
+    const positionAccount = await program.account.userPosition.fetch(position);
+    expect(positionAccount.totalAmount.toNumber()).to.equal(6e6);
+    expect(positionAccount.betCount).to.equal(3);
+    // The position is a single account, so it counts once
+    expect((await program.account.market.fetch(market)).betCounts).to.deep.equal([1, 1]);
+
+    await sleep(4000);
+    await resolveTestMarket(market, 0);
+
+    const before = await tokenBalance(user1TokenAccount);
+    await program.methods
+      .claimPosition()
+      .accountsPartial({
+        position,
+        market,
+        bettor: user1.publicKey,
+        bettorTokenAccount: user1TokenAccount,
+        marketTokenAccount: vault,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([user1])
+      .rpc();
+    // 6 of the 6 tokens on the winner take the whole 12 token pool
+    expect((await tokenBalance(user1TokenAccount)) - before).to.equal(12e6);
+    expect(await program.account.userPosition.fetchNullable(position)).to.be.null;
+    expect((await program.account.market.fetch(market)).claimedCount).to.equal(1);
+  });
+
+  it("Closes a fully claimed market and returns its rent to the authority", async () => {
+    const { market, vault } = await createTestMarket(2, { ...defaultConfig(), closeGracePeriod: new anchor.BN(4) });
+    const bet = await placeTestBet(user1, user1TokenAccount, market, vault, 0, 1e6);
+    await placeTestBet(user2, user2TokenAccount, market, vault, 1, 1e6);
+    await sleep(3000);
+    await resolveTestMarket(market, 0);
+
+    const closeMarketIx = () =>
+      program.methods
+        .closeMarket()
+        .accountsPartial({
+          market,
+          authority: authority.publicKey,
+          marketTokenAccount: vault,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([authority]);
+    const closeMarket = () => closeMarketIx().rpc();
+
+    try {
+      await closeMarket();
+      expect.fail("the winner hasn't claimed");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("ClaimsOutstanding");
+    }
+
+    await claimTestPayout(user1, user1TokenAccount, market, vault, bet);
+    expect(await tokenBalance(vault)).to.equal(0);
+    try {
+      await closeMarket();
+      expect.fail("the grace period is still running");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("CloseGracePeriodActive");
+    }
+
+    await sleep(4000);
+    // Closing reports the market's lifetime totals
+    const report = (await closeMarketIx().simulate()).events.find((e) => e.name === "settlementReport").data;
+    expect(report.totalVolume.toNumber()).to.equal(2e6);
+    expect(report.winnersPaid).to.equal(1);
+    expect(report.totalPaidOut.toNumber()).to.equal(2e6);
+    expect(report.dustSwept.toNumber()).to.equal(0);
+
+    const rent =
+      (await provider.connection.getBalance(market)) + (await provider.connection.getBalance(vault));
+    const before = await provider.connection.getBalance(authority.publicKey);
+    await closeMarket();
+    expect(await provider.connection.getAccountInfo(market)).to.be.null;
+    expect(await provider.connection.getAccountInfo(vault)).to.be.null;
+    // The provider wallet pays the fee, so the authority gains exactly the rent
+    expect((await provider.connection.getBalance(authority.publicKey)) - before).to.equal(rent);
+  });
+
+  it("Activates once every outcome meets the minimum liquidity", async () => {
+    const { market, vault } = await createTestMarket(2, {
+      ...defaultConfig(),
+      minLiquidityPerOutcome: new anchor.BN(2e6),
+    });
+    expect((await program.account.market.fetch(market)).active).to.be.false;
+
+    await placeTestBet(user1, user1TokenAccount, market, vault, 0, 2e6);
+    expect((await program.account.market.fetch(market)).active).to.be.false;
+    await placeTestBet(user2, user2TokenAccount, market, vault, 1, 2e6);
+    expect((await program.account.market.fetch(market)).active).to.be.true;
+
+    await sleep(3000);
+    await resolveTestMarket(market, 0);
+    expect((await program.account.market.fetch(market)).resolved).to.be.true;
+  });
+
+  it("Refunds a market that never reaches its minimum liquidity", async () => {
+    const { market, vault } = await createTestMarket(2, {
+      ...defaultConfig(),
+      minLiquidityPerOutcome: new anchor.BN(2e6),
+    });
+    const bet = await placeTestBet(user1, user1TokenAccount, market, vault, 0, 3e6);
+    await placeTestBet(user2, user2TokenAccount, market, vault, 1, 1e6);
+
+    await sleep(3000);
+    await resolveTestMarket(market, 0);
+    const marketAccount = await program.account.market.fetch(market);
+    expect(marketAccount.active).to.be.false;
+    expect(marketAccount.resolved).to.be.false;
+    expect(marketAccount.voided).to.be.true;
+
+    const before = await tokenBalance(user1TokenAccount);
+    await program.methods
+      .claimRefund()
+      .accountsPartial({
+        bet,
+        market,
+        bettor: user1.publicKey,
+        bettorTokenAccount: user1TokenAccount,
+        marketTokenAccount: vault,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([user1])
+      .rpc();
+    expect((await tokenBalance(user1TokenAccount)) - before).to.equal(3e6);
+  });
+
+  it("Pays an early bettor more than a late one on a time-weighted market", async () => {
+    const { market, vault } = await createTestMarket(8, { ...defaultConfig(), weighted: true });
+    const early = await placeTestBet(user1, user1TokenAccount, market, vault, 0, 1e6);
+    await placeTestBet(authority, authorityTokenAccount, market, vault, 1, 1e6);
+    await sleep(4000);
+    const late = await placeTestBet(user2, user2TokenAccount, market, vault, 0, 1e6);
+
+    const earlyWeight = (await program.account.bet.fetch(early)).weight.toNumber();
+    const lateWeight = (await program.account.bet.fetch(late)).weight.toNumber();
+    expect(earlyWeight).to.be.greaterThan(lateWeight);
+    const { weightedPools } = await program.account.market.fetch(market);
+    expect(weightedPools[0].toNumber()).to.equal(earlyWeight + lateWeight);
+
+    await sleep(5000);
+    await resolveTestMarket(market, 0);
+
+    const earlyBefore = await tokenBalance(user1TokenAccount);
+    await claimTestPayout(user1, user1TokenAccount, market, vault, early);
+    const earlyPayout = (await tokenBalance(user1TokenAccount)) - earlyBefore;
+    const lateBefore = await tokenBalance(user2TokenAccount);
+    await claimTestPayout(user2, user2TokenAccount, market, vault, late);
+    const latePayout = (await tokenBalance(user2TokenAccount)) - lateBefore;
+
+    // Same stake, but the whole 3 token pool splits by weight rather than evenly
+    expect(earlyPayout).to.be.greaterThan(latePayout);
+    expect(earlyPayout).to.equal(Math.floor((earlyWeight * 3e6) / (earlyWeight + lateWeight)));
+    expect(earlyPayout + latePayout).to.be.within(3e6 - 1, 3e6);
+  });
+
+  it("Cancels a bet before betting closes, keeping the penalty in the pool", async () => {
+    const { market, vault } = await createTestMarket(3600, { ...defaultConfig(), cancelPenaltyBps: 1000 });
+    const bet = await placeTestBet(user1, user1TokenAccount, market, vault, 0, 2e6);
+    await placeTestBet(user2, user2TokenAccount, market, vault, 1, 1e6);
+
+    const cancelBet = () =>
+      program.methods
+        .cancelBet()
+        .accountsPartial({
+          bet,
+          market,
+          bettor: user1.publicKey,
+          bettorTokenAccount: user1TokenAccount,
+          marketTokenAccount: vault,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([user1])
+        .rpc();
+
+    const before = await tokenBalance(user1TokenAccount);
+    await cancelBet();
+    // 10% of the 2 token stake stays behind
+    expect((await tokenBalance(user1TokenAccount)) - before).to.equal(1.8e6);
+    expect(await program.account.bet.fetchNullable(bet)).to.be.null;
+
+    const marketAccount = await program.account.market.fetch(market);
+    expect(marketAccount.outcomePools.map((pool) => pool.toNumber())).to.deep.equal([0, 1e6]);
+    expect(marketAccount.totalPool.toNumber()).to.equal(1.2e6);
+    expect(marketAccount.betCounts).to.deep.equal([0, 1]);
+    expect(await tokenBalance(vault)).to.equal(1.2e6);
+
+    try {
+      await cancelBet();
+      expect.fail("a cancelled bet is gone");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("AccountNotInitialized");
+    }
+  });
+
+  it("Accepts bets after a cancellation on a circuit-breaker market", async () => {
+    const { market, vault } = await createTestMarket(3600, {
+      ...defaultConfig(),
+      circuitBreakerBps: 5000,
+      circuitBreakerWindow: new anchor.BN(3600),
+      cancelPenaltyBps: 0,
+    });
+    // This bet seeds the breaker window, then leaves the pool
+    const bet = await placeTestBet(user1, user1TokenAccount, market, vault, 0, 2e6);
+    await program.methods
+      .cancelBet()
+      .accountsPartial({
+        bet,
+        market,
+        bettor: user1.publicKey,
+        bettorTokenAccount: user1TokenAccount,
+        marketTokenAccount: vault,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([user1])
+      .rpc();
+
+    await placeTestBet(user2, user2TokenAccount, market, vault, 1, 1e6);
+    const marketAccount = await program.account.market.fetch(market);
+    expect(marketAccount.totalPool.toNumber()).to.equal(1e6);
+    expect(marketAccount.circuitBreakerTripped).to.be.false;
+  });
+
+  it("Stores category and tags and includes them in MarketCreated", async () => {
+    const { market } = await createTestMarket(3600, {
+      ...defaultConfig(),
+      category: { sports: {} },
+      tags: ["nba", "finals"],
+    });
+
+    const marketAccount = await program.account.market.fetch(market);
+    expect(marketAccount.category).to.deep.equal({ sports: {} });
+    expect(marketAccount.tags).to.deep.equal(["nba", "finals"]);
+
+    const [{ signature }] = await provider.connection.getSignaturesForAddress(market, {}, "confirmed");
+    const tx = await provider.connection.getTransaction(signature, {
+      commitment: "confirmed",
+      maxSupportedTransactionVersion: 0,
+    });
+    const parser = new anchor.EventParser(program.programId, program.coder);
+    const marketCreated = [...parser.parseLogs(tx.meta.logMessages)].find((event) => event.name === "marketCreated");
+    expect(marketCreated.data.category).to.deep.equal({ sports: {} });
+    expect(marketCreated.data.tags).to.deep.equal(["nba", "finals"]);
+
+    try {
+      await createTestMarket(3600, { ...defaultConfig(), tags: ["a", "b", "c", "d", "e", "f"] });
+      expect.fail("at most 5 tags fit");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("InvalidTags");
+    }
+  });
+
+  it("Sizes the market account exactly for its question and outcomes", async () => {
+    // Ten 50-byte labels are the most a market holds; a long question on top of them wouldn't
+    // fit in one transaction, and long questions are covered by the max_question_len test
+    const labels = Array.from({ length: 10 }, (_, i) => String(i).padEnd(50, "x"));
+    const { market: large } = await createTestMarket(3600, defaultConfig(), labels, "Max");
+    const { market: small } = await createTestMarket(3600);
+
+    const largeAccount = await program.account.market.fetch(large);
+    expect(largeAccount.outcomes).to.deep.equal(labels);
+    const largeLen = (await provider.connection.getAccountInfo(large)).data.length;
+    const smallLen = (await provider.connection.getAccountInfo(small)).data.length;
+    // Only the question bytes and each label's 4-byte prefix and bytes differ
+    const labelSpace = (outcomes: string[]) => outcomes.reduce((sum, label) => sum + 4 + label.length, 0);
+    expect(largeLen - smallLen).to.equal(
+      "Max".length - "Test market".length + labelSpace(labels) - labelSpace(["Yes", "No"])
+    );
+  });
+
+  it("Resolves an oracle market only from its oracle", async () => {
+    const oracle = anchor.web3.Keypair.generate();
+    await provider.connection.confirmTransaction(
+      await provider.connection.requestAirdrop(oracle.publicKey, anchor.web3.LAMPORTS_PER_SOL)
+    );
+    const resolveAs = (market: anchor.web3.PublicKey, signer: anchor.web3.Keypair) =>
+      program.methods
+        .resolveMarket(0, null, 0, [])
+        .accountsPartial({ market, authority: signer.publicKey })
+        .signers([signer])
+        .rpc();
+
+    const { market: oracleMarket, vault } = await createTestMarket(2, { ...defaultConfig(), oracle: oracle.publicKey });
+    const { market: authorityMarket, vault: authorityVault } = await createTestMarket(2);
+    await placeTestBet(user1, user1TokenAccount, oracleMarket, vault, 0, 1e6);
+    await placeTestBet(user1, user1TokenAccount, authorityMarket, authorityVault, 0, 1e6);
+    await sleep(3000);
+
+    try {
+      await resolveAs(oracleMarket, authority);
+      expect.fail("the creator can't report an oracle market's result");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("Unauthorized");
+    }
+    await resolveAs(oracleMarket, oracle);
+    expect((await program.account.market.fetch(oracleMarket)).resolved).to.be.true;
+
+    try {
+      await resolveAs(authorityMarket, oracle);
+      expect.fail("without an oracle only the authority resolves");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("Unauthorized");
+    }
+    await resolveAs(authorityMarket, authority);
+    expect((await program.account.market.fetch(authorityMarket)).resolved).to.be.true;
+  });
+
+  it("Pays a bet claimed in two halves the same as one full claim", async () => {
+    const claimPartial = (
+      market: anchor.web3.PublicKey,
+      vault: anchor.web3.PublicKey,
+      bet: anchor.web3.PublicKey,
+      requestedAmount: number
+    ) =>
+      program.methods
+        .claimPayoutPartial(new anchor.BN(requestedAmount))
+        .accountsPartial({
+          bet,
+          market,
+          bettor: user1.publicKey,
+          bettorTokenAccount: user1TokenAccount,
+          marketTokenAccount: vault,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([user1])
+        .rpc();
+
+    const { market: fullMarket, vault: fullVault } = await createTestMarket(2);
+    const { market: splitMarket, vault: splitVault } = await createTestMarket(2);
+    const fullBet = await placeTestBet(user1, user1TokenAccount, fullMarket, fullVault, 0, 1e6);
+    await placeTestBet(user2, user2TokenAccount, fullMarket, fullVault, 1, 2e6);
+    const splitBet = await placeTestBet(user1, user1TokenAccount, splitMarket, splitVault, 0, 1e6);
+    await placeTestBet(user2, user2TokenAccount, splitMarket, splitVault, 1, 2e6);
+    await sleep(3000);
+    await resolveTestMarket(fullMarket, 0);
+    await resolveTestMarket(splitMarket, 0);
+
+    let before = await tokenBalance(user1TokenAccount);
+    await claimTestPayout(user1, user1TokenAccount, fullMarket, fullVault, fullBet);
+    const fullPayout = (await tokenBalance(user1TokenAccount)) - before;
+
+    before = await tokenBalance(user1TokenAccount);
+    const half = Math.floor(fullPayout / 2);
+    await claimPartial(splitMarket, splitVault, splitBet, half);
+    const betAccount = await program.account.bet.fetch(splitBet);
+    expect(betAccount.claimed).to.be.false;
+    expect(betAccount.claimedAmount.toNumber()).to.equal(half);
+    expect(betAccount.payoutDue.toNumber()).to.equal(fullPayout);
+    // The winner counts from its first claim
+    expect((await program.account.market.fetch(splitMarket)).winnersPaid).to.equal(1);
+
+    // Asking for more than is left pays just the remainder and closes the bet
+    await claimPartial(splitMarket, splitVault, splitBet, fullPayout);
+    expect((await tokenBalance(user1TokenAccount)) - before).to.equal(fullPayout);
+    expect(await provider.connection.getAccountInfo(splitBet)).to.be.null;
+    const splitAccount = await program.account.market.fetch(splitMarket);
+    expect(splitAccount.claimedCount).to.equal(1);
+    expect(splitAccount.winnersPaid).to.equal(1);
+    expect(splitAccount.winningsPaid.toNumber()).to.equal(fullPayout);
+  });
+
+  it("Records who resolved a market and when", async () => {
+    const { market, vault } = await createTestMarket(2);
+    const { market: emptyMarket } = await createTestMarket(2);
+    await placeTestBet(user1, user1TokenAccount, market, vault, 0, 1e6);
+    await sleep(3000);
+
+    try {
+      await resolveTestMarket(emptyMarket, 0);
+      expect.fail("a market without stake has nothing to resolve");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("EmptyMarket");
+    }
+
+    const signature = await program.methods
+      .resolveMarket(0, null, 0, [])
+      .accountsPartial({ market, authority: authority.publicKey })
+      .signers([authority])
+      .rpc({ commitment: "confirmed" });
+    const tx = await provider.connection.getTransaction(signature, {
+      commitment: "confirmed",
+      maxSupportedTransactionVersion: 0,
+    });
+    const marketAccount = await program.account.market.fetch(market);
+    expect(marketAccount.resolvedBy.toBase58()).to.equal(authority.publicKey.toBase58());
+    expect(Math.abs(marketAccount.resolvedAt.toNumber() - Date.now() / 1000)).to.be.lessThan(30);
+
+    const parser = new anchor.EventParser(program.programId, program.coder);
+    const resolved = [...parser.parseLogs(tx.meta.logMessages)].find((event) => event.name === "marketResolved");
+    expect(resolved.data.resolvedBy.toBase58()).to.equal(authority.publicKey.toBase58());
+    expect(resolved.data.resolvedAt.toNumber()).to.equal(marketAccount.resolvedAt.toNumber());
+  });
+
+  it("Rejects markets whose minimum bet is below the global floor", async () => {
+    // createTestMarket always sets a 1e6 minimum bet
+    const setGlobalMinBet = (globalMinBet: number) =>
+      program.methods
+        .setGlobalMinBet(new anchor.BN(globalMinBet))
+        .accountsPartial({ globalState, authority: authority.publicKey })
+        .signers([authority])
+        .rpc();
+
+    try {
+      await setGlobalMinBet(5e5);
+      await createTestMarket(3600);
+
+      await setGlobalMinBet(2e6);
+      try {
+        await createTestMarket(3600);
+        expect.fail("a 1e6 minimum is below the 2e6 floor");
+      } catch (err) {
+        expect(err.error.errorCode.code).to.equal("MinBetBelowFloor");
+      }
+    } finally {
+      await setGlobalMinBet(0);
+    }
+  });
+
+  it("Splits a scalar market's pool by where the value lands between its bounds", async () => {
+    const scalarConfig = {
+      ...defaultConfig(),
+      marketType: { scalar: {} },
+      scalarLowerBound: new anchor.BN(0),
+      scalarUpperBound: new anchor.BN(100),
+    };
+    // user1 is long 1e6 and user2 short 3e6 on each market; returns what each is paid
+    const settleAt = async (value: number) => {
+      const { market, vault } = await createTestMarket(2, scalarConfig);
+      const longBet = await placeTestBet(user1, user1TokenAccount, market, vault, 0, 1e6);
+      const shortBet = await placeTestBet(user2, user2TokenAccount, market, vault, 1, 3e6);
+      await sleep(3000);
+      await program.methods
+        .resolveMarketScalar(new anchor.BN(value))
+        .accountsPartial({ market, authority: authority.publicKey })
+        .signers([authority])
+        .rpc();
+
+      const claim = async (bettor: anchor.web3.Keypair, tokenAccount: anchor.web3.PublicKey, bet: anchor.web3.PublicKey) => {
+        const before = await tokenBalance(tokenAccount);
+        try {
+          await claimTestPayout(bettor, tokenAccount, market, vault, bet);
+        } catch (err) {
+          expect(err.error.errorCode.code).to.equal("LosingBet");
+        }
+        return (await tokenBalance(tokenAccount)) - before;
+      };
+      return [await claim(user1, user1TokenAccount, longBet), await claim(user2, user2TokenAccount, shortBet)];
+    };
+
+    // Values past a bound count as the bound
+    expect(await settleAt(-50)).to.deep.equal([0, 4e6]);
+    expect(await settleAt(100)).to.deep.equal([4e6, 0]);
+    expect(await settleAt(50)).to.deep.equal([2e6, 2e6]);
+
+    try {
+      await createTestMarket(3600, { ...scalarConfig, scalarUpperBound: new anchor.BN(0) });
+      expect.fail("the upper bound must be above the lower");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("InvalidScalarMarket");
+    }
+  });
+
+  it("Rejects a second initialize without resetting the platform", async () => {
+    const before = await program.account.globalState.fetch(globalState);
+    expect(before.marketCount.toNumber()).to.be.greaterThan(0);
+
+    try {
+      await program.methods
+        .initialize(500, new anchor.BN(0))
+        .accountsPartial({
+          globalState,
+          authority: user1.publicKey,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([user1])
+        .rpc();
+      expect.fail("the platform is already set up");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("AlreadyInitialized");
+    }
+
+    const after = await program.account.globalState.fetch(globalState);
+    expect(after.marketCount.toNumber()).to.equal(before.marketCount.toNumber());
+    expect(after.authority.toBase58()).to.equal(authority.publicKey.toBase58());
+    expect(after.feeBps).to.equal(before.feeBps);
+  });
+
+  it("Marks betting closed on-chain once the cutoff passes", async () => {
+    const { market, vault } = await createTestMarket(2);
+    const markClosed = () =>
+      program.methods
+        .markBettingClosed()
+        .accountsPartial({ market, caller: user1.publicKey })
+        .signers([user1])
+        .rpc({ commitment: "confirmed" });
+
+    try {
+      await markClosed();
+      expect.fail("betting is still open");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("BettingStillOpen");
+    }
+
+    await sleep(3000);
+    const signature = await markClosed();
+    expect((await program.account.market.fetch(market)).bettingClosed).to.be.true;
+
+    const tx = await provider.connection.getTransaction(signature, {
+      commitment: "confirmed",
+      maxSupportedTransactionVersion: 0,
+    });
+    const parser = new anchor.EventParser(program.programId, program.coder);
+    const closed = [...parser.parseLogs(tx.meta.logMessages)].find((event) => event.name === "bettingClosed");
+    expect(closed.data.caller.toBase58()).to.equal(user1.publicKey.toBase58());
+
+    // A repeat call is a no-op, and the market refuses stakes
+    await markClosed();
+    try {
+      await placeTestBet(user1, user1TokenAccount, market, vault, 0, 1e6);
+      expect.fail("betting is closed");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("BettingClosed");
+    }
+  });
+
+  it("Adds donations to winners' payouts and returns them to the donor on a void", async () => {
+    const donationAddress = (market: anchor.web3.PublicKey) =>
+      anchor.web3.PublicKey.findProgramAddressSync(
+        [Buffer.from("donation"), market.toBuffer(), authority.publicKey.toBuffer()],
+        program.programId
+      )[0];
+    const donate = (market: anchor.web3.PublicKey, vault: anchor.web3.PublicKey, amount: number) =>
+      program.methods
+        .donate(new anchor.BN(amount))
+        .accountsPartial({
+          market,
+          donor: authority.publicKey,
+          donorTokenAccount: authorityTokenAccount,
+          marketTokenAccount: vault,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([authority])
+        .rpc();
+
+    // The same bets with and without a 2e6 donation
+    const { market: plain, vault: plainVault } = await createTestMarket(3);
+    const { market: donated, vault: donatedVault } = await createTestMarket(3);
+    const plainBet = await placeTestBet(user1, user1TokenAccount, plain, plainVault, 0, 1e6);
+    await placeTestBet(user2, user2TokenAccount, plain, plainVault, 1, 1e6);
+    const donatedBet = await placeTestBet(user1, user1TokenAccount, donated, donatedVault, 0, 1e6);
+    await placeTestBet(user2, user2TokenAccount, donated, donatedVault, 1, 1e6);
+    await donate(donated, donatedVault, 2e6);
+
+    const marketAccount = await program.account.market.fetch(donated);
+    expect(marketAccount.totalPool.toNumber()).to.equal(4e6);
+    expect(marketAccount.donatedTotal.toNumber()).to.equal(2e6);
+    expect(marketAccount.outcomePools.map((pool) => pool.toNumber())).to.deep.equal([1e6, 1e6]);
+
+    await sleep(4000);
+    await resolveTestMarket(plain, 0);
+    await resolveTestMarket(donated, 0);
+    let before = await tokenBalance(user1TokenAccount);
+    await claimTestPayout(user1, user1TokenAccount, plain, plainVault, plainBet);
+    expect((await tokenBalance(user1TokenAccount)) - before).to.equal(2e6);
+    before = await tokenBalance(user1TokenAccount);
+    await claimTestPayout(user1, user1TokenAccount, donated, donatedVault, donatedBet);
+    expect((await tokenBalance(user1TokenAccount)) - before).to.equal(4e6);
+
+    // Voided markets hand donations back to the donor
+    const { market: voided, vault: voidedVault } = await createTestMarket(3600);
+    await placeTestBet(user1, user1TokenAccount, voided, voidedVault, 0, 1e6);
+    await donate(voided, voidedVault, 5e5);
+    await program.methods
+      .cancelMarket()
+      .accountsPartial({ market: voided, authority: authority.publicKey })
+      .signers([authority])
+      .rpc();
+
+    before = await tokenBalance(authorityTokenAccount);
+    await program.methods
+      .reclaimDonation()
+      .accountsPartial({
+        market: voided,
+        donor: authority.publicKey,
+        donorTokenAccount: authorityTokenAccount,
+        marketTokenAccount: voidedVault,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([authority])
+      .rpc();
+    expect((await tokenBalance(authorityTokenAccount)) - before).to.equal(5e5);
+    expect(await provider.connection.getAccountInfo(donationAddress(voided))).to.be.null;
+  });
+
+  it("Fails the loser of two creations racing on one market id with MarketIdTaken", async () => {
+    // Both creators read the same count; the first to land takes the id
+    const { marketCount } = await program.account.globalState.fetch(globalState);
+    const racingConfig = { ...defaultConfig(), marketId: marketCount };
+    const { market } = await createTestMarket(3600, racingConfig);
+    expect((await program.account.market.fetch(market)).marketId.toNumber()).to.equal(marketCount.toNumber());
+
+    try {
+      await createTestMarket(3600, racingConfig);
+      expect.fail("the id is already taken");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("MarketIdTaken");
+    }
+  });
+
+  it("Snapshots the pools and the winner's implied probability at resolution", async () => {
+    const { market, vault } = await createTestMarket(2);
+    await placeTestBet(user1, user1TokenAccount, market, vault, 0, 3e6);
+    await placeTestBet(user2, user2TokenAccount, market, vault, 1, 1e6);
+    await sleep(3000);
+
+    const signature = await program.methods
+      .resolveMarket(0, null, 0, [])
+      .accountsPartial({ market, authority: authority.publicKey })
+      .signers([authority])
+      .rpc({ commitment: "confirmed" });
+    const tx = await provider.connection.getTransaction(signature, {
+      commitment: "confirmed",
+      maxSupportedTransactionVersion: 0,
+    });
+    const parser = new anchor.EventParser(program.programId, program.coder);
+    const resolved = [...parser.parseLogs(tx.meta.logMessages)].find((event) => event.name === "marketResolved");
+    expect(resolved.data.finalPools.map((pool) => pool.toNumber())).to.deep.equal([3e6, 1e6]);
+    expect(resolved.data.winningProbabilityBps).to.equal(7500);
+
+    const marketAccount = await program.account.market.fetch(market);
+    expect(marketAccount.finalPools.map((pool) => pool.toNumber())).to.deep.equal([3e6, 1e6]);
+  });
+
+  it("Rejects duplicate and blank outcome labels", async () => {
+    const { market } = await createTestMarket(3600, defaultConfig(), ["Yes", "No", "Maybe"]);
+    expect((await program.account.market.fetch(market)).outcomes).to.deep.equal(["Yes", "No", "Maybe"]);
+
+    for (const [outcomes, code] of [
+      [["Yes", " yes "], "DuplicateOutcome"],
+      [["Yes", "No", "NO"], "DuplicateOutcome"],
+      [["Yes", "   "], "EmptyOutcome"],
+      [["", "No"], "EmptyOutcome"],
+    ] as [string[], string][]) {
+      try {
+        await createTestMarket(3600, defaultConfig(), outcomes);
+        expect.fail(`${JSON.stringify(outcomes)} should be rejected`);
+      } catch (err) {
+        expect(err.error.errorCode.code).to.equal(code);
+      }
+    }
+  });
+
+  it("Extends the claim deadline by time spent in dispute", async () => {
+    const { market, vault } = await createTestMarket(2, {
+      ...defaultConfig(),
+      guardian: user2.publicKey,
+      vetoWindow: new anchor.BN(3),
+      maxVetoes: 1,
+      claimDeadline: new anchor.BN(5),
+    });
+    const bet = await placeTestBet(user1, user1TokenAccount, market, vault, 0, 1e6);
+    await placeTestBet(user2, user2TokenAccount, market, vault, 1, 1e6);
+    const { marketId } = await program.account.market.fetch(market);
+    const [resolvedOutcome] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("resolved_outcome"), marketId.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+
+    await sleep(3000);
+    await resolveTestMarket(market, 1);
+    await sleep(2000);
+    await program.methods
+      .vetoResolution()
+      .accountsPartial({ market, resolvedOutcome, marketAuthority: authority.publicKey, guardian: user2.publicKey })
+      .signers([user2])
+      .rpc();
+    await resolveTestMarket(market, 0);
+    expect((await program.account.market.fetch(market)).disputeDuration.toNumber()).to.be.at.least(1);
+
+    // Past the 5 second window counted from resolution_time, and from the final resolution,
+    // but not from when its veto window closed
+    await sleep(6000);
+    const before = await tokenBalance(user1TokenAccount);
+    await claimTestPayout(user1, user1TokenAccount, market, vault, bet);
+    expect((await tokenBalance(user1TokenAccount)) - before).to.equal(2e6);
+  });
+
+  it("Lets the creator reclaim winnings left unclaimed past the claim deadline", async () => {
+    // user2's token account stands in for a charity's
+    const { market, vault } = await createTestMarket(2, {
+      ...defaultConfig(),
+      claimDeadline: new anchor.BN(6),
+      residualDestination: user2TokenAccount,
+    });
+    const claimedBet = await placeTestBet(user1, user1TokenAccount, market, vault, 0, 1e6);
+    const unclaimedBet = await placeTestBet(user1, user1TokenAccount, market, vault, 0, 1e6);
+    await placeTestBet(user2, user2TokenAccount, market, vault, 1, 2e6);
+    await sleep(3000);
+    await resolveTestMarket(market, 0);
+
+    const reclaim = () =>
+      program.methods
+        .reclaimUnclaimed()
+        .accountsPartial({
+          market,
+          authority: authority.publicKey,
+          marketTokenAccount: vault,
+          destination: user2TokenAccount,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([authority])
+        .rpc();
+
+    // A winner inside the window is paid as usual, and the creator has to wait
+    let before = await tokenBalance(user1TokenAccount);
+    await claimTestPayout(user1, user1TokenAccount, market, vault, claimedBet);
+    expect((await tokenBalance(user1TokenAccount)) - before).to.equal(2e6);
+    try {
+      await reclaim();
+      expect.fail("claims are still open");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("ClaimDeadlineNotReached");
+    }
+
+    await sleep(6000);
+    try {
+      await claimTestPayout(user1, user1TokenAccount, market, vault, unclaimedBet);
+      expect.fail("the claim window has closed");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("ClaimDeadlinePassed");
+    }
+
+    before = await tokenBalance(user2TokenAccount);
+    await reclaim();
+    expect((await tokenBalance(user2TokenAccount)) - before).to.equal(2e6);
+    expect(await tokenBalance(vault)).to.equal(0);
+    const marketAccount = await program.account.market.fetch(market);
+    expect(marketAccount.unclaimedReclaimed).to.be.true;
+    expect(marketAccount.unclaimedForfeited.toNumber()).to.equal(2e6);
+  });
+
+  it("Settles a TWAP scalar market on its sampled average", async () => {
+    // A price feed that stays at 30, so the average is 30 whenever it's sampled
+    const priceFeed = await createAccount(provider.connection, authority, mint, anchor.web3.Keypair.generate().publicKey);
+    await mintTo(provider.connection, authority, mint, priceFeed, authority, 30);
+    const { market, vault } = await createTestMarket(8, {
+      ...defaultConfig(),
+      marketType: { scalar: {} },
+      scalarLowerBound: new anchor.BN(0),
+      scalarUpperBound: new anchor.BN(100),
+      twap: { source: priceFeed, offset: 64, window: new anchor.BN(14), minSpacing: new anchor.BN(3), minSamples: 2 },
+    });
+    const longBet = await placeTestBet(user1, user1TokenAccount, market, vault, 0, 1e6);
+    await placeTestBet(user2, user2TokenAccount, market, vault, 1, 3e6);
+
+    const sample = () => program.methods.sampleTwap().accountsPartial({ market, sourceAccount: priceFeed }).rpc();
+    await sample();
+    await sleep(3500);
+    await sample();
+    await sleep(5000);
+
+    try {
+      await program.methods
+        .resolveMarketScalar(new anchor.BN(100))
+        .accountsPartial({ market, authority: authority.publicKey })
+        .signers([authority])
+        .rpc();
+      expect.fail("the average decides, not a reported value");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("TwapResolutionOnly");
+    }
+    await program.methods
+      .resolveFromTwap()
+      .accountsPartial({ market, caller: user2.publicKey })
+      .signers([user2])
+      .rpc();
+
+    // Long takes 30% of the 4e6 pool
+    const marketAccount = await program.account.market.fetch(market);
+    expect(marketAccount.scalarValue.toNumber()).to.equal(30);
+    expect(marketAccount.scalarLongShare.toNumber()).to.equal(1.2e6);
+    const before = await tokenBalance(user1TokenAccount);
+    await claimTestPayout(user1, user1TokenAccount, market, vault, longBet);
+    expect((await tokenBalance(user1TokenAccount)) - before).to.equal(1.2e6);
+  });
+
+  it("Settles a tranche scalar market on its preliminary value, then the final one", async () => {
+    const trancheConfig = {
+      ...defaultConfig(),
+      marketType: { scalar: {} },
+      scalarLowerBound: new anchor.BN(0),
+      scalarUpperBound: new anchor.BN(100),
+      preliminaryTrancheBps: 5000,
+    };
+    const { market, vault } = await createTestMarket(2, trancheConfig);
+    const longBet = await placeTestBet(user1, user1TokenAccount, market, vault, 0, 1e6);
+    const shortBet = await placeTestBet(user2, user2TokenAccount, market, vault, 1, 3e6);
+    await sleep(3000);
+
+    const resolveAt = (value: number) =>
+      program.methods
+        .resolveMarketScalar(new anchor.BN(value))
+        .accountsPartial({ market, authority: authority.publicKey })
+        .signers([authority])
+        .rpc();
+    const claimTranche = (bettor: anchor.web3.Keypair, tokenAccount: anchor.web3.PublicKey, bet: anchor.web3.PublicKey) =>
+      program.methods
+        .claimPreliminaryTranche()
+        .accountsPartial({
+          bet,
+          market,
+          bettor: bettor.publicKey,
+          bettorTokenAccount: tokenAccount,
+          marketTokenAccount: vault,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([bettor])
+        .rpc();
+
+    try {
+      await resolveAt(50);
+      expect.fail("the preliminary tranche settles first");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("PreliminaryValueRequired");
+    }
+
+    // Half the 4e6 pool settles at 100, all of it to the long side
+    await program.methods
+      .reportPreliminaryValue(new anchor.BN(100))
+      .accountsPartial({ market, authority: authority.publicKey })
+      .signers([authority])
+      .rpc();
+    let marketAccount = await program.account.market.fetch(market);
+    expect(marketAccount.preliminaryValue.toNumber()).to.equal(100);
+    expect(marketAccount.preliminaryLongShare.toNumber()).to.equal(2e6);
+
+    let before = await tokenBalance(user1TokenAccount);
+    await claimTranche(user1, user1TokenAccount, longBet);
+    expect((await tokenBalance(user1TokenAccount)) - before).to.equal(2e6);
+    expect((await program.account.bet.fetch(longBet)).trancheClaimed).to.be.true;
+    try {
+      await claimTranche(user1, user1TokenAccount, longBet);
+      expect.fail("the tranche is paid once");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("AlreadyClaimed");
+    }
+    try {
+      await claimTranche(user2, user2TokenAccount, shortBet);
+      expect.fail("the short side gets nothing of this tranche");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("LosingBet");
+    }
+
+    // With a tranche out, the market can no longer refund
+    try {
+      await program.methods
+        .cancelMarket()
+        .accountsPartial({ market, authority: authority.publicKey })
+        .signers([authority])
+        .rpc();
+      expect.fail("a paid tranche can't be refunded");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("PreliminaryTranchePaid");
+    }
+
+    // The other half settles at 50: 1e6 more to each side
+    await resolveAt(50);
+    marketAccount = await program.account.market.fetch(market);
+    expect(marketAccount.scalarLongShare.toNumber()).to.equal(3e6);
+
+    before = await tokenBalance(user1TokenAccount);
+    await claimTestPayout(user1, user1TokenAccount, market, vault, longBet);
+    expect((await tokenBalance(user1TokenAccount)) - before).to.equal(1e6);
+    before = await tokenBalance(user2TokenAccount);
+    await claimTestPayout(user2, user2TokenAccount, market, vault, shortBet);
+    expect((await tokenBalance(user2TokenAccount)) - before).to.equal(1e6);
+
+    try {
+      await createTestMarket(3600, { ...trancheConfig, marketType: { standard: {} } });
+      expect.fail("only scalar markets settle in tranches");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("InvalidScalarMarket");
+    }
+  });
+
+  it("Reports odds, countdowns and status through get_market_state", async () => {
+    const marketState = (market: anchor.web3.PublicKey) =>
+      program.methods.getMarketState().accountsPartial({ market }).view();
+
+    const { market, vault } = await createTestMarket(3);
+    let state = await marketState(market);
+    expect(state.status).to.deep.equal({ open: {} });
+    expect(state.impliedProbabilitiesBps).to.deep.equal([0, 0]);
+    expect(state.timeUntilResolution.toNumber()).to.be.greaterThan(0);
+
+    await placeTestBet(user1, user1TokenAccount, market, vault, 0, 3e6);
+    await placeTestBet(user2, user2TokenAccount, market, vault, 1, 1e6);
+    state = await marketState(market);
+    expect(state.totalPool.toNumber()).to.equal(4e6);
+    expect(state.impliedProbabilitiesBps).to.deep.equal([7500, 2500]);
+
+    await sleep(4000);
+    state = await marketState(market);
+    expect(state.status).to.deep.equal({ awaitingResolution: {} });
+    expect(state.timeUntilCutoff.toNumber()).to.equal(0);
+    expect(state.timeUntilResolution.toNumber()).to.equal(0);
+
+    await resolveTestMarket(market, 0);
+    expect((await marketState(market)).status).to.deep.equal({ resolved: {} });
+
+    // Betting closes at the cutoff, an hour before resolution
+    const { market: cutoffMarket } = await createTestMarket(3600, {
+      ...defaultConfig(),
+      bettingCutoffOffset: new anchor.BN(3597),
+    });
+    await sleep(4000);
+    state = await marketState(cutoffMarket);
+    expect(state.status).to.deep.equal({ bettingClosed: {} });
+    expect(state.timeUntilResolution.toNumber()).to.be.greaterThan(3500);
+
+    await program.methods
+      .cancelMarket()
+      .accountsPartial({ market: cutoffMarket, authority: authority.publicKey })
+      .signers([authority])
+      .rpc();
+    expect((await marketState(cutoffMarket)).status).to.deep.equal({ cancelled: {} });
+  });
+
+  it("Refuses to resolve an empty market through any resolution path", async () => {
+    const { market: pivotMarket } = await createTestMarket(
+      2,
+      { ...defaultConfig(), marketType: { pivot: {} }, pivotValue: new anchor.BN(50) },
+      ["Over 50", "Under 50"]
+    );
+    const { market: committeeMarket } = await createTestMarket(2, {
+      ...defaultConfig(),
+      resolvers: [user1.publicKey],
+      resolutionThreshold: 1,
+    });
+    await sleep(3000);
+
+    // A push voids rather than resolves, and is refused all the same
+    for (const actualValue of [60, 50]) {
+      try {
+        await program.methods
+          .resolvePivotMarket(new anchor.BN(actualValue))
+          .accountsPartial({ market: pivotMarket, authority: authority.publicKey })
+          .signers([authority])
+          .rpc();
+        expect.fail("an empty pivot market has nothing to settle");
+      } catch (err) {
+        expect(err.error.errorCode.code).to.equal("EmptyMarket");
+      }
+    }
+
+    try {
+      await program.methods
+        .submitResolutionVote(0)
+        .accountsPartial({ market: committeeMarket, voter: user1.publicKey })
+        .signers([user1])
+        .rpc();
+      expect.fail("an empty committee market has nothing to settle");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("EmptyMarket");
+    }
+  });
+
+  it("Diverts the insurance share of withdrawn fees into the insurance fund", async () => {
+    const [insuranceFund] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("insurance_fund"), mint.toBuffer()],
+      program.programId
+    );
+    const [insuranceVault] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("insurance_vault"), mint.toBuffer()],
+      program.programId
+    );
+    const setFees = async (feeBps: number, insuranceFeeBps: number) => {
+      await program.methods
+        .setFee(feeBps)
+        .accountsPartial({ globalState, authority: authority.publicKey })
+        .signers([authority])
+        .rpc();
+      await program.methods
+        .setInsuranceFee(insuranceFeeBps)
+        .accountsPartial({ globalState, authority: authority.publicKey })
+        .signers([authority])
+        .rpc();
+    };
+    const withdrawFees = (market: anchor.web3.PublicKey, vault: anchor.web3.PublicKey, withFund: boolean) =>
+      program.methods
+        .withdrawFees()
+        .accountsPartial({
+          globalState,
+          market,
+          authority: authority.publicKey,
+          marketTokenAccount: vault,
+          destination: authorityTokenAccount,
+          insuranceFund: withFund ? insuranceFund : null,
+          insuranceVault: withFund ? insuranceVault : null,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([authority])
+        .rpc();
+
+    await setFees(500, 2500);
+    try {
+      const { market, vault } = await createTestMarket(3);
+      const bet = await placeTestBet(user1, user1TokenAccount, market, vault, 0, 2 * 1e6);
+      await placeTestBet(user2, user2TokenAccount, market, vault, 1, 2 * 1e6);
+      await sleep(4000);
+      await resolveTestMarket(market, 0);
+      await claimTestPayout(user1, user1TokenAccount, market, vault, bet);
+
+      try {
+        await withdrawFees(market, vault, false);
+        expect.fail("the fund's share needs somewhere to go");
+      } catch (err) {
+        expect(err.error.errorCode.code).to.equal("InsuranceFundRequired");
+      }
+
+      const contributed = (await program.account.insuranceFund.fetch(insuranceFund)).totalContributed.toNumber();
+      const treasuryBefore = await tokenBalance(authorityTokenAccount);
+      const fundBefore = await tokenBalance(insuranceVault);
+      await withdrawFees(market, vault, true);
+      // A quarter of the 0.2 token fee goes to the fund
+      expect((await tokenBalance(authorityTokenAccount)) - treasuryBefore).to.equal(0.15 * 1e6);
+      expect((await tokenBalance(insuranceVault)) - fundBefore).to.equal(0.05 * 1e6);
+      expect((await program.account.insuranceFund.fetch(insuranceFund)).totalContributed.toNumber()).to.equal(
+        contributed + 0.05 * 1e6
+      );
+      expect(await tokenBalance(vault)).to.equal(0);
+    } finally {
+      await setFees(0, 0);
+    }
+  });
+
   it("Display market summary", async () => {
     const [market] = anchor.web3.PublicKey.findProgramAddressSync(
       [Buffer.from("market"), Buffer.from([0, 0, 0, 0, 0, 0, 0, 0])],